@@ -0,0 +1,75 @@
+use async_std::task;
+use async_trait::async_trait;
+use chrono::Utc;
+use criterion::{
+    async_executor::AsyncStdExecutor, black_box, criterion_group, criterion_main, Criterion,
+    Throughput,
+};
+use futures::TryStreamExt;
+use proptest::test_runner::{RngAlgorithm, TestRng};
+use std::{borrow::Cow, rc::Rc, time::Duration};
+use wnfs::{
+    common::{BlockStore, MemoryBlockStore, PrefetchingBlockStore},
+    libipld::{Cid, IpldCodec},
+    namefilter::Namefilter,
+    private::{PrivateFile, PrivateForest, MAX_BLOCK_CONTENT_SIZE},
+};
+
+const BLOCK_COUNT: usize = 32;
+const LATENCY: Duration = Duration::from_millis(2);
+
+/// A memory block store that simulates network latency on every read.
+#[derive(Debug, Default)]
+struct LatencyBlockStore(MemoryBlockStore);
+
+#[async_trait(?Send)]
+impl BlockStore for LatencyBlockStore {
+    async fn get_block(&self, cid: &Cid) -> anyhow::Result<Cow<Vec<u8>>> {
+        task::sleep(LATENCY).await;
+        self.0.get_block(cid).await
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> anyhow::Result<Cid> {
+        self.0.put_block(bytes, codec).await
+    }
+}
+
+fn stream_content_with_prefetch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("private file stream_content");
+    group.throughput(Throughput::Bytes(
+        (BLOCK_COUNT * MAX_BLOCK_CONTENT_SIZE) as u64,
+    ));
+    group.sample_size(10);
+
+    for depth in [0, 4] {
+        let store = PrefetchingBlockStore::new(LatencyBlockStore::default(), depth);
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let file = task::block_on(PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            vec![0xab; BLOCK_COUNT * MAX_BLOCK_CONTENT_SIZE],
+            forest,
+            &store,
+            rng,
+        ))
+        .expect("Couldn't setup private file");
+
+        group.bench_function(format!("prefetch depth {depth}"), |b| {
+            b.to_async(AsyncStdExecutor).iter(|| async {
+                let chunks: Vec<Vec<u8>> = file
+                    .stream_content(0, forest, &store)
+                    .try_collect()
+                    .await
+                    .unwrap();
+                black_box(chunks);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, stream_content_with_prefetch);
+
+criterion_main!(benches);
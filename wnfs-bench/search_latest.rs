@@ -0,0 +1,71 @@
+use async_std::task;
+use chrono::Utc;
+use criterion::{
+    async_executor::AsyncStdExecutor, black_box, criterion_group, criterion_main, Criterion,
+};
+use proptest::test_runner::{RngAlgorithm, TestRng};
+use std::rc::Rc;
+use wnfs::{
+    common::MemoryBlockStore,
+    namefilter::Namefilter,
+    private::{PrivateDirectory, PrivateForest, PrivateNode, SearchLatestCache},
+};
+
+const REVISION_COUNT: usize = 500;
+
+fn search_latest(c: &mut Criterion) {
+    let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let forest = &mut Rc::new(PrivateForest::new());
+    let store = &MemoryBlockStore::new();
+
+    let dir = &mut Rc::new(PrivateDirectory::new(
+        Namefilter::default(),
+        Utc::now(),
+        rng,
+    ));
+    task::block_on(dir.store(forest, store, rng)).expect("Couldn't store directory");
+    let original = PrivateNode::Dir(Rc::clone(dir));
+
+    for i in 0..REVISION_COUNT {
+        task::block_on(async {
+            dir.write(
+                &["file.txt".into()],
+                true,
+                Utc::now(),
+                i.to_le_bytes().to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await?;
+            dir.store(forest, store, rng).await
+        })
+        .expect("Couldn't write revision");
+    }
+
+    let mut group = c.benchmark_group("private node search_latest");
+
+    group.bench_function(format!("{REVISION_COUNT} revisions uncached"), |b| {
+        b.to_async(AsyncStdExecutor).iter(|| async {
+            black_box(original.search_latest(forest, store).await.unwrap());
+        })
+    });
+
+    let cache = &SearchLatestCache::new();
+    group.bench_function(format!("{REVISION_COUNT} revisions cached"), |b| {
+        b.to_async(AsyncStdExecutor).iter(|| async {
+            black_box(
+                original
+                    .search_latest_cached(forest, store, cache)
+                    .await
+                    .unwrap(),
+            );
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, search_latest);
+
+criterion_main!(benches);
@@ -1,13 +1,37 @@
-use crate::{dagcbor, AsyncSerialize, BlockStoreError, MAX_BLOCK_SIZE};
-use anyhow::{bail, Result};
+use crate::{
+    dagcbor::{self, DecodeLimits},
+    AsyncSerialize, BlockStoreError, MAX_BLOCK_SIZE,
+};
+use anyhow::{bail, ensure, Result};
 use async_trait::async_trait;
+use futures::{
+    future::{self, LocalBoxFuture},
+    io::{AsyncRead, Cursor},
+    Future,
+};
 use libipld::{
     cid::Version,
     multihash::{Code, MultihashDigest},
     serde as ipld_serde, Cid, IpldCodec,
 };
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{
+        hash_map::{DefaultHasher, RandomState},
+        BTreeMap, HashMap,
+    },
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash, Hasher},
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -21,7 +45,7 @@ pub trait BlockStore: Sized {
 
     async fn get_deserializable<V: DeserializeOwned>(&self, cid: &Cid) -> Result<V> {
         let bytes = self.get_block(cid).await?;
-        let ipld = dagcbor::decode(bytes.as_ref())?;
+        let ipld = dagcbor::decode_with_limits(bytes.as_ref(), &self.decode_limits())?;
         Ok(ipld_serde::from_ipld::<V>(ipld)?)
     }
 
@@ -36,14 +60,123 @@ pub trait BlockStore: Sized {
         self.put_block(bytes, IpldCodec::DagCbor).await
     }
 
+    /// Checks whether the store has a block with given CID.
+    ///
+    /// The default tries to read the block. Only a [`BlockStoreError::NotFound`] error counts
+    /// as the block missing, any other error gets returned. Stores that can check cheaper, or
+    /// that report missing blocks differently, should override this.
+    async fn has_block(&self, cid: &Cid) -> Result<bool> {
+        match self.get_block(cid).await {
+            Ok(_) => Ok(true),
+            Err(error) if is_not_found_error(&error) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns a reader yielding exactly the bytes of the block with given CID.
+    ///
+    /// Errors if the block is missing. The default reads the whole block with `get_block`.
+    /// Stores that can read blocks incrementally should override this.
+    async fn get_block_stream(&self, cid: &Cid) -> Result<Pin<Box<dyn AsyncRead>>> {
+        let bytes = self.get_block(cid).await?.into_owned();
+        Ok(Box::pin(Cursor::new(bytes)))
+    }
+
+    /// Stores several blocks and returns their CIDs in the same order as the given blocks.
+    ///
+    /// The default stores the blocks one after the other. Stores with per-request
+    /// latency should override this to pipeline the writes.
+    async fn put_block_many(&self, blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+        let mut cids = Vec::with_capacity(blocks.len());
+        for (bytes, codec) in blocks {
+            cids.push(self.put_block(bytes, codec).await?);
+        }
+
+        Ok(cids)
+    }
+
+    /// Stores bytes under a CID computed by the caller, without checking that the bytes hash to it.
+    ///
+    /// This is for wrappers that transform bytes before storing them, like `CompressingBlockStore`.
+    /// The default returns `BlockStoreError::Unsupported`.
+    async fn put_block_keyed(&self, _cid: Cid, _bytes: Vec<u8>) -> Result<()> {
+        bail!(BlockStoreError::Unsupported("put_block_keyed"))
+    }
+
+    /// Removes the block with given CID from the store.
+    ///
+    /// Deleting a block that isn't in the store is not an error.
+    /// The default returns `BlockStoreError::Unsupported`, for append-only stores.
+    async fn delete_block(&self, _cid: &Cid) -> Result<()> {
+        bail!(BlockStoreError::Unsupported("delete_block"))
+    }
+
+    /// Returns the CIDs of all blocks in the store.
+    ///
+    /// The default returns `BlockStoreError::Unsupported`, for stores that can't enumerate their blocks.
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        bail!(BlockStoreError::Unsupported("list_blocks"))
+    }
+
+    /// Returns how many blocks and bytes this store has read and written, if it keeps count.
+    ///
+    /// The default returns `None`.
+    fn stats(&self) -> Option<BlockStoreStats> {
+        None
+    }
+
+    /// Resets the counters returned by `stats`. The default does nothing.
+    fn reset_stats(&self) {}
+
+    /// Hints that the blocks with given CIDs are going to be requested soon.
+    ///
+    /// This is advisory only. Implementations may fetch the blocks ahead of time,
+    /// but must not fail or change what `get_block` returns. The default does nothing.
+    async fn prefetch(&self, _cids: &[Cid]) {}
+
+    /// The number of blocks sequential readers should look ahead and pass to `prefetch`.
+    ///
+    /// Zero, the default, means readers don't prefetch at all.
+    fn prefetch_depth(&self) -> usize {
+        0
+    }
+
+    /// The limits enforced when decoding DagCbor blocks read from this store.
+    fn decode_limits(&self) -> DecodeLimits {
+        DecodeLimits::default()
+    }
+
+    /// The codecs this store accepts blocks for. Blocks with any other codec are rejected.
+    ///
+    /// Defaults to all codecs supported by `IpldCodec`.
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        &[
+            IpldCodec::Raw,
+            IpldCodec::DagCbor,
+            IpldCodec::DagJson,
+            IpldCodec::DagPb,
+        ]
+    }
+
+    /// The multihash used to compute CIDs of blocks put into this store.
+    ///
+    /// Defaults to `Code::Sha2_256`. Blocks hashed with any other multihash can still be read.
+    fn hash_code(&self) -> Code {
+        Code::Sha2_256
+    }
+
     // This should be the same in all implementations of BlockStore
     fn create_cid(&self, bytes: &Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        // If the codec isn't allowed by this store, abandon this task
+        if !self.allowed_codecs().contains(&codec) {
+            bail!(BlockStoreError::DisallowedCodec(codec.into()))
+        }
         // If there are too many bytes, abandon this task
         if bytes.len() > MAX_BLOCK_SIZE {
             bail!(BlockStoreError::MaximumBlockSizeExceeded(bytes.len()))
         }
-        // Compute the SHA256 hash of the bytes
-        let hash = Code::Sha2_256.digest(bytes);
+        // Hash the bytes with this store's multihash
+        let hash = self.hash_code().digest(bytes);
         // Represent the hash as a V1 CID
         let cid = Cid::new(Version::V1, codec.into(), hash)?;
         // Return Ok with the CID
@@ -51,6 +184,49 @@ pub trait BlockStore: Sized {
     }
 }
 
+/// For block stores that can be shared between threads, e.g. as `Arc<dyn ThreadSafeBlockStore>`.
+///
+/// `BlockStore` futures aren't `Send`, so they can only run on single-threaded executors, which is
+/// what WASM needs. Stores implementing this trait instead can be used from multithreaded runtimes,
+/// and every `Arc` of one is also a `BlockStore`, so it can be passed to all WNFS APIs.
+#[async_trait]
+pub trait ThreadSafeBlockStore: Send + Sync {
+    async fn get_block(&self, cid: &Cid) -> Result<Vec<u8>>;
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid>;
+}
+
+/// Read and write statistics of a block store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockStoreStats {
+    pub blocks_read: u64,
+    pub blocks_written: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+/// Called with every block a capacity-bounded `MemoryBlockStore` evicts.
+type EvictionCallback = Arc<dyn Fn(Cid, Vec<u8>) + Send + Sync>;
+
+/// Byte accounting of a `MemoryBlockStore` created with `with_capacity`.
+#[derive(Clone)]
+struct Capacity {
+    max_bytes: usize,
+    size: usize,
+    clock: u64,
+    inserted_at: HashMap<String, u64>,
+    order: BTreeMap<u64, String>,
+    on_evict: Option<EvictionCallback>,
+}
+
+/// Counters backing `BlockStoreStats`, cheap enough to update on every operation.
+#[derive(Debug, Default)]
+struct StatsCounters {
+    blocks_read: AtomicU64,
+    blocks_written: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -58,125 +234,2353 @@ pub trait BlockStore: Sized {
 /// An in-memory block store to simulate IPFS.
 ///
 /// IPFS is basically a glorified HashMap.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct MemoryBlockStore(RefCell<HashMap<String, Vec<u8>>>);
+///
+/// Only the blocks are serialized. A deserialized store has no capacity, computes CIDs with
+/// `Code::Sha2_256` and starts counting stats from zero.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryBlockStore {
+    blocks: RefCell<HashMap<String, Vec<u8>>>,
+    stats: StatsCounters,
+    capacity: RefCell<Option<Capacity>>,
+    hash_code: Option<Code>,
+}
 
 impl MemoryBlockStore {
     /// Creates a new in-memory block store.
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Creates an in-memory block store holding at most `max_bytes` of block data.
+    ///
+    /// When a put would exceed the limit, the least recently inserted blocks are evicted
+    /// until the new block fits. Blocks larger than the whole capacity are rejected.
+    pub fn with_capacity(max_bytes: usize) -> Self {
+        let capacity = Capacity {
+            max_bytes,
+            size: 0,
+            clock: 0,
+            inserted_at: HashMap::new(),
+            order: BTreeMap::new(),
+            on_evict: None,
+        };
+
+        Self {
+            capacity: RefCell::new(Some(capacity)),
+            ..Default::default()
+        }
+    }
+
+    /// Makes the store compute CIDs with given multihash instead of `Code::Sha2_256`.
+    pub fn with_hash_code(mut self, code: Code) -> Self {
+        self.hash_code = Some(code);
+        self
+    }
+
+    /// Sets a callback that's handed every block evicted to stay within capacity,
+    /// e.g. to spill it to disk. Has no effect on stores without a capacity.
+    pub fn on_evict(self, callback: impl Fn(Cid, Vec<u8>) + Send + Sync + 'static) -> Self {
+        if let Some(capacity) = self.capacity.borrow_mut().as_mut() {
+            capacity.on_evict = Some(Arc::new(callback));
+        }
+
+        self
+    }
+
+    /// Returns the total size of all stored blocks in bytes.
+    pub fn stored_bytes(&self) -> usize {
+        self.blocks.borrow().values().map(Vec::len).sum()
+    }
+
+    /// Inserts the block, evicting older blocks first if the store has a capacity.
+    fn insert(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        let key = cid.to_string();
+        let mut bounds = self.capacity.borrow_mut();
+        let Some(capacity) = bounds.as_mut() else {
+            self.blocks.borrow_mut().insert(key, bytes);
+            return Ok(());
+        };
+
+        if bytes.len() > capacity.max_bytes {
+            bail!(BlockStoreError::MaximumBlockSizeExceeded(bytes.len()))
+        }
+
+        let mut blocks = self.blocks.borrow_mut();
+        if let Some(old) = blocks.remove(&key) {
+            capacity.remove(&key, old.len());
+        }
+
+        let mut evicted = Vec::new();
+        while capacity.size + bytes.len() > capacity.max_bytes {
+            let Some((_, oldest)) = capacity.order.pop_first() else {
+                break;
+            };
+
+            capacity.inserted_at.remove(&oldest);
+            if let Some(old) = blocks.remove(&oldest) {
+                capacity.size -= old.len();
+                evicted.push((oldest, old));
+            }
+        }
+
+        capacity.clock += 1;
+        capacity.order.insert(capacity.clock, key.clone());
+        capacity.inserted_at.insert(key.clone(), capacity.clock);
+        capacity.size += bytes.len();
+        blocks.insert(key, bytes);
+        drop(blocks);
+
+        // Call back without holding any borrows, in case the callback uses this store
+        let on_evict = capacity.on_evict.clone();
+        drop(bounds);
+        if let Some(on_evict) = on_evict {
+            for (key, bytes) in evicted {
+                on_evict(Cid::try_from(key.as_str())?, bytes);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Capacity {
+    fn remove(&mut self, key: &str, len: usize) {
+        if let Some(inserted_at) = self.inserted_at.remove(key) {
+            self.order.remove(&inserted_at);
+            self.size -= len;
+        }
+    }
+}
+
+impl Debug for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capacity")
+            .field("max_bytes", &self.max_bytes)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Serialize for MemoryBlockStore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct("MemoryBlockStore", &self.blocks)
+    }
+}
+
+impl<'de> Deserialize<'de> for MemoryBlockStore {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "MemoryBlockStore")]
+        struct Blocks(RefCell<HashMap<String, Vec<u8>>>);
+
+        let Blocks(blocks) = Blocks::deserialize(deserializer)?;
+        Ok(Self {
+            blocks,
+            ..Default::default()
+        })
+    }
+}
+
+impl StatsCounters {
+    fn record_read(&self, bytes: usize) {
+        self.blocks_read.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn record_write(&self, bytes: usize) {
+        self.blocks_written.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> BlockStoreStats {
+        BlockStoreStats {
+            blocks_read: self.blocks_read.load(Ordering::Relaxed),
+            blocks_written: self.blocks_written.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.blocks_read.store(0, Ordering::Relaxed);
+        self.blocks_written.store(0, Ordering::Relaxed);
+        self.bytes_read.store(0, Ordering::Relaxed);
+        self.bytes_written.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for StatsCounters {
+    fn clone(&self) -> Self {
+        let stats = self.snapshot();
+        Self {
+            blocks_read: AtomicU64::new(stats.blocks_read),
+            blocks_written: AtomicU64::new(stats.blocks_written),
+            bytes_read: AtomicU64::new(stats.bytes_read),
+            bytes_written: AtomicU64::new(stats.bytes_written),
+        }
+    }
 }
 
 #[async_trait(?Send)]
 impl BlockStore for MemoryBlockStore {
     /// Retrieves an array of bytes from the block store with given CID.
     async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
-        Ok(Cow::Owned(
-            self.0
-                .borrow()
-                .get(&cid.to_string())
-                .ok_or(BlockStoreError::CIDNotFound(*cid))?
-                .clone(),
-        ))
+        let bytes = self
+            .blocks
+            .borrow()
+            .get(&cid.to_string())
+            .ok_or(BlockStoreError::NotFound(*cid))?
+            .clone();
+        self.stats.record_read(bytes.len());
+        Ok(Cow::Owned(bytes))
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool> {
+        Ok(self.blocks.borrow().contains_key(&cid.to_string()))
     }
 
     /// Stores an array of bytes in the block store.
     async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
         // Try to build the CID from the bytes and codec
         let cid = self.create_cid(&bytes, codec)?;
+        let len = bytes.len();
         // Insert the bytes into the HashMap using the CID as the key
-        self.0.borrow_mut().insert(cid.to_string(), bytes);
+        self.insert(cid, bytes)?;
+        self.stats.record_write(len);
         // Return Ok status with the generated CID
         Ok(cid)
     }
+
+    /// Stores an array of bytes under the given CID.
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        if bytes.len() > MAX_BLOCK_SIZE {
+            bail!(BlockStoreError::MaximumBlockSizeExceeded(bytes.len()))
+        }
+
+        let len = bytes.len();
+        self.insert(cid, bytes)?;
+        self.stats.record_write(len);
+        Ok(())
+    }
+
+    /// Removes the bytes stored under given CID, if any.
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        let key = cid.to_string();
+        let removed = self.blocks.borrow_mut().remove(&key);
+        if let (Some(bytes), Some(capacity)) = (removed, self.capacity.borrow_mut().as_mut()) {
+            capacity.remove(&key, bytes.len());
+        }
+
+        Ok(())
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.blocks
+            .borrow()
+            .keys()
+            .map(|key| Ok(Cid::try_from(key.as_str())?))
+            .collect()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.hash_code.unwrap_or(Code::Sha2_256)
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        Some(self.stats.snapshot())
+    }
+
+    fn reset_stats(&self) {
+        self.stats.reset()
+    }
 }
 
-//--------------------------------------------------------------------------------------------------
-// Tests
-//--------------------------------------------------------------------------------------------------
+/// An in-memory block store that can be shared between threads.
+#[derive(Debug, Default)]
+pub struct ThreadSafeMemoryBlockStore(RwLock<HashMap<Cid, Vec<u8>>>);
 
-/// The following methods are generic functions that can be used to test any type that conforms to the BlockStore trait.
-/// In utilizing this structure, externally defined types can still test for retrieval, duplication, and serialization compatibility.
-pub async fn bs_retrieval_test<T: BlockStore + Send + 'static>(store: &T) -> Result<()> {
-    // Example objects to insert and remove from the blockstore
-    let first_bytes = vec![1, 2, 3, 4, 5];
-    let second_bytes = b"hello world".to_vec();
+impl ThreadSafeMemoryBlockStore {
+    /// Creates a new thread-safe in-memory block store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
-    // Insert the objects into the blockstore
-    let first_cid = store.put_serializable(&first_bytes).await?;
-    let second_cid = store.put_serializable(&second_bytes).await?;
+#[async_trait]
+impl ThreadSafeBlockStore for ThreadSafeMemoryBlockStore {
+    async fn get_block(&self, cid: &Cid) -> Result<Vec<u8>> {
+        let blocks = self.0.read().map_err(|_| BlockStoreError::LockPoisoned)?;
+        let bytes = blocks.get(cid).ok_or(BlockStoreError::NotFound(*cid))?;
+        Ok(bytes.clone())
+    }
 
-    // Retrieve the objects from the blockstore
-    let first_loaded: Vec<u8> = store.get_deserializable(&first_cid).await?;
-    let second_loaded: Vec<u8> = store.get_deserializable(&second_cid).await?;
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        if bytes.len() > MAX_BLOCK_SIZE {
+            bail!(BlockStoreError::MaximumBlockSizeExceeded(bytes.len()))
+        }
 
-    // Assert that the objects are the same as the ones we inserted
-    assert_eq!(first_loaded, first_bytes);
-    assert_eq!(second_loaded, second_bytes);
+        let cid = Cid::new(Version::V1, codec.into(), Code::Sha2_256.digest(&bytes))?;
+        let mut blocks = self.0.write().map_err(|_| BlockStoreError::LockPoisoned)?;
+        blocks.insert(cid, bytes);
+        Ok(cid)
+    }
+}
 
-    // Return Ok
-    Ok(())
+#[async_trait(?Send)]
+impl<T: ThreadSafeBlockStore + ?Sized> BlockStore for Arc<T> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        Ok(Cow::Owned(T::get_block(self, cid).await?))
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.create_cid(&bytes, codec)?;
+        let stored = T::put_block(self, bytes, codec).await?;
+        if stored != cid {
+            bail!(BlockStoreError::CIDMismatch(stored))
+        }
+
+        Ok(cid)
+    }
 }
 
-// Generic function used to test any type that conforms to the BlockStore trait
-pub async fn bs_duplication_test<T: BlockStore + Send + 'static>(store: &T) -> Result<()> {
-    // Example objects to insert and remove from the blockstore
-    let first_bytes = vec![1, 2, 3, 4, 5];
-    let second_bytes = first_bytes.clone();
+/// A block store wrapper that fetches blocks ahead of time when asked to via `prefetch`.
+///
+/// Prefetched blocks are kept until they're read once, so sequential readers like
+/// `PrivateFile::stream_content` can overlap the latency of several block fetches.
+#[derive(Debug)]
+pub struct PrefetchingBlockStore<B: BlockStore> {
+    inner: B,
+    depth: usize,
+    cache: RefCell<HashMap<Cid, Vec<u8>>>,
+}
 
-    // Insert the objects into the blockstore
-    let first_cid = store.put_serializable(&first_bytes).await?;
-    let second_cid = store.put_serializable(&second_bytes).await?;
+impl<B: BlockStore> PrefetchingBlockStore<B> {
+    /// Wraps a block store, advising readers to stay `depth` blocks ahead.
+    pub fn new(inner: B, depth: usize) -> Self {
+        Self {
+            inner,
+            depth,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
 
-    // Assert that the two vecs produced the same CID
-    assert_eq!(first_cid, second_cid);
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
 
-    // Retrieve the objects from the blockstore
-    let first_loaded: Vec<u8> = store.get_deserializable(&first_cid).await?;
-    let second_loaded: Vec<u8> = store.get_deserializable(&second_cid).await?;
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for PrefetchingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        let prefetched = self.cache.borrow_mut().remove(cid);
+        match prefetched {
+            Some(bytes) => Ok(Cow::Owned(bytes)),
+            None => Ok(Cow::Owned(self.inner.get_block(cid).await?.into_owned())),
+        }
+    }
 
-    // Assert that the objects are the same as the ones we inserted
-    assert_eq!(first_loaded, first_bytes);
-    assert_eq!(second_loaded, second_bytes);
-    // Assert that the objects we loaded are the same
-    assert_eq!(first_loaded, second_loaded);
-    // Return Ok
-    Ok(())
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        self.inner.put_block(bytes, codec).await
+    }
+
+    async fn put_block_many(&self, blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+        self.inner.put_block_many(blocks).await
+    }
+
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        self.cache.borrow_mut().remove(&cid);
+        self.inner.put_block_keyed(cid, bytes).await
+    }
+
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        self.cache.borrow_mut().remove(cid);
+        self.inner.delete_block(cid).await
+    }
+
+    /// Fetches all given blocks concurrently and keeps them until they're read.
+    /// Blocks that fail to load are skipped.
+    async fn prefetch(&self, cids: &[Cid]) {
+        let missing = {
+            let cache = self.cache.borrow();
+            cids.iter()
+                .filter(|cid| !cache.contains_key(cid))
+                .collect::<Vec<_>>()
+        };
+
+        let blocks = future::join_all(missing.iter().map(|cid| self.inner.get_block(cid))).await;
+
+        let mut cache = self.cache.borrow_mut();
+        for (cid, block) in missing.into_iter().zip(blocks) {
+            if let Ok(bytes) = block {
+                cache.insert(*cid, bytes.into_owned());
+            }
+        }
+    }
+
+    fn prefetch_depth(&self) -> usize {
+        self.depth
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.inner.list_blocks().await
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.inner.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
 }
 
-pub async fn bs_serialization_test<
-    T: BlockStore + Send + Serialize + 'static + for<'de> Deserialize<'de>,
->(
-    store: &T,
-) -> Result<()> {
-    // Example objects to insert and remove from the blockstore
-    let bytes = vec![1, 2, 3, 4, 5];
-    // Insert the object into the blockstore
-    let cid = store.put_serializable(&bytes).await?;
-    // Serialize the BlockStore
-    let serial_store: Vec<u8> = dagcbor::encode(&store)?;
-    // Construct a new BlockStore from the Serialized object
-    let deserial_store: T = dagcbor::decode(&serial_store)?;
-    // Retrieve the object from the blockstore
-    let loaded: Vec<u8> = deserial_store.get_deserializable(&cid).await?;
-    // Assert that the objects are the same as the ones we inserted
-    assert_eq!(loaded, bytes);
-    // Return Ok
-    Ok(())
+/// A block store that writes every block to several replicas.
+///
+/// Writes and deletes go to all replicas concurrently and succeed once at least `quorum` of them succeed.
+/// Reads are served by the first replica that has the block.
+#[derive(Debug)]
+pub struct ReplicatingBlockStore<B: BlockStore> {
+    replicas: Vec<B>,
+    quorum: usize,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anyhow::Result;
+impl<B: BlockStore> ReplicatingBlockStore<B> {
+    /// Creates a store replicating to given block stores, requiring `quorum` successful writes.
+    ///
+    /// Fails unless the quorum is between one and the number of replicas.
+    pub fn new(replicas: Vec<B>, quorum: usize) -> Result<Self> {
+        ensure!(
+            (1..=replicas.len()).contains(&quorum),
+            BlockStoreError::InvalidQuorum {
+                quorum,
+                replicas: replicas.len(),
+            }
+        );
 
-    #[async_std::test]
-    async fn memory_blockstore() -> Result<()> {
-        let store = &MemoryBlockStore::new();
-        bs_retrieval_test(store).await?;
-        bs_duplication_test(store).await?;
-        bs_serialization_test(store).await?;
+        Ok(Self { replicas, quorum })
+    }
+
+    /// Returns the replicas.
+    pub fn replicas(&self) -> &[B] {
+        &self.replicas
+    }
+
+    /// Returns the number of replicas that must accept a write.
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    fn ensure_quorum<T>(&self, results: &[Result<T>]) -> Result<()> {
+        let failed = results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| result.is_err())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let succeeded = results.len() - failed.len();
+        if succeeded < self.quorum {
+            bail!(BlockStoreError::QuorumNotReached {
+                required: self.quorum,
+                succeeded,
+                failed,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for ReplicatingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        let mut failure = None;
+        for replica in self.replicas.iter() {
+            match replica.get_block(cid).await {
+                Ok(bytes) => return Ok(Cow::Owned(bytes.into_owned())),
+                Err(e) if !is_not_found_error(&e) => failure = Some(e),
+                Err(_) => {}
+            }
+        }
+
+        // Only report the block missing if no replica failed in some other way
+        match failure {
+            Some(e) => Err(e),
+            None => bail!(BlockStoreError::NotFound(*cid)),
+        }
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.create_cid(&bytes, codec)?;
+        let results = future::join_all(
+            self.replicas
+                .iter()
+                .map(|replica| replica.put_block(bytes.clone(), codec)),
+        )
+        .await;
+
+        self.ensure_quorum(&results)?;
+        Ok(cid)
+    }
+
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        let results = future::join_all(
+            self.replicas
+                .iter()
+                .map(|replica| replica.put_block_keyed(cid, bytes.clone())),
+        )
+        .await;
+
+        self.ensure_quorum(&results)
+    }
+
+    /// Deletes the block from all replicas, requiring `quorum` of them to succeed.
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        let results = future::join_all(
+            self.replicas
+                .iter()
+                .map(|replica| replica.delete_block(cid)),
+        )
+        .await;
+
+        self.ensure_quorum(&results)
+    }
+
+    /// Uses the limits of the first replica, like its multihash.
+    fn decode_limits(&self) -> DecodeLimits {
+        self.replicas
+            .first()
+            .map_or_else(DecodeLimits::default, BlockStore::decode_limits)
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.replicas
+            .first()
+            .map_or(&[], BlockStore::allowed_codecs)
+    }
+
+    /// Uses the multihash of the first replica, which all replicas are expected to share.
+    fn hash_code(&self) -> Code {
+        self.replicas
+            .first()
+            .map_or(Code::Sha2_256, BlockStore::hash_code)
+    }
+}
+
+/// A block store wrapper that keeps recently used blocks in memory.
+///
+/// Blocks are cached when they're fetched from or written to the inner store,
+/// and the least recently used ones are evicted once the cached bytes exceed the capacity.
+#[derive(Debug)]
+pub struct CachedBlockStore<B: BlockStore> {
+    inner: B,
+    capacity: usize,
+    cache: RefCell<LruCache>,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+/// Cached blocks ordered by when they were last used.
+#[derive(Debug, Default)]
+struct LruCache {
+    entries: HashMap<Cid, (Vec<u8>, u64)>,
+    order: BTreeMap<u64, Cid>,
+    size: usize,
+    clock: u64,
+}
+
+impl<B: BlockStore> CachedBlockStore<B> {
+    /// Wraps a block store, caching up to `capacity` bytes of blocks.
+    pub fn new(inner: B, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            cache: RefCell::new(LruCache::default()),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// The number of `get_block` calls served from the cache.
+    pub fn cache_hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    /// The number of `get_block` calls that went to the inner store.
+    pub fn cache_misses(&self) -> usize {
+        self.misses.get()
+    }
+
+    /// The number of bytes currently cached.
+    pub fn cached_size(&self) -> usize {
+        self.cache.borrow().size
+    }
+}
+
+impl LruCache {
+    fn get(&mut self, cid: &Cid) -> Option<Vec<u8>> {
+        self.clock += 1;
+        let (bytes, last_used) = self.entries.get_mut(cid)?;
+        self.order.remove(last_used);
+        self.order.insert(self.clock, *cid);
+        *last_used = self.clock;
+        Some(bytes.clone())
+    }
+
+    fn insert(&mut self, cid: Cid, bytes: Vec<u8>, capacity: usize) {
+        self.remove(&cid);
+        if bytes.len() > capacity {
+            return;
+        }
+
+        self.clock += 1;
+        self.size += bytes.len();
+        self.order.insert(self.clock, cid);
+        self.entries.insert(cid, (bytes, self.clock));
+
+        while self.size > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((bytes, _)) = self.entries.remove(&oldest) {
+                self.size -= bytes.len();
+            }
+        }
+    }
+
+    fn remove(&mut self, cid: &Cid) {
+        if let Some((bytes, last_used)) = self.entries.remove(cid) {
+            self.order.remove(&last_used);
+            self.size -= bytes.len();
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for CachedBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        let cached = self.cache.borrow_mut().get(cid);
+        if let Some(bytes) = cached {
+            self.hits.set(self.hits.get() + 1);
+            return Ok(Cow::Owned(bytes));
+        }
+
+        self.misses.set(self.misses.get() + 1);
+        let bytes = self.inner.get_block(cid).await?.into_owned();
+        self.cache
+            .borrow_mut()
+            .insert(*cid, bytes.clone(), self.capacity);
+
+        Ok(Cow::Owned(bytes))
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.inner.put_block(bytes.clone(), codec).await?;
+        self.cache.borrow_mut().insert(cid, bytes, self.capacity);
+        Ok(cid)
+    }
+
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        self.cache.borrow_mut().remove(&cid);
+        self.inner.put_block_keyed(cid, bytes).await
+    }
+
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        self.cache.borrow_mut().remove(cid);
+        self.inner.delete_block(cid).await
+    }
+
+    async fn prefetch(&self, cids: &[Cid]) {
+        self.inner.prefetch(cids).await
+    }
+
+    fn prefetch_depth(&self) -> usize {
+        self.inner.prefetch_depth()
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.inner.list_blocks().await
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.inner.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
+}
+
+/// A block store that serves reads from a fast tier first and keeps every block in a slow, durable tier.
+///
+/// Blocks read from the slow tier are promoted into the fast tier, and blocks written go to both.
+/// Both tiers hold blocks under the CID computed by the slow tier, so the fast tier needs to
+/// support `put_block_keyed`. Writes to the fast tier are best effort, e.g. a bounded fast tier
+/// may reject or evict blocks, since the slow tier always has them.
+#[derive(Debug)]
+pub struct TieredBlockStore<A: BlockStore, B: BlockStore> {
+    fast: A,
+    slow: B,
+}
+
+impl<A: BlockStore, B: BlockStore> TieredBlockStore<A, B> {
+    /// Combines a fast block store with a slow, durable one.
+    pub fn new(fast: A, slow: B) -> Self {
+        Self { fast, slow }
+    }
+
+    /// Returns the fast tier.
+    pub fn fast(&self) -> &A {
+        &self.fast
+    }
+
+    /// Returns the slow tier.
+    pub fn slow(&self) -> &B {
+        &self.slow
+    }
+}
+
+#[async_trait(?Send)]
+impl<A: BlockStore, B: BlockStore> BlockStore for TieredBlockStore<A, B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        if let Ok(bytes) = self.fast.get_block(cid).await {
+            return Ok(Cow::Owned(bytes.into_owned()));
+        }
+
+        let bytes = self.slow.get_block(cid).await?.into_owned();
+        let _ = self.fast.put_block_keyed(*cid, bytes.clone()).await;
+        Ok(Cow::Owned(bytes))
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.slow.put_block(bytes.clone(), codec).await?;
+        let _ = self.fast.put_block_keyed(cid, bytes).await;
+        Ok(cid)
+    }
+
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        self.slow.put_block_keyed(cid, bytes.clone()).await?;
+        let _ = self.fast.put_block_keyed(cid, bytes).await;
+        Ok(())
+    }
+
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        self.slow.delete_block(cid).await?;
+        let _ = self.fast.delete_block(cid).await;
+        Ok(())
+    }
+
+    /// Checks the fast tier, then the slow tier, without fetching or promoting the block.
+    async fn has_block(&self, cid: &Cid) -> Result<bool> {
+        if let Ok(true) = self.fast.has_block(cid).await {
+            return Ok(true);
+        }
+
+        self.slow.has_block(cid).await
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.slow.list_blocks().await
+    }
+
+    /// Uses the slow tier's limits, like its multihash.
+    fn decode_limits(&self) -> DecodeLimits {
+        self.slow.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.slow.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.slow.hash_code()
+    }
+
+    /// Counts the slow tier only, so blocks held in both tiers aren't counted twice.
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.slow.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.fast.reset_stats();
+        self.slow.reset_stats()
+    }
+}
+
+/// A block store wrapper that only allows reads, for handing a store to code that mustn't mutate it.
+///
+/// All writes and deletes fail with `BlockStoreError::ReadOnly`.
+#[derive(Debug)]
+pub struct ReadOnlyBlockStore<B: BlockStore> {
+    inner: B,
+}
+
+impl<B: BlockStore> ReadOnlyBlockStore<B> {
+    /// Wraps a block store, rejecting all writes to it.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for ReadOnlyBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        self.inner.get_block(cid).await
+    }
+
+    async fn put_block(&self, _bytes: Vec<u8>, _codec: IpldCodec) -> Result<Cid> {
+        bail!(BlockStoreError::ReadOnly)
+    }
+
+    async fn put_block_many(&self, _blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+        bail!(BlockStoreError::ReadOnly)
+    }
+
+    async fn put_block_keyed(&self, _cid: Cid, _bytes: Vec<u8>) -> Result<()> {
+        bail!(BlockStoreError::ReadOnly)
+    }
+
+    async fn delete_block(&self, _cid: &Cid) -> Result<()> {
+        bail!(BlockStoreError::ReadOnly)
+    }
+
+    async fn get_block_stream(&self, cid: &Cid) -> Result<Pin<Box<dyn AsyncRead>>> {
+        self.inner.get_block_stream(cid).await
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.inner.list_blocks().await
+    }
+
+    async fn prefetch(&self, cids: &[Cid]) {
+        self.inner.prefetch(cids).await
+    }
+
+    fn prefetch_depth(&self) -> usize {
+        self.inner.prefetch_depth()
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.inner.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
+}
+
+/// When and how often `RetryingBlockStore` retries failed operations.
+///
+/// Built with `RetryPolicy::default()` and the builder methods, e.g.
+/// `RetryPolicy::default().max_retries(5).initial_backoff(Duration::from_millis(50))`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    is_transient: fn(&anyhow::Error) -> bool,
+}
+
+/// A block store wrapper that retries reads and writes failing with transient errors.
+///
+/// Retries back off exponentially, starting at the policy's initial backoff and doubling up to its
+/// maximum. Waiting is done through the `sleep` function given on construction, so the wrapper
+/// works with any async runtime, e.g. by passing `async_std::task::sleep`.
+pub struct RetryingBlockStore<B: BlockStore> {
+    inner: B,
+    policy: RetryPolicy,
+    sleep: Box<dyn Fn(Duration) -> LocalBoxFuture<'static, ()>>,
+}
+
+impl RetryPolicy {
+    /// Sets how many times an operation is retried after its first attempt. Defaults to 3.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets how long to wait before the first retry. Defaults to 100ms.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the longest wait between two attempts. Defaults to 10s.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets whether waits are randomly shortened by up to half, so that many clients
+    /// retrying at once spread out. Enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets which errors are worth retrying. Defaults to `is_transient_error`.
+    pub fn retry_if(mut self, is_transient: fn(&anyhow::Error) -> bool) -> Self {
+        self.is_transient = is_transient;
+        self
+    }
+
+    fn delay(&self, backoff: Duration) -> Duration {
+        if !self.jitter {
+            return backoff;
+        }
+
+        let random = RandomState::new().build_hasher().finish();
+        backoff / 2 + backoff.mul_f64((random % 1000) as f64 / 2000.0)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            is_transient: is_transient_error,
+        }
+    }
+}
+
+impl<B: BlockStore> RetryingBlockStore<B> {
+    /// Wraps a block store, retrying with the default policy and waiting with given `sleep` function.
+    pub fn new<F>(inner: B, sleep: impl Fn(Duration) -> F + 'static) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        Self {
+            inner,
+            policy: RetryPolicy::default(),
+            sleep: Box::new(move |duration| Box::pin(sleep(duration))),
+        }
+    }
+
+    /// Replaces the retry policy.
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    async fn retry<T, F>(&self, mut operation: impl FnMut() -> F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut backoff = self.policy.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match operation().await {
+                Err(e) if retries < self.policy.max_retries && (self.policy.is_transient)(&e) => {
+                    retries += 1;
+                    (self.sleep)(self.policy.delay(backoff)).await;
+                    backoff = (backoff * 2).min(self.policy.max_backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<B: BlockStore + Debug> Debug for RetryingBlockStore<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryingBlockStore")
+            .field("inner", &self.inner)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for RetryingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        self.retry(|| self.inner.get_block(cid)).await
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        self.retry(|| self.inner.put_block(bytes.clone(), codec))
+            .await
+    }
+
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        self.retry(|| self.inner.put_block_keyed(cid, bytes.clone()))
+            .await
+    }
+
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        self.inner.delete_block(cid).await
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.inner.list_blocks().await
+    }
+
+    async fn prefetch(&self, cids: &[Cid]) {
+        self.inner.prefetch(cids).await
+    }
+
+    fn prefetch_depth(&self) -> usize {
+        self.inner.prefetch_depth()
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.inner.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
+}
+
+/// A block store wrapper that skips writing blocks the inner store already has.
+///
+/// CIDs of written blocks are remembered in a Bloom filter. Since the filter may report false
+/// positives and blocks may have been deleted since, a block is only skipped after `has_block`
+/// confirms the inner store still has it.
+#[derive(Debug)]
+pub struct DedupBlockStore<B: BlockStore> {
+    inner: B,
+    filter: RefCell<BloomFilter>,
+    skipped: Cell<usize>,
+}
+
+/// A Bloom filter over CIDs.
+#[derive(Debug)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    hash_count: u64,
+}
+
+impl<B: BlockStore> DedupBlockStore<B> {
+    /// Wraps a block store, sizing the filter for about `expected_blocks` blocks at a 1% false positive rate.
+    pub fn new(inner: B, expected_blocks: usize) -> Self {
+        Self {
+            inner,
+            filter: RefCell::new(BloomFilter::new(expected_blocks)),
+            skipped: Cell::new(0),
+        }
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// The number of `put_block` calls that didn't reach the inner store.
+    pub fn skipped_writes(&self) -> usize {
+        self.skipped.get()
+    }
+}
+
+impl BloomFilter {
+    fn new(expected_items: usize) -> Self {
+        // About 10 bits and 7 hashes per item give a false positive rate of about 1%
+        let words = (expected_items.max(1) * 10).div_ceil(64);
+        Self {
+            bits: vec![0; words],
+            hash_count: 7,
+        }
+    }
+
+    fn insert(&mut self, cid: &Cid) {
+        for index in self.indices(cid).collect::<Vec<_>>() {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    fn contains(&self, cid: &Cid) -> bool {
+        self.indices(cid)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// Derives the bit indices of a CID by double hashing.
+    fn indices(&self, cid: &Cid) -> impl Iterator<Item = usize> {
+        let [first, second] = [0u8, 1].map(|seed| {
+            let mut hasher = DefaultHasher::new();
+            (seed, cid).hash(&mut hasher);
+            hasher.finish()
+        });
+
+        let len = (self.bits.len() * 64) as u64;
+        (0..self.hash_count)
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(second | 1)) % len) as usize)
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for DedupBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        self.inner.get_block(cid).await
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.create_cid(&bytes, codec)?;
+        let maybe_known = self.filter.borrow().contains(&cid);
+        if maybe_known && self.inner.has_block(&cid).await? {
+            self.skipped.set(self.skipped.get() + 1);
+            return Ok(cid);
+        }
+
+        let cid = self.inner.put_block(bytes, codec).await?;
+        self.filter.borrow_mut().insert(&cid);
+        Ok(cid)
+    }
+
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        self.inner.put_block_keyed(cid, bytes).await?;
+        self.filter.borrow_mut().insert(&cid);
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool> {
+        self.inner.has_block(cid).await
+    }
+
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        self.inner.delete_block(cid).await
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.inner.list_blocks().await
+    }
+
+    async fn prefetch(&self, cids: &[Cid]) {
+        self.inner.prefetch(cids).await
+    }
+
+    fn prefetch_depth(&self) -> usize {
+        self.inner.prefetch_depth()
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.inner.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
+}
+
+/// A block store wrapper that zstd-compresses blocks before handing them to the inner store.
+///
+/// CIDs are always computed over the uncompressed bytes, so they're the same as without the wrapper.
+/// Compressed blocks are stored under their original CID using `put_block_keyed` and prefixed
+/// with a header, so blocks written to the same inner store without compression can still be read.
+/// Blocks that don't get smaller when compressed are stored as they are.
+///
+/// Reading compressed blocks from the inner store directly yields the framed compressed bytes.
+#[cfg(feature = "compression")]
+#[derive(Debug)]
+pub struct CompressingBlockStore<B: BlockStore> {
+    inner: B,
+    level: i32,
+}
+
+#[cfg(feature = "compression")]
+impl<B: BlockStore> CompressingBlockStore<B> {
+    /// The header compressed blocks are prefixed with in the inner store.
+    pub const HEADER: &'static [u8] = b"wnfs/zstd\0";
+
+    /// Wraps a block store, compressing with zstd's default compression level.
+    pub fn new(inner: B) -> Self {
+        Self::with_level(inner, zstd::DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Wraps a block store, compressing with given zstd compression level.
+    pub fn with_level(inner: B, level: i32) -> Self {
+        Self { inner, level }
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Compresses bytes and frames them with the header, unless that doesn't make them smaller.
+    fn compress(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+        let compressed = zstd::bulk::compress(bytes, self.level)?;
+        if Self::HEADER.len() + compressed.len() >= bytes.len() {
+            return Ok(None);
+        }
+
+        let mut framed = Self::HEADER.to_vec();
+        framed.extend(compressed);
+        Ok(Some(framed))
+    }
+
+    fn decompress(cid: &Cid, bytes: &[u8]) -> Option<Vec<u8>> {
+        let compressed = bytes.strip_prefix(Self::HEADER)?;
+        let decompressed = zstd::bulk::decompress(compressed, MAX_BLOCK_SIZE).ok()?;
+        hashes_to(cid, &decompressed).then_some(decompressed)
+    }
+}
+
+#[cfg(feature = "compression")]
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for CompressingBlockStore<B> {
+    /// Retrieves a block from the inner store, decompressing it if it was stored compressed.
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        let bytes = self.inner.get_block(cid).await?;
+        if let Some(decompressed) = Self::decompress(cid, &bytes) {
+            return Ok(Cow::Owned(decompressed));
+        }
+
+        if !hashes_to(cid, &bytes) {
+            bail!(BlockStoreError::CIDMismatch(*cid))
+        }
+
+        Ok(Cow::Owned(bytes.into_owned()))
+    }
+
+    /// Compresses a block and stores it in the inner store under the CID of the uncompressed bytes.
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.create_cid(&bytes, codec)?;
+        match self.compress(&bytes)? {
+            Some(framed) => self.inner.put_block_keyed(cid, framed).await?,
+            None => {
+                self.inner.put_block(bytes, codec).await?;
+            }
+        }
+
+        Ok(cid)
+    }
+
+    /// Compresses a block like `put_block` does and stores it in the inner store under given CID.
+    async fn put_block_keyed(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        let bytes = self.compress(&bytes)?.unwrap_or(bytes);
+        self.inner.put_block_keyed(cid, bytes).await
+    }
+
+    async fn delete_block(&self, cid: &Cid) -> Result<()> {
+        self.inner.delete_block(cid).await
+    }
+
+    async fn prefetch(&self, cids: &[Cid]) {
+        self.inner.prefetch(cids).await
+    }
+
+    fn prefetch_depth(&self) -> usize {
+        self.inner.prefetch_depth()
+    }
+
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.inner.list_blocks().await
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+
+    fn stats(&self) -> Option<BlockStoreStats> {
+        self.inner.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Checks whether an error is likely to go away when retrying, like a timeout or a reset connection.
+///
+/// Missing blocks and other errors from the block store itself are never transient.
+pub fn is_transient_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<io::Error>().is_some_and(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            )
+        })
+    })
+}
+
+/// Checks whether an error says the requested block is missing, as opposed to the block store
+/// failing in any other way.
+pub fn is_not_found_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<BlockStoreError>(),
+        Some(BlockStoreError::NotFound(_))
+    )
+}
+
+/// Checks whether given bytes hash to the multihash of given CID.
+pub(crate) fn hashes_to(cid: &Cid, bytes: &[u8]) -> bool {
+    Code::try_from(cid.hash().code())
+        .map(|code| code.digest(bytes) == *cid.hash())
+        .unwrap_or(false)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+/// The following methods are generic functions that can be used to test any type that conforms to the BlockStore trait.
+/// In utilizing this structure, externally defined types can still test for retrieval, duplication, and serialization compatibility.
+pub async fn bs_retrieval_test<T: BlockStore + Send + 'static>(store: &T) -> Result<()> {
+    // Example objects to insert and remove from the blockstore
+    let first_bytes = vec![1, 2, 3, 4, 5];
+    let second_bytes = b"hello world".to_vec();
+
+    // Insert the objects into the blockstore
+    let first_cid = store.put_serializable(&first_bytes).await?;
+    let second_cid = store.put_serializable(&second_bytes).await?;
+
+    // Retrieve the objects from the blockstore
+    let first_loaded: Vec<u8> = store.get_deserializable(&first_cid).await?;
+    let second_loaded: Vec<u8> = store.get_deserializable(&second_cid).await?;
+
+    // Assert that the objects are the same as the ones we inserted
+    assert_eq!(first_loaded, first_bytes);
+    assert_eq!(second_loaded, second_bytes);
+
+    // Return Ok
+    Ok(())
+}
+
+// Generic function used to test any type that conforms to the BlockStore trait
+pub async fn bs_duplication_test<T: BlockStore + Send + 'static>(store: &T) -> Result<()> {
+    // Example objects to insert and remove from the blockstore
+    let first_bytes = vec![1, 2, 3, 4, 5];
+    let second_bytes = first_bytes.clone();
+
+    // Insert the objects into the blockstore
+    let first_cid = store.put_serializable(&first_bytes).await?;
+    let second_cid = store.put_serializable(&second_bytes).await?;
+
+    // Assert that the two vecs produced the same CID
+    assert_eq!(first_cid, second_cid);
+
+    // Retrieve the objects from the blockstore
+    let first_loaded: Vec<u8> = store.get_deserializable(&first_cid).await?;
+    let second_loaded: Vec<u8> = store.get_deserializable(&second_cid).await?;
+
+    // Assert that the objects are the same as the ones we inserted
+    assert_eq!(first_loaded, first_bytes);
+    assert_eq!(second_loaded, second_bytes);
+    // Assert that the objects we loaded are the same
+    assert_eq!(first_loaded, second_loaded);
+    // Return Ok
+    Ok(())
+}
+
+pub async fn bs_serialization_test<
+    T: BlockStore + Send + Serialize + 'static + for<'de> Deserialize<'de>,
+>(
+    store: &T,
+) -> Result<()> {
+    // Example objects to insert and remove from the blockstore
+    let bytes = vec![1, 2, 3, 4, 5];
+    // Insert the object into the blockstore
+    let cid = store.put_serializable(&bytes).await?;
+    // Serialize the BlockStore
+    let serial_store: Vec<u8> = dagcbor::encode(&store)?;
+    // Construct a new BlockStore from the Serialized object
+    let deserial_store: T = dagcbor::decode(&serial_store)?;
+    // Retrieve the object from the blockstore
+    let loaded: Vec<u8> = deserial_store.get_deserializable(&cid).await?;
+    // Assert that the objects are the same as the ones we inserted
+    assert_eq!(loaded, bytes);
+    // Return Ok
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use anyhow::Result;
+    use futures::AsyncReadExt;
+    use std::rc::Rc;
+
+    #[async_std::test]
+    async fn memory_blockstore() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+        bs_retrieval_test(store).await?;
+        bs_duplication_test(store).await?;
+        bs_serialization_test(store).await?;
+        Ok(())
+    }
+
+    /// A store that only accepts the codecs WNFS itself writes.
+    struct HardenedBlockStore(MemoryBlockStore);
+
+    #[async_trait(?Send)]
+    impl BlockStore for HardenedBlockStore {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+            self.0.get_block(cid).await
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+            let cid = self.create_cid(&bytes, codec)?;
+            self.0.put_block(bytes, codec).await?;
+            Ok(cid)
+        }
+
+        fn allowed_codecs(&self) -> &[IpldCodec] {
+            &[IpldCodec::Raw, IpldCodec::DagCbor]
+        }
+
+        fn decode_limits(&self) -> DecodeLimits {
+            DecodeLimits {
+                max_depth: 8,
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Checks that a store wrapping a `HardenedBlockStore` holds blocks to the same limits.
+    fn assert_hardening_forwarded(store: &impl BlockStore) {
+        let hardened = HardenedBlockStore(MemoryBlockStore::new());
+        assert_eq!(store.allowed_codecs(), hardened.allowed_codecs());
+        assert_eq!(store.decode_limits(), hardened.decode_limits());
+    }
+
+    #[async_std::test]
+    async fn disallowed_codecs_are_rejected() -> Result<()> {
+        let store = &HardenedBlockStore(MemoryBlockStore::new());
+
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+
+        let cid = store.put_block(b"raw".to_vec(), IpldCodec::Raw).await?;
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"raw");
+
+        let error = store
+            .put_block(b"{}".to_vec(), IpldCodec::DagJson)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<BlockStoreError>(),
+            Some(BlockStoreError::DisallowedCodec(0x0129))
+        ));
+
+        let json_cid = store.0.create_cid(&b"{}".to_vec(), IpldCodec::DagJson)?;
+        assert!(store.0.get_block(&json_cid).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_deserializable_enforces_decode_limits() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let mut nested = vec![0x81; 1000];
+        nested.push(0x00);
+        let cid = store.put_block(nested, IpldCodec::DagCbor).await?;
+
+        let error = store
+            .get_deserializable::<libipld::Ipld>(&cid)
+            .await
+            .unwrap_err();
+
+        assert!(error.downcast_ref::<crate::DecodeError>().is_some());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn prefetching_blockstore() -> Result<()> {
+        let store = &PrefetchingBlockStore::new(MemoryBlockStore::new(), 4);
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+        bs_retrieval_test(store).await?;
+        bs_duplication_test(store).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn prefetching_blockstore_forwards_limits() {
+        let store = PrefetchingBlockStore::new(HardenedBlockStore(MemoryBlockStore::new()), 4);
+        assert_hardening_forwarded(&store);
+    }
+
+    #[async_std::test]
+    async fn prefetch_is_best_effort() -> Result<()> {
+        let store = PrefetchingBlockStore::new(MemoryBlockStore::new(), 4);
+        let cid = store
+            .put_block(b"prefetched".to_vec(), IpldCodec::Raw)
+            .await?;
+        let missing_cid = store.create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+
+        store.prefetch(&[cid, missing_cid]).await;
+        assert!(store.cache.borrow().contains_key(&cid));
+        assert!(!store.cache.borrow().contains_key(&missing_cid));
+
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"prefetched");
+        assert!(store.cache.borrow().is_empty());
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"prefetched");
+        assert!(store.get_block(&missing_cid).await.is_err());
+
+        Ok(())
+    }
+
+    /// A store that can be told to reject all writes.
+    struct FlakyBlockStore {
+        inner: MemoryBlockStore,
+        fail_writes: bool,
+    }
+
+    impl FlakyBlockStore {
+        fn new(fail_writes: bool) -> Self {
+            Self {
+                inner: MemoryBlockStore::new(),
+                fail_writes,
+            }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl BlockStore for FlakyBlockStore {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+            self.inner.get_block(cid).await
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+            if self.fail_writes {
+                bail!("replica is unavailable");
+            }
+
+            self.inner.put_block(bytes, codec).await
+        }
+
+        async fn delete_block(&self, cid: &Cid) -> Result<()> {
+            if self.fail_writes {
+                bail!("replica is unavailable");
+            }
+
+            self.inner.delete_block(cid).await
+        }
+    }
+
+    #[async_std::test]
+    async fn replicating_blockstore() -> Result<()> {
+        let replicas = (0..3).map(|_| FlakyBlockStore::new(false)).collect();
+        let store = &ReplicatingBlockStore::new(replicas, 3)?;
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+        bs_retrieval_test(store).await?;
+        bs_duplication_test(store).await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn replicating_blockstore_enforces_quorum() -> Result<()> {
+        let replicas = vec![
+            FlakyBlockStore::new(false),
+            FlakyBlockStore::new(true),
+            FlakyBlockStore::new(false),
+        ];
+
+        let store = ReplicatingBlockStore::new(replicas, 2)?;
+        let cid = store
+            .put_block(b"replicated".to_vec(), IpldCodec::Raw)
+            .await?;
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"replicated");
+        assert!(store.replicas()[0].get_block(&cid).await.is_ok());
+        assert!(store.replicas()[1].get_block(&cid).await.is_err());
+        assert!(store.replicas()[2].get_block(&cid).await.is_ok());
+
+        let store = ReplicatingBlockStore::new(store.replicas, 3)?;
+        let error = store
+            .put_block(b"unreplicated".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap_err();
+
+        match error.downcast_ref::<BlockStoreError>() {
+            Some(BlockStoreError::QuorumNotReached {
+                required,
+                succeeded,
+                failed,
+            }) => {
+                assert_eq!(*required, 3);
+                assert_eq!(*succeeded, 2);
+                assert_eq!(failed, &vec![1]);
+            }
+            _ => panic!("unexpected error: {error}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn replicating_blockstore_forwards_limits() -> Result<()> {
+        let replicas = vec![HardenedBlockStore(MemoryBlockStore::new())];
+        let store = ReplicatingBlockStore::new(replicas, 1)?;
+        assert_hardening_forwarded(&store);
+        Ok(())
+    }
+
+    #[test]
+    fn replicating_blockstore_rejects_unreachable_quorums() {
+        for quorum in [0, 3] {
+            let replicas = (0..2).map(|_| MemoryBlockStore::new()).collect();
+            let error = ReplicatingBlockStore::new(replicas, quorum).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<BlockStoreError>(),
+                Some(BlockStoreError::InvalidQuorum { quorum: q, replicas: 2 }) if *q == quorum
+            ));
+        }
+
+        assert!(ReplicatingBlockStore::<MemoryBlockStore>::new(vec![], 0).is_err());
+    }
+
+    #[async_std::test]
+    async fn bounded_store_evicts_least_recently_inserted_first() -> Result<()> {
+        let evicted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let store = &MemoryBlockStore::with_capacity(30).on_evict({
+            let evicted = Arc::clone(&evicted);
+            move |cid, bytes| evicted.lock().unwrap().push((cid, bytes))
+        });
+
+        let first = store.put_block(vec![1; 10], IpldCodec::Raw).await?;
+        let second = store.put_block(vec![2; 10], IpldCodec::Raw).await?;
+        let third = store.put_block(vec![3; 10], IpldCodec::Raw).await?;
+        assert!(evicted.lock().unwrap().is_empty());
+
+        let fourth = store.put_block(vec![4; 15], IpldCodec::Raw).await?;
+
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            vec![(first, vec![1; 10]), (second, vec![2; 10])]
+        );
+        assert!(matches!(
+            store.get_block(&first).await.unwrap_err().downcast_ref(),
+            Some(BlockStoreError::NotFound(cid)) if *cid == first
+        ));
+        assert!(store.get_block(&second).await.is_err());
+        assert_eq!(store.get_block(&third).await?.as_ref(), &vec![3; 10]);
+        assert_eq!(store.get_block(&fourth).await?.as_ref(), &vec![4; 15]);
+        assert_eq!(store.stored_bytes(), 25);
+
+        let too_large = store.put_block(vec![5; 31], IpldCodec::Raw).await;
+        assert!(too_large.is_err());
+        assert_eq!(store.stored_bytes(), 25);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bounded_store_accounts_for_overwrites_and_deletes() -> Result<()> {
+        let store = &MemoryBlockStore::with_capacity(30);
+        let first = store.put_block(vec![1; 10], IpldCodec::Raw).await?;
+        let second = store.put_block(vec![2; 10], IpldCodec::Raw).await?;
+
+        // Overwriting the same CID doesn't count its bytes twice, but makes it the newest block
+        for _ in 0..10 {
+            store.put_block(vec![1; 10], IpldCodec::Raw).await?;
+        }
+        assert_eq!(store.stored_bytes(), 20);
+
+        store.put_block(vec![3; 10], IpldCodec::Raw).await?;
+        assert_eq!(store.stored_bytes(), 30);
+
+        store.put_block(vec![4; 10], IpldCodec::Raw).await?;
+        assert!(store.get_block(&second).await.is_err());
+        assert!(store.get_block(&first).await.is_ok());
+
+        // Deleted bytes free up room again
+        store.delete_block(&first).await?;
+        store.put_block(vec![5; 10], IpldCodec::Raw).await?;
+        assert_eq!(store.list_blocks().await?.len(), 3);
+        assert_eq!(store.stored_bytes(), 30);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn block_streams_yield_the_block_bytes() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let bytes = (0..MAX_BLOCK_SIZE)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let cid = store.put_block(bytes, IpldCodec::Raw).await?;
+
+        let mut streamed = Vec::new();
+        let mut stream = store.get_block_stream(&cid).await?;
+        let mut chunk = [0; 4096];
+        loop {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            streamed.extend_from_slice(&chunk[..n]);
+        }
+
+        assert_eq!(&streamed, store.get_block(&cid).await?.as_ref());
+
+        let missing = store.create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+        assert!(store.get_block_stream(&missing).await.is_err());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn deleted_blocks_are_gone() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let cid = store.put_block(b"deleted".to_vec(), IpldCodec::Raw).await?;
+        let kept_cid = store.put_block(b"kept".to_vec(), IpldCodec::Raw).await?;
+
+        store.delete_block(&cid).await?;
+
+        assert!(store.get_block(&cid).await.is_err());
+        assert_eq!(store.get_block(&kept_cid).await?.as_ref(), b"kept");
+
+        // Deleting again is a no-op
+        store.delete_block(&cid).await?;
+        assert_eq!(store.list_blocks().await?, vec![kept_cid]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn delete_block_is_unsupported_by_default() -> Result<()> {
+        let store = &HardenedBlockStore(MemoryBlockStore::new());
+        let cid = store.put_block(b"kept".to_vec(), IpldCodec::Raw).await?;
+
+        let error = store.delete_block(&cid).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<BlockStoreError>(),
+            Some(BlockStoreError::Unsupported("delete_block"))
+        ));
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"kept");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn replicating_blockstore_deletes_from_all_replicas() -> Result<()> {
+        let replicas = (0..3).map(|_| FlakyBlockStore::new(false)).collect();
+        let store = ReplicatingBlockStore::new(replicas, 3)?;
+        let cid = store
+            .put_block(b"replicated".to_vec(), IpldCodec::Raw)
+            .await?;
+
+        store.delete_block(&cid).await?;
+
+        assert!(store.get_block(&cid).await.is_err());
+        for replica in store.replicas() {
+            assert!(replica.get_block(&cid).await.is_err());
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn memory_blockstore_counts_reads_and_writes() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+
+        let mut cids = vec![];
+        for i in 0..5u8 {
+            cids.push(store.put_block(vec![i; 10], IpldCodec::Raw).await?);
+        }
+        for cid in cids.iter().take(3) {
+            store.get_block(cid).await?;
+        }
+        let missing = store.create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+        assert!(store.get_block(&missing).await.is_err());
+
+        assert_eq!(
+            store.stats(),
+            Some(BlockStoreStats {
+                blocks_read: 3,
+                blocks_written: 5,
+                bytes_read: 30,
+                bytes_written: 50,
+            })
+        );
+
+        let cached = CachedBlockStore::new(store.clone(), 1024);
+        assert_eq!(cached.stats(), store.stats());
+
+        store.reset_stats();
+        assert_eq!(store.stats(), Some(BlockStoreStats::default()));
+
+        let restored: MemoryBlockStore = dagcbor::decode(&dagcbor::encode(store)?)?;
+        assert_eq!(restored.get_block(&cids[0]).await?.as_ref(), &vec![0; 10]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn put_block_many_preserves_order() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let blocks = (0..10u8)
+            .map(|i| {
+                let codec = if i % 2 == 0 {
+                    IpldCodec::Raw
+                } else {
+                    IpldCodec::DagCbor
+                };
+                (vec![i; i as usize + 1], codec)
+            })
+            .collect::<Vec<_>>();
+
+        let cids = store.put_block_many(blocks.clone()).await?;
+
+        assert_eq!(cids.len(), blocks.len());
+        for (cid, (bytes, codec)) in cids.iter().zip(blocks) {
+            assert_eq!(*cid, store.create_cid(&bytes, codec)?);
+            assert_eq!(store.get_block(cid).await?.as_ref(), &bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Counts how many blocks get read and written through it.
+    #[derive(Default)]
+    struct CountingBlockStore {
+        inner: MemoryBlockStore,
+        gets: Cell<usize>,
+        puts: Cell<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl BlockStore for CountingBlockStore {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+            self.gets.set(self.gets.get() + 1);
+            self.inner.get_block(cid).await
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+            self.puts.set(self.puts.get() + 1);
+            self.inner.put_block(bytes, codec).await
+        }
+
+        async fn delete_block(&self, cid: &Cid) -> Result<()> {
+            self.inner.delete_block(cid).await
+        }
+    }
+
+    #[async_std::test]
+    async fn dedup_blockstore_writes_identical_blocks_once() -> Result<()> {
+        let store = DedupBlockStore::new(CountingBlockStore::default(), 100);
+
+        let cid = store.put_block(b"same".to_vec(), IpldCodec::Raw).await?;
+        for _ in 0..5 {
+            assert_eq!(
+                store.put_block(b"same".to_vec(), IpldCodec::Raw).await?,
+                cid
+            );
+        }
+        assert_eq!((store.inner.puts.get(), store.skipped_writes()), (1, 5));
+
+        for i in 0..50u8 {
+            store.put_block(vec![i; 8], IpldCodec::Raw).await?;
+        }
+        assert_eq!(store.inner.puts.get(), 51);
+
+        // Deleted blocks are written again despite being in the filter
+        store.delete_block(&cid).await?;
+        store.put_block(b"same".to_vec(), IpldCodec::Raw).await?;
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"same");
+        assert_eq!(store.into_inner().puts.get(), 52);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn thread_safe_blockstore() -> Result<()> {
+        let store = &Arc::new(ThreadSafeMemoryBlockStore::new());
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+        bs_retrieval_test(store).await?;
+        bs_duplication_test(store).await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn thread_safe_blockstore_is_shared_between_tasks() -> Result<()> {
+        let store: Arc<dyn ThreadSafeBlockStore> = Arc::new(ThreadSafeMemoryBlockStore::new());
+
+        let tasks = (0..8u8).map(|i| {
+            let store = Arc::clone(&store);
+            async_std::task::spawn(async move {
+                let store = store.as_ref();
+                let mut cids = Vec::new();
+                for j in 0..10u8 {
+                    cids.push(store.put_block(vec![i, j], IpldCodec::Raw).await?);
+                }
+                for (j, cid) in cids.iter().enumerate() {
+                    assert_eq!(store.get_block(cid).await?, vec![i, j as u8]);
+                }
+                Ok::<_, anyhow::Error>(cids)
+            })
+        });
+
+        let cids = future::try_join_all(tasks).await?;
+        for (i, cid) in cids.iter().flatten().enumerate() {
+            let bytes = vec![(i / 10) as u8, (i % 10) as u8];
+            assert_eq!(store.get_block(cid).await?.as_ref(), &bytes);
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn cached_blockstore() -> Result<()> {
+        let store = &CachedBlockStore::new(MemoryBlockStore::new(), MAX_BLOCK_SIZE);
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+        bs_retrieval_test(store).await?;
+        bs_duplication_test(store).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn cached_blockstore_forwards_limits() {
+        let store = CachedBlockStore::new(HardenedBlockStore(MemoryBlockStore::new()), 1024);
+        assert_hardening_forwarded(&store);
+    }
+
+    #[async_std::test]
+    async fn cached_blockstore_serves_repeated_reads_from_cache() -> Result<()> {
+        let inner = CountingBlockStore::default();
+        let cid = inner.put_block(b"cached".to_vec(), IpldCodec::Raw).await?;
+        let store = CachedBlockStore::new(inner, 1024);
+
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"cached");
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"cached");
+        assert_eq!((store.cache_hits(), store.cache_misses()), (1, 1));
+
+        let written_cid = store.put_block(b"written".to_vec(), IpldCodec::Raw).await?;
+        assert_eq!(store.get_block(&written_cid).await?.as_ref(), b"written");
+        assert_eq!((store.cache_hits(), store.cache_misses()), (2, 1));
+
+        assert_eq!(store.into_inner().gets.get(), 1);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn cached_blockstore_evicts_least_recently_used() -> Result<()> {
+        let store = CachedBlockStore::new(MemoryBlockStore::new(), 20);
+        let first = store.put_block(vec![1; 8], IpldCodec::Raw).await?;
+        let second = store.put_block(vec![2; 8], IpldCodec::Raw).await?;
+
+        // Using the first block makes the second the least recently used one
+        store.get_block(&first).await?;
+        let third = store.put_block(vec![3; 8], IpldCodec::Raw).await?;
+        assert_eq!(store.cached_size(), 16);
+
+        store.get_block(&first).await?;
+        store.get_block(&third).await?;
+        assert_eq!((store.cache_hits(), store.cache_misses()), (3, 0));
+
+        store.get_block(&second).await?;
+        assert_eq!(store.cache_misses(), 1);
+
+        // Blocks bigger than the capacity are never cached
+        let big = store.put_block(vec![4; 21], IpldCodec::Raw).await?;
+        store.delete_block(&first).await?;
+        assert!(store.get_block(&first).await.is_err());
+        assert!(store.cached_size() <= 20);
+        store.get_block(&big).await?;
+        assert_eq!(store.cache_misses(), 3);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn hash_code_determines_cid_multihash() -> Result<()> {
+        let bytes = b"hashed".to_vec();
+        for code in [Code::Sha2_256, Code::Blake3_256, Code::Sha3_256] {
+            let store = &MemoryBlockStore::new().with_hash_code(code);
+            let cid = store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+
+            assert_eq!(cid.hash(), &code.digest(&bytes));
+            assert_eq!(cid, Cid::new_v1(IpldCodec::Raw.into(), code.digest(&bytes)));
+            assert_eq!(store.get_block(&cid).await?.as_ref(), &bytes);
+
+            let wrapped = &CachedBlockStore::new(store.clone(), 1024);
+            assert_eq!(wrapped.create_cid(&bytes, IpldCodec::Raw)?, cid);
+        }
+
+        let default_cid = MemoryBlockStore::new().create_cid(&bytes, IpldCodec::Raw)?;
+        assert_eq!(default_cid.hash().code(), u64::from(Code::Sha2_256));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn tiered_blockstore() -> Result<()> {
+        let store = &TieredBlockStore::new(MemoryBlockStore::new(), MemoryBlockStore::new());
+        let report = testing::conformance(store).await;
+        assert!(report.passed(), "{report}");
+        bs_retrieval_test(store).await?;
+        bs_duplication_test(store).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn tiered_blockstore_forwards_limits() {
+        let store = TieredBlockStore::new(
+            MemoryBlockStore::new(),
+            HardenedBlockStore(MemoryBlockStore::new()),
+        );
+        assert_hardening_forwarded(&store);
+    }
+
+    #[async_std::test]
+    async fn tiered_blockstore_promotes_blocks_read_from_slow_tier() -> Result<()> {
+        let slow = CountingBlockStore::default();
+        let cid = slow.put_block(b"durable".to_vec(), IpldCodec::Raw).await?;
+        let store = TieredBlockStore::new(MemoryBlockStore::new(), slow);
+        assert!(store.fast().get_block(&cid).await.is_err());
+
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"durable");
+        assert_eq!(store.fast().get_block(&cid).await?.as_ref(), b"durable");
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"durable");
+        assert_eq!(store.slow().gets.get(), 1);
+
+        let written = store.put_block(b"written".to_vec(), IpldCodec::Raw).await?;
+        assert_eq!(store.fast().get_block(&written).await?.as_ref(), b"written");
+        assert_eq!(
+            store.slow().inner.get_block(&written).await?.as_ref(),
+            b"written"
+        );
+        assert_eq!(store.fast().list_blocks().await?.len(), 2);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn tiered_blockstore_checks_blocks_without_fetching() -> Result<()> {
+        let store = TieredBlockStore::new(MemoryBlockStore::new(), MemoryBlockStore::new());
+        let slow_cid = store
+            .slow()
+            .put_block(b"durable".to_vec(), IpldCodec::Raw)
+            .await?;
+        let cid = store.put_block(b"both".to_vec(), IpldCodec::Raw).await?;
+        let missing_cid = store.create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+        store.reset_stats();
+
+        assert!(store.has_block(&cid).await?);
+        assert!(store.has_block(&slow_cid).await?);
+        assert!(!store.has_block(&missing_cid).await?);
+
+        assert_eq!(store.fast().stats().unwrap().blocks_read, 0);
+        assert_eq!(store.slow().stats().unwrap().blocks_read, 0);
+        assert!(!store.fast().has_block(&slow_cid).await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read_only_blockstore_rejects_writes() -> Result<()> {
+        let inner = MemoryBlockStore::new();
+        let cid = inner
+            .put_block(b"existing".to_vec(), IpldCodec::Raw)
+            .await?;
+        let store = ReadOnlyBlockStore::new(inner);
+
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"existing");
+        assert_eq!(store.list_blocks().await?, vec![cid]);
+
+        let is_read_only =
+            |error: anyhow::Error| matches!(error.downcast_ref(), Some(BlockStoreError::ReadOnly));
+        let bytes = b"new".to_vec();
+        let put = store.put_block(bytes.clone(), IpldCodec::Raw).await;
+        assert!(is_read_only(put.unwrap_err()));
+        let put_many = store.put_block_many(vec![(bytes.clone(), IpldCodec::Raw)]);
+        assert!(is_read_only(put_many.await.unwrap_err()));
+        let put_keyed = store.put_block_keyed(cid, bytes.clone()).await;
+        assert!(is_read_only(put_keyed.unwrap_err()));
+        assert!(is_read_only(store.delete_block(&cid).await.unwrap_err()));
+
+        // Computing CIDs doesn't write anything
+        let new_cid = store.create_cid(&bytes, IpldCodec::Raw)?;
+        assert!(store.get_block(&new_cid).await.is_err());
+        assert_eq!(
+            store.into_inner().get_block(&cid).await?.as_ref(),
+            b"existing"
+        );
+        Ok(())
+    }
+
+    /// A store whose reads fail with a reset connection a given number of times before succeeding.
+    #[derive(Default)]
+    struct UnreliableBlockStore {
+        inner: MemoryBlockStore,
+        failures: Cell<usize>,
+        attempts: Cell<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl BlockStore for UnreliableBlockStore {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+            self.attempts.set(self.attempts.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(io::Error::from(io::ErrorKind::ConnectionReset).into());
+            }
+
+            self.inner.get_block(cid).await
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+            self.inner.put_block(bytes, codec).await
+        }
+    }
+
+    #[async_std::test]
+    async fn has_block_only_treats_missing_blocks_as_absent() -> Result<()> {
+        let store = UnreliableBlockStore::default();
+        let cid = store.put_block(b"present".to_vec(), IpldCodec::Raw).await?;
+        let missing_cid = store.create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+
+        assert!(store.has_block(&cid).await?);
+        assert!(!store.has_block(&missing_cid).await?);
+
+        store.failures.set(1);
+        let error = store.has_block(&cid).await.unwrap_err();
+        assert!(!is_not_found_error(&error));
+        assert!(is_transient_error(&error));
+        Ok(())
+    }
+
+    #[test]
+    fn retrying_blockstore_forwards_limits() {
+        let store = RetryingBlockStore::new(HardenedBlockStore(MemoryBlockStore::new()), |_| {
+            future::ready(())
+        });
+        assert_hardening_forwarded(&store);
+    }
+
+    #[async_std::test]
+    async fn retrying_blockstore_retries_transient_errors_with_backoff() -> Result<()> {
+        let inner = UnreliableBlockStore::default();
+        let cid = inner
+            .put_block(b"eventually".to_vec(), IpldCodec::Raw)
+            .await?;
+        inner.failures.set(2);
+
+        let sleeps = Rc::new(RefCell::new(Vec::new()));
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(10))
+            .max_backoff(Duration::from_millis(15))
+            .jitter(false);
+        let store = RetryingBlockStore::new(inner, {
+            let sleeps = Rc::clone(&sleeps);
+            move |duration| {
+                sleeps.borrow_mut().push(duration);
+                future::ready(())
+            }
+        })
+        .with_policy(policy);
+
+        assert_eq!(store.get_block(&cid).await?.as_ref(), b"eventually");
+        assert_eq!(
+            *sleeps.borrow(),
+            vec![Duration::from_millis(10), Duration::from_millis(15)]
+        );
+
+        // Missing blocks aren't retried
+        let missing = store.create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+        assert!(store.get_block(&missing).await.is_err());
+        assert_eq!(sleeps.borrow().len(), 2);
+
+        // Giving up after the maximum number of retries
+        let store = store.with_policy(RetryPolicy::default().max_retries(1).jitter(false));
+        store.inner.failures.set(2);
+        assert!(store.get_block(&cid).await.is_err());
+        assert_eq!(store.into_inner().attempts.get(), 6);
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    mod compression {
+        use super::*;
+
+        #[async_std::test]
+        async fn compressing_blockstore() -> Result<()> {
+            let store = &CompressingBlockStore::new(MemoryBlockStore::new());
+            let report = testing::conformance(store).await;
+            assert!(report.passed(), "{report}");
+            bs_retrieval_test(store).await?;
+            bs_duplication_test(store).await?;
+            Ok(())
+        }
+
+        #[test]
+        fn compressing_blockstore_forwards_limits() {
+            let store = CompressingBlockStore::new(HardenedBlockStore(MemoryBlockStore::new()));
+            assert_hardening_forwarded(&store);
+        }
+
+        #[async_std::test]
+        async fn compressed_blocks_round_trip_under_uncompressed_cids() -> Result<()> {
+            let store = &CompressingBlockStore::new(MemoryBlockStore::new());
+            let plain_store = &MemoryBlockStore::new();
+
+            let text = b"all work and no play makes jack a dull boy. ".repeat(100);
+            let noise = (0..255u8).collect::<Vec<_>>();
+
+            for bytes in [text, noise] {
+                let cid = store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+                let plain_cid = plain_store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+
+                assert_eq!(cid, plain_cid);
+                assert_eq!(store.get_block(&cid).await?.as_ref(), &bytes);
+            }
+
+            Ok(())
+        }
+
+        #[async_std::test]
+        async fn compression_is_framed_in_the_inner_store() -> Result<()> {
+            let store = CompressingBlockStore::new(MemoryBlockStore::new());
+
+            let text = b"all work and no play makes jack a dull boy. ".repeat(100);
+            let cid = store.put_block(text.clone(), IpldCodec::Raw).await?;
+            let plain_cid = store
+                .inner
+                .put_block(b"stored without compression".to_vec(), IpldCodec::Raw)
+                .await?;
+
+            let stored = store.inner.get_block(&cid).await?;
+            assert!(stored.starts_with(CompressingBlockStore::<MemoryBlockStore>::HEADER));
+            assert!(stored.len() < text.len());
+
+            assert_eq!(
+                store.get_block(&plain_cid).await?.as_ref(),
+                b"stored without compression"
+            );
+
+            // Blocks stored under a given CID get compressed the same way
+            let keyed_text = b"the quick brown fox jumps over the lazy dog. ".repeat(100);
+            let keyed_cid = store.create_cid(&keyed_text, IpldCodec::Raw)?;
+            store.put_block_keyed(keyed_cid, keyed_text.clone()).await?;
+            let keyed_stored = store.inner.get_block(&keyed_cid).await?;
+            assert!(keyed_stored.starts_with(CompressingBlockStore::<MemoryBlockStore>::HEADER));
+            assert!(keyed_stored.len() < keyed_text.len());
+            assert_eq!(store.get_block(&keyed_cid).await?.as_ref(), &keyed_text);
+
+            let mut corrupted = stored.into_owned();
+            corrupted.truncate(corrupted.len() - 1);
+            store.inner.put_block_keyed(cid, corrupted).await?;
+
+            let error = store.get_block(&cid).await.unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<BlockStoreError>(),
+                Some(BlockStoreError::CIDMismatch(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    #[async_std::test]
+    async fn missing_blocks_are_reported_as_not_found() -> Result<()> {
+        let cid = MemoryBlockStore::new().create_cid(&b"missing".to_vec(), IpldCodec::Raw)?;
+        let is_missing = |result: Result<Cow<Vec<u8>>>| {
+            matches!(
+                result.unwrap_err().downcast_ref(),
+                Some(BlockStoreError::NotFound(missing)) if *missing == cid
+            )
+        };
+
+        assert!(is_missing(MemoryBlockStore::new().get_block(&cid).await));
+        let thread_safe = Arc::new(ThreadSafeMemoryBlockStore::new());
+        assert!(is_missing(thread_safe.get_block(&cid).await));
+
+        let replicas = (0..2).map(|_| UnreliableBlockStore::default()).collect();
+        let store = ReplicatingBlockStore::new(replicas, 1).unwrap();
+        assert!(is_missing(store.get_block(&cid).await));
+
+        // Any other failure isn't mistaken for a missing block
+        store.replicas()[1].failures.set(1);
+        let error = store.get_block(&cid).await.unwrap_err();
+        assert!(!is_not_found_error(&error));
+        assert!(is_transient_error(&error));
         Ok(())
     }
 }
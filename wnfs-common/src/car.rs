@@ -0,0 +1,498 @@
+use crate::{
+    blockstore::hashes_to,
+    dagcbor::{self, DecodeLimits},
+    BlockStore, BlockStoreError, CarError, MAX_BLOCK_SIZE,
+};
+use anyhow::{bail, Result};
+use libipld::{Cid, Ipld, IpldCodec};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{self, BufReader, Cursor, ErrorKind, Read, Write},
+    path::Path,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The bytes every CARv2 starts with, a CARv1 header section of `{"version": 2}`.
+const CARV2_PRAGMA: [u8; 11] = [
+    0x0a, 0xa1, 0x67, b'v', b'e', b'r', b's', b'i', b'o', b'n', 0x02,
+];
+
+/// The size of the fixed CARv2 header following the pragma.
+const CARV2_HEADER_SIZE: usize = 40;
+
+/// The multicodec of the `IndexSorted` CARv2 index format.
+const INDEX_SORTED_CODEC: u64 = 0x0400;
+
+/// An upper bound on the size of a CID: version, codec, hash code and digest length varints
+/// followed by a digest of at most 64 bytes.
+const MAX_CID_SIZE: usize = 4 * 10 + 64;
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Imports all blocks of the CAR file at given path into a block store and returns the file's roots.
+///
+/// Every block is checked against its CID before it is put into the store, which needs to support
+/// `put_block_keyed`.
+pub async fn import_car(path: &Path, store: &impl BlockStore) -> Result<Vec<Cid>> {
+    read_car(BufReader::new(File::open(path)?), store).await
+}
+
+/// Reads a CARv1 or CARv2 from given reader, puts all its blocks into a block store and returns its roots.
+///
+/// Every block is checked against its CID before it is put into the store, which needs to support
+/// `put_block_keyed`. The index of a CARv2 is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{read_car, BlockStore, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     // A CARv1 with no roots and no blocks.
+///     let car = b"\x11\xa2\x65roots\x80\x67version\x01";
+///     let store = &MemoryBlockStore::new();
+///
+///     let roots = read_car(&car[..], store).await.unwrap();
+///
+///     assert!(roots.is_empty());
+/// }
+/// ```
+pub async fn read_car(mut reader: impl Read, store: &impl BlockStore) -> Result<Vec<Cid>> {
+    let limits = store.decode_limits();
+    let header = read_header(&mut reader, &limits)?;
+    if header.get("version") != Some(&Ipld::Integer(2)) {
+        return read_blocks(&header, reader, store).await;
+    }
+
+    let mut v2_header = [0; CARV2_HEADER_SIZE];
+    reader.read_exact(&mut v2_header)?;
+    let data_offset = u64::from_le_bytes(v2_header[16..24].try_into()?);
+    let data_size = u64::from_le_bytes(v2_header[24..32].try_into()?);
+    let Some(padding) = data_offset.checked_sub((CARV2_PRAGMA.len() + CARV2_HEADER_SIZE) as u64)
+    else {
+        bail!(CarError::MalformedHeader);
+    };
+
+    io::copy(&mut (&mut reader).take(padding), &mut io::sink())?;
+    let mut data = reader.take(data_size);
+    let header = read_header(&mut data, &limits)?;
+    read_blocks(&header, data, store).await
+}
+
+/// Writes all blocks reachable from given roots as a CARv2 with an `IndexSorted` index.
+///
+/// Links are only followed through DagCbor blocks. The roots are written into the header of the
+/// inner CARv1.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{export_carv2, read_car, BlockStore, MemoryBlockStore};
+/// use libipld::IpldCodec;
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::new();
+///     let cid = store.put_block(b"hello".to_vec(), IpldCodec::Raw).await.unwrap();
+///
+///     let mut car = Vec::new();
+///     export_carv2(&[cid], store, &mut car).await.unwrap();
+///
+///     let imported = &MemoryBlockStore::new();
+///     assert_eq!(read_car(car.as_slice(), imported).await.unwrap(), vec![cid]);
+///     assert_eq!(imported.get_block(&cid).await.unwrap().as_ref(), b"hello");
+/// }
+/// ```
+pub async fn export_carv2(
+    roots: &[Cid],
+    store: &impl BlockStore,
+    mut out: impl Write,
+) -> Result<()> {
+    let mut data = Vec::new();
+    write_section(&encode_header(roots)?, &mut data);
+
+    // Index entries by digest length, each holding the digest and the offset of its section
+    let mut buckets = BTreeMap::<u32, Vec<(Vec<u8>, u64)>>::new();
+    let mut visited = HashSet::new();
+    let mut stack = roots.iter().rev().cloned().collect::<Vec<_>>();
+    while let Some(cid) = stack.pop() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        let bytes = store.get_block(&cid).await?;
+        let digest = cid.hash().digest().to_vec();
+        buckets
+            .entry(digest.len() as u32 + 8)
+            .or_default()
+            .push((digest, data.len() as u64));
+        write_section(&[cid.to_bytes(), bytes.to_vec()].concat(), &mut data);
+
+        let mut links = Vec::new();
+        if cid.codec() == u64::from(IpldCodec::DagCbor) {
+            dagcbor::decode::<Ipld>(&bytes)?.references(&mut links);
+        }
+        stack.extend(links.into_iter().rev());
+    }
+
+    let mut index = Vec::new();
+    write_varint(INDEX_SORTED_CODEC, &mut index);
+    index.extend((buckets.len() as u32).to_le_bytes());
+    for (width, mut entries) in buckets {
+        entries.sort();
+        index.extend(width.to_le_bytes());
+        index.extend((entries.len() as u64 * width as u64).to_le_bytes());
+        for (digest, offset) in entries {
+            index.extend(digest);
+            index.extend(offset.to_le_bytes());
+        }
+    }
+
+    let data_offset = (CARV2_PRAGMA.len() + CARV2_HEADER_SIZE) as u64;
+    let data_size = data.len() as u64;
+    out.write_all(&CARV2_PRAGMA)?;
+    out.write_all(&[0; 16])?;
+    out.write_all(&data_offset.to_le_bytes())?;
+    out.write_all(&data_size.to_le_bytes())?;
+    out.write_all(&(data_offset + data_size).to_le_bytes())?;
+    out.write_all(&data)?;
+    out.write_all(&index)?;
+    Ok(())
+}
+
+/// Writes given blocks as a CARv1 with given roots, in order and skipping repeated CIDs.
+///
+/// Unlike [`export_carv2`], no links are followed, so the caller decides which blocks go in.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{write_carv1, read_car, BlockStore, MemoryBlockStore};
+/// use libipld::IpldCodec;
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::new();
+///     let cid = store.put_block(b"hello".to_vec(), IpldCodec::Raw).await.unwrap();
+///
+///     let mut car = Vec::new();
+///     write_carv1(&[cid], [cid, cid], store, &mut car).await.unwrap();
+///
+///     let imported = &MemoryBlockStore::new();
+///     assert_eq!(read_car(car.as_slice(), imported).await.unwrap(), vec![cid]);
+///     assert_eq!(imported.get_block(&cid).await.unwrap().as_ref(), b"hello");
+/// }
+/// ```
+pub async fn write_carv1(
+    roots: &[Cid],
+    blocks: impl IntoIterator<Item = Cid>,
+    store: &impl BlockStore,
+    mut out: impl Write,
+) -> Result<()> {
+    let mut section = Vec::new();
+    write_section(&encode_header(roots)?, &mut section);
+    out.write_all(&section)?;
+
+    let mut written = HashSet::new();
+    for cid in blocks {
+        if !written.insert(cid) {
+            continue;
+        }
+
+        let bytes = store.get_block(&cid).await?;
+        section.clear();
+        write_section(&[cid.to_bytes(), bytes.to_vec()].concat(), &mut section);
+        out.write_all(&section)?;
+    }
+
+    Ok(())
+}
+
+/// Encodes the header section of a CARv1 with given roots.
+fn encode_header(roots: &[Cid]) -> Result<Vec<u8>> {
+    let header = Ipld::Map(BTreeMap::from([
+        (
+            "roots".to_string(),
+            Ipld::List(roots.iter().cloned().map(Ipld::Link).collect()),
+        ),
+        ("version".to_string(), Ipld::Integer(1)),
+    ]));
+
+    dagcbor::encode(&header)
+}
+
+/// Reads a DagCbor encoded CAR header section.
+fn read_header(reader: &mut impl Read, limits: &DecodeLimits) -> Result<BTreeMap<String, Ipld>> {
+    let Some(bytes) = read_section(reader, limits.max_bytes)? else {
+        bail!(CarError::MalformedHeader);
+    };
+
+    let Ipld::Map(map) = dagcbor::decode_with_limits(&bytes, limits)? else {
+        bail!(CarError::MalformedHeader);
+    };
+
+    Ok(map)
+}
+
+/// Reads the block sections following a CARv1 header into the store and returns the header's roots.
+async fn read_blocks(
+    header: &BTreeMap<String, Ipld>,
+    mut reader: impl Read,
+    store: &impl BlockStore,
+) -> Result<Vec<Cid>> {
+    let roots = parse_roots(header)?;
+    while let Some(section) = read_section(&mut reader, MAX_BLOCK_SIZE + MAX_CID_SIZE)? {
+        let mut cursor = Cursor::new(section.as_slice());
+        let cid = Cid::read_bytes(&mut cursor)?;
+        let bytes = section[cursor.position() as usize..].to_vec();
+        if !hashes_to(&cid, &bytes) {
+            bail!(BlockStoreError::CIDMismatch(cid));
+        }
+
+        store.put_block_keyed(cid, bytes).await?;
+    }
+
+    Ok(roots)
+}
+
+/// Parses the roots out of a CARv1 header.
+fn parse_roots(map: &BTreeMap<String, Ipld>) -> Result<Vec<Cid>> {
+    match map.get("version") {
+        Some(Ipld::Integer(1)) => {}
+        Some(Ipld::Integer(version)) => bail!(CarError::UnsupportedVersion(*version as u64)),
+        _ => bail!(CarError::MalformedHeader),
+    }
+
+    let Some(Ipld::List(roots)) = map.get("roots") else {
+        bail!(CarError::MalformedHeader);
+    };
+
+    roots
+        .iter()
+        .map(|root| match root {
+            Ipld::Link(cid) => Ok(*cid),
+            _ => bail!(CarError::MalformedHeader),
+        })
+        .collect()
+}
+
+/// Reads a varint length prefixed section of at most `max_len` bytes, or `None` at the end of the
+/// input.
+fn read_section(reader: &mut impl Read, max_len: usize) -> Result<Option<Vec<u8>>> {
+    let Some(len) = read_varint(reader)? else {
+        return Ok(None);
+    };
+
+    if len > max_len as u64 {
+        bail!(CarError::SectionTooLarge(len, max_len));
+    }
+
+    let mut section = vec![0; len as usize];
+    reader.read_exact(&mut section)?;
+    Ok(Some(section))
+}
+
+/// Reads an unsigned LEB128 varint, or `None` at the end of the input.
+fn read_varint(reader: &mut impl Read) -> Result<Option<u64>> {
+    let mut value = 0u64;
+    for i in 0..10 {
+        let mut byte = [0u8];
+        match reader.read_exact(&mut byte) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            result => result?,
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    bail!(CarError::MalformedVarint)
+}
+
+/// Writes a varint length prefixed section.
+fn write_section(section: &[u8], out: &mut Vec<u8>) {
+    write_varint(section.len() as u64, out);
+    out.extend(section);
+}
+
+/// Writes an unsigned LEB128 varint.
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryBlockStore;
+
+    fn write_car(roots: &[Cid], blocks: &[(Cid, Vec<u8>)]) -> Vec<u8> {
+        let header = Ipld::Map(BTreeMap::from([
+            (
+                "roots".to_string(),
+                Ipld::List(roots.iter().cloned().map(Ipld::Link).collect()),
+            ),
+            ("version".to_string(), Ipld::Integer(1)),
+        ]));
+        let header = dagcbor::encode(&header).unwrap();
+
+        let mut car = Vec::new();
+        write_section(&header, &mut car);
+        for (cid, bytes) in blocks {
+            write_section(&[cid.to_bytes(), bytes.clone()].concat(), &mut car);
+        }
+
+        car
+    }
+
+    #[async_std::test]
+    async fn car_blocks_can_be_read_back() {
+        let source = &MemoryBlockStore::new();
+        let mut blocks = Vec::new();
+        for bytes in [b"first".to_vec(), vec![7; 1000], b"third".to_vec()] {
+            let cid = source
+                .put_block(bytes.clone(), IpldCodec::Raw)
+                .await
+                .unwrap();
+            blocks.push((cid, bytes));
+        }
+
+        let car = write_car(&[blocks[0].0], &blocks);
+        let path = std::env::temp_dir().join("wnfs_common_car_import_test.car");
+        std::fs::write(&path, car).unwrap();
+
+        let store = &MemoryBlockStore::new();
+        let roots = import_car(&path, store).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(roots, vec![blocks[0].0]);
+        for (cid, bytes) in blocks {
+            assert_eq!(store.get_block(&cid).await.unwrap().as_ref(), &bytes);
+        }
+    }
+
+    #[async_std::test]
+    async fn car_blocks_with_wrong_cid_are_rejected() {
+        let source = &MemoryBlockStore::new();
+        let cid = source
+            .put_block(b"original".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let car = write_car(&[cid], &[(cid, b"tampered".to_vec())]);
+        let store = &MemoryBlockStore::new();
+        let error = read_car(car.as_slice(), store).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<BlockStoreError>(),
+            Some(BlockStoreError::CIDMismatch(c)) if *c == cid
+        ));
+        assert!(store.get_block(&cid).await.is_err());
+    }
+
+    #[async_std::test]
+    async fn oversized_section_lengths_are_rejected() {
+        let store = &MemoryBlockStore::new();
+        let mut car = write_car(&[], &[]);
+        write_varint(u64::MAX, &mut car);
+
+        let error = read_car(car.as_slice(), store).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CarError>(),
+            Some(CarError::SectionTooLarge(u64::MAX, _))
+        ));
+
+        let mut headerless = Vec::new();
+        write_varint(u64::MAX, &mut headerless);
+        let error = read_car(headerless.as_slice(), store).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CarError>(),
+            Some(CarError::SectionTooLarge(u64::MAX, _))
+        ));
+    }
+
+    #[async_std::test]
+    async fn carv2_export_reimports_and_indexes_reachable_blocks() {
+        let store = &MemoryBlockStore::new();
+        let leaf = store
+            .put_block(b"leaf".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+        let shared = store
+            .put_block(vec![42; 300], IpldCodec::Raw)
+            .await
+            .unwrap();
+        let child = store
+            .put_serializable(&Ipld::List(vec![Ipld::Link(leaf), Ipld::Link(shared)]))
+            .await
+            .unwrap();
+        let root = store
+            .put_serializable(&Ipld::List(vec![Ipld::Link(child), Ipld::Link(shared)]))
+            .await
+            .unwrap();
+        let unreachable = store
+            .put_block(b"unreachable".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let mut car = Vec::new();
+        export_carv2(&[root], store, &mut car).await.unwrap();
+        assert_eq!(car[..CARV2_PRAGMA.len()], CARV2_PRAGMA);
+
+        let imported = &MemoryBlockStore::new();
+        let roots = read_car(car.as_slice(), imported).await.unwrap();
+        assert_eq!(roots, vec![root]);
+        for cid in [root, child, leaf, shared] {
+            assert_eq!(
+                imported.get_block(&cid).await.unwrap(),
+                store.get_block(&cid).await.unwrap()
+            );
+        }
+        assert!(imported.get_block(&unreachable).await.is_err());
+
+        // Every index entry points at the section of the block with that digest
+        let header = &car[CARV2_PRAGMA.len()..];
+        let data_offset = u64::from_le_bytes(header[16..24].try_into().unwrap()) as usize;
+        let index_offset = u64::from_le_bytes(header[32..40].try_into().unwrap()) as usize;
+        let index = &mut &car[index_offset..];
+        assert_eq!(read_varint(index).unwrap(), Some(INDEX_SORTED_CODEC));
+
+        let read_u64 = |index: &mut &[u8], n: usize| {
+            let mut bytes = [0; 8];
+            index.read_exact(&mut bytes[..n]).unwrap();
+            u64::from_le_bytes(bytes) as usize
+        };
+        assert_eq!(read_u64(index, 4), 1);
+        let width = read_u64(index, 4);
+        let count = read_u64(index, 8) / width;
+        assert_eq!(count, 4);
+
+        let mut digests = Vec::new();
+        for _ in 0..count {
+            let mut digest = vec![0; width - 8];
+            index.read_exact(&mut digest).unwrap();
+            let section = &mut &car[data_offset + read_u64(index, 8)..];
+            let section = read_section(section, usize::MAX).unwrap().unwrap();
+            let cid = Cid::read_bytes(section.as_slice()).unwrap();
+            assert_eq!(cid.hash().digest(), digest);
+            digests.push(digest);
+        }
+        assert!(digests.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
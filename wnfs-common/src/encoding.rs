@@ -1,6 +1,6 @@
 /// Helper methods for decoding and encoding values into DagCbor.
 pub mod dagcbor {
-    use crate::{AsyncSerialize, BlockStore};
+    use crate::{AsyncSerialize, BlockStore, DecodeError, MAX_BLOCK_SIZE};
     use anyhow::Result;
     use libipld::{
         cbor::DagCborCodec,
@@ -10,6 +10,31 @@ pub mod dagcbor {
     use serde::{de::DeserializeOwned, Serialize};
     use std::io::Cursor;
 
+    /// Limits enforced when decoding DagCbor from untrusted sources.
+    ///
+    /// Checking these happens in a single non-recursive pass over the bytes,
+    /// before any decoding, so hostile input can't overflow the stack or
+    /// trigger huge allocations.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DecodeLimits {
+        /// The maximum nesting depth of arrays, maps and tags.
+        pub max_depth: usize,
+        /// The maximum number of items in an array or entries in a map.
+        pub max_collection_length: u64,
+        /// The maximum number of bytes to decode.
+        pub max_bytes: usize,
+    }
+
+    impl Default for DecodeLimits {
+        fn default() -> Self {
+            Self {
+                max_depth: 64,
+                max_collection_length: 1 << 16,
+                max_bytes: MAX_BLOCK_SIZE,
+            }
+        }
+    }
+
     /// Encodes a serializable value into DagCbor bytes.
     pub fn encode<S: Serialize>(value: &S) -> Result<Vec<u8>> {
         let ipld = ipld_serde::to_ipld(value)?;
@@ -34,4 +59,241 @@ pub mod dagcbor {
         let ipld = Ipld::decode(DagCborCodec, &mut Cursor::new(bytes))?;
         Ok(ipld_serde::from_ipld::<_>(ipld)?)
     }
+
+    /// Decodes DagCbor bytes into a deserializable value, rejecting input that exceeds given limits.
+    pub fn decode_with_limits<D: DeserializeOwned>(
+        bytes: &[u8],
+        limits: &DecodeLimits,
+    ) -> Result<D> {
+        check_limits(bytes, limits)?;
+        decode(bytes)
+    }
+
+    /// Checks that DagCbor bytes stay within given limits without decoding them.
+    ///
+    /// Returns a `DecodeError` describing the first limit that was exceeded.
+    pub fn check_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<(), DecodeError> {
+        if bytes.len() > limits.max_bytes {
+            return Err(DecodeError::SizeLimitExceeded(bytes.len()));
+        }
+
+        let mut position = 0;
+        // The number of items left to read at each nesting level.
+        let mut remaining = vec![1u64];
+
+        while let Some(count) = remaining.last_mut() {
+            if *count == 0 {
+                remaining.pop();
+                continue;
+            }
+            *count -= 1;
+
+            let start = position;
+            let initial = *bytes.get(position).ok_or(DecodeError::Malformed(start))?;
+            position += 1;
+
+            let major = initial >> 5;
+            let argument = read_argument(bytes, &mut position, initial & 0x1f)
+                .ok_or(DecodeError::Malformed(start))?;
+
+            let items = match major {
+                // Integers
+                0 | 1 => 0,
+                // Byte and text strings
+                2 | 3 => {
+                    position = usize::try_from(argument)
+                        .ok()
+                        .and_then(|len| position.checked_add(len))
+                        .filter(|end| *end <= bytes.len())
+                        .ok_or(DecodeError::Malformed(start))?;
+                    0
+                }
+                // Arrays and maps
+                4 | 5 => {
+                    if argument > limits.max_collection_length {
+                        return Err(DecodeError::CollectionLimitExceeded(argument));
+                    }
+                    if major == 5 {
+                        argument
+                            .checked_mul(2)
+                            .ok_or(DecodeError::CollectionLimitExceeded(argument))?
+                    } else {
+                        argument
+                    }
+                }
+                // Tags wrap exactly one item
+                6 => 1,
+                // Simple values and floats
+                _ => 0,
+            };
+
+            // Every item takes at least one byte, so this also rules out
+            // collections claiming more items than there are bytes left.
+            if items > (bytes.len() - position) as u64 {
+                return Err(DecodeError::Malformed(start));
+            }
+
+            if (4..=6).contains(&major) && items > 0 {
+                if remaining.len() >= limits.max_depth {
+                    return Err(DecodeError::DepthLimitExceeded(limits.max_depth));
+                }
+                remaining.push(items);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the argument following a CBOR initial byte.
+    /// Returns `None` for indefinite lengths, which DagCbor doesn't allow, and truncated input.
+    fn read_argument(bytes: &[u8], position: &mut usize, additional: u8) -> Option<u64> {
+        let size = match additional {
+            0..=23 => return Some(additional as u64),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return None,
+        };
+
+        let argument_bytes = bytes.get(*position..*position + size)?;
+        *position += size;
+
+        Some(
+            argument_bytes
+                .iter()
+                .fold(0u64, |acc, byte| (acc << 8) | *byte as u64),
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Tests
+    //----------------------------------------------------------------------------------------------
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use libipld::Cid;
+        use std::collections::BTreeMap;
+
+        fn nested_arrays(depth: usize) -> Vec<u8> {
+            let mut bytes = vec![0x81; depth];
+            bytes.push(0x00);
+            bytes
+        }
+
+        #[test]
+        fn values_within_limits_decode() {
+            let value = BTreeMap::from([
+                ("list".to_string(), Ipld::List(vec![Ipld::Integer(1); 10])),
+                ("link".to_string(), Ipld::Link(Cid::default())),
+                ("text".to_string(), Ipld::String("hello".into())),
+            ]);
+            let bytes = encode(&value).unwrap();
+
+            let decoded: BTreeMap<String, Ipld> =
+                decode_with_limits(&bytes, &DecodeLimits::default()).unwrap();
+
+            assert_eq!(decoded, value);
+        }
+
+        #[test]
+        fn deeply_nested_input_is_rejected() {
+            let bytes = nested_arrays(100_000);
+
+            let error = decode_with_limits::<Ipld>(&bytes, &DecodeLimits::default()).unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<DecodeError>(),
+                Some(DecodeError::DepthLimitExceeded(64))
+            ));
+        }
+
+        #[test]
+        fn nesting_up_to_the_limit_is_accepted() {
+            let limits = DecodeLimits::default();
+
+            assert!(check_limits(&nested_arrays(limits.max_depth - 1), &limits).is_ok());
+            assert!(check_limits(&nested_arrays(limits.max_depth), &limits).is_err());
+        }
+
+        #[test]
+        fn oversized_collections_are_rejected() {
+            let limits = DecodeLimits {
+                max_collection_length: 4,
+                ..Default::default()
+            };
+            let bytes = encode(&vec![0u8; 5]).unwrap();
+
+            assert!(matches!(
+                check_limits(&bytes, &limits),
+                Err(DecodeError::CollectionLimitExceeded(5))
+            ));
+        }
+
+        #[test]
+        fn collections_claiming_more_items_than_bytes_are_rejected() {
+            // An array claiming 2^32 items followed by nothing.
+            let bytes = [0x9b, 0, 0, 0, 1, 0, 0, 0, 0];
+            let limits = DecodeLimits {
+                max_collection_length: u64::MAX,
+                ..Default::default()
+            };
+
+            assert!(matches!(
+                check_limits(&bytes, &limits),
+                Err(DecodeError::Malformed(0))
+            ));
+        }
+
+        #[test]
+        fn maps_claiming_overflowing_entry_counts_are_rejected() {
+            // A map claiming 2^63 entries followed by nothing.
+            let bytes = [0xbb, 0x80, 0, 0, 0, 0, 0, 0, 0];
+            let limits = DecodeLimits {
+                max_collection_length: u64::MAX,
+                ..Default::default()
+            };
+
+            assert!(matches!(
+                check_limits(&bytes, &limits),
+                Err(DecodeError::CollectionLimitExceeded(argument)) if argument == 1 << 63
+            ));
+        }
+
+        #[test]
+        fn oversized_input_is_rejected() {
+            let limits = DecodeLimits {
+                max_bytes: 8,
+                ..Default::default()
+            };
+            let bytes = encode(&vec![0u8; 16]).unwrap();
+
+            assert!(matches!(
+                check_limits(&bytes, &limits),
+                Err(DecodeError::SizeLimitExceeded(_))
+            ));
+        }
+    }
+
+    #[cfg(test)]
+    mod proptests {
+        use super::*;
+        use proptest::{collection::vec, prelude::*};
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(256))]
+
+            #[test]
+            fn arbitrary_bytes_never_panic(bytes in vec(any::<u8>(), 0..512)) {
+                let _ = decode_with_limits::<Ipld>(&bytes, &DecodeLimits::default());
+            }
+
+            #[test]
+            fn arbitrary_nesting_never_overflows(mut bytes in vec(0x80u8..0xc0, 0..10_000)) {
+                bytes.push(0x00);
+                let _ = decode_with_limits::<Ipld>(&bytes, &DecodeLimits::default());
+            }
+        }
+    }
 }
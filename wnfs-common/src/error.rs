@@ -14,8 +14,72 @@ pub enum BlockStoreError {
     MaximumBlockSizeExceeded(usize),
 
     #[error("Cannot find specified CID in block store: {0}")]
-    CIDNotFound(Cid),
+    NotFound(Cid),
 
     #[error("Lock poisoned")]
     LockPoisoned,
+
+    #[error("Codec {0:#x} is not allowed in this block store")]
+    DisallowedCodec(u64),
+
+    #[error("Operation not supported by this block store: {0}")]
+    Unsupported(&'static str),
+
+    #[error("Block store is read-only")]
+    ReadOnly,
+
+    #[error("Block bytes don't hash to their CID: {0}")]
+    CIDMismatch(Cid),
+
+    #[error("Write quorum not reached: {succeeded} of {required} required replicas succeeded, replicas {failed:?} failed")]
+    QuorumNotReached {
+        required: usize,
+        succeeded: usize,
+        failed: Vec<usize>,
+    },
+
+    #[error("Write quorum of {quorum} is invalid for {replicas} replicas")]
+    InvalidQuorum { quorum: usize, replicas: usize },
+}
+
+/// Errors from reading CAR files.
+#[derive(Debug, Error)]
+pub enum CarError {
+    #[error("Unsupported CAR version: {0}")]
+    UnsupportedVersion(u64),
+
+    #[error("Malformed CAR header")]
+    MalformedHeader,
+
+    #[error("Malformed varint")]
+    MalformedVarint,
+
+    #[error("Expected a single CAR root, found {0}")]
+    UnexpectedRootCount(usize),
+
+    #[error("CAR section of {0} bytes exceeds the limit of {1} bytes")]
+    SectionTooLarge(u64, usize),
+}
+
+/// Metadata errors.
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    #[error("Metadata key is reserved: {0}")]
+    ReservedKey(String),
+}
+
+/// Errors from decoding untrusted DagCbor bytes.
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("Maximum nesting depth of {0} exceeded")]
+    DepthLimitExceeded(usize),
+
+    #[error("Maximum collection length exceeded: Encountered collection with {0} items")]
+    CollectionLimitExceeded(u64),
+
+    #[error("Maximum decode size exceeded: Encountered {0} bytes")]
+    SizeLimitExceeded(usize),
+
+    #[error("Malformed DagCbor at byte {0}")]
+    Malformed(usize),
 }
@@ -1,16 +1,19 @@
 //! This crate contains the common types and functions used by the WNFS crates.
 mod async_serialize;
 pub mod blockstore;
+mod car;
 mod encoding;
 mod error;
 mod link;
 mod metadata;
 mod pathnodes;
+pub mod testing;
 mod traits;
 pub mod utils;
 
 pub use async_serialize::*;
 pub use blockstore::*;
+pub use car::*;
 pub use encoding::*;
 pub use error::*;
 pub use link::*;
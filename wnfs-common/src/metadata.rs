@@ -1,11 +1,70 @@
 //! File system metadata.
 
+use crate::MetadataError;
 use anyhow::{bail, Result};
 use chrono::{DateTime, TimeZone, Utc};
 use libipld::Ipld;
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 use std::{collections::BTreeMap, convert::TryInto};
 
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// Metadata keys WNFS itself uses, which attributes can't be set under.
+pub const RESERVED_METADATA_KEYS: [&str; 7] = [
+    "created", "modified", "accessed", "symlink", "mode", "uid", "gid",
+];
+
+/// The permission bits of files that don't have a mode set.
+pub const DEFAULT_FILE_MODE: u32 = 0o644;
+
+/// The permission bits of directories that don't have a mode set.
+pub const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// The metadata key [`Metadata::guess_and_set_mime`] stores the content type under.
+pub const CONTENT_TYPE_KEY: &str = "content-type";
+
+/// Signatures at the start of content that identify its type.
+const MIME_MAGIC_BYTES: [(&[u8], &str); 10] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x00asm", "application/wasm"),
+    (b"OggS", "audio/ogg"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+];
+
+/// Content types by lowercase file extension.
+const MIME_EXTENSIONS: [(&str, &str); 22] = [
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("js", "text/javascript"),
+    ("py", "text/x-python"),
+    ("rs", "text/x-rust"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("wasm", "application/wasm"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+];
+
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
@@ -50,6 +109,35 @@ impl ToString for NodeType {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Metadata(pub BTreeMap<String, Ipld>);
 
+/// Builds a `Metadata` with typed values instead of raw `Ipld`.
+///
+/// Like [`Metadata::set_attribute`], it refuses keys WNFS uses itself, listed in
+/// [`RESERVED_METADATA_KEYS`]. Using one makes `build` fail with [`MetadataError::ReservedKey`].
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::MetadataBuilder;
+/// use chrono::Utc;
+///
+/// let metadata = MetadataBuilder::new(Utc::now())
+///     .string("author", "alice")
+///     .integer("rating", 5)
+///     .bool("starred", true)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(metadata.get_string("author"), Some("alice"));
+/// assert_eq!(metadata.get_integer("rating"), Some(5));
+/// assert_eq!(metadata.get_bool("starred"), Some(true));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataBuilder {
+    metadata: Metadata,
+    /// The first reserved key a value was set under.
+    reserved_key: Option<String>,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -95,6 +183,30 @@ impl Metadata {
         self.0.insert("modified".into(), time.timestamp().into());
     }
 
+    /// Updates the access time.
+    ///
+    /// Nothing sets this on its own: reads leave metadata untouched unless the caller opts in,
+    /// e.g. with `PrivateDirectory::read_with_atime`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::{Utc, TimeZone};
+    ///
+    /// let time = Utc::now();
+    /// let mut metadata = Metadata::new(time);
+    /// assert_eq!(metadata.get_accessed(), None);
+    ///
+    /// metadata.upsert_atime(time);
+    ///
+    /// let imprecise_time = Utc.timestamp_opt(time.timestamp(), 0).single();
+    /// assert_eq!(metadata.get_accessed(), imprecise_time);
+    /// ```
+    pub fn upsert_atime(&mut self, time: DateTime<Utc>) {
+        self.0.insert("accessed".into(), time.timestamp().into());
+    }
+
     /// Returns the created time.
     ///
     /// # Examples
@@ -113,10 +225,7 @@ impl Metadata {
     /// Will return `None` if there's no created metadata on the
     /// node or if it's not a second-based POSIX timestamp integer.
     pub fn get_created(&self) -> Option<DateTime<Utc>> {
-        self.0.get("created").and_then(|ipld| match ipld {
-            Ipld::Integer(i) => Utc.timestamp_opt(i64::try_from(*i).ok()?, 0).single(),
-            _ => None,
-        })
+        self.get_timestamp("created")
     }
 
     /// Returns the modified time.
@@ -137,10 +246,64 @@ impl Metadata {
     /// Will return `None` if there's no created metadata on the
     /// node or if it's not a second-based POSIX timestamp integer.
     pub fn get_modified(&self) -> Option<DateTime<Utc>> {
-        self.0.get("modified").and_then(|ipld| match ipld {
-            Ipld::Integer(i) => Utc.timestamp_opt(i64::try_from(*i).ok()?, 0).single(),
+        self.get_timestamp("modified")
+    }
+
+    /// Returns the access time.
+    ///
+    /// Will return `None` if the node was never read with access time tracking
+    /// or if it's not a second-based POSIX timestamp integer.
+    pub fn get_accessed(&self) -> Option<DateTime<Utc>> {
+        self.get_timestamp("accessed")
+    }
+
+    /// Returns the string value of a key.
+    ///
+    /// Will return `None` if the key is missing or its value isn't a string.
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        match self.0.get(key) {
+            Some(Ipld::String(value)) => Some(value),
             _ => None,
-        })
+        }
+    }
+
+    /// Returns the integer value of a key.
+    ///
+    /// Will return `None` if the key is missing or its value isn't an integer.
+    pub fn get_integer(&self, key: &str) -> Option<i128> {
+        match self.0.get(key) {
+            Some(Ipld::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean value of a key.
+    ///
+    /// Will return `None` if the key is missing or its value isn't a boolean.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.0.get(key) {
+            Some(Ipld::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes value of a key.
+    ///
+    /// Will return `None` if the key is missing or its value isn't bytes.
+    pub fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.0.get(key) {
+            Some(Ipld::Bytes(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the timestamp value of a key.
+    ///
+    /// Will return `None` if the key is missing or its value
+    /// isn't a second-based POSIX timestamp integer.
+    pub fn get_timestamp(&self, key: &str) -> Option<DateTime<Utc>> {
+        let seconds = i64::try_from(self.get_integer(key)?).ok()?;
+        Utc.timestamp_opt(seconds, 0).single()
     }
 
     /// Inserts a key-value pair into the metadata.
@@ -184,6 +347,150 @@ impl Metadata {
         self.0.remove(key)
     }
 
+    /// Sets the POSIX permission bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// assert_eq!(metadata.get_mode(), None);
+    ///
+    /// metadata.set_mode(0o600);
+    /// assert_eq!(metadata.get_mode(), Some(0o600));
+    /// ```
+    pub fn set_mode(&mut self, mode: u32) {
+        self.0.insert("mode".into(), mode.into());
+    }
+
+    /// Returns the POSIX permission bits.
+    ///
+    /// Will return `None` if no mode was set. Nodes fall back to
+    /// [`DEFAULT_FILE_MODE`] or [`DEFAULT_DIR_MODE`] then.
+    pub fn get_mode(&self) -> Option<u32> {
+        self.get_u32("mode")
+    }
+
+    /// Sets the id of the owning user.
+    pub fn set_uid(&mut self, uid: u32) {
+        self.0.insert("uid".into(), uid.into());
+    }
+
+    /// Returns the id of the owning user.
+    ///
+    /// Will return `None` if no user id was set.
+    pub fn get_uid(&self) -> Option<u32> {
+        self.get_u32("uid")
+    }
+
+    /// Sets the id of the owning group.
+    pub fn set_gid(&mut self, gid: u32) {
+        self.0.insert("gid".into(), gid.into());
+    }
+
+    /// Returns the id of the owning group.
+    ///
+    /// Will return `None` if no group id was set.
+    pub fn get_gid(&self) -> Option<u32> {
+        self.get_u32("gid")
+    }
+
+    fn get_u32(&self, key: &str) -> Option<u32> {
+        u32::try_from(self.get_integer(key)?).ok()
+    }
+
+    /// Sets a custom attribute, like a content type or tags, and returns its previous value.
+    ///
+    /// Fails with [`MetadataError::ReservedKey`] for keys WNFS uses itself, listed in
+    /// [`RESERVED_METADATA_KEYS`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    /// use libipld::Ipld;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.set_attribute("content-type", Ipld::String("text/plain".into())).unwrap();
+    ///
+    /// assert_eq!(
+    ///     metadata.get_attribute("content-type"),
+    ///     Some(&Ipld::String("text/plain".into()))
+    /// );
+    /// assert!(metadata.set_attribute("modified", Ipld::Integer(0)).is_err());
+    /// ```
+    pub fn set_attribute(&mut self, key: &str, value: Ipld) -> Result<Option<Ipld>> {
+        Self::ensure_not_reserved(key)?;
+        Ok(self.0.insert(key.into(), value))
+    }
+
+    /// Returns the value of an attribute.
+    ///
+    /// Will return `None` if the key is missing.
+    pub fn get_attribute(&self, key: &str) -> Option<&Ipld> {
+        self.0.get(key)
+    }
+
+    /// Removes a custom attribute and returns its value.
+    ///
+    /// Fails with [`MetadataError::ReservedKey`] for keys WNFS uses itself, listed in
+    /// [`RESERVED_METADATA_KEYS`].
+    pub fn remove_attribute(&mut self, key: &str) -> Result<Option<Ipld>> {
+        Self::ensure_not_reserved(key)?;
+        Ok(self.0.remove(key))
+    }
+
+    fn ensure_not_reserved(key: &str) -> Result<()> {
+        if RESERVED_METADATA_KEYS.contains(&key) {
+            bail!(MetadataError::ReservedKey(key.into()));
+        }
+
+        Ok(())
+    }
+
+    /// Guesses the content type from the first bytes of the content, falling back to the
+    /// file name's extension, and stores it under [`CONTENT_TYPE_KEY`].
+    ///
+    /// Returns the guessed type. If neither gives it away, the metadata is left as it is, rather
+    /// than claiming `application/octet-stream`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.guess_and_set_mime("notes.md", b"# Notes");
+    ///
+    /// assert_eq!(metadata.get_string("content-type"), Some("text/markdown"));
+    /// ```
+    pub fn guess_and_set_mime(
+        &mut self,
+        filename: &str,
+        content_prefix: &[u8],
+    ) -> Option<&'static str> {
+        let sniffed = MIME_MAGIC_BYTES
+            .iter()
+            .find(|(magic, _)| content_prefix.starts_with(magic))
+            .map(|(_, mime)| *mime);
+
+        let mime = sniffed.or_else(|| {
+            let (_, extension) = filename.rsplit_once('.')?;
+            let extension = extension.to_lowercase();
+            MIME_EXTENSIONS
+                .iter()
+                .find(|(known, _)| *known == extension)
+                .map(|(_, mime)| *mime)
+        })?;
+
+        self.0.insert(CONTENT_TYPE_KEY.into(), mime.into());
+        Some(mime)
+    }
+
     /// Updates this metadata with the contents of another metadata. merge strategy is to take theirs.
     ///
     /// # Examples
@@ -206,6 +513,60 @@ impl Metadata {
     }
 }
 
+impl MetadataBuilder {
+    /// Starts building metadata with given creation and modification time.
+    pub fn new(time: DateTime<Utc>) -> Self {
+        Self {
+            metadata: Metadata::new(time),
+            reserved_key: None,
+        }
+    }
+
+    /// Sets a string value.
+    pub fn string(self, key: &str, value: impl Into<String>) -> Self {
+        self.set(key, Ipld::String(value.into()))
+    }
+
+    /// Sets an integer value.
+    pub fn integer(self, key: &str, value: impl Into<i128>) -> Self {
+        self.set(key, Ipld::Integer(value.into()))
+    }
+
+    /// Sets a boolean value.
+    pub fn bool(self, key: &str, value: bool) -> Self {
+        self.set(key, Ipld::Bool(value))
+    }
+
+    /// Sets a bytes value.
+    pub fn bytes(self, key: &str, value: impl Into<Vec<u8>>) -> Self {
+        self.set(key, Ipld::Bytes(value.into()))
+    }
+
+    /// Sets a timestamp value, stored as seconds since the POSIX epoch like `created` and `modified`.
+    pub fn timestamp(self, key: &str, value: DateTime<Utc>) -> Self {
+        self.set(key, Ipld::Integer(value.timestamp().into()))
+    }
+
+    /// Returns the built metadata.
+    ///
+    /// Fails with [`MetadataError::ReservedKey`] if a value was set under a reserved key.
+    pub fn build(self) -> Result<Metadata> {
+        if let Some(key) = self.reserved_key {
+            bail!(MetadataError::ReservedKey(key));
+        }
+
+        Ok(self.metadata)
+    }
+
+    fn set(mut self, key: &str, value: Ipld) -> Self {
+        if self.metadata.set_attribute(key, value).is_err() {
+            self.reserved_key.get_or_insert_with(|| key.into());
+        }
+
+        self
+    }
+}
+
 impl TryFrom<&Ipld> for NodeType {
     type Error = anyhow::Error;
 
@@ -270,8 +631,11 @@ impl<'de> Deserialize<'de> for NodeType {
 
 #[cfg(test)]
 mod tests {
-    use crate::{dagcbor, Metadata};
-    use chrono::Utc;
+    use crate::{
+        dagcbor, Metadata, MetadataBuilder, MetadataError, CONTENT_TYPE_KEY, RESERVED_METADATA_KEYS,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use libipld::Ipld;
 
     #[async_std::test]
     async fn metadata_can_encode_decode_as_cbor() {
@@ -282,4 +646,119 @@ mod tests {
 
         assert_eq!(metadata, decoded_metadata);
     }
+
+    #[test]
+    fn typed_values_round_trip() {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let metadata = MetadataBuilder::new(time)
+            .string("string", "value")
+            .integer("integer", -42)
+            .bool("bool", true)
+            .bytes("bytes", vec![1, 2, 3])
+            .timestamp("timestamp", time + Duration::days(1))
+            .build()
+            .unwrap();
+
+        let encoded = dagcbor::encode(&metadata).unwrap();
+        let decoded = dagcbor::decode::<Metadata>(encoded.as_ref()).unwrap();
+
+        assert_eq!(decoded, metadata);
+        assert_eq!(decoded.get_string("string"), Some("value"));
+        assert_eq!(decoded.get_integer("integer"), Some(-42));
+        assert_eq!(decoded.get_bool("bool"), Some(true));
+        assert_eq!(decoded.get_bytes("bytes"), Some(&[1, 2, 3][..]));
+        assert_eq!(
+            decoded.get_timestamp("timestamp"),
+            Some(time + Duration::days(1))
+        );
+        assert_eq!(decoded.get_created(), Some(time));
+    }
+
+    #[test]
+    fn typed_getters_reject_mismatched_types() {
+        let metadata = MetadataBuilder::new(Utc::now())
+            .string("string", "value")
+            .integer("huge", i128::MAX)
+            .build()
+            .unwrap();
+
+        assert_eq!(metadata.get_integer("string"), None);
+        assert_eq!(metadata.get_bool("string"), None);
+        assert_eq!(metadata.get_bytes("string"), None);
+        assert_eq!(metadata.get_string("missing"), None);
+        assert_eq!(metadata.get_timestamp("huge"), None);
+    }
+
+    #[test]
+    fn builder_rejects_reserved_keys() {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        for key in RESERVED_METADATA_KEYS {
+            let error = MetadataBuilder::new(time)
+                .string("author", "alice")
+                .integer(key, 0)
+                .bool("starred", true)
+                .build()
+                .unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<MetadataError>(),
+                Some(MetadataError::ReservedKey(reserved)) if reserved == key
+            ));
+        }
+    }
+
+    #[test]
+    fn ownership_and_mode_round_trip() {
+        let mut metadata = Metadata::new(Utc::now());
+        metadata.set_mode(0o750);
+        metadata.set_uid(1000);
+        metadata.set_gid(100);
+
+        let encoded = dagcbor::encode(&metadata).unwrap();
+        let decoded = dagcbor::decode::<Metadata>(encoded.as_ref()).unwrap();
+
+        assert_eq!(decoded.get_mode(), Some(0o750));
+        assert_eq!(decoded.get_uid(), Some(1000));
+        assert_eq!(decoded.get_gid(), Some(100));
+
+        metadata.put("uid", Ipld::Integer(-1));
+        assert_eq!(metadata.get_uid(), None);
+    }
+
+    #[test]
+    fn mime_is_guessed_from_extension_or_magic_bytes() {
+        let mut script = Metadata::new(Utc::now());
+        let mime = script.guess_and_set_mime("main.py", b"print('hello world')");
+        assert_eq!(mime, Some("text/x-python"));
+        assert_eq!(script.get_string(CONTENT_TYPE_KEY), Some("text/x-python"));
+
+        let mut image = Metadata::new(Utc::now());
+        let png_prefix = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+        let mime = image.guess_and_set_mime("upload", png_prefix);
+        assert_eq!(mime, Some("image/png"));
+        assert_eq!(image.get_string(CONTENT_TYPE_KEY), Some("image/png"));
+
+        let mut blob = Metadata::new(Utc::now());
+        let before = blob.clone();
+        assert_eq!(blob.guess_and_set_mime("blob.bin", &[0, 1, 2, 3]), None);
+        assert_eq!(blob, before);
+    }
+
+    #[test]
+    fn reserved_keys_cant_be_set_or_removed_as_attributes() {
+        let mut metadata = Metadata::new(Utc::now());
+        let before = metadata.clone();
+
+        for key in RESERVED_METADATA_KEYS {
+            assert!(metadata.set_attribute(key, Ipld::Integer(0)).is_err());
+            assert!(metadata.remove_attribute(key).is_err());
+        }
+        assert_eq!(metadata, before);
+
+        let tags = Ipld::List(vec![Ipld::String("draft".into())]);
+        assert_eq!(metadata.set_attribute("tags", tags.clone()).unwrap(), None);
+        assert_eq!(metadata.get_attribute("tags"), Some(&tags));
+        assert_eq!(metadata.remove_attribute("tags").unwrap(), Some(tags));
+        assert_eq!(metadata.get_attribute("tags"), None);
+    }
 }
@@ -0,0 +1,272 @@
+//! Conformance checks for `BlockStore` implementations.
+//!
+//! Downstream crates implementing their own block store can run these from their test suite:
+//!
+//! ```
+//! use wnfs_common::{testing, MemoryBlockStore};
+//!
+//! #[async_std::main]
+//! async fn main() {
+//!     let store = MemoryBlockStore::default();
+//!     let report = testing::conformance(&store).await;
+//!
+//!     assert!(report.passed(), "{report}");
+//! }
+//! ```
+
+use crate::{BlockStore, BlockStoreError, MAX_BLOCK_SIZE};
+use anyhow::{anyhow, ensure, Result};
+use libipld::{Cid, IpldCodec};
+use std::fmt;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The outcome of a single conformance check.
+#[derive(Debug)]
+pub struct ConformanceCheck {
+    pub name: &'static str,
+    pub result: Result<()>,
+}
+
+/// The outcomes of all conformance checks run against a block store.
+#[derive(Debug, Default)]
+pub struct ConformanceReport {
+    pub checks: Vec<ConformanceCheck>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl ConformanceReport {
+    /// Returns true if every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.result.is_ok())
+    }
+
+    /// Returns the checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCheck> {
+        self.checks.iter().filter(|check| check.result.is_err())
+    }
+
+    fn record(&mut self, name: &'static str, result: Result<()>) {
+        self.checks.push(ConformanceCheck { name, result });
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in self.checks.iter() {
+            match &check.result {
+                Ok(()) => writeln!(f, "[pass] {}", check.name)?,
+                Err(err) => writeln!(f, "[fail] {}: {err:#}", check.name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Runs all conformance checks against given block store and returns a report.
+///
+/// A failing check doesn't stop the remaining ones from running.
+pub async fn conformance(store: &impl BlockStore) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+
+    report.record("retrieval", check_retrieval(store).await);
+    report.record("duplication", check_duplication(store).await);
+    report.record("codec", check_codec(store).await);
+    report.record("missing_block", check_missing_block(store).await);
+    report.record("has_block", check_has_block(store).await);
+    report.record("maximum_block_size", check_maximum_block_size(store).await);
+    report.record("deletion", check_deletion(store).await);
+
+    report
+}
+
+/// Blocks that were put can be retrieved byte-for-byte under the returned CID.
+async fn check_retrieval(store: &impl BlockStore) -> Result<()> {
+    let first_bytes = vec![1, 2, 3, 4, 5];
+    let second_bytes = b"hello world".to_vec();
+
+    let first_cid = store.put_serializable(&first_bytes).await?;
+    let second_cid = store.put_serializable(&second_bytes).await?;
+
+    let first_loaded: Vec<u8> = store.get_deserializable(&first_cid).await?;
+    let second_loaded: Vec<u8> = store.get_deserializable(&second_cid).await?;
+
+    ensure!(
+        first_loaded == first_bytes,
+        "first block doesn't round trip"
+    );
+    ensure!(
+        second_loaded == second_bytes,
+        "second block doesn't round trip"
+    );
+
+    Ok(())
+}
+
+/// Putting the same bytes twice yields the same CID.
+async fn check_duplication(store: &impl BlockStore) -> Result<()> {
+    let bytes = b"duplicated".to_vec();
+
+    let first_cid = store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+    let second_cid = store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+
+    ensure!(
+        first_cid == second_cid,
+        "same bytes produced different CIDs: {first_cid} and {second_cid}"
+    );
+    ensure!(
+        store.get_block(&first_cid).await?.as_ref() == &bytes,
+        "duplicated block doesn't round trip"
+    );
+
+    Ok(())
+}
+
+/// The CID returned by `put_block` carries the codec it was stored with.
+async fn check_codec(store: &impl BlockStore) -> Result<()> {
+    let bytes = b"codec".to_vec();
+
+    let raw_cid = store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+    let cbor_cid = store.put_block(bytes, IpldCodec::DagCbor).await?;
+
+    ensure!(
+        raw_cid.codec() == u64::from(IpldCodec::Raw),
+        "raw block stored under codec {:#x}",
+        raw_cid.codec()
+    );
+    ensure!(
+        cbor_cid.codec() == u64::from(IpldCodec::DagCbor),
+        "dag-cbor block stored under codec {:#x}",
+        cbor_cid.codec()
+    );
+    ensure!(raw_cid != cbor_cid, "codec is not part of the CID");
+
+    Ok(())
+}
+
+/// Getting a block that was never put is an error rather than empty bytes.
+async fn check_missing_block(store: &impl BlockStore) -> Result<()> {
+    let cid = store.create_cid(&b"never stored".to_vec(), IpldCodec::Raw)?;
+
+    match store.get_block(&cid).await {
+        Ok(_) => Err(anyhow!("got bytes for a CID that was never put: {cid}")),
+        Err(_) => Ok(()),
+    }
+}
+
+/// `has_block` reports blocks that were put, and only those.
+async fn check_has_block(store: &impl BlockStore) -> Result<()> {
+    let stored_cid = store.put_block(b"present".to_vec(), IpldCodec::Raw).await?;
+    let missing_cid = store.create_cid(&b"never stored".to_vec(), IpldCodec::Raw)?;
+
+    ensure!(
+        store.has_block(&stored_cid).await?,
+        "doesn't have a block that was put: {stored_cid}"
+    );
+    ensure!(
+        !store.has_block(&missing_cid).await?,
+        "has a block that was never put: {missing_cid}"
+    );
+
+    Ok(())
+}
+
+/// Blocks bigger than `MAX_BLOCK_SIZE` are rejected.
+async fn check_maximum_block_size(store: &impl BlockStore) -> Result<()> {
+    let result: Result<Cid> = store
+        .put_block(vec![0; MAX_BLOCK_SIZE + 1], IpldCodec::Raw)
+        .await;
+
+    match result {
+        Ok(cid) => Err(anyhow!("accepted an oversized block as {cid}")),
+        Err(err) => {
+            ensure!(
+                matches!(
+                    err.downcast_ref::<BlockStoreError>(),
+                    Some(BlockStoreError::MaximumBlockSizeExceeded(_))
+                ),
+                "oversized block rejected with unexpected error: {err}"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Deleted blocks can't be retrieved anymore, and deleting a missing block is not an error.
+///
+/// Stores that don't support deletion pass by returning `BlockStoreError::Unsupported`.
+async fn check_deletion(store: &impl BlockStore) -> Result<()> {
+    let cid = store.put_block(b"deleted".to_vec(), IpldCodec::Raw).await?;
+
+    if let Err(err) = store.delete_block(&cid).await {
+        return match err.downcast_ref::<BlockStoreError>() {
+            Some(BlockStoreError::Unsupported(_)) => Ok(()),
+            _ => Err(err),
+        };
+    }
+
+    ensure!(
+        store.get_block(&cid).await.is_err(),
+        "got bytes for a deleted block: {cid}"
+    );
+    store
+        .delete_block(&cid)
+        .await
+        .map_err(|err| anyhow!("deleting a missing block failed: {err}"))?;
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::borrow::Cow;
+
+    /// A store that accepts everything and never returns the right bytes.
+    struct BrokenBlockStore;
+
+    #[async_trait(?Send)]
+    impl BlockStore for BrokenBlockStore {
+        async fn get_block(&self, _: &Cid) -> Result<Cow<Vec<u8>>> {
+            Ok(Cow::Owned(vec![]))
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, _: IpldCodec) -> Result<Cid> {
+            self.create_cid(&bytes, IpldCodec::Raw)
+        }
+    }
+
+    #[async_std::test]
+    async fn broken_store_fails_conformance() {
+        let report = conformance(&BrokenBlockStore).await;
+
+        assert!(!report.passed());
+
+        let failed = report.failures().map(|c| c.name).collect::<Vec<_>>();
+        assert_eq!(
+            failed,
+            vec![
+                "retrieval",
+                "duplication",
+                "codec",
+                "missing_block",
+                "has_block"
+            ]
+        );
+    }
+}
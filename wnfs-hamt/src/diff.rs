@@ -106,6 +106,13 @@ where
         }
     }
 
+    // Nodes that haven't been stored yet can still be shared between both tries.
+    if let (Some(node), Some(node2)) = (main_link.get_value(), other_link.get_value()) {
+        if Rc::ptr_eq(node, node2) {
+            return Ok(vec![]);
+        }
+    }
+
     // Otherwise, get nodes from store.
     let mut main_node = main_link.resolve_owned_value(store).await?;
 
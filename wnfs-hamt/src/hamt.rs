@@ -1,8 +1,9 @@
-use super::{KeyValueChange, Node, HAMT_VERSION};
+use super::{KeyValueChange, Node, Pair, HAMT_VERSION};
 use crate::Hasher;
 use anyhow::Result;
 use async_trait::async_trait;
-use libipld::{serde as ipld_serde, Ipld};
+use futures::Stream;
+use libipld::{serde as ipld_serde, Cid, Ipld};
 use semver::Version;
 use serde::{
     de::{DeserializeOwned, Error as DeError},
@@ -80,6 +81,47 @@ impl<K, V, H: Hasher> Hamt<K, V, H> {
         }
     }
 
+    /// Inserts all given key-value pairs, then stores the HAMT and returns its CID.
+    ///
+    /// The inserts only touch the node tree in memory. Each node that changed gets serialized
+    /// once when the HAMT is stored at the end, rather than after every insert. The result is the
+    /// same as inserting the pairs one by one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_hamt::Hamt;
+    /// use wnfs_common::MemoryBlockStore;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let mut hamt = Hamt::<String, usize>::new();
+    ///
+    ///     let entries = (0..100).map(|i| (i.to_string(), i));
+    ///     let cid = hamt.insert_many(entries, store).await.unwrap();
+    ///
+    ///     assert_eq!(hamt.root.get(&"42".into(), store).await.unwrap(), Some(&42));
+    ///     println!("Stored HAMT as {cid}");
+    /// }
+    /// ```
+    pub async fn insert_many(
+        &mut self,
+        entries: impl IntoIterator<Item = (K, V)>,
+        store: &impl BlockStore,
+    ) -> Result<Cid>
+    where
+        K: DeserializeOwned + Serialize + Clone + AsRef<[u8]>,
+        V: DeserializeOwned + Serialize + Clone,
+        H: 'static,
+    {
+        for (key, value) in entries {
+            self.root.set(key, value, store).await?;
+        }
+
+        store.put_async_serializable(self).await
+    }
+
     /// Gets the difference between two HAMTs at the key-value level.
     ///
     /// # Examples
@@ -129,6 +171,47 @@ impl<K, V, H: Hasher> Hamt<K, V, H> {
         .await
     }
 
+    /// Streams all the key-value pairs of the HAMT, sorted by the hash of their keys.
+    ///
+    /// The order only depends on the keys, not the order they were inserted in, so it's stable
+    /// across HAMTs with the same content. Nodes are loaded from the store as the stream reaches
+    /// them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use futures::TryStreamExt;
+    /// use wnfs_hamt::{Hamt, Node, Pair};
+    /// use wnfs_common::MemoryBlockStore;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let hamt = Hamt::<String, usize>::with_root({
+    ///         let mut node = Rc::new(Node::default());
+    ///         node.set("foo".into(), 400, store).await.unwrap();
+    ///         node.set("bar".into(), 500, store).await.unwrap();
+    ///         node
+    ///     });
+    ///
+    ///     let pairs = hamt.ordered_iter(store).try_collect::<Vec<_>>().await.unwrap();
+    ///
+    ///     assert_eq!(pairs.len(), 2);
+    /// }
+    /// ```
+    pub fn ordered_iter<'a, B: BlockStore>(
+        &self,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Pair<K, V>>> + 'a
+    where
+        K: DeserializeOwned + Clone + AsRef<[u8]> + 'a,
+        V: DeserializeOwned + Clone + 'a,
+        H: 'static,
+    {
+        self.root.ordered_pairs(store)
+    }
+
     async fn to_ipld<B: BlockStore + ?Sized>(&self, store: &B) -> Result<Ipld>
     where
         K: Serialize,
@@ -224,8 +307,167 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ChangeType;
+    use futures::TryStreamExt;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
     use wnfs_common::{dagcbor, MemoryBlockStore};
 
+    #[async_std::test]
+    async fn ordered_iter_is_sorted_regardless_of_insertion_order() {
+        let store = &MemoryBlockStore::default();
+        let keys = (0..500_u32).map(|i| i.to_le_bytes()).collect::<Vec<_>>();
+
+        let mut orders = Vec::new();
+        for seed in 0..3 {
+            let mut shuffled = keys.clone();
+            shuffled.shuffle(&mut StdRng::seed_from_u64(seed));
+
+            let mut root = Rc::new(Node::<[u8; 4], u32>::default());
+            for key in shuffled {
+                root.set(key, u32::from_le_bytes(key), store).await.unwrap();
+            }
+
+            // Load the HAMT back, so nodes are streamed from the store
+            let cid = store
+                .put_async_serializable(&Hamt::with_root(root))
+                .await
+                .unwrap();
+            let hamt = store
+                .get_deserializable::<Hamt<[u8; 4], u32>>(&cid)
+                .await
+                .unwrap();
+
+            let pairs = hamt
+                .ordered_iter(store)
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            orders.push(pairs.into_iter().map(|pair| pair.key).collect::<Vec<_>>());
+        }
+
+        let mut expected = keys;
+        expected.sort_by_cached_key(Sha3_256::hash);
+        for order in orders {
+            assert_eq!(order, expected);
+        }
+    }
+
+    #[async_std::test]
+    async fn insert_many_matches_sequential_inserts() {
+        let entries = (0..300_u32)
+            .map(|i| (i.to_le_bytes(), i.to_string()))
+            .collect::<Vec<_>>();
+
+        let bulk_store = &MemoryBlockStore::default();
+        let mut bulk = Hamt::<[u8; 4], String>::new();
+        let bulk_cid = bulk.insert_many(entries.clone(), bulk_store).await.unwrap();
+
+        // Storing after every insert, like building a forest label by label does
+        let sequential_store = &MemoryBlockStore::default();
+        let mut sequential = Hamt::<[u8; 4], String>::new();
+        let mut sequential_cid = None;
+        for (key, value) in entries {
+            sequential
+                .root
+                .set(key, value, sequential_store)
+                .await
+                .unwrap();
+            sequential_cid = Some(
+                sequential_store
+                    .put_async_serializable(&sequential)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!(Some(bulk_cid), sequential_cid);
+        assert!(
+            bulk_store.list_blocks().await.unwrap().len()
+                < sequential_store.list_blocks().await.unwrap().len()
+        );
+    }
+
+    async fn store_and_load(
+        hamt: &Hamt<[u8; 4], String>,
+        store: &MemoryBlockStore,
+    ) -> Hamt<[u8; 4], String> {
+        let cid = store.put_async_serializable(hamt).await.unwrap();
+        store.get_deserializable(&cid).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn diff_only_reports_changed_keys() {
+        let store = &MemoryBlockStore::new();
+        let mut old = Hamt::<[u8; 4], String>::new();
+        let entries = (0..500_u32).map(|i| (i.to_le_bytes(), i.to_string()));
+        old.insert_many(entries, store).await.unwrap();
+        let old = store_and_load(&old, store).await;
+
+        let mut new = old.clone();
+        new.root
+            .set(500_u32.to_le_bytes(), "500".into(), store)
+            .await
+            .unwrap();
+        new.root
+            .set(7_u32.to_le_bytes(), "seven".into(), store)
+            .await
+            .unwrap();
+        new.root.remove(&42_u32.to_le_bytes(), store).await.unwrap();
+        let new = store_and_load(&new, store).await;
+
+        let block_count = store.list_blocks().await.unwrap().len();
+        store.reset_stats();
+        let mut changes = new
+            .diff(&old, store)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|change| (u32::from_le_bytes(change.key), change.r#type))
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|(key, _)| *key);
+
+        assert_eq!(
+            changes,
+            vec![
+                (7, ChangeType::Modify),
+                (42, ChangeType::Remove),
+                (500, ChangeType::Add)
+            ]
+        );
+        // Unchanged subtrees are skipped without loading them
+        assert!(store.stats().unwrap().blocks_read < block_count as u64 / 4);
+    }
+
+    #[async_std::test]
+    async fn diff_of_identical_hamts_is_empty_and_loads_nothing() {
+        let store = &MemoryBlockStore::new();
+        let mut hamt = Hamt::<[u8; 4], String>::new();
+        let entries = (0..500_u32).map(|i| (i.to_le_bytes(), i.to_string()));
+        hamt.insert_many(entries, store).await.unwrap();
+        let hamt = store_and_load(&hamt, store).await;
+        let same_hamt = store_and_load(&hamt, store).await;
+
+        store.reset_stats();
+        assert!(hamt.diff(&same_hamt, store).await.unwrap().is_empty());
+        assert_eq!(store.stats().unwrap().blocks_read, 0);
+
+        // Unstored tries sharing nodes are recognized too
+        let mut unstored = Hamt::<[u8; 4], String>::new();
+        for i in 0..500_u32 {
+            unstored
+                .root
+                .set(i.to_le_bytes(), i.to_string(), store)
+                .await
+                .unwrap();
+        }
+        assert!(unstored
+            .diff(&unstored.clone(), store)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(store.stats().unwrap().blocks_read, 0);
+    }
+
     #[async_std::test]
     async fn hamt_can_encode_decode_as_cbor() {
         let store = &mut MemoryBlockStore::default();
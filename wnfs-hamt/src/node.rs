@@ -10,7 +10,7 @@ use async_recursion::async_recursion;
 use async_trait::async_trait;
 use bitvec::array::BitArray;
 use either::{Either, Either::*};
-use futures::future::LocalBoxFuture;
+use futures::{future::LocalBoxFuture, stream, Stream};
 use libipld::{serde as ipld_serde, Cid, Ipld};
 #[cfg(feature = "log")]
 use log::debug;
@@ -21,7 +21,7 @@ use serde::{
 };
 use sha3::Sha3_256;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt::{self, Debug, Formatter},
     hash::Hash,
     marker::PhantomData,
@@ -533,6 +533,143 @@ where
         Ok(items)
     }
 
+    /// Streams all the key-value pairs in the trie, in the same order as `flat_map` visits them.
+    ///
+    /// Child nodes are only loaded once the stream reaches them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use futures::TryStreamExt;
+    /// use wnfs_hamt::Node;
+    /// use wnfs_common::MemoryBlockStore;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::new();
+    ///     let mut node = Rc::new(Node::<[u8; 4], String>::default());
+    ///     for i in 0..99_u32 {
+    ///         node
+    ///             .set(i.to_le_bytes(), i.to_string(), store)
+    ///             .await
+    ///             .unwrap();
+    ///     }
+    ///
+    ///     let pairs = node.pairs(store).try_collect::<Vec<_>>().await.unwrap();
+    ///
+    ///     assert_eq!(pairs.len(), 99);
+    /// }
+    /// ```
+    pub fn pairs<'a, B: BlockStore>(
+        self: &Rc<Self>,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Pair<K, V>>> + 'a
+    where
+        K: DeserializeOwned + Clone + 'a,
+        V: DeserializeOwned + Clone + 'a,
+        H: 'a,
+    {
+        self.stream_pairs(store, |_| {})
+    }
+
+    /// Streams all the key-value pairs in the trie, sorted by the hash of their keys.
+    ///
+    /// Pointers within a node are ordered by the nibble of the key hashes they cover, so a
+    /// depth-first walk visits the buckets in hash order. The pairs within each bucket get sorted
+    /// on the way, so the order doesn't rely on how the bucket was written. Child nodes are only
+    /// loaded once the stream reaches them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use futures::TryStreamExt;
+    /// use sha3::Sha3_256;
+    /// use wnfs_hamt::{Hasher, Node};
+    /// use wnfs_common::MemoryBlockStore;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::new();
+    ///     let mut node = Rc::new(Node::<[u8; 4], String>::default());
+    ///     for i in 0..99_u32 {
+    ///         node
+    ///             .set(i.to_le_bytes(), i.to_string(), store)
+    ///             .await
+    ///             .unwrap();
+    ///     }
+    ///
+    ///     let pairs = node.ordered_pairs(store).try_collect::<Vec<_>>().await.unwrap();
+    ///     let hashes = pairs.iter().map(|pair| Sha3_256::hash(&pair.key)).collect::<Vec<_>>();
+    ///
+    ///     assert_eq!(pairs.len(), 99);
+    ///     assert!(hashes.windows(2).all(|w| w[0] < w[1]));
+    /// }
+    /// ```
+    pub fn ordered_pairs<'a, B: BlockStore>(
+        self: &Rc<Self>,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Pair<K, V>>> + 'a
+    where
+        K: DeserializeOwned + Clone + AsRef<[u8]> + 'a,
+        V: DeserializeOwned + Clone + 'a,
+        H: 'a,
+    {
+        self.stream_pairs(store, |bucket| {
+            bucket
+                .make_contiguous()
+                .sort_by_cached_key(|pair| H::hash(&pair.key))
+        })
+    }
+
+    /// Streams the pairs of the trie depth-first, letting `prepare_bucket` reorder the pairs of
+    /// each bucket before they're yielded.
+    fn stream_pairs<'a, B: BlockStore>(
+        self: &Rc<Self>,
+        store: &'a B,
+        prepare_bucket: impl Fn(&mut VecDeque<Pair<K, V>>) + Copy + 'a,
+    ) -> impl Stream<Item = Result<Pair<K, V>>> + 'a
+    where
+        K: DeserializeOwned + Clone + 'a,
+        V: DeserializeOwned + Clone + 'a,
+        H: 'a,
+    {
+        // Each node being visited, with the index of the next pointer to visit in it.
+        let nodes = vec![(Rc::clone(self), 0)];
+        stream::try_unfold(
+            (nodes, VecDeque::new()),
+            move |(mut nodes, mut pairs)| async move {
+                loop {
+                    if let Some(pair) = pairs.pop_front() {
+                        return Ok(Some((pair, (nodes, pairs))));
+                    }
+
+                    let Some((node, index)) = nodes.last_mut() else {
+                        return Ok(None);
+                    };
+
+                    let Some(pointer) = node.pointers.get(*index) else {
+                        nodes.pop();
+                        continue;
+                    };
+
+                    *index += 1;
+                    match pointer {
+                        Pointer::Values(values) => {
+                            pairs.extend(values.iter().cloned());
+                            prepare_bucket(&mut pairs);
+                        }
+                        Pointer::Link(link) => {
+                            let child = Rc::clone(link.resolve_value(store).await?);
+                            nodes.push((child, 0));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
     /// Given a hashprefix representing the path to a node in the trie. This function will
     /// return the key-value pair or the intermediate node that the hashprefix points to.
     ///
@@ -818,6 +955,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::TryStreamExt;
     use helper::*;
     use wnfs_common::{utils, MemoryBlockStore};
 
@@ -1075,6 +1213,34 @@ mod tests {
         assert_eq!(keys.len(), 99);
     }
 
+    #[async_std::test]
+    async fn pairs_streams_the_same_pairs_as_flat_map() {
+        let store = &mut MemoryBlockStore::default();
+
+        let node = &mut Rc::new(Node::<[u8; 4], String>::default());
+        for i in 0..99_u32 {
+            node.set(i.to_le_bytes(), i.to_string(), store)
+                .await
+                .unwrap();
+        }
+        let expected = node
+            .flat_map(&|pair| Ok(pair.clone()), store)
+            .await
+            .unwrap();
+
+        // Reload the trie, so child nodes have to be fetched from the store
+        let cid = store.put_async_serializable(node).await.unwrap();
+        let node = Rc::new(
+            store
+                .get_deserializable::<Node<[u8; 4], String>>(&cid)
+                .await
+                .unwrap(),
+        );
+        let pairs = node.pairs(store).try_collect::<Vec<_>>().await.unwrap();
+
+        assert_eq!(pairs, expected);
+    }
+
     #[async_std::test]
     async fn can_fetch_node_at_hashprefix() {
         let store = &mut MemoryBlockStore::default();
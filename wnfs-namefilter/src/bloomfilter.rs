@@ -50,6 +50,7 @@ pub struct BloomFilter<const N: usize, const K: usize> {
 pub struct HashIndexIterator<'a, T: AsRef<[u8]>, const N: usize> {
     item: &'a T,
     index: u64,
+    bit_size: usize,
 }
 
 //------------------------------------------------------------------------------
@@ -59,7 +60,16 @@ pub struct HashIndexIterator<'a, T: AsRef<[u8]>, const N: usize> {
 impl<'a, T: AsRef<[u8]>, const N: usize> HashIndexIterator<'a, T, N> {
     /// Creates a new iterator.
     pub(super) fn new(item: &'a T) -> Self {
-        Self { item, index: 0 }
+        Self::with_bit_size(item, Self::bit_size())
+    }
+
+    /// Creates a new iterator that only generates indices below `bit_size`.
+    pub(super) fn with_bit_size(item: &'a T, bit_size: usize) -> Self {
+        Self {
+            item,
+            index: 0,
+            bit_size,
+        }
     }
 
     /// Returns the size of the bloom filter in bits.
@@ -73,7 +83,7 @@ impl<T: AsRef<[u8]>, const N: usize> Iterator for HashIndexIterator<'_, T, N> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let hash = xxh3::xxh3_64_with_seed(self.item.as_ref(), self.index) as usize;
-        let value = hash % Self::bit_size();
+        let value = hash % self.bit_size;
         self.index += 1;
         Some(value)
     }
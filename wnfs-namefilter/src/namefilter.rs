@@ -1,4 +1,5 @@
-use super::BloomFilter;
+use super::{BloomFilter, HashIndexIterator};
+use anyhow::{bail, ensure, Result};
 use sha3::{
     digest::{ExtendableOutput, Update, XofReader},
     Shake256,
@@ -11,6 +12,18 @@ use wnfs_common::HASH_BYTE_SIZE;
 
 pub const SATURATION_THRESHOLD: usize = 1019;
 
+/// The number of bits in a namefilter.
+const BIT_COUNT: usize = 2048;
+
+/// Bits needed to address any of the 2048 bits of a namefilter.
+const INDEX_BIT_SIZE: usize = 11;
+
+/// Marks compact bytes holding the filter's raw bits.
+const COMPACT_RAW_TAG: u8 = 0;
+
+/// Marks compact bytes holding the indices of the set bits.
+const COMPACT_SPARSE_TAG: u8 = 1;
+
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
@@ -31,26 +44,122 @@ pub const SATURATION_THRESHOLD: usize = 1019;
 /// ```
 pub type Namefilter = BloomFilter<256, 30>;
 
+/// The parameters a namefilter gets filled and checked with.
+///
+/// The default matches the spec: all 2048 bits, 30 hashes per item, and saturation up to
+/// [`SATURATION_THRESHOLD`] set bits. Fewer bits make filters cheaper to compare at the cost of
+/// more false positives, so deployments with very deep trees may want to tune this. Use the same
+/// config for `add_with`, `contains_with` and `saturate_with` on a filter, as items added with
+/// one config are generally not found with another.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_namefilter::{Namefilter, NamefilterConfig};
+///
+/// let config = NamefilterConfig::new(1024, 20, 500).unwrap();
+/// let mut filter = Namefilter::default();
+/// filter.add_with(&[0xF5u8; 32], &config);
+///
+/// assert!(filter.contains_with(&[0xF5u8; 32], &config));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamefilterConfig {
+    bit_count: usize,
+    hash_count: usize,
+    saturation_threshold: usize,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
 
+impl NamefilterConfig {
+    /// Creates a config that uses the first `bit_count` bits of a filter, sets `hash_count` bits
+    /// per item and saturates filters up to `saturation_threshold` set bits.
+    ///
+    /// Fails if there are no bits or more than the 2048 of a namefilter, no hashes, or the
+    /// threshold leaves no bits unset.
+    pub fn new(bit_count: usize, hash_count: usize, saturation_threshold: usize) -> Result<Self> {
+        ensure!(bit_count > 0, "Namefilter bit count must not be zero");
+        ensure!(
+            bit_count <= BIT_COUNT,
+            "Namefilter bit count {bit_count} exceeds the {BIT_COUNT} bits of a namefilter"
+        );
+        ensure!(hash_count > 0, "Namefilter hash count must not be zero");
+        ensure!(
+            saturation_threshold < bit_count,
+            "Saturation threshold {saturation_threshold} must be below the bit count {bit_count}"
+        );
+
+        Ok(Self {
+            bit_count,
+            hash_count,
+            saturation_threshold,
+        })
+    }
+
+    /// Returns the number of bits of the filter in use.
+    pub fn bit_count(&self) -> usize {
+        self.bit_count
+    }
+
+    /// Returns the number of bits set per item.
+    pub fn hash_count(&self) -> usize {
+        self.hash_count
+    }
+
+    /// Returns the number of set bits saturation stops at.
+    pub fn saturation_threshold(&self) -> usize {
+        self.saturation_threshold
+    }
+}
+
+impl Default for NamefilterConfig {
+    fn default() -> Self {
+        Self {
+            bit_count: BIT_COUNT,
+            hash_count: 30,
+            saturation_threshold: SATURATION_THRESHOLD,
+        }
+    }
+}
+
 impl Namefilter {
-    /// Adds hashes to filter until is is over the saturation threshold.
+    /// Adds an item to the filter using the bit and hash count of given config.
+    pub fn add_with<T>(&mut self, item: &T, config: &NamefilterConfig)
+    where
+        T: AsRef<[u8]>,
+    {
+        for i in Self::config_indices(item, config) {
+            self.bits.set(i, true);
+        }
+    }
+
+    /// Checks if an item added with the same config is in the filter.
+    pub fn contains_with<T>(&self, item: &T, config: &NamefilterConfig) -> bool
+    where
+        T: AsRef<[u8]>,
+    {
+        Self::config_indices(item, config).all(|i| self.bits[i])
+    }
+
+    /// Adds hashes to the filter until it is over the saturation threshold of given config.
     ///
     /// # Examples
     ///
     /// ```
-    /// use wnfs_namefilter::{SATURATION_THRESHOLD, Namefilter};
+    /// use wnfs_namefilter::{Namefilter, NamefilterConfig};
     ///
+    /// let config = NamefilterConfig::new(1024, 20, 500).unwrap();
     /// let mut filter = Namefilter::default();
-    /// filter.add(&[0xF5u8; 32]);
-    /// filter.saturate();
+    /// filter.add_with(&[0xF5u8; 32], &config);
+    /// filter.saturate_with(&config);
     ///
-    /// assert!(filter.contains(&[0xF5u8; 32]));
-    /// assert!(filter.count_ones() <= SATURATION_THRESHOLD);
+    /// assert!(filter.contains_with(&[0xF5u8; 32], &config));
+    /// assert!(filter.count_ones() <= config.saturation_threshold());
     /// ```
-    pub fn saturate(&mut self) {
+    pub fn saturate_with(&mut self, config: &NamefilterConfig) {
         let mut xof = {
             let mut h = Shake256::default();
             h.update(self.as_bytes());
@@ -61,13 +170,125 @@ impl Namefilter {
         loop {
             xof.read(hash);
             let mut clone = self.clone();
-            clone.add(hash);
-            if clone.count_ones() > SATURATION_THRESHOLD {
+            clone.add_with(hash, config);
+            if clone.count_ones() > config.saturation_threshold {
                 break;
             }
             *self = clone
         }
     }
+
+    fn config_indices<'a, T>(
+        item: &'a T,
+        config: &NamefilterConfig,
+    ) -> impl Iterator<Item = usize> + 'a
+    where
+        T: AsRef<[u8]>,
+    {
+        HashIndexIterator::<_, 256>::with_bit_size(item, config.bit_count).take(config.hash_count)
+    }
+
+    /// Adds hashes to filter until is is over the saturation threshold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_namefilter::{SATURATION_THRESHOLD, Namefilter};
+    ///
+    /// let mut filter = Namefilter::default();
+    /// filter.add(&[0xF5u8; 32]);
+    /// filter.saturate();
+    ///
+    /// assert!(filter.contains(&[0xF5u8; 32]));
+    /// assert!(filter.count_ones() <= SATURATION_THRESHOLD);
+    /// ```
+    pub fn saturate(&mut self) {
+        self.saturate_with(&NamefilterConfig::default())
+    }
+
+    /// Encodes the namefilter in as few bytes as possible.
+    ///
+    /// Filters with few bits set, like the bare names of nodes that aren't nested deeply, are
+    /// encoded as the 11-bit indices of their set bits, packed tightly. Fuller filters fall back
+    /// to their raw 256 bytes. Either way a leading tag byte says which encoding follows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_namefilter::Namefilter;
+    ///
+    /// let mut filter = Namefilter::default();
+    /// filter.add(&[0xF5u8; 32]);
+    ///
+    /// let bytes = filter.to_compact_bytes();
+    /// assert!(bytes.len() < filter.as_bytes().len());
+    /// assert_eq!(Namefilter::from_compact_bytes(&bytes).unwrap(), filter);
+    /// ```
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let sparse_size = (self.count_ones() * INDEX_BIT_SIZE).div_ceil(8);
+        if sparse_size >= self.as_bytes().len() {
+            return [&[COMPACT_RAW_TAG], self.as_bytes()].concat();
+        }
+
+        let mut bytes = Vec::with_capacity(1 + sparse_size);
+        bytes.push(COMPACT_SPARSE_TAG);
+
+        let (mut buffer, mut buffered_bits) = (0u32, 0);
+        for index in self.bits.iter_ones() {
+            buffer = (buffer << INDEX_BIT_SIZE) | index as u32;
+            buffered_bits += INDEX_BIT_SIZE;
+            while buffered_bits >= 8 {
+                buffered_bits -= 8;
+                bytes.push((buffer >> buffered_bits) as u8);
+            }
+        }
+
+        if buffered_bits > 0 {
+            bytes.push((buffer << (8 - buffered_bits)) as u8);
+        }
+
+        bytes
+    }
+
+    /// Decodes a namefilter encoded with [`to_compact_bytes`](Self::to_compact_bytes).
+    ///
+    /// Fails if the bytes aren't in either encoding, or list set bits out of order.
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some((tag, body)) = bytes.split_first() else {
+            bail!("Compact namefilter bytes are empty");
+        };
+
+        match *tag {
+            COMPACT_RAW_TAG => Self::try_from(body.to_vec()),
+            COMPACT_SPARSE_TAG => {
+                let mut namefilter = Self::default();
+                let index_count = body.len() * 8 / INDEX_BIT_SIZE;
+                let (mut buffer, mut buffered_bits) = (0u32, 0);
+                let mut previous = None;
+                let mut bytes = body.iter();
+                for _ in 0..index_count {
+                    while buffered_bits < INDEX_BIT_SIZE {
+                        // There are enough bytes for all indices by construction of index_count
+                        buffer = (buffer << 8) | *bytes.next().unwrap() as u32;
+                        buffered_bits += 8;
+                    }
+
+                    buffered_bits -= INDEX_BIT_SIZE;
+                    let index = ((buffer >> buffered_bits) & ((1 << INDEX_BIT_SIZE) - 1)) as usize;
+                    ensure!(
+                        previous < Some(index),
+                        "Compact namefilter indices must be strictly increasing"
+                    );
+
+                    namefilter.bits.set(index, true);
+                    previous = Some(index);
+                }
+
+                Ok(namefilter)
+            }
+            other => bail!("Unknown compact namefilter encoding: {other}"),
+        }
+    }
 }
 
 impl AsRef<[u8]> for Namefilter {
@@ -101,4 +322,120 @@ mod tests {
             assert!(namefilter.count_ones() <= SATURATION_THRESHOLD);
         }
     }
+
+    #[test]
+    fn default_config_matches_spec_parameters() {
+        let config = NamefilterConfig::default();
+        let mut with_config = Namefilter::new();
+        let mut without_config = Namefilter::new();
+        for i in 0..10u8 {
+            with_config.add_with(&[i], &config);
+            without_config.add(&[i]);
+        }
+
+        assert_eq!(with_config, without_config);
+
+        with_config.saturate_with(&config);
+        without_config.saturate();
+
+        assert_eq!(with_config, without_config);
+    }
+
+    #[test]
+    fn configured_filters_have_no_false_negatives() {
+        let config = NamefilterConfig::new(512, 8, 250).unwrap();
+        let mut namefilter = Namefilter::new();
+        let items = (0..20u32).map(|i| i.to_le_bytes()).collect::<Vec<_>>();
+        for item in items.iter() {
+            namefilter.add_with(item, &config);
+        }
+
+        namefilter.saturate_with(&config);
+
+        assert!(namefilter.count_ones() <= config.saturation_threshold());
+        assert!(namefilter.bits[config.bit_count()..].not_any());
+        for item in items.iter() {
+            assert!(namefilter.contains_with(item, &config));
+        }
+    }
+
+    #[test]
+    fn configured_false_positive_rate_stays_within_bound() {
+        let (bit_count, hash_count, item_count) = (1024, 10, 50);
+        let config = NamefilterConfig::new(bit_count, hash_count, 500).unwrap();
+        let mut namefilter = Namefilter::new();
+        for i in 0..item_count as u32 {
+            namefilter.add_with(&i.to_le_bytes(), &config);
+        }
+
+        // (1 - e^(-kn/m))^k is about 7e-5 for these parameters
+        let exponent = -((hash_count * item_count) as f64) / bit_count as f64;
+        let expected_rate = (1.0 - exponent.exp()).powi(hash_count as i32);
+
+        let samples = 100_000;
+        let false_positives = (item_count as u32..item_count as u32 + samples)
+            .filter(|i| namefilter.contains_with(&i.to_le_bytes(), &config))
+            .count();
+        let rate = false_positives as f64 / samples as f64;
+
+        assert!(rate <= expected_rate * 3.0, "{rate} > 3 * {expected_rate}");
+    }
+
+    #[test]
+    fn compact_bytes_round_trip() {
+        let mut namefilter = Namefilter::new();
+        let mut namefilters = vec![namefilter.clone()];
+        for i in 0..47u8 {
+            namefilter.add(&[i]);
+            namefilters.push(namefilter.clone());
+        }
+        namefilter.saturate();
+        namefilters.push(namefilter);
+
+        for namefilter in namefilters {
+            let bytes = namefilter.to_compact_bytes();
+            let decoded = Namefilter::from_compact_bytes(&bytes).unwrap();
+
+            assert_eq!(decoded, namefilter);
+            for i in 0..=u8::MAX {
+                assert_eq!(decoded.contains(&[i]), namefilter.contains(&[i]));
+            }
+        }
+    }
+
+    #[test]
+    fn compact_bytes_are_smaller_for_bare_names() {
+        let mut namefilter = Namefilter::new();
+        for depth in 0..3u8 {
+            namefilter.add(&[depth; 32]);
+        }
+
+        let compact = namefilter.to_compact_bytes();
+        let serialized = wnfs_common::dagcbor::encode(&namefilter).unwrap();
+
+        assert!(compact.len() < serialized.len() / 2);
+
+        // Saturated names don't get larger than their raw bytes plus the tag
+        namefilter.saturate();
+        assert_eq!(namefilter.to_compact_bytes().len(), 257);
+    }
+
+    #[test]
+    fn malformed_compact_bytes_are_rejected() {
+        assert!(Namefilter::from_compact_bytes(&[]).is_err());
+        assert!(Namefilter::from_compact_bytes(&[2]).is_err());
+        assert!(Namefilter::from_compact_bytes(&[COMPACT_RAW_TAG, 0, 0]).is_err());
+
+        // The indices 1 and 0, out of order
+        let unordered = [COMPACT_SPARSE_TAG, 0b0000_0000, 0b0010_0000, 0b0000_0000];
+        assert!(Namefilter::from_compact_bytes(&unordered).is_err());
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        assert!(NamefilterConfig::new(0, 30, 0).is_err());
+        assert!(NamefilterConfig::new(2048, 0, 1019).is_err());
+        assert!(NamefilterConfig::new(1024, 30, 1024).is_err());
+        assert!(NamefilterConfig::new(4096, 30, 1019).is_err());
+    }
 }
@@ -7,14 +7,12 @@ use crate::{
     value,
 };
 use js_sys::{Error, Promise, Uint8Array};
-use std::{collections::BTreeSet, rc::Rc};
+use std::rc::Rc;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen_futures::future_to_promise;
 use wnfs::{
-    hamt::{ChangeType, KeyValueChange},
-    libipld::Cid,
-    namefilter::Namefilter as WnfsNamefilter,
-    private::PrivateNode as WnfsPrivateNode,
+    hamt::ChangeType,
+    private::{ForestChange as WnfsForestChange, PrivateNode as WnfsPrivateNode},
     traits::Id,
 };
 
@@ -27,7 +25,7 @@ use wnfs::{
 pub struct PrivateNode(pub(crate) WnfsPrivateNode);
 
 #[wasm_bindgen]
-pub struct ForestChange(pub(crate) KeyValueChange<WnfsNamefilter, BTreeSet<Cid>>);
+pub struct ForestChange(pub(crate) WnfsForestChange);
 
 //--------------------------------------------------------------------------------------------------
 // Implementations
@@ -10,7 +10,7 @@ use libipld::{Cid, IpldCodec};
 use rand::thread_rng;
 use std::{borrow::Cow, rc::Rc};
 use wnfs::private::{PrivateDirectory, PrivateForest, PrivateNode};
-use wnfs_common::{BlockStore, MemoryBlockStore};
+use wnfs_common::{is_not_found_error, BlockStore, MemoryBlockStore};
 use wnfs_namefilter::Namefilter;
 
 #[async_std::main]
@@ -107,9 +107,8 @@ impl<H: BlockStore, C: BlockStore> BlockStore for TieredBlockStore<H, C> {
     async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
         match self.hot.get_block(cid).await {
             Ok(block) => Ok(block),
-            // We could technically get better about this
-            // and only match "NotFound" errors.
-            Err(_) => self.cold.get_block(cid).await,
+            Err(e) if is_not_found_error(&e) => self.cold.get_block(cid).await,
+            Err(e) => Err(e),
         }
     }
 
@@ -1,5 +1,6 @@
 //! Errors
 
+use libipld::{Cid, Ipld};
 use semver::Version;
 use skip_ratchet::PreviousErr;
 use thiserror::Error;
@@ -15,11 +16,14 @@ pub enum FsError {
     #[error("Invalid WNFS path")]
     InvalidPath,
 
-    #[error("Expected a file")]
-    NotAFile,
+    #[error("Invalid path segment: {0:?}")]
+    InvalidPathSegment(String),
 
-    #[error("Expected a directory")]
-    NotADirectory,
+    #[error("Expected a file{}", display_path(.0))]
+    NotAFile(Vec<String>),
+
+    #[error("Expected a directory{}", display_path(.0))]
+    NotADirectory(Vec<String>),
 
     #[error("Cannot find file or directory")]
     NotFound,
@@ -50,6 +54,29 @@ pub enum FsError {
 
     #[error("Cannot find private ref with specified root path")]
     PrivateRefNotFound,
+
+    #[error("Invalid file content: {0}")]
+    InvalidFileContent(String),
+
+    #[error("Invalid file config: {0}")]
+    InvalidFileConfig(String),
+
+    #[error(
+        "Found multiple values at a private forest label, but none decrypt with the given key"
+    )]
+    LabelCollision,
+
+    #[error("Cannot merge revisions of unrelated nodes")]
+    UnrelatedRevisions,
+
+    #[error("Revision {0} is not in the history of this node")]
+    NotInHistory(Cid),
+
+    #[error("Unsupported private node header version: {0:?}")]
+    UnsupportedHeaderVersion(Ipld),
+
+    #[error("Nodes loaded from a snapshot key are read-only")]
+    ReadOnlySnapshot,
 }
 
 /// Data sharing related errors
@@ -65,6 +92,15 @@ pub enum ShareError {
     UnsupportedSnapshotShareReceipt,
 }
 
+/// Private forest errors.
+#[derive(Debug, Error)]
+pub enum ForestError {
+    #[error(
+        "Label already holds a node with header {existing}, can't add a node with header {new}"
+    )]
+    LabelCollision { existing: Cid, new: Cid },
+}
+
 /// AES-GCM errors.
 #[derive(Debug, Error)]
 pub enum AesError {
@@ -82,9 +118,25 @@ pub enum RsaError {
     #[error("Invalid public key: {0}")]
     InvalidPublicKey(anyhow::Error),
 
+    #[error("Invalid private key: {0}")]
+    InvalidPrivateKey(anyhow::Error),
+
     #[error("Encryption failed: {0}")]
     EncryptionFailed(anyhow::Error),
 
     #[error("Decryption failed: {0}")]
     DecryptionFailed(anyhow::Error),
 }
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Formats the path of a type-mismatch error, if one is known.
+fn display_path(path_segments: &[String]) -> String {
+    if path_segments.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", path_segments.join("/"))
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{stream::FuturesUnordered, TryStreamExt};
+use libipld::{multihash::Code, Cid, IpldCodec};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+use wnfs_common::{dagcbor::DecodeLimits, BlockStore, BlockStoreError};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Holds the blocks put into it in memory until they're flushed to the wrapped store all at once.
+///
+/// CIDs are computed the way the wrapped store computes them, so they don't change when the
+/// blocks get flushed.
+pub(crate) struct BufferedBlockStore<'a, B: BlockStore> {
+    inner: &'a B,
+    buffered: RefCell<HashMap<Cid, Vec<u8>>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a, B: BlockStore> BufferedBlockStore<'a, B> {
+    pub(crate) fn new(inner: &'a B) -> Self {
+        Self {
+            inner,
+            buffered: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Puts all buffered blocks into the wrapped store concurrently.
+    pub(crate) async fn flush(self) -> Result<()> {
+        let blocks = self.buffered.into_inner();
+        blocks
+            .into_iter()
+            .map(|(cid, bytes)| async move {
+                let codec = IpldCodec::try_from(cid.codec())?;
+                let stored_cid = self.inner.put_block(bytes, codec).await?;
+                if stored_cid != cid {
+                    Err(BlockStoreError::CIDMismatch(cid))?;
+                }
+
+                Ok(())
+            })
+            .collect::<FuturesUnordered<_>>()
+            .try_collect()
+            .await
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for BufferedBlockStore<'_, B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        if let Some(bytes) = self.buffered.borrow().get(cid) {
+            return Ok(Cow::Owned(bytes.clone()));
+        }
+
+        Ok(Cow::Owned(self.inner.get_block(cid).await?.into_owned()))
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.create_cid(&bytes, codec)?;
+        self.buffered.borrow_mut().insert(cid, bytes);
+        Ok(cid)
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+
+    fn hash_code(&self) -> Code {
+        self.inner.hash_code()
+    }
+}
@@ -0,0 +1,219 @@
+use super::{PrivateForest, PrivateNode, PrivateRef, KEY_BYTE_SIZE};
+use anyhow::Result;
+use libipld::Cid;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+use wnfs_common::{BlockStore, HashOutput};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A private forest that remembers the nodes loaded through it.
+///
+/// Loading the same [`PrivateRef`] again returns the node loaded the first time, along with every
+/// child node resolved under it since, so repeated traversals don't fetch and decrypt the same
+/// blocks again. Rewriting a node gives it a new ref, so the cached revision is never returned for
+/// it.
+///
+/// It dereferences to the wrapped forest, so it can be passed wherever a forest is expected,
+/// including to mutations. The cache lives as long as the `CachingForest`, so it's meant to be
+/// scoped to a session or a logical operation.
+#[derive(Debug)]
+pub struct CachingForest {
+    forest: Rc<PrivateForest>,
+    cache: RefCell<HashMap<CacheKey, PrivateNode>>,
+}
+
+/// Everything in a [`PrivateRef`], including the key, so a node is only returned for refs that
+/// could decrypt it.
+type CacheKey = (HashOutput, [u8; KEY_BYTE_SIZE], Cid);
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl CachingForest {
+    /// Wraps a forest with an empty cache.
+    pub fn new(forest: Rc<PrivateForest>) -> Self {
+        Self {
+            forest,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Loads the node the ref points to, reusing it if it was loaded through this forest before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{CachingForest, PrivateDirectory, PrivateForest},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let forest = CachingForest::new(Rc::clone(forest));
+    ///     let node = forest.load(&private_ref, store).await.unwrap();
+    ///     let cached_node = forest.load(&private_ref, store).await.unwrap();
+    ///
+    ///     assert!(Rc::ptr_eq(&node.as_dir().unwrap(), &cached_node.as_dir().unwrap()));
+    /// }
+    /// ```
+    pub async fn load(
+        &self,
+        private_ref: &PrivateRef,
+        store: &impl BlockStore,
+    ) -> Result<PrivateNode> {
+        let key = (
+            private_ref.saturated_name_hash,
+            private_ref.temporal_key.0.clone().bytes(),
+            private_ref.content_cid,
+        );
+
+        if let Some(node) = self.cache.borrow().get(&key) {
+            return Ok(node.clone());
+        }
+
+        let node = PrivateNode::load(private_ref, &self.forest, store).await?;
+        self.cache.borrow_mut().insert(key, node.clone());
+        Ok(node)
+    }
+
+    /// Returns the wrapped forest, dropping the cache.
+    pub fn into_inner(self) -> Rc<PrivateForest> {
+        self.forest
+    }
+}
+
+impl Deref for CachingForest {
+    type Target = Rc<PrivateForest>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.forest
+    }
+}
+
+impl DerefMut for CachingForest {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.forest
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private::PrivateDirectory;
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+    use wnfs_common::MemoryBlockStore;
+    use wnfs_namefilter::Namefilter;
+
+    #[test(async_std::test)]
+    async fn repeated_traversals_reuse_resolved_nodes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = ["docs".to_string(), "nested".into(), "notes.txt".into()];
+        root_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                b"Hello".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+
+        let forest = CachingForest::new(Rc::clone(forest));
+        let traverse = || async {
+            store.reset_stats();
+            let dir = forest
+                .load(&private_ref, store)
+                .await
+                .unwrap()
+                .as_dir()
+                .unwrap();
+            let content = dir.read(&path, false, &forest, store).await.unwrap();
+            assert_eq!(content, b"Hello");
+            store.stats().unwrap().blocks_read
+        };
+
+        let first_gets = traverse().await;
+        let second_gets = traverse().await;
+        assert!(first_gets > 0);
+        assert!(second_gets < first_gets);
+    }
+
+    #[test(async_std::test)]
+    async fn rewritten_nodes_are_not_served_from_cache() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut CachingForest::new(Rc::new(PrivateForest::new()));
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let old_ref = root_dir.store(forest, store, rng).await.unwrap();
+        forest.load(&old_ref, store).await.unwrap();
+
+        root_dir
+            .mkdir(&["docs".into()], true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+        let new_ref = root_dir.store(forest, store, rng).await.unwrap();
+        assert_ne!(new_ref, old_ref);
+
+        let old_dir = forest
+            .load(&old_ref, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+        let new_dir = forest
+            .load(&new_ref, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+        assert!(old_dir
+            .ls(&[], false, forest, store)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            new_dir.ls(&[], false, forest, store).await.unwrap().len(),
+            1
+        );
+    }
+}
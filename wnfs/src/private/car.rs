@@ -0,0 +1,262 @@
+use super::{gc::header_links, PrivateForest, PrivateNode, PrivateRef};
+use anyhow::{bail, Result};
+use libipld::{Cid, Ipld, IpldCodec};
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+    rc::Rc,
+};
+use wnfs_common::{dagcbor, read_car, write_carv1, BlockStore, CarError};
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Writes a whole private file system as a CARv1, with the forest's CID as its only root.
+///
+/// The CAR holds every block the forest refers to: its HAMT nodes, the encrypted node headers and
+/// contents, and the blocks of file content. Each block is written once. The root ref isn't
+/// written, as it holds the keys, but it has to load from the forest for the export to start.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use chrono::Utc;
+/// use rand::thread_rng;
+/// use wnfs::{
+///     private::{export_filesystem_car, PrivateDirectory, PrivateForest},
+///     common::{read_car, MemoryBlockStore},
+///     namefilter::Namefilter,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///     let forest = &mut Rc::new(PrivateForest::new());
+///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+///     let root = dir.store(forest, store, rng).await.unwrap();
+///
+///     let mut car = Vec::new();
+///     export_filesystem_car(&root, forest, store, &mut car).await.unwrap();
+///
+///     let roots = read_car(car.as_slice(), &MemoryBlockStore::default()).await.unwrap();
+///     assert_eq!(roots, vec![forest.store(store).await.unwrap()]);
+/// }
+/// ```
+pub async fn export_filesystem_car(
+    root: &PrivateRef,
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+    out: impl Write,
+) -> Result<()> {
+    PrivateNode::load(root, forest, store).await?;
+    let forest_cid = forest.store(store).await?;
+
+    let mut blocks = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![forest_cid];
+    while let Some(cid) = stack.pop() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        blocks.push(cid);
+        if cid.codec() == u64::from(IpldCodec::DagCbor) {
+            let mut links = Vec::new();
+            dagcbor::decode::<Ipld>(&store.get_block(&cid).await?)?.references(&mut links);
+            stack.extend(links.into_iter().rev());
+        } else {
+            stack.extend(header_links(&cid, store).await?);
+        }
+    }
+
+    write_carv1(&[forest_cid], blocks, store, out).await
+}
+
+/// Reads a CAR written by [`export_filesystem_car`] into the store, and loads the forest at its
+/// root. Returns the forest's CID along with the forest.
+///
+/// Every block is checked against its CID, so corrupted blocks fail the import. The store needs to
+/// support `put_block_keyed`.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use chrono::Utc;
+/// use rand::thread_rng;
+/// use wnfs::{
+///     private::{
+///         export_filesystem_car, import_filesystem_car, PrivateDirectory, PrivateForest,
+///         PrivateNode,
+///     },
+///     common::MemoryBlockStore,
+///     namefilter::Namefilter,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///     let forest = &mut Rc::new(PrivateForest::new());
+///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+///     let root = dir.store(forest, store, rng).await.unwrap();
+///
+///     let mut car = Vec::new();
+///     export_filesystem_car(&root, forest, store, &mut car).await.unwrap();
+///
+///     let imported_store = &MemoryBlockStore::default();
+///     let (_, imported_forest) = import_filesystem_car(car.as_slice(), imported_store)
+///         .await
+///         .unwrap();
+///
+///     let node = PrivateNode::load(&root, &imported_forest, imported_store).await.unwrap();
+///     assert!(node.is_dir());
+/// }
+/// ```
+pub async fn import_filesystem_car(
+    reader: impl Read,
+    store: &impl BlockStore,
+) -> Result<(Cid, Rc<PrivateForest>)> {
+    let roots = read_car(reader, store).await?;
+    let [forest_cid] = roots[..] else {
+        bail!(CarError::UnexpectedRootCount(roots.len()));
+    };
+
+    let forest = PrivateForest::load(&forest_cid, store).await?;
+    Ok((forest_cid, Rc::new(forest)))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private::PrivateDirectory;
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use std::rc::Rc;
+    use test_log::test;
+    use wnfs_common::{BlockStoreError, MemoryBlockStore, MAX_BLOCK_SIZE};
+    use wnfs_namefilter::Namefilter;
+
+    #[test(async_std::test)]
+    async fn exported_filesystem_reads_back_from_fresh_store() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let large_content = (0..MAX_BLOCK_SIZE * 2)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let files: Vec<(Vec<String>, Vec<u8>)> = vec![
+            (vec!["readme.md".into()], b"# Readme".to_vec()),
+            (
+                vec!["docs".into(), "nested".into(), "notes.txt".into()],
+                b"Some notes".to_vec(),
+            ),
+            (vec!["docs".into(), "large.bin".into()], large_content),
+        ];
+        for (path, content) in files.iter() {
+            root_dir
+                .write(path, true, Utc::now(), content.clone(), forest, store, rng)
+                .await
+                .unwrap();
+        }
+        let root = root_dir.store(forest, store, rng).await.unwrap();
+
+        // Blocks the file system doesn't refer to stay behind
+        let orphan = store
+            .put_block(b"orphan".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let mut car = Vec::new();
+        export_filesystem_car(&root, forest, store, &mut car)
+            .await
+            .unwrap();
+
+        let imported_store = &MemoryBlockStore::new();
+        let (forest_cid, imported_forest) = import_filesystem_car(car.as_slice(), imported_store)
+            .await
+            .unwrap();
+        assert_eq!(forest_cid, forest.store(store).await.unwrap());
+
+        let imported_dir = PrivateNode::load(&root, &imported_forest, imported_store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+
+        for (path, content) in files.iter() {
+            let read = imported_dir
+                .read(path, true, &imported_forest, imported_store)
+                .await
+                .unwrap();
+            assert_eq!(&read, content);
+        }
+
+        let imported_blocks = imported_store.list_blocks().await.unwrap();
+        assert!(!imported_blocks.contains(&orphan));
+        assert_eq!(
+            imported_blocks.len(),
+            store.list_blocks().await.unwrap().len() - 1
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn corrupted_and_rootless_cars_are_rejected() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        root_dir
+            .write(
+                &["notes.txt".into()],
+                true,
+                Utc::now(),
+                b"Hello".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let root = root_dir.store(forest, store, rng).await.unwrap();
+
+        let mut car = Vec::new();
+        export_filesystem_car(&root, forest, store, &mut car)
+            .await
+            .unwrap();
+        let last_byte = car.len() - 1;
+        car[last_byte] ^= 1;
+
+        let result = import_filesystem_car(car.as_slice(), &MemoryBlockStore::new()).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<BlockStoreError>(),
+            Some(BlockStoreError::CIDMismatch(_))
+        ));
+
+        let mut rootless = Vec::new();
+        write_carv1(&[], [], store, &mut rootless).await.unwrap();
+
+        let result = import_filesystem_car(rootless.as_slice(), &MemoryBlockStore::new()).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<CarError>(),
+            Some(CarError::UnexpectedRootCount(0))
+        ));
+    }
+}
@@ -0,0 +1,266 @@
+use super::{PrivateDirectory, PrivateForest, PrivateNode};
+use anyhow::Result;
+use async_recursion::async_recursion;
+use std::rc::Rc;
+use wnfs_common::BlockStore;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A change to a node between two revisions of a directory, with the path to the node.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeChange {
+    /// The node only exists in the new revision.
+    Added(Vec<String>),
+    /// The node only exists in the old revision. Its descendants aren't listed separately.
+    Removed(Vec<String>),
+    /// The file's content or metadata changed, or the node turned into a file from a directory
+    /// or the other way around.
+    Modified(Vec<String>),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Compares two revisions of a directory and returns the changes made to get from `old` to `new`.
+///
+/// Entries are compared recursively. Subtrees whose stored content CIDs match are skipped
+/// without being loaded.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use chrono::Utc;
+/// use rand::thread_rng;
+/// use wnfs::{
+///     private::{diff, NodeChange, PrivateDirectory, PrivateForest},
+///     common::MemoryBlockStore,
+///     namefilter::Namefilter,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///     let forest = &mut Rc::new(PrivateForest::new());
+///     let old_dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+///     old_dir.store(forest, store, rng).await.unwrap();
+///
+///     let new_dir = &mut Rc::clone(&old_dir);
+///     new_dir
+///         .write(&["hello.txt".into()], true, Utc::now(), b"Hello".to_vec(), forest, store, rng)
+///         .await
+///         .unwrap();
+///
+///     let changes = diff(&old_dir, new_dir, forest, store).await.unwrap();
+///
+///     assert_eq!(changes, vec![NodeChange::Added(vec!["hello.txt".into()])]);
+/// }
+/// ```
+pub async fn diff(
+    old: &Rc<PrivateDirectory>,
+    new: &Rc<PrivateDirectory>,
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+) -> Result<Vec<NodeChange>> {
+    let mut changes = vec![];
+    diff_helper(&mut vec![], old, new, forest, store, &mut changes).await?;
+    Ok(changes)
+}
+
+#[async_recursion(?Send)]
+async fn diff_helper(
+    path: &mut Vec<String>,
+    old: &PrivateDirectory,
+    new: &PrivateDirectory,
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+    changes: &mut Vec<NodeChange>,
+) -> Result<()> {
+    let old_entries = &old.content.entries;
+    let new_entries = &new.content.entries;
+
+    for (name, old_link) in old_entries {
+        path.push(name.clone());
+        match new_entries.get(name) {
+            None => changes.push(NodeChange::Removed(path.clone())),
+            Some(new_link) if !old_link.is_same_revision(new_link) => {
+                let old_node = old_link.resolve_node(forest, store).await?;
+                let new_node = new_link.resolve_node(forest, store).await?;
+                match (old_node, new_node) {
+                    (PrivateNode::Dir(old_dir), PrivateNode::Dir(new_dir)) => {
+                        diff_helper(path, old_dir, new_dir, forest, store, changes).await?;
+                    }
+                    (PrivateNode::File(old_file), PrivateNode::File(new_file)) => {
+                        if old_file.content.content != new_file.content.content
+                            || old_file.content.metadata != new_file.content.metadata
+                        {
+                            changes.push(NodeChange::Modified(path.clone()));
+                        }
+                    }
+                    _ => changes.push(NodeChange::Modified(path.clone())),
+                }
+            }
+            Some(_) => {}
+        }
+        path.pop();
+    }
+
+    for name in new_entries.keys() {
+        if !old_entries.contains_key(name) {
+            path.push(name.clone());
+            changes.push(NodeChange::Added(path.clone()));
+            path.pop();
+        }
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::path;
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+    use wnfs_common::MemoryBlockStore;
+    use wnfs_namefilter::Namefilter;
+
+    #[test(async_std::test)]
+    async fn diff_detects_added_removed_and_modified_nodes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        for file in [
+            "docs/a.txt",
+            "docs/b.txt",
+            "pics/cats/c.jpg",
+            "static/d.txt",
+        ] {
+            root_dir
+                .write(
+                    &path(file),
+                    true,
+                    Utc::now(),
+                    b"old".to_vec(),
+                    forest,
+                    store,
+                    rng,
+                )
+                .await
+                .unwrap();
+        }
+
+        root_dir.store(forest, store, rng).await.unwrap();
+        let old_dir = Rc::clone(root_dir);
+
+        root_dir
+            .write(
+                &path("docs/new.txt"),
+                true,
+                Utc::now(),
+                b"new".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        root_dir
+            .write(
+                &path("docs/a.txt"),
+                true,
+                Utc::now(),
+                b"changed".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        root_dir
+            .rm(&path("pics"), true, forest, store)
+            .await
+            .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+
+        let changes = diff(&old_dir, root_dir, forest, store).await.unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                NodeChange::Modified(path("docs/a.txt")),
+                NodeChange::Added(path("docs/new.txt")),
+                NodeChange::Removed(path("pics")),
+            ]
+        );
+
+        let changes = diff(root_dir, &old_dir, forest, store).await.unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                NodeChange::Modified(path("docs/a.txt")),
+                NodeChange::Removed(path("docs/new.txt")),
+                NodeChange::Added(path("pics")),
+            ]
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn diff_skips_identical_subtrees_without_loading_them() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        root_dir
+            .write(
+                &path("docs/a.txt"),
+                true,
+                Utc::now(),
+                b"a".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+
+        let old_dir = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+        let new_dir = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+
+        // Loading the "docs" subtree from this store would fail
+        let empty_store = &MemoryBlockStore::default();
+        let changes = diff(&old_dir, &new_dir, forest, empty_store).await.unwrap();
+
+        assert!(changes.is_empty());
+    }
+}
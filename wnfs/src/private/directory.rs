@@ -1,12 +1,21 @@
 use super::{
-    encrypted::Encrypted, link::PrivateLink, AesKey, PrivateDirectoryContentSerializable,
-    PrivateFile, PrivateForest, PrivateNode, PrivateNodeContentSerializable, PrivateNodeHeader,
-    PrivateRef, SnapshotKey, TemporalKey, KEY_BYTE_SIZE,
+    buffered::BufferedBlockStore, encrypted::Encrypted, link::PrivateLink, unixfs,
+    PrivateDirectoryContentSerializable, PrivateFile, PrivateFileConfig, PrivateForest,
+    PrivateNode, PrivateNodeContentSerializable, PrivateNodeHeader, PrivateRef, SnapshotKey,
+    TemporalKey, Transaction,
+};
+use crate::{
+    error::FsError,
+    public::{PublicDirectory, PublicNode},
+    traits::Id,
+    SearchResult, WNFS_VERSION,
 };
-use crate::{error::FsError, traits::Id, SearchResult, WNFS_VERSION};
 use anyhow::{bail, ensure, Result};
 use async_once_cell::OnceCell;
+use async_recursion::async_recursion;
+use async_stream::try_stream;
 use chrono::{DateTime, Utc};
+use futures::{future, future::LocalBoxFuture, Future, Stream, TryStreamExt};
 use libipld::{Cid, Ipld};
 use rand_core::RngCore;
 use std::{
@@ -16,7 +25,7 @@ use std::{
 };
 use wnfs_common::{
     utils::{self, error},
-    BlockStore, HashOutput, Metadata, PathNodes, PathNodesResult,
+    BlockStore, HashOutput, Metadata, PathNodes, PathNodesResult, DEFAULT_DIR_MODE,
 };
 use wnfs_namefilter::Namefilter;
 
@@ -164,6 +173,108 @@ impl PrivateDirectory {
         Ok(dir)
     }
 
+    /// Recreates a public directory and everything below it as private nodes.
+    ///
+    /// Every node gets a fresh header and file content gets encrypted into the forest. The
+    /// metadata of all nodes is carried over, but their history isn't. Public file content
+    /// has to be stored the way [`PublicFile::get_content`] can read it.
+    ///
+    /// The returned directory isn't stored yet.
+    ///
+    /// [`PublicFile::get_content`]: crate::public::PublicFile::get_content
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use libipld::IpldCodec;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     public::PublicDirectory,
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///
+    ///     let public_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+    ///     let content_cid = store
+    ///         .put_block(b"Hello".to_vec(), IpldCodec::Raw)
+    ///         .await
+    ///         .unwrap();
+    ///     public_dir
+    ///         .write(&["hello.txt".into()], content_cid, Utc::now(), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let private_dir = Rc::new(
+    ///         PrivateDirectory::from_public(public_dir, Namefilter::default(), forest, store, rng)
+    ///             .await
+    ///             .unwrap(),
+    ///     );
+    ///
+    ///     let content = private_dir
+    ///         .read(&["hello.txt".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(content, b"Hello");
+    /// }
+    /// ```
+    #[async_recursion(?Send)]
+    pub async fn from_public(
+        public_dir: &PublicDirectory,
+        parent_bare_name: Namefilter,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<Self> {
+        let mut dir = Self::new(parent_bare_name, Utc::now(), rng);
+        dir.content.metadata = public_dir.metadata.clone();
+
+        for (name, link) in public_dir.userland.iter() {
+            let node = match link.resolve_value(store).await? {
+                PublicNode::Dir(public_child) => {
+                    let child = Self::from_public(
+                        public_child,
+                        dir.header.bare_name.clone(),
+                        forest,
+                        store,
+                        rng,
+                    )
+                    .await?;
+                    PrivateNode::Dir(Rc::new(child))
+                }
+                PublicNode::File(public_file) => {
+                    let content = public_file.get_content(store).await?;
+                    let mut file = PrivateFile::with_content(
+                        dir.header.bare_name.clone(),
+                        Utc::now(),
+                        content,
+                        forest,
+                        store,
+                        rng,
+                    )
+                    .await?;
+                    file.content.metadata = public_file.metadata.clone();
+                    PrivateNode::File(Rc::new(file))
+                }
+            };
+
+            dir.content
+                .entries
+                .insert(name.clone(), PrivateLink::from(node));
+        }
+
+        Ok(dir)
+    }
+
     /// Uses specified path segments and their existence in the file tree to generate `PathNodes`.
     ///
     /// Supports cases where the entire path does not exist.
@@ -237,6 +348,18 @@ impl PrivateDirectory {
         &self.content.metadata
     }
 
+    /// Gets the POSIX permission bits of the directory, or [`DEFAULT_DIR_MODE`] if none were set.
+    pub fn get_mode(self: &Rc<Self>) -> u32 {
+        self.get_metadata().get_mode().unwrap_or(DEFAULT_DIR_MODE)
+    }
+
+    /// Gets the metadata of the directory for changing it, e.g. to set attributes.
+    ///
+    /// If the directory was stored before, this starts a new revision of it.
+    pub fn get_metadata_mut(self: &mut Rc<Self>) -> Result<&mut Metadata> {
+        Ok(&mut self.prepare_next_revision()?.content.metadata)
+    }
+
     /// Looks up a node by its path name in the current directory.
     ///
     /// # Examples
@@ -283,6 +406,7 @@ impl PrivateDirectory {
         forest: &PrivateForest,
         store: &impl BlockStore,
     ) -> Result<Option<PrivateNode>> {
+        crate::utils::ensure_valid_path_segment(path_segment)?;
         Ok(match self.content.entries.get(path_segment) {
             Some(private_link) => {
                 let private_node = private_link.resolve_node(forest, store).await?;
@@ -384,11 +508,36 @@ impl PrivateDirectory {
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<&'a mut Self> {
+        let (dir, _) = self
+            .get_or_create_leaf_dir_mut_counting(
+                path_segments,
+                time,
+                search_latest,
+                forest,
+                store,
+                rng,
+            )
+            .await?;
+
+        Ok(dir)
+    }
+
+    /// Like `get_or_create_leaf_dir_mut`, but also returns how many of the trailing path
+    /// segments had to be created.
+    async fn get_or_create_leaf_dir_mut_counting<'a>(
+        self: &'a mut Rc<Self>,
+        path_segments: &[String],
+        time: DateTime<Utc>,
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<(&'a mut Self, usize)> {
         match self
             .get_leaf_dir_mut(path_segments, search_latest, forest, store)
             .await?
         {
-            SearchResult::Found(dir) => Ok(dir),
+            SearchResult::Found(dir) => Ok((dir, 0)),
             SearchResult::Missing(mut dir, depth) => {
                 for segment in &path_segments[depth..] {
                     dir = Rc::make_mut(
@@ -410,9 +559,11 @@ impl PrivateDirectory {
                     );
                 }
 
-                Ok(dir)
+                Ok((dir, path_segments.len() - depth))
+            }
+            SearchResult::NotADir(_, depth) => {
+                bail!(FsError::NotADirectory(path_segments[..=depth].to_vec()))
             }
-            SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
         }
     }
 
@@ -423,6 +574,8 @@ impl PrivateDirectory {
     /// Otherwise, it clones itself, stores its current CID in the previous links and
     /// advances its ratchet.
     pub(crate) fn prepare_next_revision<'a>(self: &'a mut Rc<Self>) -> Result<&'a mut Self> {
+        self.header.ensure_writable()?;
+
         let Some(previous_cid) = self.content.persisted_as.get().cloned() else {
             // The current revision wasn't written yet.
             // There's no point in advancing the revision even further.
@@ -450,6 +603,101 @@ impl PrivateDirectory {
         })
     }
 
+    /// Rotates the keys of this directory and every node below it, so the subtree can be handed to
+    /// a new owner.
+    ///
+    /// Every node gets a fresh ratchet and inumber and inherits the write access rules of
+    /// `parent_bare_name`. The entries of the subtree's current revisions, including external
+    /// file content, are removed from the forest, so private refs from before the rotation
+    /// can't be used to read them anymore. Older revisions are left untouched.
+    ///
+    /// The forest is only updated once the whole subtree has been rotated. If anything fails
+    /// along the way, both the forest and this directory stay as they were.
+    ///
+    /// Returns the private ref to the rotated directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateNode, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = &mut Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///
+    ///     dir.mkdir(&["pictures".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     let old_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let new_ref = dir
+    ///         .rotate_subtree_keys(Namefilter::default(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(PrivateNode::load(&old_ref, forest, store).await.is_err());
+    ///     assert!(PrivateNode::load(&new_ref, forest, store).await.is_ok());
+    /// }
+    /// ```
+    pub async fn rotate_subtree_keys(
+        self: &mut Rc<Self>,
+        parent_bare_name: Namefilter,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<PrivateRef> {
+        let mut working_forest = Rc::clone(forest);
+
+        // Collect the labels the subtree is currently stored at, before its keys change.
+        let mut old_labels = self
+            .get_private_ref()
+            .map(|private_ref| private_ref.saturated_name_hash)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let descendants = self
+            .walk(&[], false, forest, store)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        for (_, node) in descendants {
+            old_labels.extend(
+                node.get_private_ref()
+                    .map(|private_ref| private_ref.saturated_name_hash),
+            );
+
+            if let PrivateNode::File(file) = node {
+                old_labels.extend(file.get_shard_label_hashes());
+            }
+        }
+
+        let mut node = PrivateNode::Dir(Rc::clone(self));
+        node.update_ancestry(parent_bare_name, &mut working_forest, store, rng)
+            .await?;
+
+        for label in old_labels.iter() {
+            working_forest.remove_encrypted(label, store).await?;
+        }
+
+        let rotated = node.as_dir()?;
+        let private_ref = rotated.store(&mut working_forest, store, rng).await?;
+
+        *forest = working_forest;
+        *self = rotated;
+
+        Ok(private_ref)
+    }
+
     /// This prepares this directory for key rotation, usually for moving or
     /// copying the directory to some other place.
     ///
@@ -516,6 +764,7 @@ impl PrivateDirectory {
         forest: &PrivateForest,
         store: &impl BlockStore,
     ) -> Result<Option<PrivateNode>> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
         let Some((tail, path)) = path_segments.split_last() else {
             return Ok(None);
         };
@@ -530,6 +779,202 @@ impl PrivateDirectory {
         dir.lookup_node(tail, search_latest, forest, store).await
     }
 
+    /// Decrypts the node at the given path and everything below it, and writes the plaintext
+    /// into `out_store` as a UnixFS DAG that IPFS gateways can serve.
+    ///
+    /// Files are split into raw leaf blocks. Files spanning more than one block are linked by
+    /// a balanced tree of dag-pb file nodes. Directories become dag-pb directory nodes. An empty
+    /// path exports this directory.
+    ///
+    /// Returns the CID of the exported node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let out_store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = &mut Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///
+    ///     dir.write(&["hello.txt".into()], true, Utc::now(), b"Hello".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let cid = dir
+    ///         .export_unixfs(&["hello.txt".into()], true, forest, store, out_store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     // Small files are a single raw block
+    ///     assert_eq!(out_store.get_block(&cid).await.unwrap().as_slice(), b"Hello");
+    /// }
+    /// ```
+    pub async fn export_unixfs(
+        self: &Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+        out_store: &impl BlockStore,
+    ) -> Result<Cid> {
+        let node = if path_segments.is_empty() {
+            PrivateNode::Dir(Rc::clone(self))
+        } else {
+            self.get_node(path_segments, search_latest, forest, store)
+                .await?
+                .ok_or(FsError::NotFound)?
+        };
+
+        unixfs::export_node(&node, forest, store, out_store).await
+    }
+
+    /// Reads the UnixFS DAG with the given root CID from `source_store` and writes it into
+    /// this directory at the given path, like `write` and `mkdir` would.
+    ///
+    /// Files can be made of raw leaves or of dag-pb leaves. A file root gets written as a file
+    /// at the path. A directory root gets its entries imported below the path, merging them into
+    /// a directory that's already there. Sharded directories and symlinks aren't supported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use libipld::IpldCodec;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let ipfs_store = &MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = &mut Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///
+    ///     // A single raw block is a valid UnixFS file
+    ///     let cid = ipfs_store
+    ///         .put_block(b"Hello".to_vec(), IpldCodec::Raw)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     dir.import_unixfs(cid, &["hello.txt".into()], true, Utc::now(), ipfs_store, forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let content = dir.read(&["hello.txt".into()], true, forest, store).await.unwrap();
+    ///     assert_eq!(content, b"Hello");
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_unixfs(
+        self: &mut Rc<Self>,
+        root: Cid,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        source_store: &impl BlockStore,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        let mut stack = vec![(root, path_segments.to_vec())];
+
+        while let Some((cid, path)) = stack.pop() {
+            match unixfs::load_node(&cid, source_store).await? {
+                unixfs::UnixFsNode::File(content) => {
+                    self.write(&path, search_latest, time, content, forest, store, rng)
+                        .await?;
+                }
+                unixfs::UnixFsNode::Directory(entries) => {
+                    self.mkdir(&path, search_latest, time, forest, store, rng)
+                        .await?;
+
+                    stack.extend(
+                        entries
+                            .into_iter()
+                            .rev()
+                            .map(|(name, cid)| (cid, [&path[..], &[name]].concat())),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether there's a file or directory at the given path.
+    ///
+    /// Missing intermediate directories and files in their place mean the node doesn't exist.
+    /// Failing to load any node along the path is still an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .mkdir(&["pictures".into(), "cats".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(root_dir
+    ///         .exists(&["pictures".into(), "cats".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap());
+    ///     assert!(!root_dir
+    ///         .exists(&["music".into(), "jazz".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap());
+    /// }
+    /// ```
+    pub async fn exists(
+        self: &Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<bool> {
+        Ok(self
+            .get_node(path_segments, search_latest, forest, store)
+            .await?
+            .is_some())
+    }
+
     /// Reads specified file content from the directory.
     ///
     /// # Examples
@@ -587,6 +1032,7 @@ impl PrivateDirectory {
         forest: &PrivateForest,
         store: &impl BlockStore,
     ) -> Result<Vec<u8>> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
         let (path, filename) = crate::utils::split_last(path_segments)?;
         match self
             .get_leaf_dir(path, search_latest, forest, store)
@@ -597,27 +1043,112 @@ impl PrivateDirectory {
                     .lookup_node(filename, search_latest, forest, store)
                     .await?
                 {
-                    Some(PrivateNode::File(file)) => Ok(file.get_content(forest, store).await?),
-                    Some(_) => error(FsError::NotAFile),
+                    Some(node) => {
+                        let file = node
+                            .as_file()
+                            .map_err(|_| FsError::NotAFile(path_segments.to_vec()))?;
+                        Ok(file.get_content(forest, store).await?)
+                    }
                     None => error(FsError::NotFound),
                 }
             }
+            SearchResult::NotADir(_, depth) => {
+                error(FsError::NotADirectory(path_segments[..=depth].to_vec()))
+            }
             _ => error(FsError::NotFound),
         }
     }
 
-    /// Opens a mutable reference to the specified file.
-    /// If the file is missing, it initializes an empty file and give a mut reference to that.
-    /// If the file already exists, it will copy it to the next revision, update the edit time, and give a mut reference to that.
+    /// Reads specified file content from the directory and records the read as the file's
+    /// access time.
+    ///
+    /// Unlike [`read`](Self::read), this mutates the directory: the file gets a new revision that
+    /// only changes its metadata, and the directories on the path get new revisions pointing to
+    /// it. Store the directory afterwards to persist the access time.
+    ///
     /// # Examples
+    ///
     /// ```
     /// use std::rc::Rc;
     /// use chrono::Utc;
     /// use rand::thread_rng;
     /// use wnfs::{
-    ///    private::{PrivateForest, PrivateRef, PrivateDirectory},
-    ///    common::{BlockStore, MemoryBlockStore},
-    ///    namefilter::Namefilter,
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let path = &["pictures".into(), "cats".into(), "tabby.png".into()];
+    ///     root_dir
+    ///         .write(path, true, Utc::now(), b"meow".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let content = root_dir
+    ///         .read_with_atime(path, true, Utc::now(), forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(content, b"meow");
+    /// }
+    /// ```
+    pub async fn read_with_atime(
+        self: &mut Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Vec<u8>> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (path, filename) = crate::utils::split_last(path_segments)?;
+        let SearchResult::Found(dir) = self
+            .get_leaf_dir_mut(path, search_latest, forest, store)
+            .await?
+        else {
+            return error(FsError::NotFound);
+        };
+
+        match dir
+            .lookup_node_mut(filename, search_latest, forest, store)
+            .await?
+        {
+            Some(PrivateNode::File(file)) => {
+                let content = file.get_content(forest, store).await?;
+                file.prepare_next_revision()?
+                    .content
+                    .metadata
+                    .upsert_atime(time);
+                Ok(content)
+            }
+            Some(_) => error(FsError::NotAFile(path_segments.to_vec())),
+            None => error(FsError::NotFound),
+        }
+    }
+
+    /// Opens a mutable reference to the specified file.
+    /// If the file is missing, it initializes an empty file and give a mut reference to that.
+    /// If the file already exists, it will copy it to the next revision, update the edit time, and give a mut reference to that.
+    /// # Examples
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///    private::{PrivateForest, PrivateRef, PrivateDirectory},
+    ///    common::{BlockStore, MemoryBlockStore},
+    ///    namefilter::Namefilter,
     /// };
     /// #[async_std::main]
     /// async fn main() {
@@ -675,6 +1206,7 @@ impl PrivateDirectory {
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<&'a mut PrivateFile> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
         let (path, filename) = crate::utils::split_last(path_segments)?;
         let dir = self
             .get_or_create_leaf_dir_mut(path, time, search_latest, forest, store, rng)
@@ -686,16 +1218,14 @@ impl PrivateDirectory {
             let link = PrivateLink::from(PrivateNode::File(file_ref));
             dir.content.entries.insert(filename.to_string(), link);
         }
-        let lookup_result = dir
+        let file = dir
             .lookup_node_mut(filename, search_latest, forest, store)
-            .await?;
-        if let Some(PrivateNode::File(file)) = lookup_result {
-            let file = file.prepare_next_revision()?;
-            file.content.metadata.upsert_mtime(time);
-            Ok(file)
-        } else {
-            bail!(FsError::NotAFile);
-        }
+            .await?
+            .and_then(|node| node.as_file_mut().ok())
+            .ok_or_else(|| FsError::NotAFile(path_segments.to_vec()))?
+            .prepare_next_revision()?;
+        file.content.metadata.upsert_mtime(time);
+        Ok(file)
     }
 
     /// Writes a file to the directory.
@@ -757,118 +1287,31 @@ impl PrivateDirectory {
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<()> {
-        let (path, filename) = crate::utils::split_last(path_segments)?;
-        let dir = self
-            .get_or_create_leaf_dir_mut(path, time, search_latest, forest, store, rng)
-            .await?;
-
-        match dir
-            .lookup_node_mut(filename, search_latest, forest, store)
-            .await?
-        {
-            Some(PrivateNode::File(file)) => {
-                let file = file.prepare_next_revision()?;
-                let content = PrivateFile::prepare_content(
-                    &file.header.bare_name,
-                    content,
-                    forest,
-                    store,
-                    rng,
-                )
-                .await?;
-                file.content.content = content;
-                file.content.metadata.upsert_mtime(time);
-            }
-            Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
-            None => {
-                let file = PrivateFile::with_content(
-                    dir.header.bare_name.clone(),
-                    time,
-                    content,
-                    forest,
-                    store,
-                    rng,
-                )
-                .await?;
-                let link = PrivateLink::with_file(file);
-                dir.content.entries.insert(filename.to_string(), link);
-            }
-        };
+        self.write_returning(
+            path_segments,
+            search_latest,
+            time,
+            content,
+            forest,
+            store,
+            rng,
+        )
+        .await?;
 
         Ok(())
     }
 
-    /// Gets the latest version of the directory using exponential search.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use std::rc::Rc;
-    /// use chrono::Utc;
-    /// use rand::thread_rng;
-    /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateNode, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
-    ///     namefilter::Namefilter,
-    /// };
-    ///
-    /// #[async_std::main]
-    /// async fn main() {
-    ///     let store = &mut MemoryBlockStore::default();
-    ///     let rng = &mut thread_rng();
-    ///     let forest = &mut Rc::new(PrivateForest::new());
-    ///     let mut init_dir = PrivateDirectory::new_and_store(
-    ///         Default::default(),
-    ///         Utc::now(),
-    ///         forest,
-    ///         store,
-    ///         rng
-    ///     ).await.unwrap();
-    ///
-    ///     let dir_clone = &mut Rc::clone(&init_dir);
-    ///
-    ///     dir_clone
-    ///         .mkdir(&["pictures".into(), "cats".into()], true, Utc::now(), forest, store, rng)
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     dir_clone.store(forest, store, rng).await.unwrap();
-    ///
-    ///     let latest_dir = init_dir.search_latest(forest, store).await.unwrap();
-    ///
-    ///     let found_node = latest_dir
-    ///         .lookup_node("pictures", true, forest, store)
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     assert!(found_node.is_some());
-    /// }
-    /// ```
-    #[inline]
-    pub async fn search_latest(
-        self: Rc<Self>,
-        forest: &PrivateForest,
-        store: &impl BlockStore,
-    ) -> Result<Rc<Self>> {
-        PrivateNode::Dir(self)
-            .search_latest(forest, store)
-            .await?
-            .as_dir()
-    }
-
-    /// Creates a new directory at the specified path.
+    /// Writes a file to the directory like `write` does, but returns the written file node.
     ///
     /// # Examples
     ///
     /// ```
     /// use std::rc::Rc;
-    ///
     /// use chrono::Utc;
     /// use rand::thread_rng;
-    ///
     /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
     ///     namefilter::Namefilter,
     /// };
     ///
@@ -883,53 +1326,66 @@ impl PrivateDirectory {
     ///         rng,
     ///     ));
     ///
-    ///     root_dir
-    ///         .mkdir(&["pictures".into(), "cats".into()], true, Utc::now(), forest, store, rng)
+    ///     let node = root_dir
+    ///         .write_returning(
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
     ///         .await
     ///         .unwrap();
     ///
-    ///     let node = root_dir.lookup_node("pictures", true, forest, store)
-    ///         .await
-    ///         .unwrap();
+    ///     let content = node.as_file().unwrap().get_content(forest, store).await.unwrap();
     ///
-    ///     assert!(node.is_some());
+    ///     assert_eq!(&content, b"print('hello world')");
     /// }
     /// ```
-    pub async fn mkdir(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write_returning(
         self: &mut Rc<Self>,
         path_segments: &[String],
         search_latest: bool,
         time: DateTime<Utc>,
-        forest: &PrivateForest,
+        content: Vec<u8>,
+        forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
-    ) -> Result<()> {
-        let _ = self
-            .get_or_create_leaf_dir_mut(path_segments, time, search_latest, forest, store, rng)
-            .await?;
-
-        Ok(())
+    ) -> Result<PrivateNode> {
+        self.write_node(
+            path_segments,
+            search_latest,
+            time,
+            content,
+            &PrivateFileConfig::default(),
+            forest,
+            store,
+            rng,
+        )
+        .await
     }
 
-    /// Returns names and metadata of directory's immediate children.
+    /// Writes a file to the directory like `write` does, storing its content the way `config`
+    /// describes.
     ///
     /// # Examples
     ///
     /// ```
     /// use std::rc::Rc;
-    ///
     /// use chrono::Utc;
     /// use rand::thread_rng;
-    ///
     /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     private::{PrivateDirectory, PrivateFileConfig, PrivateForest},
+    ///     common::MemoryBlockStore,
     ///     namefilter::Namefilter,
     /// };
     ///
     /// #[async_std::main]
     /// async fn main() {
-    ///     let store = &mut MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::default();
     ///     let rng = &mut thread_rng();
     ///     let forest = &mut Rc::new(PrivateForest::new());
     ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
@@ -937,76 +1393,156 @@ impl PrivateDirectory {
     ///         Utc::now(),
     ///         rng,
     ///     ));
+    ///     let config = PrivateFileConfig {
+    ///         chunk_size: 64 * 1024,
+    ///         ..Default::default()
+    ///     };
     ///
     ///     root_dir
-    ///         .write(
-    ///             &["code".into(), "hello.py".into()],
+    ///         .write_with_config(
+    ///             &["video.mp4".into()],
     ///             true,
     ///             Utc::now(),
-    ///             b"print('hello world')".to_vec(),
+    ///             vec![0; 100_000],
+    ///             &config,
     ///             forest,
     ///             store,
-    ///             rng
+    ///             rng,
     ///         )
     ///         .await
     ///         .unwrap();
     ///
-    ///     root_dir
-    ///         .mkdir(&["code".into(), "bin".into()], true, Utc::now(), forest, store, rng)
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     let result = root_dir
-    ///         .ls(&["code".into()], true, forest, store)
+    ///     let content = root_dir
+    ///         .read(&["video.mp4".into()], true, forest, store)
     ///         .await
     ///         .unwrap();
     ///
-    ///     assert_eq!(result.len(), 2);
-    ///     assert_eq!(
-    ///         result.iter().map(|t| &t.0).collect::<Vec<_>>(),
-    ///         ["bin", "hello.py"]
-    ///     );
+    ///     assert_eq!(content.len(), 100_000);
     /// }
     /// ```
-    pub async fn ls(
-        self: &Rc<Self>,
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write_with_config(
+        self: &mut Rc<Self>,
         path_segments: &[String],
         search_latest: bool,
-        forest: &PrivateForest,
+        time: DateTime<Utc>,
+        content: Vec<u8>,
+        config: &PrivateFileConfig,
+        forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
-    ) -> Result<Vec<(String, Metadata)>> {
-        match self
-            .get_leaf_dir(path_segments, search_latest, forest, store)
-            .await?
-        {
-            SearchResult::Found(dir) => {
-                let mut result = vec![];
-                for (name, link) in dir.content.entries.iter() {
-                    match link.resolve_node(forest, store).await? {
-                        PrivateNode::File(file) => {
-                            result.push((name.clone(), file.content.metadata.clone()));
-                        }
-                        PrivateNode::Dir(dir) => {
-                            result.push((name.clone(), dir.content.metadata.clone()));
-                        }
-                    }
-                }
-                Ok(result)
-            }
-            SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
-            _ => bail!(FsError::NotFound),
-        }
-    }
-
-    /// Get the names of directory's immediate children.
-    ///
-    /// Other than [PrivateDirectory::ls] this returns only the names, without loading the
-    /// metadata for each node from the store.
-    pub fn get_entries<'a>(self: &'a Rc<Self>) -> impl Iterator<Item = &'a String> {
-        self.content.entries.iter().map(|x| x.0)
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        self.write_node(
+            path_segments,
+            search_latest,
+            time,
+            content,
+            config,
+            forest,
+            store,
+            rng,
+        )
+        .await?;
+
+        Ok(())
     }
 
-    /// Removes a file or directory from the directory.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_node(
+        self: &mut Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        content: Vec<u8>,
+        config: &PrivateFileConfig,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<PrivateNode> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (path, filename) = crate::utils::split_last(path_segments)?;
+        let dir = self
+            .get_or_create_leaf_dir_mut(path, time, search_latest, forest, store, rng)
+            .await?;
+
+        dir.write_leaf(
+            filename,
+            search_latest,
+            time,
+            content,
+            config,
+            forest,
+            store,
+            rng,
+        )
+        .await
+    }
+
+    /// Writes a file directly into this directory.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_leaf(
+        &mut self,
+        filename: &str,
+        search_latest: bool,
+        time: DateTime<Utc>,
+        content: Vec<u8>,
+        config: &PrivateFileConfig,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<PrivateNode> {
+        match self
+            .lookup_node_mut(filename, search_latest, forest, store)
+            .await?
+        {
+            Some(PrivateNode::File(file_rc)) => {
+                let file = file_rc.prepare_next_revision()?;
+                // The file keeps the cipher it was created with.
+                let config = PrivateFileConfig {
+                    cipher: file.get_cipher(),
+                    ..*config
+                };
+                let content = PrivateFile::prepare_content(
+                    &file.header.bare_name,
+                    content,
+                    &config,
+                    forest,
+                    store,
+                    rng,
+                )
+                .await?;
+                file.content.content = content;
+                file.content.metadata.upsert_mtime(time);
+
+                Ok(PrivateNode::File(Rc::clone(file_rc)))
+            }
+            Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
+            None => {
+                let file = PrivateFile::with_content_config(
+                    self.header.bare_name.clone(),
+                    time,
+                    content,
+                    config,
+                    forest,
+                    store,
+                    rng,
+                )
+                .await?;
+                let node = PrivateNode::File(Rc::new(file));
+                self.content
+                    .entries
+                    .insert(filename.to_string(), PrivateLink::from(node.clone()));
+
+                Ok(node)
+            }
+        }
+    }
+
+    /// Writes many files at once, with the same result as calling `write` for each of them with
+    /// `search_latest` set.
+    ///
+    /// Files are grouped by their parent directory, so each directory only gets looked up or
+    /// created once, no matter how many files get written into it.
     ///
     /// # Examples
     ///
@@ -1015,14 +1551,14 @@ impl PrivateDirectory {
     /// use chrono::Utc;
     /// use rand::thread_rng;
     /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
     ///     namefilter::Namefilter,
     /// };
     ///
     /// #[async_std::main]
     /// async fn main() {
-    ///     let store = &mut MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::default();
     ///     let rng = &mut thread_rng();
     ///     let forest = &mut Rc::new(PrivateForest::new());
     ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
@@ -1031,28 +1567,13 @@ impl PrivateDirectory {
     ///         rng,
     ///     ));
     ///
-    ///     root_dir
-    ///         .write(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             true,
-    ///             Utc::now(),
-    ///             b"print('hello world')".to_vec(),
-    ///             forest,
-    ///             store,
-    ///             rng
-    ///         )
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     let result = root_dir
-    ///         .ls(&["code".into()], true, forest, store)
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     assert_eq!(result.len(), 1);
+    ///     let entries = vec![
+    ///         (vec!["code".into(), "hello.py".into()], b"print('hello')".to_vec()),
+    ///         (vec!["code".into(), "bye.py".into()], b"print('bye')".to_vec()),
+    ///     ];
     ///
     ///     root_dir
-    ///         .rm(&["code".into(), "python".into()], true, forest, store)
+    ///         .write_many(entries, Utc::now(), forest, store, rng)
     ///         .await
     ///         .unwrap();
     ///
@@ -1061,112 +1582,68 @@ impl PrivateDirectory {
     ///         .await
     ///         .unwrap();
     ///
-    ///     assert_eq!(result.len(), 0);
+    ///     assert_eq!(result.len(), 2);
     /// }
     /// ```
-    pub async fn rm(
-        self: &mut Rc<Self>,
-        path_segments: &[String],
-        search_latest: bool,
-        forest: &PrivateForest,
-        store: &impl BlockStore,
-    ) -> Result<PrivateNode> {
-        let (path, node_name) = crate::utils::split_last(path_segments)?;
-        let SearchResult::Found(dir) = self
-            .get_leaf_dir_mut(path, search_latest, forest, store)
-            .await?
-        else {
-            bail!(FsError::NotFound)
-        };
-
-        let removed_node = match dir.content.entries.remove(node_name) {
-            Some(link) => link.resolve_owned_node(forest, store).await?,
-            None => bail!(FsError::NotFound),
-        };
-
-        Ok(removed_node)
-    }
-
-    /// Attaches a node to the specified directory.
-    ///
-    /// Fixes up the subtree bare names to refer to the new parent.
-    #[allow(clippy::too_many_arguments)]
-    async fn attach(
+    pub async fn write_many(
         self: &mut Rc<Self>,
-        mut node: PrivateNode,
-        path_segments: &[String],
-        search_latest: bool,
+        entries: Vec<(Vec<String>, Vec<u8>)>,
         time: DateTime<Utc>,
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<()> {
-        let (path, node_name) = crate::utils::split_last(path_segments)?;
-        let SearchResult::Found(dir) = self
-            .get_leaf_dir_mut(path, search_latest, forest, store)
-            .await?
-        else {
-            bail!(FsError::NotFound);
-        };
-
-        ensure!(
-            !dir.content.entries.contains_key(node_name),
-            FsError::FileAlreadyExists
-        );
-
-        node.upsert_mtime(time);
-        node.update_ancestry(dir.header.bare_name.clone(), forest, store, rng)
-            .await?;
-
-        dir.content
-            .entries
-            .insert(node_name.clone(), PrivateLink::from(node));
-
-        Ok(())
-    }
+        let mut groups: BTreeMap<Vec<String>, Vec<(String, Vec<u8>)>> = BTreeMap::new();
+        for (mut path_segments, content) in entries {
+            crate::utils::ensure_valid_path_segments(&path_segments)?;
+            let Some(filename) = path_segments.pop() else {
+                bail!(FsError::InvalidPath);
+            };
 
-    /// Attaches a node to the specified directory without modifying the node.
-    #[allow(clippy::too_many_arguments)]
-    async fn attach_link(
-        self: &mut Rc<Self>,
-        node: PrivateNode,
-        path_segments: &[String],
-        search_latest: bool,
-        forest: &mut Rc<PrivateForest>,
-        store: &impl BlockStore,
-    ) -> Result<()> {
-        let (path, node_name) = crate::utils::split_last(path_segments)?;
-        let SearchResult::Found(dir) = self
-            .get_leaf_dir_mut(path, search_latest, forest, store)
-            .await?
-        else {
-            bail!(FsError::NotFound);
-        };
+            groups
+                .entry(path_segments)
+                .or_default()
+                .push((filename, content));
+        }
 
-        ensure!(
-            !dir.content.entries.contains_key(node_name),
-            FsError::FileAlreadyExists
-        );
+        let config = &PrivateFileConfig::default();
+        for (path, files) in groups {
+            let dir = self
+                .get_or_create_leaf_dir_mut(&path, time, true, forest, store, rng)
+                .await?;
 
-        dir.content
-            .entries
-            .insert(node_name.clone(), PrivateLink::from(node));
+            for (filename, content) in files {
+                dir.write_leaf(&filename, true, time, content, config, forest, store, rng)
+                    .await?;
+            }
+        }
 
         Ok(())
     }
 
-    /// Moves a file or directory from one path to another.
+    /// Writes a new file whose keys are derived from the ratchet seed and inumber provided,
+    /// instead of random ones.
+    ///
+    /// This makes the file's private ref predictable, so its keys can be handed to
+    /// a recipient before the file is written.
+    ///
+    /// # Security
+    ///
+    /// Anyone who learns the seed and inumber can derive every key of the file's first
+    /// revision and all revisions after it. Reusing the same secrets for more than one file
+    /// links those files together and gives up forward secrecy between them.
+    ///
+    /// Fails if there's already a file or directory at the path.
     ///
     /// # Examples
     ///
     /// ```
     /// use std::rc::Rc;
     /// use chrono::Utc;
-    /// use rand::thread_rng;
-    ///
+    /// use rand::{thread_rng, Rng};
     /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
     ///     namefilter::Namefilter,
     /// };
     ///
@@ -1181,80 +1658,86 @@ impl PrivateDirectory {
     ///         rng,
     ///     ));
     ///
-    ///     root_dir
-    ///         .write(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             true,
-    ///             Utc::now(),
-    ///             b"print('hello world')".to_vec(),
-    ///             forest,
-    ///             store,
-    ///             rng
-    ///         )
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     let result = root_dir
-    ///         .basic_mv(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             &["code".into(), "hello.py".into()],
+    ///     let file = root_dir
+    ///         .write_with_keys(
+    ///             &["shared.txt".into()],
     ///             true,
     ///             Utc::now(),
+    ///             b"Hello".to_vec(),
+    ///             rng.gen::<[u8; 32]>(),
+    ///             rng.gen::<[u8; 32]>(),
     ///             forest,
     ///             store,
-    ///             rng
+    ///             rng,
     ///         )
     ///         .await
     ///         .unwrap();
     ///
-    ///     let result = root_dir
-    ///         .ls(&["code".into()], true, forest, store)
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     assert_eq!(result.len(), 2);
+    ///     println!("file = {:?}", file);
     /// }
     /// ```
     #[allow(clippy::too_many_arguments)]
-    pub async fn basic_mv(
+    pub async fn write_with_keys(
         self: &mut Rc<Self>,
-        path_segments_from: &[String],
-        path_segments_to: &[String],
+        path_segments: &[String],
         search_latest: bool,
         time: DateTime<Utc>,
+        content: Vec<u8>,
+        ratchet_seed: HashOutput,
+        inumber: HashOutput,
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
-    ) -> Result<()> {
-        let removed_node = self
-            .rm(path_segments_from, search_latest, forest, store)
+    ) -> Result<PrivateNode> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (path, filename) = crate::utils::split_last(path_segments)?;
+        let dir = self
+            .get_or_create_leaf_dir_mut(path, time, search_latest, forest, store, rng)
             .await?;
 
-        self.attach(
-            removed_node,
-            path_segments_to,
-            search_latest,
-            time,
+        match dir
+            .lookup_node(filename, search_latest, forest, store)
+            .await?
+        {
+            Some(PrivateNode::File(_)) => bail!(FsError::FileAlreadyExists),
+            Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
+            None => (),
+        }
+
+        let mut file =
+            PrivateFile::with_seed(dir.header.bare_name.clone(), time, ratchet_seed, inumber);
+        file.content.content = PrivateFile::prepare_content(
+            &file.header.bare_name,
+            content,
+            &PrivateFileConfig::default(),
             forest,
             store,
             rng,
         )
-        .await
+        .await?;
+
+        let node = PrivateNode::File(Rc::new(file));
+        dir.content
+            .entries
+            .insert(filename.to_string(), PrivateLink::from(node.clone()));
+
+        Ok(node)
     }
 
-    /// Copies a file or directory from one path to another.
+    /// Replaces a file's content with the result of applying `f` to its current content.
+    ///
+    /// A missing file is treated as empty and gets created. The new content is written as
+    /// the file's next revision.
     ///
     /// # Examples
     ///
     /// ```
     /// use std::rc::Rc;
-    ///
     /// use chrono::Utc;
     /// use rand::thread_rng;
-    ///
     /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
     ///     namefilter::Namefilter,
     /// };
     ///
@@ -1268,97 +1751,186 @@ impl PrivateDirectory {
     ///         Utc::now(),
     ///         rng,
     ///     ));
+    ///     let path = ["log.txt".into()];
     ///
-    ///     root_dir
-    ///         .write(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             true,
-    ///             Utc::now(),
-    ///             b"print('hello world')".to_vec(),
-    ///             forest,
-    ///             store,
-    ///             rng
-    ///         )
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     let result = root_dir
-    ///         .cp(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             &["code".into(), "hello.py".into()],
-    ///             true,
-    ///             Utc::now(),
-    ///             forest,
-    ///             store,
-    ///             rng
-    ///         )
-    ///         .await
-    ///         .unwrap();
+    ///     for line in ["first\n", "second\n"] {
+    ///         root_dir
+    ///             .update(&path, true, Utc::now(), forest, store, rng, |mut content| {
+    ///                 content.extend_from_slice(line.as_bytes());
+    ///                 content
+    ///             })
+    ///             .await
+    ///             .unwrap();
+    ///     }
     ///
-    ///     let result = root_dir
-    ///         .ls(&["code".into()], true, forest, store)
-    ///         .await
-    ///         .unwrap();
+    ///     let content = root_dir.read(&path, true, forest, store).await.unwrap();
     ///
-    ///     assert_eq!(result.len(), 2);
+    ///     assert_eq!(&content, b"first\nsecond\n");
     /// }
     /// ```
     #[allow(clippy::too_many_arguments)]
-    pub async fn cp(
+    pub async fn update(
         self: &mut Rc<Self>,
-        path_segments_from: &[String],
-        path_segments_to: &[String],
+        path_segments: &[String],
         search_latest: bool,
         time: DateTime<Utc>,
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
+        f: impl FnOnce(Vec<u8>) -> Vec<u8>,
     ) -> Result<()> {
-        let result = self
-            .get_node(path_segments_from, search_latest, forest, store)
-            .await?;
-
-        self.attach(
-            result.ok_or(FsError::NotFound)?,
-            path_segments_to,
+        self.update_async(
+            path_segments,
             search_latest,
             time,
             forest,
             store,
             rng,
+            |content| future::ready(Ok(f(content))),
         )
         .await
     }
 
-    /// Copies a file or directory from one path to another without modifying it
+    /// Like `update`, but takes a closure returning a future, for edits that need to do IO.
+    ///
+    /// If the future fails, nothing is written.
     #[allow(clippy::too_many_arguments)]
-    pub async fn cp_link(
+    pub async fn update_async<F, Fut>(
         self: &mut Rc<Self>,
-        path_segments_from: &[String],
-        path_segments_to: &[String],
+        path_segments: &[String],
         search_latest: bool,
+        time: DateTime<Utc>,
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
-    ) -> Result<()> {
-        let result = self
-            .get_node(path_segments_from, search_latest, forest, store)
-            .await?;
+        rng: &mut impl RngCore,
+        f: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(Vec<u8>) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>>>,
+    {
+        let current = match self
+            .get_node(path_segments, search_latest, forest, store)
+            .await?
+        {
+            Some(PrivateNode::File(file)) => file.get_content(forest, store).await?,
+            Some(PrivateNode::Dir(_)) => bail!(FsError::NotAFile(path_segments.to_vec())),
+            None => vec![],
+        };
 
-        self.attach_link(
-            result.ok_or(FsError::NotFound)?,
-            path_segments_to,
+        let content = f(current).await?;
+
+        self.write(
+            path_segments,
             search_latest,
+            time,
+            content,
             forest,
             store,
+            rng,
         )
         .await
     }
 
-    /// Write a Symlink to the filesystem with the reference path at the path segments specified
-    #[allow(clippy::too_many_arguments)]
-    pub async fn write_symlink(
+    /// Gets the latest version of the directory using exponential search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateNode, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let mut init_dir = PrivateDirectory::new_and_store(
+    ///         Default::default(),
+    ///         Utc::now(),
+    ///         forest,
+    ///         store,
+    ///         rng
+    ///     ).await.unwrap();
+    ///
+    ///     let dir_clone = &mut Rc::clone(&init_dir);
+    ///
+    ///     dir_clone
+    ///         .mkdir(&["pictures".into(), "cats".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     dir_clone.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let latest_dir = init_dir.search_latest(forest, store).await.unwrap();
+    ///
+    ///     let found_node = latest_dir
+    ///         .lookup_node("pictures", true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(found_node.is_some());
+    /// }
+    /// ```
+    #[inline]
+    pub async fn search_latest(
+        self: Rc<Self>,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Rc<Self>> {
+        PrivateNode::Dir(self)
+            .search_latest(forest, store)
+            .await?
+            .as_dir()
+    }
+
+    /// Creates a new directory at the specified path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .mkdir(&["pictures".into(), "cats".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let node = root_dir.lookup_node("pictures", true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(node.is_some());
+    /// }
+    /// ```
+    pub async fn mkdir(
         self: &mut Rc<Self>,
-        path: String,
         path_segments: &[String],
         search_latest: bool,
         time: DateTime<Utc>,
@@ -1366,39 +1938,18 @@ impl PrivateDirectory {
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<()> {
-        let (path_segments, filename) = crate::utils::split_last(path_segments)?;
-
-        let dir = self
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let _ = self
             .get_or_create_leaf_dir_mut(path_segments, time, search_latest, forest, store, rng)
             .await?;
 
-        match dir
-            .lookup_node_mut(filename, search_latest, forest, store)
-            .await?
-        {
-            Some(PrivateNode::File(file)) => {
-                let file = file.prepare_next_revision()?;
-                file.content.content = super::FileContent::Inline { data: vec![] };
-                file.content.metadata.upsert_mtime(time);
-                // Write the path into the Metadata HashMap
-                file.content
-                    .metadata
-                    .0
-                    .insert(String::from("symlink"), Ipld::String(path));
-            }
-            Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
-            None => {
-                let file =
-                    PrivateFile::new_symlink(path, dir.header.bare_name.clone(), time, rng).await?;
-                let link = PrivateLink::with_file(file);
-                dir.content.entries.insert(filename.to_string(), link);
-            }
-        };
-
         Ok(())
     }
 
-    /// Stores this PrivateDirectory in the PrivateForest.
+    /// Creates a new directory at the specified path like `mkdir` does, and returns the paths of
+    /// the directories it actually created, parents first.
+    ///
+    /// Directories on the path that already existed aren't reported.
     ///
     /// # Examples
     ///
@@ -1407,277 +1958,3101 @@ impl PrivateDirectory {
     /// use chrono::Utc;
     /// use rand::thread_rng;
     /// use wnfs::{
-    ///     private::{PrivateForest, PrivateRef, PrivateNode, PrivateDirectory},
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
     ///     namefilter::Namefilter,
     /// };
     ///
     /// #[async_std::main]
     /// async fn main() {
-    ///     let store = &mut MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::default();
     ///     let rng = &mut thread_rng();
     ///     let forest = &mut Rc::new(PrivateForest::new());
-    ///     let dir = &mut Rc::new(PrivateDirectory::new(
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
     ///         Namefilter::default(),
     ///         Utc::now(),
     ///         rng,
     ///     ));
     ///
-    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///     root_dir
+    ///         .mkdir(&["pictures".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
     ///
-    ///     let node = PrivateNode::Dir(Rc::clone(&dir));
+    ///     let created = root_dir
+    ///         .mkdir_reporting(&["pictures".into(), "cats".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
     ///
-    ///     assert_eq!(
-    ///         PrivateNode::load(&private_ref, forest, store).await.unwrap(),
-    ///         node
-    ///     );
+    ///     assert_eq!(created, vec![vec!["pictures".to_string(), "cats".to_string()]]);
     /// }
     /// ```
-    pub async fn store(
-        &self,
-        forest: &mut Rc<PrivateForest>,
+    pub async fn mkdir_reporting(
+        self: &mut Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &PrivateForest,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
-    ) -> Result<PrivateRef> {
-        let header_cid = self.header.store(store).await?;
-        let temporal_key = self.header.derive_temporal_key();
-        let label = self.header.get_saturated_name();
-
-        let content_cid = self
-            .content
-            .store(header_cid, &temporal_key, forest, store, rng)
-            .await?;
-
-        forest
-            .put_encrypted(label, [header_cid, content_cid], store)
+    ) -> Result<Vec<Vec<String>>> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (_, created) = self
+            .get_or_create_leaf_dir_mut_counting(
+                path_segments,
+                time,
+                search_latest,
+                forest,
+                store,
+                rng,
+            )
             .await?;
 
-        Ok(self
-            .header
-            .derive_revision_ref()
-            .as_private_ref(content_cid))
+        let first_created = path_segments.len() - created;
+        Ok((first_created..path_segments.len())
+            .map(|end| path_segments[..=end].to_vec())
+            .collect())
     }
 
-    /// Creates a  new [`PrivateDirectory`] from a [`PrivateDirectoryContentSerializable`].
-    pub(crate) async fn from_serializable_temporal(
-        serializable: PrivateDirectoryContentSerializable,
-        temporal_key: &TemporalKey,
-        cid: Cid,
-        store: &impl BlockStore,
-    ) -> Result<Self> {
-        if serializable.version.major != 0 || serializable.version.minor != 2 {
-            bail!(FsError::UnexpectedVersion(serializable.version));
-        }
-
-        let mut entries_decrypted = BTreeMap::new();
-        for (name, private_ref_serializable) in serializable.entries {
-            let private_ref =
-                PrivateRef::from_serializable(private_ref_serializable, temporal_key)?;
-            entries_decrypted.insert(name, PrivateLink::from_ref(private_ref));
-        }
+    /// Returns names and metadata of directory's immediate children.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     root_dir
+    ///         .mkdir(&["code".into(), "bin".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .ls(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 2);
+    ///     assert_eq!(
+    ///         result.iter().map(|t| &t.0).collect::<Vec<_>>(),
+    ///         ["bin", "hello.py"]
+    ///     );
+    /// }
+    /// ```
+    pub async fn ls(
+        self: &Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Vec<(String, Metadata)>> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        match self
+            .get_leaf_dir(path_segments, search_latest, forest, store)
+            .await?
+        {
+            SearchResult::Found(dir) => {
+                let mut result = vec![];
+                for (name, link) in dir.content.entries.iter() {
+                    match link.resolve_node(forest, store).await? {
+                        PrivateNode::File(file) => {
+                            result.push((name.clone(), file.content.metadata.clone()));
+                        }
+                        PrivateNode::Dir(dir) => {
+                            result.push((name.clone(), dir.content.metadata.clone()));
+                        }
+                    }
+                }
+                Ok(result)
+            }
+            SearchResult::NotADir(_, depth) => {
+                bail!(FsError::NotADirectory(path_segments[..=depth].to_vec()))
+            }
+            _ => bail!(FsError::NotFound),
+        }
+    }
+
+    /// Walks every node below the directory at `base_path`, depth-first, yielding each with its
+    /// path relative to `base_path`.
+    ///
+    /// Nodes are resolved as they're reached, so only the path to the current node and the names
+    /// of siblings still to visit are held in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let paths = root_dir
+    ///         .walk(&["code".into()], true, forest, store)
+    ///         .map_ok(|(path, _)| path.join("/"))
+    ///         .try_collect::<Vec<_>>()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(paths, ["python", "python/hello.py"]);
+    /// }
+    /// ```
+    pub fn walk<'a>(
+        self: &'a Rc<Self>,
+        base_path: &'a [String],
+        search_latest: bool,
+        forest: &'a PrivateForest,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<(Vec<String>, PrivateNode)>> + 'a {
+        Box::pin(try_stream! {
+            crate::utils::ensure_valid_path_segments(base_path)?;
+            let base_dir = match self
+                .get_leaf_dir(base_path, search_latest, forest, store)
+                .await?
+            {
+                SearchResult::Found(dir) => dir,
+                SearchResult::NotADir(_, depth) => {
+                    Err(FsError::NotADirectory(base_path[..=depth].to_vec()))?
+                }
+                _ => Err(FsError::NotFound)?,
+            };
+
+            // Siblings are pushed in reverse, so they get popped in order.
+            let mut stack = base_dir
+                .content
+                .entries
+                .keys()
+                .rev()
+                .map(|name| (Rc::clone(&base_dir), vec![name.clone()]))
+                .collect::<Vec<_>>();
+
+            while let Some((parent, path)) = stack.pop() {
+                let name = path.last().expect("Paths on the stack are never empty");
+                let node = parent
+                    .lookup_node(name, search_latest, forest, store)
+                    .await?
+                    .ok_or(FsError::NotFound)?;
+
+                if let PrivateNode::Dir(dir) = &node {
+                    for child in dir.content.entries.keys().rev() {
+                        let mut child_path = path.clone();
+                        child_path.push(child.clone());
+                        stack.push((Rc::clone(dir), child_path));
+                    }
+                }
+
+                yield (path, node);
+            }
+        })
+    }
+
+    /// Walks every node below the directory at `base_path` like `walk` does, but only yields the
+    /// nodes whose name and metadata satisfy the predicate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     for path in [["code", "hello.py"], ["code", "README.md"]] {
+    ///         let path = path.map(String::from);
+    ///         root_dir
+    ///             .write(&path, true, Utc::now(), b"...".to_vec(), forest, store, rng)
+    ///             .await
+    ///             .unwrap();
+    ///     }
+    ///
+    ///     let paths = root_dir
+    ///         .find(&[], |name, _| name.ends_with(".py"), forest, store)
+    ///         .map_ok(|(path, _)| path.join("/"))
+    ///         .try_collect::<Vec<_>>()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(paths, ["code/hello.py"]);
+    /// }
+    /// ```
+    pub fn find<'a>(
+        self: &'a Rc<Self>,
+        base_path: &'a [String],
+        predicate: impl Fn(&str, &Metadata) -> bool + 'a,
+        forest: &'a PrivateForest,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<(Vec<String>, PrivateNode)>> + 'a {
+        self.walk(base_path, true, forest, store)
+            .try_filter(move |(path, node)| {
+                let name = path.last().expect("Walked paths are never empty");
+                let metadata = match node {
+                    PrivateNode::File(file) => &file.content.metadata,
+                    PrivateNode::Dir(dir) => &dir.content.metadata,
+                };
+
+                future::ready(predicate(name, metadata))
+            })
+    }
+
+    /// Finds all nodes below the directory at `base_path` whose name matches a glob pattern.
+    ///
+    /// In the pattern, `*` matches any run of characters and `?` matches any single character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     for path in [["code", "hello.py"], ["code", "README.md"]] {
+    ///         let path = path.map(String::from);
+    ///         root_dir
+    ///             .write(&path, true, Utc::now(), b"...".to_vec(), forest, store, rng)
+    ///             .await
+    ///             .unwrap();
+    ///     }
+    ///
+    ///     let paths = root_dir
+    ///         .find_glob(&[], "*.py", forest, store)
+    ///         .map_ok(|(path, _)| path.join("/"))
+    ///         .try_collect::<Vec<_>>()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(paths, ["code/hello.py"]);
+    /// }
+    /// ```
+    pub fn find_glob<'a>(
+        self: &'a Rc<Self>,
+        base_path: &'a [String],
+        pattern: &'a str,
+        forest: &'a PrivateForest,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<(Vec<String>, PrivateNode)>> + 'a {
+        self.find(
+            base_path,
+            move |name, _| crate::utils::glob_match(pattern, name),
+            forest,
+            store,
+        )
+    }
+
+    /// Sums up the content sizes of all files below the directory at the given path.
+    ///
+    /// File sizes are looked up with [`PrivateFile::get_size`], so file content isn't downloaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let size = root_dir.size(&["code".into()], true, forest, store).await.unwrap();
+    ///
+    ///     assert_eq!(size, 20);
+    /// }
+    /// ```
+    pub async fn size(
+        self: &Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<u64> {
+        let mut nodes = self.walk(path_segments, search_latest, forest, store);
+        let mut size = 0;
+        while let Some((_, node)) = nodes.try_next().await? {
+            if let PrivateNode::File(file) = node {
+                size += file.get_size(forest, store).await?;
+            }
+        }
+
+        Ok(size)
+    }
+
+    /// Finds the path of the node the given private ref points to.
+    ///
+    /// The tree is searched depth-first, and the first path found is returned. Only nodes that
+    /// have been stored can be found, since only those have a private ref. Directories are loaded
+    /// on the way, but no file content is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let path = vec!["code".into(), "hello.py".into()];
+    ///     let node = root_dir
+    ///         .write_returning(&path, true, Utc::now(), b"print('hello')".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     root_dir.store(forest, store, rng).await.unwrap();
+    ///     let private_ref = node.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let result = root_dir.path_of(&private_ref, forest, store).await.unwrap();
+    ///
+    ///     assert_eq!(result, Some(path));
+    /// }
+    /// ```
+    pub async fn path_of(
+        self: &Rc<Self>,
+        target: &PrivateRef,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Option<Vec<String>>> {
+        let is_target = |private_ref: Option<PrivateRef>| matches!(private_ref, Some(r) if r.content_cid == target.content_cid);
+
+        if is_target(self.get_private_ref()) {
+            return Ok(Some(vec![]));
+        }
+
+        let mut stack = vec![(vec![], Rc::clone(self))];
+        while let Some((path, dir)) = stack.pop() {
+            let mut subdirs = vec![];
+            for (name, link) in dir.content.entries.iter() {
+                let mut child_path = path.clone();
+                child_path.push(name.clone());
+                if is_target(link.get_ref()) {
+                    return Ok(Some(child_path));
+                }
+
+                if let PrivateNode::Dir(child_dir) = link.resolve_node(forest, store).await? {
+                    subdirs.push((child_path, Rc::clone(child_dir)));
+                }
+            }
+
+            // Subdirectories are pushed in reverse, so they get popped in order.
+            stack.extend(subdirs.into_iter().rev());
+        }
+
+        Ok(None)
+    }
+
+    /// Get the names of directory's immediate children.
+    ///
+    /// Other than [PrivateDirectory::ls] this returns only the names, without loading the
+    /// metadata for each node from the store.
+    pub fn get_entries<'a>(self: &'a Rc<Self>) -> impl Iterator<Item = &'a String> {
+        self.content.entries.iter().map(|x| x.0)
+    }
+
+    /// Removes a file or directory from the directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .ls(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 1);
+    ///
+    ///     root_dir
+    ///         .rm(&["code".into(), "python".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .ls(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 0);
+    /// }
+    /// ```
+    pub async fn rm(
+        self: &mut Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<PrivateNode> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (path, node_name) = crate::utils::split_last(path_segments)?;
+        let SearchResult::Found(dir) = self
+            .get_leaf_dir_mut(path, search_latest, forest, store)
+            .await?
+        else {
+            bail!(FsError::NotFound)
+        };
+
+        let removed_node = match dir.content.entries.remove(node_name) {
+            Some(link) => link.resolve_owned_node(forest, store).await?,
+            None => bail!(FsError::NotFound),
+        };
+
+        Ok(removed_node)
+    }
+
+    /// Removes a file or directory from the directory like `rm` does, and returns the number of
+    /// nodes it detached, counting the removed entry itself and everything below it.
+    ///
+    /// Only the removed subtree is walked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let removed = root_dir
+    ///         .rm_recursive(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(removed, 3);
+    /// }
+    /// ```
+    pub async fn rm_recursive(
+        self: &mut Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<usize> {
+        let removed_node = self.rm(path_segments, search_latest, forest, store).await?;
+
+        let mut count = 0;
+        let mut stack = vec![removed_node];
+        while let Some(node) = stack.pop() {
+            count += 1;
+            if let PrivateNode::Dir(dir) = node {
+                for link in dir.content.entries.values() {
+                    stack.push(link.resolve_node(forest, store).await?.clone());
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Attaches a node to the specified directory.
+    ///
+    /// Fixes up the subtree bare names to refer to the new parent.
+    #[allow(clippy::too_many_arguments)]
+    async fn attach(
+        self: &mut Rc<Self>,
+        mut node: PrivateNode,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        let (path, node_name) = crate::utils::split_last(path_segments)?;
+        let SearchResult::Found(dir) = self
+            .get_leaf_dir_mut(path, search_latest, forest, store)
+            .await?
+        else {
+            bail!(FsError::NotFound);
+        };
+
+        ensure!(
+            !dir.content.entries.contains_key(node_name),
+            FsError::FileAlreadyExists
+        );
+
+        node.upsert_mtime(time);
+        node.update_ancestry(dir.header.bare_name.clone(), forest, store, rng)
+            .await?;
+
+        dir.content
+            .entries
+            .insert(node_name.clone(), PrivateLink::from(node));
+
+        Ok(())
+    }
+
+    /// Attaches a node to the specified directory without modifying the node.
+    #[allow(clippy::too_many_arguments)]
+    async fn attach_link(
+        self: &mut Rc<Self>,
+        node: PrivateNode,
+        path_segments: &[String],
+        search_latest: bool,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (path, node_name) = crate::utils::split_last(path_segments)?;
+        let SearchResult::Found(dir) = self
+            .get_leaf_dir_mut(path, search_latest, forest, store)
+            .await?
+        else {
+            bail!(FsError::NotFound);
+        };
+
+        ensure!(
+            !dir.content.entries.contains_key(node_name),
+            FsError::FileAlreadyExists
+        );
+
+        dir.content
+            .entries
+            .insert(node_name.clone(), PrivateLink::from(node));
+
+        Ok(())
+    }
+
+    /// Moves a file or directory from one path to another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .basic_mv(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .ls(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 2);
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn basic_mv(
+        self: &mut Rc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(path_segments_from)?;
+        crate::utils::ensure_valid_path_segments(path_segments_to)?;
+        let removed_node = self
+            .rm(path_segments_from, search_latest, forest, store)
+            .await?;
+
+        self.attach(
+            removed_node,
+            path_segments_to,
+            search_latest,
+            time,
+            forest,
+            store,
+            rng,
+        )
+        .await
+    }
+
+    /// Renames a file or directory.
+    ///
+    /// If both paths are in the same directory, the node just gets listed under its new name.
+    /// Unlike `basic_mv`, its keys, bare name and modification time stay the same.
+    /// Otherwise the node gets moved the way `basic_mv` moves it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     root_dir
+    ///         .rename(
+    ///             &["code".into(), "hello.py".into()],
+    ///             &["code".into(), "main.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .ls(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result[0].0, "main.py");
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn rename(
+        self: &mut Rc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(path_segments_from)?;
+        crate::utils::ensure_valid_path_segments(path_segments_to)?;
+        let (path_from, name_from) = crate::utils::split_last(path_segments_from)?;
+        let (path_to, name_to) = crate::utils::split_last(path_segments_to)?;
+        if path_from != path_to {
+            return self
+                .basic_mv(
+                    path_segments_from,
+                    path_segments_to,
+                    search_latest,
+                    time,
+                    forest,
+                    store,
+                    rng,
+                )
+                .await;
+        }
+
+        let SearchResult::Found(dir) = self
+            .get_leaf_dir_mut(path_from, search_latest, forest, store)
+            .await?
+        else {
+            bail!(FsError::NotFound);
+        };
+
+        ensure!(
+            dir.content.entries.contains_key(name_from),
+            FsError::NotFound
+        );
+
+        if name_from == name_to {
+            return Ok(());
+        }
+
+        ensure!(
+            !dir.content.entries.contains_key(name_to),
+            FsError::FileAlreadyExists
+        );
+
+        let link = dir.content.entries.remove(name_from).expect("Entry exists");
+        dir.content.entries.insert(name_to.clone(), link);
+
+        Ok(())
+    }
+
+    /// Replaces the node at the given path with the content of one of its past revisions.
+    ///
+    /// The restored node becomes the next revision of the current node, so its previous link
+    /// points to the current revision, and history from before the restore stays reachable.
+    /// The target must be a revision of the node at the path, otherwise this fails with
+    /// `FsError::UnrelatedRevisions`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///     let path = &["hello.txt".into()];
+    ///
+    ///     root_dir
+    ///         .write(path, true, Utc::now(), b"Hello".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     let hello = root_dir
+    ///         .lookup_node("hello.txt", true, forest, store)
+    ///         .await
+    ///         .unwrap()
+    ///         .unwrap();
+    ///     let hello_ref = hello.store(forest, store, rng).await.unwrap();
+    ///
+    ///     root_dir
+    ///         .write(path, true, Utc::now(), b"Goodbye".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     root_dir.restore(path, &hello_ref, true, Utc::now(), forest, store).await.unwrap();
+    ///
+    ///     let content = root_dir.read(path, true, forest, store).await.unwrap();
+    ///
+    ///     assert_eq!(content, b"Hello");
+    /// }
+    /// ```
+    #[allow(clippy::mutable_key_type)]
+    pub async fn restore(
+        self: &mut Rc<Self>,
+        path_segments: &[String],
+        target: &PrivateRef,
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (path, node_name) = crate::utils::split_last(path_segments)?;
+        let target_node = PrivateNode::load(target, forest, store).await?;
+
+        let SearchResult::Found(dir) = self
+            .get_leaf_dir_mut(path, search_latest, forest, store)
+            .await?
+        else {
+            bail!(FsError::NotFound);
+        };
+
+        let Some(link) = dir.content.entries.get_mut(node_name) else {
+            bail!(FsError::NotFound);
+        };
+
+        let current = link.resolve_node(forest, store).await?;
+        let mut header = current.get_header().clone();
+        ensure!(
+            header.inumber == target_node.get_header().inumber,
+            FsError::UnrelatedRevisions
+        );
+
+        // An unstored current node takes the place of its predecessors, so the restored node takes
+        // them over instead.
+        let previous = match current.persisted_as().get() {
+            Some(cid) => {
+                let temporal_key = header.derive_temporal_key();
+                let previous = BTreeSet::from([(1, Encrypted::from_value(*cid, &temporal_key)?)]);
+                header.advance_ratchet();
+                previous
+            }
+            None => current.get_previous().clone(),
+        };
+
+        let restored = match target_node.upsert_mtime(time) {
+            PrivateNode::File(mut file) => {
+                let file_mut = Rc::make_mut(&mut file);
+                file_mut.header = header;
+                file_mut.content.persisted_as = OnceCell::new();
+                file_mut.content.previous = previous;
+                PrivateNode::File(file)
+            }
+            PrivateNode::Dir(mut dir) => {
+                let dir_mut = Rc::make_mut(&mut dir);
+                dir_mut.header = header;
+                dir_mut.content.persisted_as = OnceCell::new();
+                dir_mut.content.previous = previous;
+                PrivateNode::Dir(dir)
+            }
+        };
+
+        *link = PrivateLink::from(restored);
+
+        Ok(())
+    }
+
+    /// Copies a file or directory from one path to another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .cp(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             forest,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir
+    ///         .ls(&["code".into()], true, forest, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 2);
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn cp(
+        self: &mut Rc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(path_segments_from)?;
+        crate::utils::ensure_valid_path_segments(path_segments_to)?;
+        let result = self
+            .get_node(path_segments_from, search_latest, forest, store)
+            .await?;
+
+        self.attach(
+            result.ok_or(FsError::NotFound)?,
+            path_segments_to,
+            search_latest,
+            time,
+            forest,
+            store,
+            rng,
+        )
+        .await
+    }
+
+    /// Copies a file or directory from one path to another without modifying it
+    #[allow(clippy::too_many_arguments)]
+    pub async fn cp_link(
+        self: &mut Rc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        search_latest: bool,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(path_segments_from)?;
+        crate::utils::ensure_valid_path_segments(path_segments_to)?;
+        let result = self
+            .get_node(path_segments_from, search_latest, forest, store)
+            .await?;
+
+        self.attach_link(
+            result.ok_or(FsError::NotFound)?,
+            path_segments_to,
+            search_latest,
+            forest,
+            store,
+        )
+        .await
+    }
+
+    /// Adds a second name for a file, like a POSIX hard link.
+    ///
+    /// This is [`cp_link`](Self::cp_link) with `search_latest = true`, restricted to files.
+    /// Both paths link to the same file with the same identity, but each keeps its own link to
+    /// a revision. A write through one path is only visible through the other after it got
+    /// stored, and only to reads with `search_latest = true`.
+    /// Removing one of the paths leaves the file reachable through the other.
+    ///
+    /// Writing through both paths before storing in between forks the file into two revisions
+    /// with the same label, like concurrent writes from two devices do.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let original = &["notes.txt".into()];
+    ///     let link = &["notes-link.txt".into()];
+    ///     root_dir
+    ///         .write(original, true, Utc::now(), b"draft".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     root_dir.hard_link(original, link, forest, store).await.unwrap();
+    ///     root_dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     root_dir
+    ///         .write(link, true, Utc::now(), b"final".to_vec(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     root_dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let content = root_dir.read(original, true, forest, store).await.unwrap();
+    ///     assert_eq!(content, b"final");
+    /// }
+    /// ```
+    pub async fn hard_link(
+        self: &mut Rc<Self>,
+        source_path_segments: &[String],
+        dest_path_segments: &[String],
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(source_path_segments)?;
+        let node = self
+            .get_node(source_path_segments, true, forest, store)
+            .await?
+            .ok_or(FsError::NotFound)?;
+
+        // Linking directories twice could create cycles
+        ensure!(
+            node.is_file(),
+            FsError::NotAFile(source_path_segments.to_vec())
+        );
+
+        self.cp_link(
+            source_path_segments,
+            dest_path_segments,
+            true,
+            forest,
+            store,
+        )
+        .await
+    }
+
+    /// Write a Symlink to the filesystem with the reference path at the path segments specified
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write_symlink(
+        self: &mut Rc<Self>,
+        path: String,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        crate::utils::ensure_valid_path_segments(path_segments)?;
+        let (path_segments, filename) = crate::utils::split_last(path_segments)?;
+
+        let dir = self
+            .get_or_create_leaf_dir_mut(path_segments, time, search_latest, forest, store, rng)
+            .await?;
+
+        match dir
+            .lookup_node_mut(filename, search_latest, forest, store)
+            .await?
+        {
+            Some(PrivateNode::File(file)) => {
+                let file = file.prepare_next_revision()?;
+                file.content.content = super::FileContent::Inline {
+                    data: vec![],
+                    cipher: file.get_cipher(),
+                };
+                file.content.metadata.upsert_mtime(time);
+                // Write the path into the Metadata HashMap
+                file.content
+                    .metadata
+                    .0
+                    .insert(String::from("symlink"), Ipld::String(path));
+            }
+            Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
+            None => {
+                let file =
+                    PrivateFile::new_symlink(path, dir.header.bare_name.clone(), time, rng).await?;
+                let link = PrivateLink::with_file(file);
+                dir.content.entries.insert(filename.to_string(), link);
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Applies several mutations as one atomic step.
+    ///
+    /// The closure gets a [`Transaction`] holding working copies of this directory and the forest,
+    /// and applies its mutations to those. If it returns `Ok`, the working directory gets stored
+    /// once, producing a single new revision whose previous link points to the revision the
+    /// transaction started from. Then the working copies replace `self` and `forest`.
+    ///
+    /// If the closure returns an error, `self` and `forest` are left untouched and the error is
+    /// returned. Blocks the closure already wrote stay in the store, but nothing refers to them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     root_dir
+    ///         .transaction(forest, store, rng, |txn| {
+    ///             Box::pin(async move {
+    ///                 let path = &["pictures".into(), "cats".into(), "tabby.png".into()];
+    ///                 txn.dir
+    ///                     .write(path, true, Utc::now(), b"...".to_vec(), &mut txn.forest, txn.store, txn.rng)
+    ///                     .await?;
+    ///                 txn.dir
+    ///                     .basic_mv(path, &["tabby.png".into()], true, Utc::now(), &mut txn.forest, txn.store, txn.rng)
+    ///                     .await
+    ///             })
+    ///         })
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = root_dir.ls(&[], true, forest, store).await.unwrap();
+    ///
+    ///     assert_eq!(result.len(), 2);
+    /// }
+    /// ```
+    pub async fn transaction<'a, B: BlockStore, R: RngCore, T>(
+        self: &mut Rc<Self>,
+        forest: &mut Rc<PrivateForest>,
+        store: &'a B,
+        rng: &'a mut R,
+        f: impl for<'t> FnOnce(&'t mut Transaction<'a, B, R>) -> LocalBoxFuture<'t, Result<T>>,
+    ) -> Result<(T, PrivateRef)> {
+        let mut txn = Transaction {
+            dir: Rc::clone(self),
+            forest: Rc::clone(forest),
+            store,
+            rng,
+        };
+
+        let output = f(&mut txn).await?;
+
+        let Transaction {
+            dir,
+            forest: mut working_forest,
+            rng,
+            ..
+        } = txn;
+        let private_ref = dir.store(&mut working_forest, store, rng).await?;
+
+        *self = dir;
+        *forest = working_forest;
+        Ok((output, private_ref))
+    }
+
+    /// Stores this PrivateDirectory in the PrivateForest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef, PrivateNode, PrivateDirectory},
+    ///     common::{BlockStore, MemoryBlockStore},
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let node = PrivateNode::Dir(Rc::clone(&dir));
+    ///
+    ///     assert_eq!(
+    ///         PrivateNode::load(&private_ref, forest, store).await.unwrap(),
+    ///         node
+    ///     );
+    /// }
+    /// ```
+    pub async fn store(
+        &self,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<PrivateRef> {
+        let header_cid = self.header.store(store).await?;
+        let temporal_key = self.header.derive_temporal_key();
+        let label = self.header.get_saturated_name();
+
+        let content_cid = self
+            .content
+            .store(header_cid, &temporal_key, forest, store, rng)
+            .await?;
+
+        forest
+            .put_node(label, header_cid, content_cid, store)
+            .await?;
+
+        Ok(self
+            .header
+            .derive_revision_ref()
+            .as_private_ref(content_cid))
+    }
+
+    /// Stores this PrivateDirectory in the PrivateForest like [`store`](Self::store), but puts the
+    /// blocks into the store concurrently.
+    ///
+    /// The subtree is encrypted first, with blocks held in memory, and then all of its blocks are
+    /// put into the store at once. Encryption draws from `rng` in the same order as `store` does,
+    /// so given the same random numbers, both return the same [`PrivateRef`]. This helps with
+    /// stores where each put is a slow round trip.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateNode, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     dir.mkdir(&["pictures".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let private_ref = dir.store_parallel(forest, store, rng).await.unwrap();
+    ///
+    ///     assert_eq!(
+    ///         PrivateNode::load(&private_ref, forest, store).await.unwrap(),
+    ///         PrivateNode::Dir(Rc::clone(&dir))
+    ///     );
+    /// }
+    /// ```
+    pub async fn store_parallel(
+        &self,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<PrivateRef> {
+        let buffered_store = BufferedBlockStore::new(store);
+        let private_ref = self.store(forest, &buffered_store, rng).await?;
+        buffered_store.flush().await?;
+        Ok(private_ref)
+    }
+
+    /// Creates a  new [`PrivateDirectory`] from a [`PrivateDirectoryContentSerializable`].
+    pub(crate) async fn from_serializable_temporal(
+        serializable: PrivateDirectoryContentSerializable,
+        temporal_key: &TemporalKey,
+        cid: Cid,
+        store: &impl BlockStore,
+    ) -> Result<Self> {
+        if serializable.version.major != 0 || serializable.version.minor != 2 {
+            bail!(FsError::UnexpectedVersion(serializable.version));
+        }
+
+        let mut entries_decrypted = BTreeMap::new();
+        for (name, private_ref_serializable) in serializable.entries {
+            let private_ref =
+                PrivateRef::from_serializable(private_ref_serializable, temporal_key)?;
+            entries_decrypted.insert(name, PrivateLink::from_ref(private_ref));
+        }
+
+        let content = PrivateDirectoryContent {
+            persisted_as: OnceCell::new_with(Some(cid)),
+            metadata: serializable.metadata,
+            previous: serializable.previous.into_iter().collect(),
+            entries: entries_decrypted,
+        };
+
+        let header =
+            PrivateNodeHeader::load_temporal(&serializable.header_cid, temporal_key, store).await?;
+        Ok(Self { header, content })
+    }
+
+    /// Creates a new [`PrivateDirectory`] from a [`PrivateDirectoryContentSerializable`] but only a
+    /// snapshot key.
+    ///
+    /// Each entry carries the snapshot key of the child it points to, so children are linked
+    /// lazily and only fetched and decrypted with their snapshot keys once they're accessed.
+    /// Nodes loaded this way don't know their ratchets, so they can be read but their revisions
+    /// can't be advanced.
+    pub(crate) async fn from_serializable_snapshot(
+        serializable: PrivateDirectoryContentSerializable,
+        snapshot_key: &SnapshotKey,
+        cid: Cid,
+        store: &impl BlockStore,
+    ) -> Result<Self> {
+        if serializable.version.major != 0 || serializable.version.minor != 2 {
+            bail!(FsError::UnexpectedVersion(serializable.version));
+        }
+
+        let mut entries_decrypted = BTreeMap::new();
+        for (name, private_ref_serializable) in serializable.entries {
+            let link = PrivateLink::from_snapshot(
+                private_ref_serializable.snapshot_key,
+                private_ref_serializable.content_cid,
+            );
+            entries_decrypted.insert(name, link);
+        }
+
+        let content = PrivateDirectoryContent {
+            persisted_as: OnceCell::new_with(Some(cid)),
+            metadata: serializable.metadata,
+            previous: serializable.previous.into_iter().collect(),
+            entries: entries_decrypted,
+        };
+
+        let header =
+            PrivateNodeHeader::load_snapshot(&serializable.header_cid, snapshot_key, store).await?;
+        Ok(Self { header, content })
+    }
+
+    /// Wraps the directory in a [`PrivateNode`].
+    pub fn as_node(self: &Rc<Self>) -> PrivateNode {
+        PrivateNode::Dir(Rc::clone(self))
+    }
+}
+
+impl PrivateDirectoryContent {
+    /// Serializes the directory to dag-cbor.
+    pub(crate) async fn to_dag_cbor(
+        &self,
+        temporal_key: &TemporalKey,
+        header_cid: Cid,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<Vec<u8>> {
+        let mut entries = BTreeMap::new();
+
+        for (name, private_link) in self.entries.iter() {
+            let private_ref_serializable = private_link
+                .resolve_ref(forest, store, rng)
+                .await?
+                .to_serializable(temporal_key)?;
+            entries.insert(name.clone(), private_ref_serializable);
+        }
+
+        Ok(serde_ipld_dagcbor::to_vec(
+            &PrivateNodeContentSerializable::Dir(PrivateDirectoryContentSerializable {
+                version: WNFS_VERSION,
+                previous: self.previous.iter().cloned().collect(),
+                header_cid,
+                metadata: self.metadata.clone(),
+                entries,
+            }),
+        )?)
+    }
+
+    /// Encrypts the directory contents by
+    /// - wrapping all subdirectory temporal keys given the current temporal key
+    /// - encrypting the whole directory using the snapshot key derived from the temporal key.
+    ///
+    /// The resulting ciphertext is then stored in the given BlockStore. Its CID is finally returned.
+    ///
+    /// Randomness is required for randomized encryption.
+    ///
+    /// The header cid is required as it's not stored in the PrivateDirectoryContent itself, but
+    /// stored in the serialized format.
+    pub(crate) async fn store(
+        &self,
+        header_cid: Cid,
+        temporal_key: &TemporalKey,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<Cid> {
+        Ok(*self
+            .persisted_as
+            .get_or_try_init::<anyhow::Error>(async {
+                // TODO(matheus23) deduplicate when reworking serialization (see file.rs)
+                let snapshot_key = temporal_key.derive_snapshot_key();
+
+                // Serialize node to cbor.
+                let bytes = self
+                    .to_dag_cbor(temporal_key, header_cid, forest, store, rng)
+                    .await?;
+
+                // Encrypt bytes with snapshot key.
+                let block = snapshot_key.encrypt(&bytes, rng)?;
+
+                // Store content section in blockstore and get Cid.
+                store.put_block(block, libipld::IpldCodec::Raw).await
+            })
+            .await?)
+    }
+}
+
+impl PartialEq for PrivateDirectoryContent {
+    fn eq(&self, other: &Self) -> bool {
+        self.previous == other.previous
+            && self.metadata == other.metadata
+            && self.entries == other.entries
+    }
+}
+
+impl Clone for PrivateDirectoryContent {
+    fn clone(&self) -> Self {
+        Self {
+            persisted_as: OnceCell::new_with(self.persisted_as.get().cloned()),
+            previous: self.previous.clone(),
+            metadata: self.metadata.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl Id for PrivateDirectory {
+    fn get_id(&self) -> String {
+        format!("{:p}", &self.header)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use libipld::{codec::Codec, IpldCodec};
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use skip_ratchet::Ratchet;
+    use test_log::test;
+    use wnfs_common::{MemoryBlockStore, DEFAULT_FILE_MODE, MAX_BLOCK_SIZE};
+
+    #[test(async_std::test)]
+    async fn can_create_directories_deterministically_with_user_provided_seeds() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let ratchet_seed = utils::get_random_bytes::<32>(rng);
+        let inumber = utils::get_random_bytes::<32>(rng);
+
+        let dir1 =
+            PrivateDirectory::with_seed(Namefilter::default(), Utc::now(), ratchet_seed, inumber);
+
+        let dir2 =
+            PrivateDirectory::with_seed(Namefilter::default(), Utc::now(), ratchet_seed, inumber);
+
+        assert_eq!(
+            dir1.header.derive_temporal_key(),
+            dir2.header.derive_temporal_key()
+        );
+
+        assert_eq!(
+            dir1.header.get_saturated_name(),
+            dir2.header.get_saturated_name()
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn look_up_can_fetch_file_added_to_directory() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let content = b"Hello, World!".to_vec();
+
+        root_dir
+            .write(
+                &["text.txt".into()],
+                true,
+                Utc::now(),
+                content.clone(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let result = root_dir
+            .read(&["text.txt".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, content);
+    }
+
+    #[test(async_std::test)]
+    async fn look_up_cannot_fetch_file_not_added_to_directory() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &Rc::new(PrivateForest::new());
+
+        let node = root_dir
+            .lookup_node("Unknown", true, forest, store)
+            .await
+            .unwrap();
+
+        assert!(node.is_none());
+    }
+
+    #[test(async_std::test)]
+    async fn get_node_can_fetch_node_from_root_dir() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .mkdir(
+                &["pictures".into(), "dogs".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                true,
+                Utc::now(),
+                b"file".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        assert!(root_dir
+            .get_node(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                true,
+                forest,
+                store,
+            )
+            .await
+            .unwrap()
+            .is_some());
+
+        assert!(root_dir
+            .get_node(
+                &["pictures".into(), "cats".into(), "tabby.jpeg".into()],
+                true,
+                forest,
+                store,
+            )
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(root_dir
+            .get_node(
+                &["images".into(), "parrots".into(), "coco.png".into()],
+                true,
+                forest,
+                store,
+            )
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(root_dir
+            .get_node(
+                &["pictures".into(), "dogs".into(), "bingo.jpg".into()],
+                true,
+                forest,
+                store,
+            )
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test(async_std::test)]
+    async fn mkdir_can_create_new_directory() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let result = root_dir
+            .get_node(&["tamedun".into(), "pictures".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert!(result.is_some());
+    }
+
+    #[test(async_std::test)]
+    async fn ls_can_list_children_under_directory() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .write(
+                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
+                true,
+                Utc::now(),
+                b"puppy".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into(), "cats".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let result = root_dir
+            .ls(&["tamedun".into(), "pictures".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].0, String::from("cats"));
+        assert_eq!(result[1].0, String::from("puppy.jpg"));
+    }
+
+    #[test(async_std::test)]
+    async fn rm_can_remove_children_from_directory() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .write(
+                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
+                true,
+                Utc::now(),
+                b"puppy".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into(), "cats".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .rm(&["tamedun".into(), "pictures".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        let result = root_dir
+            .rm(&["tamedun".into(), "pictures".into()], true, forest, store)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn rm_recursive_counts_detached_nodes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        for path in [&["a", "b", "c", "deep.txt"][..], &["a", "b", "middle.txt"]] {
+            let path = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            root_dir
+                .write(&path, true, Utc::now(), b"hi".to_vec(), forest, store, rng)
+                .await
+                .unwrap();
+        }
+
+        root_dir
+            .write(
+                &["unrelated.txt".into()],
+                true,
+                Utc::now(),
+                b"hi".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let removed = root_dir
+            .rm_recursive(&["a".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        // a, b, c, deep.txt and middle.txt
+        assert_eq!(removed, 5);
+
+        let entries = root_dir.ls(&[], true, forest, store).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "unrelated.txt");
+
+        let removed = root_dir
+            .rm_recursive(&["unrelated.txt".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(root_dir
+            .ls(&[], true, forest, store)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test(async_std::test)]
+    async fn walk_yields_all_descendants_depth_first() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        for path in ["docs/a.txt", "docs/notes/b.txt", "pics/c.jpg", "top.txt"] {
+            let path = path.split('/').map(String::from).collect::<Vec<_>>();
+            root_dir
+                .write(&path, true, Utc::now(), b"hi".to_vec(), forest, store, rng)
+                .await
+                .unwrap();
+        }
+
+        root_dir
+            .mkdir(&["empty".into()], true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        let walked = root_dir
+            .walk(&[], true, forest, store)
+            .map_ok(|(path, node)| (path.join("/"), node.as_file().is_ok()))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            walked,
+            [
+                ("docs".to_string(), false),
+                ("docs/a.txt".to_string(), true),
+                ("docs/notes".to_string(), false),
+                ("docs/notes/b.txt".to_string(), true),
+                ("empty".to_string(), false),
+                ("pics".to_string(), false),
+                ("pics/c.jpg".to_string(), true),
+                ("top.txt".to_string(), true),
+            ]
+        );
+
+        let walked = root_dir
+            .walk(&["docs".into()], true, forest, store)
+            .map_ok(|(path, _)| path.join("/"))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(walked, ["a.txt", "notes", "notes/b.txt"]);
+
+        let result = root_dir
+            .walk(&["top.txt".into()], true, forest, store)
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn exists_finds_files_and_directories() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .write(
+                &["docs".into(), "a.txt".into()],
+                true,
+                Utc::now(),
+                b"hi".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let exists = |path: &[&str]| {
+            let path = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            let root_dir = Rc::clone(root_dir);
+            let forest = Rc::clone(forest);
+            async move { root_dir.exists(&path, true, &forest, store).await }
+        };
+
+        assert!(exists(&["docs", "a.txt"]).await.unwrap());
+        assert!(exists(&["docs"]).await.unwrap());
+        assert!(!exists(&["docs", "b.txt"]).await.unwrap());
+        assert!(!exists(&["missing", "a.txt"]).await.unwrap());
+        assert!(!exists(&["docs", "a.txt", "nested"]).await.unwrap());
+    }
+
+    #[async_std::test]
+    async fn exists_fails_when_nodes_cannot_be_loaded() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .write(
+                &["docs".into(), "a.txt".into()],
+                true,
+                Utc::now(),
+                b"hi".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+        let root_dir = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+
+        let empty_store = &MemoryBlockStore::default();
+        let result = root_dir
+            .exists(&["docs".into(), "a.txt".into()], false, forest, empty_store)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn size_sums_up_descendant_file_sizes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        for (path, len) in [
+            ("docs/a.txt", 10),
+            ("docs/notes/b.txt", 300_000),
+            ("docs/notes/empty.txt", 0),
+            ("top.txt", 7),
+        ] {
+            let path = path.split('/').map(String::from).collect::<Vec<_>>();
+            root_dir
+                .write(&path, true, Utc::now(), vec![1; len], forest, store, rng)
+                .await
+                .unwrap();
+        }
+
+        root_dir
+            .mkdir(
+                &["docs".into(), "nothing".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            root_dir.size(&[], true, forest, store).await.unwrap(),
+            300_017
+        );
+        assert_eq!(
+            root_dir
+                .size(&["docs".into()], true, forest, store)
+                .await
+                .unwrap(),
+            300_010
+        );
+        assert_eq!(
+            root_dir
+                .size(&["docs".into(), "nothing".into()], true, forest, store)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn write_many_matches_writing_files_one_by_one() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let time = Utc::now();
+
+        let entries = (0..50)
+            .map(|i| {
+                let path = match i % 4 {
+                    0 => vec![format!("file{i}.txt")],
+                    1 => vec!["docs".into(), format!("file{i}.txt")],
+                    2 => vec!["docs".into(), "notes".into(), format!("file{i}.txt")],
+                    _ => vec![
+                        "pics".into(),
+                        format!("dir{}", i % 3),
+                        format!("file{i}.txt"),
+                    ],
+                };
+                (path, format!("content {i}").into_bytes())
+            })
+            .collect::<Vec<_>>();
+
+        let one_by_one = &mut Rc::new(PrivateDirectory::new(Namefilter::default(), time, rng));
+        for (path, content) in entries.clone() {
+            one_by_one
+                .write(&path, true, time, content, forest, store, rng)
+                .await
+                .unwrap();
+        }
+
+        let batched = &mut Rc::new(PrivateDirectory::new(Namefilter::default(), time, rng));
+        batched
+            .write_many(entries, time, forest, store, rng)
+            .await
+            .unwrap();
+
+        let mut trees = vec![];
+        for dir in [one_by_one, batched] {
+            let mut tree = vec![];
+            let mut nodes = dir.walk(&[], true, forest, store);
+            while let Some((path, node)) = nodes.try_next().await.unwrap() {
+                let content = match node {
+                    PrivateNode::File(file) => Some(file.get_content(forest, store).await.unwrap()),
+                    PrivateNode::Dir(_) => None,
+                };
+                tree.push((path, content));
+            }
+            trees.push(tree);
+        }
+
+        assert_eq!(trees[0].len(), 50 + 6);
+        assert_eq!(trees[0], trees[1]);
+    }
+
+    #[test(async_std::test)]
+    async fn rename_within_directory_keeps_node_identity() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let original = &["docs".into(), "a.txt".into()];
+        let renamed = &["docs".into(), "b.txt".into()];
+        let moved = &["other".into(), "b.txt".into()];
+
+        root_dir
+            .write(
+                original,
+                true,
+                Utc::now(),
+                b"a".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        root_dir
+            .mkdir(&["other".into()], true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        async fn get_header(
+            dir: &Rc<PrivateDirectory>,
+            path: &[String],
+            forest: &PrivateForest,
+            store: &impl BlockStore,
+        ) -> PrivateNodeHeader {
+            dir.get_node(path, true, forest, store)
+                .await
+                .unwrap()
+                .unwrap()
+                .get_header()
+                .clone()
+        }
+
+        let original_header = get_header(root_dir, original, forest, store).await;
+
+        root_dir
+            .rename(original, renamed, true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        let renamed_header = get_header(root_dir, renamed, forest, store).await;
+        assert!(!root_dir
+            .exists(original, true, forest, store)
+            .await
+            .unwrap());
+        assert_eq!(renamed_header.inumber, original_header.inumber);
+        assert_eq!(renamed_header.bare_name, original_header.bare_name);
+        assert_eq!(
+            root_dir.read(renamed, true, forest, store).await.unwrap(),
+            b"a"
+        );
+
+        root_dir
+            .rename(renamed, moved, true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        let moved_header = get_header(root_dir, moved, forest, store).await;
+        assert_ne!(moved_header.bare_name, original_header.bare_name);
+        assert_eq!(
+            root_dir.read(moved, true, forest, store).await.unwrap(),
+            b"a"
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn path_of_finds_stored_nodes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        for path in ["docs/a.txt", "docs/notes/b.txt", "pics/c.jpg", "top.txt"] {
+            let path = path.split('/').map(String::from).collect::<Vec<_>>();
+            root_dir
+                .write(&path, true, Utc::now(), b"hi".to_vec(), forest, store, rng)
+                .await
+                .unwrap();
+        }
+
+        let root_ref = root_dir.store(forest, store, rng).await.unwrap();
+        let root_dir = PrivateNode::load(&root_ref, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+
+        let path = vec!["docs".to_string(), "notes".into(), "b.txt".into()];
+        let file_ref = root_dir
+            .get_node(&path, false, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .get_private_ref()
+            .unwrap();
+
+        assert_eq!(
+            root_dir.path_of(&file_ref, forest, store).await.unwrap(),
+            Some(path)
+        );
+        assert_eq!(
+            root_dir.path_of(&root_ref, forest, store).await.unwrap(),
+            Some(vec![])
+        );
+
+        let unrelated = PrivateFile::new(Namefilter::default(), Utc::now(), rng)
+            .store(forest, store, rng)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            root_dir.path_of(&unrelated, forest, store).await.unwrap(),
+            None
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn find_glob_matches_names_across_the_tree() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
 
-        let content = PrivateDirectoryContent {
-            persisted_as: OnceCell::new_with(Some(cid)),
-            metadata: serializable.metadata,
-            previous: serializable.previous.into_iter().collect(),
-            entries: entries_decrypted,
+        for path in [
+            "main.py",
+            "README.md",
+            "src/lib.py",
+            "src/utils/helpers.py",
+            "src/utils/helpers.pyc",
+            "docs/guide.md",
+        ] {
+            let path = path.split('/').map(String::from).collect::<Vec<_>>();
+            root_dir
+                .write(&path, true, Utc::now(), b"hi".to_vec(), forest, store, rng)
+                .await
+                .unwrap();
+        }
+
+        // A directory created at a distinct time, so the predicate can tell it apart
+        let epoch = Utc.timestamp_opt(0, 0).unwrap();
+        root_dir
+            .mkdir(
+                &["src".into(), "tests.py".into()],
+                true,
+                epoch,
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let find_glob = |base_path: Vec<String>, pattern: &'static str| {
+            let root_dir = Rc::clone(root_dir);
+            let forest = Rc::clone(forest);
+            async move {
+                root_dir
+                    .find_glob(&base_path, pattern, &forest, store)
+                    .map_ok(|(path, _)| path.join("/"))
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap()
+            }
         };
 
-        let header =
-            PrivateNodeHeader::load_temporal(&serializable.header_cid, temporal_key, store).await?;
-        Ok(Self { header, content })
-    }
+        assert_eq!(
+            find_glob(vec![], "*.py").await,
+            [
+                "main.py",
+                "src/lib.py",
+                "src/tests.py",
+                "src/utils/helpers.py"
+            ]
+        );
+        assert_eq!(
+            find_glob(vec!["src".into()], "*.py?").await,
+            ["utils/helpers.pyc"]
+        );
+        assert_eq!(
+            find_glob(vec![], "*.md").await,
+            ["README.md", "docs/guide.md"]
+        );
+
+        let files = root_dir
+            .find(
+                &[],
+                |name, metadata| name.ends_with(".py") && metadata.get_created() != Some(epoch),
+                forest,
+                store,
+            )
+            .map_ok(|(path, _)| path.join("/"))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(files, ["main.py", "src/lib.py", "src/utils/helpers.py"]);
+    }
+
+    #[test(async_std::test)]
+    async fn transaction_commits_a_single_revision() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        root_dir.store(forest, store, rng).await.unwrap();
+        let old_dir = Rc::clone(root_dir);
+
+        let (_, private_ref) = root_dir
+            .transaction(forest, store, rng, |txn| {
+                Box::pin(async move {
+                    for name in ["a.txt", "b.txt"] {
+                        txn.dir
+                            .write(
+                                &[name.into()],
+                                true,
+                                Utc::now(),
+                                name.as_bytes().to_vec(),
+                                &mut txn.forest,
+                                txn.store,
+                                txn.rng,
+                            )
+                            .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(root_dir.get_private_ref(), Some(private_ref.clone()));
+        assert_eq!(root_dir.content.previous.len(), 1);
+        assert_eq!(
+            root_dir
+                .header
+                .ratchet
+                .compare(&old_dir.header.ratchet, 10)
+                .unwrap(),
+            1
+        );
+
+        let loaded = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+        for name in ["a.txt", "b.txt"] {
+            let content = loaded
+                .read(&[name.into()], false, forest, store)
+                .await
+                .unwrap();
+            assert_eq!(content, name.as_bytes());
+        }
+    }
+
+    #[test(async_std::test)]
+    async fn failed_transaction_leaves_directory_and_forest_unchanged() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        root_dir
+            .write(
+                &["a.txt".into()],
+                true,
+                Utc::now(),
+                b"a".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let old_ref = root_dir.store(forest, store, rng).await.unwrap();
+        let old_dir = Rc::clone(root_dir);
+        let old_forest = Rc::clone(forest);
+
+        let result = root_dir
+            .transaction(forest, store, rng, |txn| {
+                Box::pin(async move {
+                    txn.dir
+                        .write(
+                            &["b.txt".into()],
+                            true,
+                            Utc::now(),
+                            b"b".to_vec(),
+                            &mut txn.forest,
+                            txn.store,
+                            txn.rng,
+                        )
+                        .await?;
+                    txn.dir
+                        .rm(&["missing.txt".into()], true, &txn.forest, txn.store)
+                        .await?;
+                    Ok(())
+                })
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert!(Rc::ptr_eq(root_dir, &old_dir));
+        assert!(Rc::ptr_eq(forest, &old_forest));
+        assert_eq!(root_dir.get_private_ref(), Some(old_ref.clone()));
+
+        let loaded = PrivateNode::load(&old_ref, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+        let entries = loaded.get_entries().cloned().collect::<Vec<_>>();
+        assert_eq!(entries, ["a.txt"]);
+        assert!(root_dir
+            .read(&["b.txt".into()], true, forest, store)
+            .await
+            .is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn mkdir_reporting_only_reports_created_directories() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = |path: &str| path.split('/').map(String::from).collect::<Vec<_>>();
+
+        let created = root_dir
+            .mkdir_reporting(&path("a/b/c"), true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        assert_eq!(created, [path("a"), path("a/b"), path("a/b/c")]);
+
+        let created = root_dir
+            .mkdir_reporting(&path("a/b/d"), true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        assert_eq!(created, [path("a/b/d")]);
+
+        let created = root_dir
+            .mkdir_reporting(&path("a/b"), true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        assert!(created.is_empty());
+    }
+
+    #[test(async_std::test)]
+    async fn restore_reverts_content_and_keeps_history() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = &["file.txt".into()];
+
+        let mut revisions = vec![];
+        for content in ["A", "B"] {
+            root_dir
+                .write(
+                    path,
+                    true,
+                    Utc::now(),
+                    content.as_bytes().to_vec(),
+                    forest,
+                    store,
+                    rng,
+                )
+                .await
+                .unwrap();
+            root_dir.store(forest, store, rng).await.unwrap();
+            let file = root_dir
+                .lookup_node("file.txt", false, forest, store)
+                .await
+                .unwrap()
+                .unwrap();
+            revisions.push(file);
+        }
+
+        let target = revisions[0].get_private_ref().unwrap();
+        root_dir
+            .restore(path, &target, false, Utc::now(), forest, store)
+            .await
+            .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+
+        let content = root_dir.read(path, false, forest, store).await.unwrap();
+        assert_eq!(content, b"A");
+
+        let restored = root_dir
+            .lookup_node("file.txt", false, forest, store)
+            .await
+            .unwrap()
+            .unwrap();
+        let history = restored
+            .history(&revisions[0], 1_000, forest, store)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut contents = vec![];
+        for node in history {
+            let content = node
+                .as_file()
+                .unwrap()
+                .get_content(forest, store)
+                .await
+                .unwrap();
+            contents.push(String::from_utf8(content).unwrap());
+        }
+
+        assert_eq!(contents, ["A", "B", "A"]);
+    }
+
+    #[test(async_std::test)]
+    async fn restore_over_unstored_edit_keeps_history() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = &["file.txt".into()];
+
+        root_dir
+            .write(path, true, Utc::now(), b"A".to_vec(), forest, store, rng)
+            .await
+            .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+        let stored = root_dir
+            .lookup_node("file.txt", false, forest, store)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Edit without storing, then restore over the edit
+        root_dir
+            .write(path, true, Utc::now(), b"B".to_vec(), forest, store, rng)
+            .await
+            .unwrap();
+        let target = stored.get_private_ref().unwrap();
+        root_dir
+            .restore(path, &target, false, Utc::now(), forest, store)
+            .await
+            .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+
+        let restored = root_dir
+            .lookup_node("file.txt", false, forest, store)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.get_previous().len(), 1);
+
+        let history = restored
+            .history(&stored, 1_000, forest, store)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut contents = vec![];
+        for node in history {
+            let content = node
+                .as_file()
+                .unwrap()
+                .get_content(forest, store)
+                .await
+                .unwrap();
+            contents.push(String::from_utf8(content).unwrap());
+        }
+
+        assert_eq!(contents, ["A", "A"]);
+    }
+
+    #[test(async_std::test)]
+    async fn restore_rejects_revisions_of_other_nodes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        for name in ["a.txt", "b.txt"] {
+            root_dir
+                .write(
+                    &[name.into()],
+                    true,
+                    Utc::now(),
+                    name.as_bytes().to_vec(),
+                    forest,
+                    store,
+                    rng,
+                )
+                .await
+                .unwrap();
+        }
+        root_dir.store(forest, store, rng).await.unwrap();
+
+        let other = root_dir
+            .lookup_node("b.txt", false, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .get_private_ref()
+            .unwrap();
+        let error = root_dir
+            .restore(&["a.txt".into()], &other, false, Utc::now(), forest, store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::UnrelatedRevisions)
+        ));
+    }
+
+    #[async_std::test]
+    async fn read_can_fetch_userland_of_file_added_to_directory() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        root_dir
+            .write(
+                &["text.txt".into()],
+                true,
+                Utc::now(),
+                b"text".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let result = root_dir
+            .read(&["text.txt".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, b"text".to_vec());
+    }
+
+    #[test(async_std::test)]
+    async fn search_latest_finds_the_most_recent() {
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut rand::thread_rng();
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let path = ["Documents".into(), "file.txt".into()];
 
-    #[allow(dead_code)]
-    /// Creates a  new [`PrivateDirectory`] from a [`PrivateDirectoryContentSerializable`].
-    pub(crate) async fn from_serializable_snapshot(
-        serializable: PrivateDirectoryContentSerializable,
-        snapshot_key: &SnapshotKey,
-        cid: Cid,
-        store: &impl BlockStore,
-    ) -> Result<Self> {
-        if serializable.version.major != 0 || serializable.version.minor != 2 {
-            bail!(FsError::UnexpectedVersion(serializable.version));
-        }
+        root_dir
+            .write(
+                &path,
+                false,
+                Utc::now(),
+                b"One".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
 
-        let mut entries_decrypted = BTreeMap::new();
-        // let temporal_key = TemporalKey(snapshot_key.0.to_owned());
-        for (name, private_ref_serializable) in serializable.entries {
-            let private_ref = PrivateRef {
-                saturated_name_hash: private_ref_serializable.saturated_name_hash,
-                // What are we supposed to do here in the absence of a parent key? This node is not decryptable
-                temporal_key: TemporalKey(AesKey::new([0u8; KEY_BYTE_SIZE])),
-                content_cid: private_ref_serializable.content_cid,
-            };
-            entries_decrypted.insert(name, PrivateLink::from_ref(private_ref));
-        }
+        root_dir.store(forest, store, rng).await.unwrap();
 
-        let content = PrivateDirectoryContent {
-            persisted_as: OnceCell::new_with(Some(cid)),
-            metadata: serializable.metadata,
-            previous: serializable.previous.into_iter().collect(),
-            entries: entries_decrypted,
-        };
+        let old_root = &Rc::clone(root_dir);
 
-        let header =
-            PrivateNodeHeader::load_snapshot(&serializable.header_cid, snapshot_key, store).await?;
-        Ok(Self { header, content })
-    }
+        root_dir
+            .write(&path, true, Utc::now(), b"Two".to_vec(), forest, store, rng)
+            .await
+            .unwrap();
 
-    /// Wraps the directory in a [`PrivateNode`].
-    pub fn as_node(self: &Rc<Self>) -> PrivateNode {
-        PrivateNode::Dir(Rc::clone(self))
-    }
-}
+        root_dir.store(forest, store, rng).await.unwrap();
 
-impl PrivateDirectoryContent {
-    /// Serializes the directory to dag-cbor.
-    pub(crate) async fn to_dag_cbor(
-        &self,
-        temporal_key: &TemporalKey,
-        header_cid: Cid,
-        forest: &mut Rc<PrivateForest>,
-        store: &impl BlockStore,
-        rng: &mut impl RngCore,
-    ) -> Result<Vec<u8>> {
-        let mut entries = BTreeMap::new();
+        let new_read = root_dir.read(&path, false, forest, store).await.unwrap();
 
-        for (name, private_link) in self.entries.iter() {
-            let private_ref_serializable = private_link
-                .resolve_ref(forest, store, rng)
-                .await?
-                .to_serializable(temporal_key)?;
-            entries.insert(name.clone(), private_ref_serializable);
-        }
+        let old_read = Rc::clone(old_root)
+            .read(&path, false, forest, store)
+            .await
+            .unwrap();
 
-        Ok(serde_ipld_dagcbor::to_vec(
-            &PrivateNodeContentSerializable::Dir(PrivateDirectoryContentSerializable {
-                version: WNFS_VERSION,
-                previous: self.previous.iter().cloned().collect(),
-                header_cid,
-                metadata: self.metadata.clone(),
-                entries,
-            }),
-        )?)
+        let old_read_latest = old_root.read(&path, true, forest, store).await.unwrap();
+        let new_read_latest = root_dir.read(&path, true, forest, store).await.unwrap();
+
+        assert_eq!(&String::from_utf8_lossy(&new_read), "Two");
+        assert_eq!(&String::from_utf8_lossy(&old_read), "One");
+        assert_eq!(&String::from_utf8_lossy(&old_read_latest), "Two");
+        assert_eq!(&String::from_utf8_lossy(&new_read_latest), "Two");
     }
 
-    /// Encrypts the directory contents by
-    /// - wrapping all subdirectory temporal keys given the current temporal key
-    /// - encrypting the whole directory using the snapshot key derived from the temporal key.
-    ///
-    /// The resulting ciphertext is then stored in the given BlockStore. Its CID is finally returned.
-    ///
-    /// Randomness is required for randomized encryption.
-    ///
-    /// The header cid is required as it's not stored in the PrivateDirectoryContent itself, but
-    /// stored in the serialized format.
-    pub(crate) async fn store(
-        &self,
-        header_cid: Cid,
-        temporal_key: &TemporalKey,
-        forest: &mut Rc<PrivateForest>,
-        store: &impl BlockStore,
-        rng: &mut impl RngCore,
-    ) -> Result<Cid> {
-        Ok(*self
-            .persisted_as
-            .get_or_try_init::<anyhow::Error>(async {
-                // TODO(matheus23) deduplicate when reworking serialization (see file.rs)
-                let snapshot_key = temporal_key.derive_snapshot_key();
+    #[async_std::test]
+    async fn cp_can_copy_sub_directory_to_another_valid_location_with_updated_ancestry() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
 
-                // Serialize node to cbor.
-                let bytes = self
-                    .to_dag_cbor(temporal_key, header_cid, forest, store, rng)
-                    .await?;
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                true,
+                Utc::now(),
+                b"tabby".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
 
-                // Encrypt bytes with snapshot key.
-                let block = snapshot_key.encrypt(&bytes, rng)?;
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "luna.png".into()],
+                true,
+                Utc::now(),
+                b"luna".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
 
-                // Store content section in blockstore and get Cid.
-                store.put_block(block, libipld::IpldCodec::Raw).await
-            })
-            .await?)
-    }
-}
+        root_dir
+            .mkdir(&["images".into()], true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
 
-impl PartialEq for PrivateDirectoryContent {
-    fn eq(&self, other: &Self) -> bool {
-        self.previous == other.previous
-            && self.metadata == other.metadata
-            && self.entries == other.entries
-    }
-}
+        root_dir
+            .cp(
+                &["pictures".into(), "cats".into()],
+                &["images".into(), "cats".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
 
-impl Clone for PrivateDirectoryContent {
-    fn clone(&self) -> Self {
-        Self {
-            persisted_as: OnceCell::new_with(self.persisted_as.get().cloned()),
-            previous: self.previous.clone(),
-            metadata: self.metadata.clone(),
-            entries: self.entries.clone(),
-        }
-    }
-}
+        let result = root_dir
+            .ls(&["images".into()], true, forest, store)
+            .await
+            .unwrap();
 
-impl Id for PrivateDirectory {
-    fn get_id(&self) -> String {
-        format!("{:p}", &self.header)
-    }
-}
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, String::from("cats"));
 
-//--------------------------------------------------------------------------------------------------
-// Tests
-//--------------------------------------------------------------------------------------------------
+        let result = root_dir
+            .ls(&["pictures".into()], true, forest, store)
+            .await
+            .unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use proptest::test_runner::{RngAlgorithm, TestRng};
-    use test_log::test;
-    use wnfs_common::MemoryBlockStore;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, String::from("cats"));
 
-    #[test(async_std::test)]
-    async fn can_create_directories_deterministically_with_user_provided_seeds() {
-        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let ratchet_seed = utils::get_random_bytes::<32>(rng);
-        let inumber = utils::get_random_bytes::<32>(rng);
+        let result = root_dir
+            .get_node(&["images".into(), "cats".into()], true, forest, store)
+            .await
+            .unwrap();
 
-        let dir1 =
-            PrivateDirectory::with_seed(Namefilter::default(), Utc::now(), ratchet_seed, inumber);
+        let cats_bare_name = result.unwrap().get_header().bare_name.clone();
 
-        let dir2 =
-            PrivateDirectory::with_seed(Namefilter::default(), Utc::now(), ratchet_seed, inumber);
+        let images_dir_inumber = root_dir
+            .lookup_node("images", true, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .get_header()
+            .inumber;
 
-        assert_eq!(
-            dir1.header.derive_temporal_key(),
-            dir2.header.derive_temporal_key()
-        );
+        let pictures_dir_inumber = root_dir
+            .lookup_node("pictures", true, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .get_header()
+            .inumber;
 
-        assert_eq!(
-            dir1.header.get_saturated_name(),
-            dir2.header.get_saturated_name()
-        );
+        assert!(cats_bare_name.contains(&images_dir_inumber));
+        assert!(!cats_bare_name.contains(&pictures_dir_inumber));
     }
 
-    #[test(async_std::test)]
-    async fn look_up_can_fetch_file_added_to_directory() {
+    #[async_std::test]
+    async fn mv_can_move_sub_directory_to_another_valid_location_with_updated_ancestry() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &mut MemoryBlockStore::default();
-        let forest = &mut Rc::new(PrivateForest::new());
 
-        let content = b"Hello, World!".to_vec();
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                true,
+                Utc::now(),
+                b"tabby".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
 
         root_dir
             .write(
-                &["text.txt".into()],
+                &["pictures".into(), "cats".into(), "luna.png".into()],
+                true,
+                Utc::now(),
+                b"luna".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        root_dir
+            .mkdir(&["images".into()], true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+
+        root_dir
+            .basic_mv(
+                &["pictures".into(), "cats".into()],
+                &["images".into(), "cats".into()],
                 true,
                 Utc::now(),
-                content.clone(),
                 forest,
                 store,
                 rng,
@@ -1686,48 +5061,108 @@ mod tests {
             .unwrap();
 
         let result = root_dir
-            .read(&["text.txt".into()], true, forest, store)
+            .ls(&["images".into()], true, forest, store)
             .await
             .unwrap();
 
-        assert_eq!(result, content);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, String::from("cats"));
+
+        let result = root_dir
+            .ls(&["pictures".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 0);
+
+        let result = root_dir
+            .get_node(&["images".into(), "cats".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        let cats_bare_name = result.unwrap().get_header().bare_name.clone();
+
+        let images_dir_inumber = root_dir
+            .lookup_node("images", true, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .get_header()
+            .inumber;
+
+        let pictures_dir_inumber = root_dir
+            .lookup_node("pictures", true, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .get_header()
+            .inumber;
+
+        assert!(cats_bare_name.contains(&images_dir_inumber));
+        assert!(!cats_bare_name.contains(&pictures_dir_inumber));
     }
 
-    #[test(async_std::test)]
-    async fn look_up_cannot_fetch_file_not_added_to_directory() {
+    #[async_std::test]
+    async fn mv_cannot_move_sub_directory_to_invalid_location() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let root_dir = Rc::new(PrivateDirectory::new(
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &MemoryBlockStore::default();
-        let forest = &Rc::new(PrivateForest::new());
 
-        let node = root_dir
-            .lookup_node("Unknown", true, forest, store)
+        root_dir
+            .mkdir(
+                &[
+                    "videos".into(),
+                    "movies".into(),
+                    "anime".into(),
+                    "ghibli".into(),
+                ],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
             .await
             .unwrap();
 
-        assert!(node.is_none());
+        let result = root_dir
+            .basic_mv(
+                &["videos".into(), "movies".into()],
+                &["videos".into(), "movies".into(), "anime".into()],
+                true,
+                Utc::now(),
+                forest,
+                store,
+                rng,
+            )
+            .await;
+
+        assert!(result.is_err());
     }
 
-    #[test(async_std::test)]
-    async fn get_node_can_fetch_node_from_root_dir() {
+    #[async_std::test]
+    async fn mv_can_rename_directories() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &mut MemoryBlockStore::default();
-        let forest = &mut Rc::new(PrivateForest::new());
+        let content = b"file".to_vec();
 
         root_dir
-            .mkdir(
-                &["pictures".into(), "dogs".into()],
+            .write(
+                &["file.txt".into()],
                 true,
                 Utc::now(),
+                content.clone(),
                 forest,
                 store,
                 rng,
@@ -1736,11 +5171,11 @@ mod tests {
             .unwrap();
 
         root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+            .basic_mv(
+                &["file.txt".into()],
+                &["renamed.txt".into()],
                 true,
                 Utc::now(),
-                b"file".to_vec(),
                 forest,
                 store,
                 rng,
@@ -1748,67 +5183,120 @@ mod tests {
             .await
             .unwrap();
 
-        assert!(root_dir
-            .get_node(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+        let result = root_dir
+            .read(&["renamed.txt".into()], true, forest, store)
+            .await
+            .unwrap();
+
+        assert!(result == content);
+
+        let result = root_dir
+            .lookup_node("file.txt", true, forest, store)
+            .await
+            .unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[async_std::test]
+    async fn mv_fails_moving_directories_to_files() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        root_dir
+            .mkdir(
+                &["movies".into(), "ghibli".into()],
                 true,
+                Utc::now(),
                 forest,
                 store,
+                rng,
             )
             .await
-            .unwrap()
-            .is_some());
+            .unwrap();
 
-        assert!(root_dir
-            .get_node(
-                &["pictures".into(), "cats".into(), "tabby.jpeg".into()],
+        root_dir
+            .write(
+                &["file.txt".into()],
                 true,
+                Utc::now(),
+                b"file".to_vec(),
                 forest,
                 store,
+                rng,
             )
             .await
-            .unwrap()
-            .is_none());
+            .unwrap();
 
-        assert!(root_dir
-            .get_node(
-                &["images".into(), "parrots".into(), "coco.png".into()],
+        let result = root_dir
+            .basic_mv(
+                &["movies".into(), "ghibli".into()],
+                &["file.txt".into()],
                 true,
+                Utc::now(),
                 forest,
                 store,
+                rng,
             )
-            .await
-            .unwrap()
-            .is_none());
+            .await;
 
-        assert!(root_dir
-            .get_node(
-                &["pictures".into(), "dogs".into(), "bingo.jpg".into()],
-                true,
+        assert!(result.is_err());
+    }
+
+    #[async_std::test]
+    async fn write_doesnt_generate_previous_link() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let old_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let new_dir = &mut Rc::clone(old_dir);
+        new_dir
+            .write(
+                &["file.txt".into()],
+                false,
+                Utc::now(),
+                b"Hello".to_vec(),
                 forest,
                 store,
+                rng,
             )
             .await
-            .unwrap()
-            .is_none());
+            .unwrap();
+
+        assert!(old_dir.content.previous.is_empty());
+        assert!(new_dir.content.previous.is_empty());
     }
 
-    #[test(async_std::test)]
-    async fn mkdir_can_create_new_directory() {
+    #[async_std::test]
+    async fn store_before_write_generates_previous_link() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let root_dir = &mut Rc::new(PrivateDirectory::new(
+        let store = &mut MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let old_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &mut MemoryBlockStore::default();
-        let forest = &mut Rc::new(PrivateForest::new());
+        old_dir.store(forest, store, rng).await.unwrap();
 
-        root_dir
-            .mkdir(
-                &["tamedun".into(), "pictures".into()],
-                true,
+        let new_dir = &mut Rc::clone(old_dir);
+        new_dir
+            .write(
+                &["file.txt".into()],
+                false,
                 Utc::now(),
+                b"Hello".to_vec(),
                 forest,
                 store,
                 rng,
@@ -1816,28 +5304,24 @@ mod tests {
             .await
             .unwrap();
 
-        let result = root_dir
-            .get_node(&["tamedun".into(), "pictures".into()], true, forest, store)
-            .await
-            .unwrap();
-
-        assert!(result.is_some());
+        assert!(old_dir.content.previous.is_empty());
+        assert_eq!(new_dir.content.previous.len(), 1);
     }
 
     #[test(async_std::test)]
-    async fn ls_can_list_children_under_directory() {
+    async fn type_mismatches_report_offending_path() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::default();
         let forest = &mut Rc::new(PrivateForest::new());
 
         root_dir
             .mkdir(
-                &["tamedun".into(), "pictures".into()],
+                &["docs".into(), "drafts".into()],
                 true,
                 Utc::now(),
                 forest,
@@ -1849,10 +5333,10 @@ mod tests {
 
         root_dir
             .write(
-                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
+                &["docs".into(), "notes.txt".into()],
                 true,
                 Utc::now(),
-                b"puppy".to_vec(),
+                b"notes".to_vec(),
                 forest,
                 store,
                 rng,
@@ -1860,44 +5344,67 @@ mod tests {
             .await
             .unwrap();
 
-        root_dir
-            .mkdir(
-                &["tamedun".into(), "pictures".into(), "cats".into()],
+        let error = root_dir
+            .read(&["docs".into(), "drafts".into()], true, forest, store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotAFile(path)) if path == &["docs", "drafts"]
+        ));
+        assert_eq!(error.to_string(), "Expected a file: docs/drafts");
+
+        let error = root_dir
+            .ls(
+                &["docs".into(), "notes.txt".into(), "inner".into()],
                 true,
-                Utc::now(),
                 forest,
                 store,
-                rng,
             )
             .await
-            .unwrap();
+            .unwrap_err();
 
-        let result = root_dir
-            .ls(&["tamedun".into(), "pictures".into()], true, forest, store)
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotADirectory(path)) if path == &["docs", "notes.txt"]
+        ));
+        assert_eq!(error.to_string(), "Expected a directory: docs/notes.txt");
+
+        let error = root_dir
+            .read(
+                &["docs".into(), "notes.txt".into(), "inner".into()],
+                true,
+                forest,
+                store,
+            )
             .await
-            .unwrap();
+            .unwrap_err();
 
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].0, String::from("cats"));
-        assert_eq!(result[1].0, String::from("puppy.jpg"));
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotADirectory(path)) if path == &["docs", "notes.txt"]
+        ));
     }
 
     #[test(async_std::test)]
-    async fn rm_can_remove_children_from_directory() {
+    async fn write_returning_gives_back_written_file() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::default();
         let forest = &mut Rc::new(PrivateForest::new());
+        let path = ["docs".into(), "notes.txt".into()];
 
-        root_dir
-            .mkdir(
-                &["tamedun".into(), "pictures".into()],
+        let created = root_dir
+            .write_returning(
+                &path,
                 true,
                 Utc::now(),
+                b"first".to_vec(),
                 forest,
                 store,
                 rng,
@@ -1905,24 +5412,20 @@ mod tests {
             .await
             .unwrap();
 
-        root_dir
-            .write(
-                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
-                true,
-                Utc::now(),
-                b"puppy".to_vec(),
-                forest,
-                store,
-                rng,
-            )
+        let content = created
+            .as_file()
+            .unwrap()
+            .get_content(forest, store)
             .await
             .unwrap();
+        assert_eq!(content, b"first");
 
-        root_dir
-            .mkdir(
-                &["tamedun".into(), "pictures".into(), "cats".into()],
+        let updated = root_dir
+            .write_returning(
+                &path,
                 true,
                 Utc::now(),
+                b"second".to_vec(),
                 forest,
                 store,
                 rng,
@@ -1930,35 +5433,88 @@ mod tests {
             .await
             .unwrap();
 
-        root_dir
-            .rm(&["tamedun".into(), "pictures".into()], true, forest, store)
+        let content = updated
+            .as_file()
+            .unwrap()
+            .get_content(forest, store)
             .await
             .unwrap();
+        assert_eq!(content, b"second");
 
-        let result = root_dir
-            .rm(&["tamedun".into(), "pictures".into()], true, forest, store)
-            .await;
+        let node = root_dir
+            .get_node(&path, true, forest, store)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(node, updated);
+    }
 
-        assert!(result.is_err());
+    #[test(async_std::test)]
+    async fn successive_updates_produce_successive_revisions() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let path = ["counter".into()];
+
+        let mut previous_file: Option<Rc<PrivateFile>> = None;
+        for expected in 1..=3u8 {
+            root_dir
+                .update(&path, true, Utc::now(), forest, store, rng, |content| {
+                    vec![content.first().copied().unwrap_or(0) + 1]
+                })
+                .await
+                .unwrap();
+            root_dir.store(forest, store, rng).await.unwrap();
+
+            let file = root_dir
+                .get_node(&path, true, forest, store)
+                .await
+                .unwrap()
+                .unwrap()
+                .as_file()
+                .unwrap();
+
+            assert_eq!(file.get_content(forest, store).await.unwrap(), [expected]);
+
+            if let Some(previous_file) = previous_file {
+                assert_eq!(file.header.inumber, previous_file.header.inumber);
+                assert_eq!(
+                    file.header
+                        .ratchet
+                        .compare(&previous_file.header.ratchet, 10)
+                        .unwrap(),
+                    1
+                );
+                assert_eq!(file.content.previous.len(), 1);
+            }
+
+            previous_file = Some(file);
+        }
     }
 
-    #[async_std::test]
-    async fn read_can_fetch_userland_of_file_added_to_directory() {
+    #[test(async_std::test)]
+    async fn failing_async_update_writes_nothing() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::default();
         let forest = &mut Rc::new(PrivateForest::new());
+        let path = ["notes.txt".into()];
 
         root_dir
             .write(
-                &["text.txt".into()],
+                &path,
                 true,
                 Utc::now(),
-                b"text".to_vec(),
+                b"kept".to_vec(),
                 forest,
                 store,
                 rng,
@@ -1967,32 +5523,40 @@ mod tests {
             .unwrap();
 
         let result = root_dir
-            .read(&["text.txt".into()], true, forest, store)
-            .await
-            .unwrap();
+            .update_async(&path, true, Utc::now(), forest, store, rng, |_| async {
+                Err(anyhow::anyhow!("edit failed"))
+            })
+            .await;
 
-        assert_eq!(result, b"text".to_vec());
+        assert!(result.is_err());
+        assert_eq!(
+            root_dir.read(&path, true, forest, store).await.unwrap(),
+            b"kept"
+        );
     }
 
     #[test(async_std::test)]
-    async fn search_latest_finds_the_most_recent() {
-        let store = &mut MemoryBlockStore::default();
-        let forest = &mut Rc::new(PrivateForest::new());
-        let rng = &mut rand::thread_rng();
+    async fn write_with_keys_yields_predictable_keys() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let path = ["shared".into(), "file.txt".into()];
+        let ratchet_seed = [1u8; 32];
+        let inumber = [2u8; 32];
 
-        let path = ["Documents".into(), "file.txt".into()];
-
-        root_dir
-            .write(
+        let node = root_dir
+            .write_with_keys(
                 &path,
-                false,
+                true,
                 Utc::now(),
-                b"One".to_vec(),
+                b"known in advance".to_vec(),
+                ratchet_seed,
+                inumber,
                 forest,
                 store,
                 rng,
@@ -2000,328 +5564,483 @@ mod tests {
             .await
             .unwrap();
 
-        root_dir.store(forest, store, rng).await.unwrap();
+        let file = node.as_file().unwrap();
+        let expected_key = TemporalKey::from(&Ratchet::zero(ratchet_seed));
 
-        let old_root = &Rc::clone(root_dir);
+        assert_eq!(file.header.derive_temporal_key(), expected_key);
+        assert_eq!(file.header.inumber, inumber);
 
-        root_dir
-            .write(&path, true, Utc::now(), b"Two".to_vec(), forest, store, rng)
+        let private_ref = node.store(forest, store, rng).await.unwrap();
+        assert_eq!(private_ref.temporal_key, expected_key);
+
+        let loaded = PrivateNode::load(&private_ref, forest, store)
             .await
+            .unwrap()
+            .as_file()
             .unwrap();
+        assert_eq!(
+            loaded.get_content(forest, store).await.unwrap(),
+            b"known in advance"
+        );
 
-        root_dir.store(forest, store, rng).await.unwrap();
+        let result = root_dir
+            .write_with_keys(
+                &path,
+                true,
+                Utc::now(),
+                vec![],
+                ratchet_seed,
+                inumber,
+                forest,
+                store,
+                rng,
+            )
+            .await;
+        assert!(result.is_err());
+    }
 
-        let new_read = root_dir.read(&path, false, forest, store).await.unwrap();
+    #[test(async_std::test)]
+    async fn invalid_path_segments_are_rejected_before_mutation() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
 
-        let old_read = Rc::clone(old_root)
-            .read(&path, false, forest, store)
+        root_dir
+            .mkdir(&["pictures".into()], true, Utc::now(), forest, store, rng)
             .await
             .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+        let forest_cid = forest.flush(store).await.unwrap();
+        let before = Rc::clone(root_dir);
+
+        for segment in ["", "cats/dogs", "cats\0"] {
+            let path = ["pictures".to_string(), segment.to_string()];
+            let results = [
+                root_dir
+                    .write(&path, true, Utc::now(), vec![], forest, store, rng)
+                    .await,
+                root_dir
+                    .mkdir(&path, true, Utc::now(), forest, store, rng)
+                    .await,
+                root_dir
+                    .basic_mv(
+                        &["pictures".into()],
+                        &path,
+                        true,
+                        Utc::now(),
+                        forest,
+                        store,
+                        rng,
+                    )
+                    .await,
+                root_dir
+                    .lookup_node(segment, true, forest, store)
+                    .await
+                    .map(|_| ()),
+            ];
+
+            for result in results {
+                assert!(matches!(
+                    result.unwrap_err().downcast_ref::<FsError>(),
+                    Some(FsError::InvalidPathSegment(s)) if s == segment
+                ));
+            }
+        }
 
-        let old_read_latest = old_root.read(&path, true, forest, store).await.unwrap();
-        let new_read_latest = root_dir.read(&path, true, forest, store).await.unwrap();
-
-        assert_eq!(&String::from_utf8_lossy(&new_read), "Two");
-        assert_eq!(&String::from_utf8_lossy(&old_read), "One");
-        assert_eq!(&String::from_utf8_lossy(&old_read_latest), "Two");
-        assert_eq!(&String::from_utf8_lossy(&new_read_latest), "Two");
+        assert_eq!(root_dir, &before);
+        assert_eq!(forest.flush(store).await.unwrap(), forest_cid);
     }
 
-    #[async_std::test]
-    async fn cp_can_copy_sub_directory_to_another_valid_location_with_updated_ancestry() {
+    #[test(async_std::test)]
+    async fn mode_survives_store_load_and_new_revisions() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::new();
         let forest = &mut Rc::new(PrivateForest::new());
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
+        let file = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
 
-        root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
-                true,
-                Utc::now(),
-                b"tabby".to_vec(),
-                forest,
-                store,
-                rng,
-            )
-            .await
-            .unwrap();
+        assert_eq!(root_dir.get_mode(), DEFAULT_DIR_MODE);
+        assert_eq!(file.get_mode(), DEFAULT_FILE_MODE);
+        assert_eq!(root_dir.get_metadata().get_uid(), None);
 
-        root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "luna.png".into()],
-                true,
-                Utc::now(),
-                b"luna".to_vec(),
-                forest,
-                store,
-                rng,
-            )
-            .await
-            .unwrap();
+        let metadata = root_dir.get_metadata_mut().unwrap();
+        metadata.set_mode(0o700);
+        metadata.set_uid(1000);
 
-        root_dir
-            .mkdir(&["images".into()], true, Utc::now(), forest, store, rng)
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+        let loaded = &mut PrivateNode::load(&private_ref, forest, store)
             .await
+            .unwrap()
+            .as_dir()
             .unwrap();
 
-        root_dir
-            .cp(
-                &["pictures".into(), "cats".into()],
-                &["images".into(), "cats".into()],
-                true,
-                Utc::now(),
-                forest,
-                store,
-                rng,
-            )
-            .await
-            .unwrap();
+        assert_eq!(loaded.get_mode(), 0o700);
+        assert_eq!(loaded.get_metadata().get_uid(), Some(1000));
 
-        let result = root_dir
-            .ls(&["images".into()], true, forest, store)
-            .await
-            .unwrap();
+        let next = loaded.prepare_next_revision().unwrap();
+        assert_eq!(next.content.previous.len(), 1);
+        assert_eq!(next.content.metadata.get_mode(), Some(0o700));
+        assert_eq!(next.content.metadata.get_uid(), Some(1000));
+    }
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].0, String::from("cats"));
+    #[test(async_std::test)]
+    async fn only_opted_in_reads_update_atime() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = &["docs".into(), "notes.txt".into()];
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
 
-        let result = root_dir
-            .ls(&["pictures".into()], true, forest, store)
+        root_dir
+            .write(path, true, time, b"Hello".to_vec(), forest, store, rng)
             .await
             .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].0, String::from("cats"));
+        async fn get_accessed(
+            dir: &Rc<PrivateDirectory>,
+            path: &[String],
+            forest: &PrivateForest,
+            store: &MemoryBlockStore,
+        ) -> Option<DateTime<Utc>> {
+            dir.get_node(path, true, forest, store)
+                .await
+                .unwrap()
+                .unwrap()
+                .as_file()
+                .unwrap()
+                .get_metadata()
+                .get_accessed()
+        }
 
-        let result = root_dir
-            .get_node(&["images".into(), "cats".into()], true, forest, store)
+        let before = Rc::clone(root_dir);
+        let content = root_dir.read(path, true, forest, store).await.unwrap();
+        assert_eq!(content, b"Hello");
+        assert!(Rc::ptr_eq(root_dir, &before));
+        assert_eq!(get_accessed(root_dir, path, forest, store).await, None);
+
+        let read_time = time + Duration::days(1);
+        let content = root_dir
+            .read_with_atime(path, true, read_time, forest, store)
             .await
             .unwrap();
+        assert_eq!(content, b"Hello");
+        assert_eq!(
+            get_accessed(root_dir, path, forest, store).await,
+            Some(read_time)
+        );
 
-        let cats_bare_name = result.unwrap().get_header().bare_name.clone();
-
-        let images_dir_inumber = root_dir
-            .lookup_node("images", true, forest, store)
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+        let loaded = PrivateNode::load(&private_ref, forest, store)
             .await
             .unwrap()
-            .unwrap()
-            .get_header()
-            .inumber;
-
-        let pictures_dir_inumber = root_dir
-            .lookup_node("pictures", true, forest, store)
+            .as_dir()
+            .unwrap();
+        let file = loaded
+            .get_node(path, true, forest, store)
             .await
             .unwrap()
             .unwrap()
-            .get_header()
-            .inumber;
-
-        assert!(cats_bare_name.contains(&images_dir_inumber));
-        assert!(!cats_bare_name.contains(&pictures_dir_inumber));
+            .as_file()
+            .unwrap();
+        assert_eq!(file.get_metadata().get_accessed(), Some(read_time));
+        assert_eq!(file.get_metadata().get_modified(), Some(time));
+        assert_eq!(file.get_content(forest, store).await.unwrap(), b"Hello");
     }
-
-    #[async_std::test]
-    async fn mv_can_move_sub_directory_to_another_valid_location_with_updated_ancestry() {
+
+    #[test(async_std::test)]
+    async fn hard_linked_files_share_writes() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::new();
         let forest = &mut Rc::new(PrivateForest::new());
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
+        let original = &["docs".into(), "notes.txt".into()];
+        let link = &["notes.txt".into()];
 
         root_dir
             .write(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                original,
                 true,
                 Utc::now(),
-                b"tabby".to_vec(),
+                b"v1".to_vec(),
                 forest,
                 store,
                 rng,
             )
             .await
             .unwrap();
-
         root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "luna.png".into()],
-                true,
-                Utc::now(),
-                b"luna".to_vec(),
-                forest,
-                store,
-                rng,
-            )
+            .hard_link(original, link, forest, store)
             .await
             .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
 
         root_dir
-            .mkdir(&["images".into()], true, Utc::now(), forest, store, rng)
+            .write(link, true, Utc::now(), b"v2".to_vec(), forest, store, rng)
             .await
             .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+        let content = root_dir.read(original, true, forest, store).await.unwrap();
+        assert_eq!(content, b"v2");
 
         root_dir
-            .basic_mv(
-                &["pictures".into(), "cats".into()],
-                &["images".into(), "cats".into()],
+            .write(
+                original,
                 true,
                 Utc::now(),
+                b"v3".to_vec(),
                 forest,
                 store,
                 rng,
             )
             .await
             .unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+        let content = root_dir.read(link, true, forest, store).await.unwrap();
+        assert_eq!(content, b"v3");
+
+        root_dir.rm(original, true, forest, store).await.unwrap();
+        root_dir.store(forest, store, rng).await.unwrap();
+        let content = root_dir.read(link, true, forest, store).await.unwrap();
+        assert_eq!(content, b"v3");
 
         let result = root_dir
-            .ls(&["images".into()], true, forest, store)
-            .await
-            .unwrap();
+            .hard_link(&["docs".into()], &["docs-link".into()], forest, store)
+            .await;
+        assert!(result.is_err());
+    }
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].0, String::from("cats"));
+    #[test(async_std::test)]
+    async fn rotating_subtree_keys_locks_out_old_refs() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
 
-        let result = root_dir
-            .ls(&["pictures".into()], true, forest, store)
+        let path = ["docs".into(), "big.txt".into()];
+        let content = utils::get_random_bytes::<100_000>(rng).to_vec();
+        root_dir
+            .write(&path, true, Utc::now(), content.clone(), forest, store, rng)
             .await
             .unwrap();
+        let old_ref = root_dir.store(forest, store, rng).await.unwrap();
 
-        assert_eq!(result.len(), 0);
-
-        let result = root_dir
-            .get_node(&["images".into(), "cats".into()], true, forest, store)
+        let mut old_refs = vec![old_ref.clone()];
+        let mut old_shard_labels = vec![];
+        let nodes = root_dir
+            .walk(&[], true, forest, store)
+            .try_collect::<Vec<_>>()
             .await
             .unwrap();
+        for (_, node) in nodes {
+            old_refs.push(node.get_private_ref().unwrap());
+            if let PrivateNode::File(file) = node {
+                old_shard_labels.extend(file.get_shard_label_hashes());
+            }
+        }
+        assert_eq!(old_refs.len(), 3);
+        assert!(!old_shard_labels.is_empty());
 
-        let cats_bare_name = result.unwrap().get_header().bare_name.clone();
-
-        let images_dir_inumber = root_dir
-            .lookup_node("images", true, forest, store)
+        let new_ref = root_dir
+            .rotate_subtree_keys(Namefilter::default(), forest, store, rng)
             .await
-            .unwrap()
-            .unwrap()
-            .get_header()
-            .inumber;
+            .unwrap();
 
-        let pictures_dir_inumber = root_dir
-            .lookup_node("pictures", true, forest, store)
+        for old_ref in old_refs.iter() {
+            assert!(PrivateNode::load(old_ref, forest, store).await.is_err());
+        }
+        for label in old_shard_labels.iter() {
+            assert!(forest.get_encrypted(label, store).await.unwrap().is_none());
+        }
+        assert_ne!(new_ref.temporal_key, old_ref.temporal_key);
+
+        let new_root = PrivateNode::load(&new_ref, forest, store)
             .await
             .unwrap()
-            .unwrap()
-            .get_header()
-            .inumber;
-
-        assert!(cats_bare_name.contains(&images_dir_inumber));
-        assert!(!cats_bare_name.contains(&pictures_dir_inumber));
+            .as_dir()
+            .unwrap();
+        let read = new_root.read(&path, true, forest, store).await.unwrap();
+        assert_eq!(read, content);
     }
 
-    #[async_std::test]
-    async fn mv_cannot_move_sub_directory_to_invalid_location() {
+    #[test(async_std::test)]
+    async fn from_public_recreates_tree_with_metadata() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::default();
         let forest = &mut Rc::new(PrivateForest::new());
-        let root_dir = &mut Rc::new(PrivateDirectory::new(
-            Namefilter::default(),
-            Utc::now(),
-            rng,
-        ));
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+
+        let public_dir = &mut Rc::new(PublicDirectory::new(time));
+        for (path, content) in [
+            (vec!["drafts".into(), "a.md".into()], b"# A".to_vec()),
+            (
+                vec!["drafts".into(), "nested".into(), "b.md".into()],
+                b"# B".to_vec(),
+            ),
+            (vec!["c.txt".into()], b"C".to_vec()),
+        ] {
+            let content_cid = store.put_block(content, IpldCodec::Raw).await.unwrap();
+            public_dir
+                .write(&path, content_cid, time, store)
+                .await
+                .unwrap();
+        }
 
-        root_dir
-            .mkdir(
-                &[
-                    "videos".into(),
-                    "movies".into(),
-                    "anime".into(),
-                    "ghibli".into(),
-                ],
-                true,
-                Utc::now(),
-                forest,
-                store,
-                rng,
-            )
+        let private_dir = &mut Rc::new(
+            PrivateDirectory::from_public(public_dir, Namefilter::default(), forest, store, rng)
+                .await
+                .unwrap(),
+        );
+        let private_ref = private_dir.store(forest, store, rng).await.unwrap();
+        let private_dir = PrivateNode::load(&private_ref, forest, store)
             .await
+            .unwrap()
+            .as_dir()
             .unwrap();
 
-        let result = root_dir
-            .basic_mv(
-                &["videos".into(), "movies".into()],
-                &["videos".into(), "movies".into(), "anime".into()],
-                true,
-                Utc::now(),
-                forest,
-                store,
-                rng,
-            )
-            .await;
+        for (path, content) in [
+            (vec!["drafts".into(), "a.md".into()], b"# A".to_vec()),
+            (
+                vec!["drafts".into(), "nested".into(), "b.md".into()],
+                b"# B".to_vec(),
+            ),
+            (vec!["c.txt".into()], b"C".to_vec()),
+        ] {
+            let read = private_dir.read(&path, true, forest, store).await.unwrap();
+            assert_eq!(read, content);
+
+            let node = private_dir
+                .get_node(&path, true, forest, store)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                node.as_file().unwrap().get_metadata().get_modified(),
+                Some(time)
+            );
+        }
 
-        assert!(result.is_err());
+        assert_eq!(private_dir.get_metadata().get_created(), Some(time));
     }
 
-    #[async_std::test]
-    async fn mv_can_rename_directories() {
+    #[async_recursion(?Send)]
+    async fn read_unixfs(cid: &Cid, path: &[String], store: &MemoryBlockStore) -> Vec<u8> {
+        let block = store.get_block(cid).await.unwrap();
+        if cid.codec() == u64::from(IpldCodec::Raw) {
+            assert!(path.is_empty());
+            return block.to_vec();
+        }
+
+        let node: Ipld = IpldCodec::DagPb.decode(&block).unwrap();
+        let Ok(Ipld::List(links)) = node.get("Links") else {
+            panic!("dag-pb node without links");
+        };
+        let Ok(Ipld::Bytes(data)) = node.get("Data") else {
+            panic!("dag-pb node without UnixFS data");
+        };
+
+        let link_cid = |link: &Ipld| match link.get("Hash") {
+            Ok(Ipld::Link(cid)) => *cid,
+            _ => panic!("dag-pb link without hash"),
+        };
+
+        match data[..2] {
+            // Directory
+            [0x08, 0x01] => {
+                let (name, rest) = path.split_first().unwrap();
+                let link = links
+                    .iter()
+                    .find(|link| matches!(link.get("Name"), Ok(Ipld::String(n)) if n == name))
+                    .unwrap();
+                read_unixfs(&link_cid(link), rest, store).await
+            }
+            // File
+            [0x08, 0x02] => {
+                let mut content = vec![];
+                for link in links.iter() {
+                    content.extend(read_unixfs(&link_cid(link), path, store).await);
+                }
+                content
+            }
+            _ => panic!("Unexpected UnixFS data type"),
+        }
+    }
+
+    #[test(async_std::test)]
+    async fn exported_unixfs_reads_back_identical_bytes() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::default();
+        let out_store = &MemoryBlockStore::default();
         let forest = &mut Rc::new(PrivateForest::new());
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-        let content = b"file".to_vec();
-
-        root_dir
-            .write(
-                &["file.txt".into()],
-                true,
-                Utc::now(),
-                content.clone(),
-                forest,
-                store,
-                rng,
-            )
-            .await
-            .unwrap();
 
-        root_dir
-            .basic_mv(
-                &["file.txt".into()],
-                &["renamed.txt".into()],
-                true,
-                Utc::now(),
-                forest,
-                store,
-                rng,
-            )
-            .await
-            .unwrap();
+        let big = (0..3 * MAX_BLOCK_SIZE + 100)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let files = [
+            (vec!["docs".to_string(), "big.bin".to_string()], big),
+            (
+                vec!["docs".to_string(), "small.txt".to_string()],
+                b"small".to_vec(),
+            ),
+            (vec!["empty.txt".to_string()], vec![]),
+        ];
+
+        for (path, content) in files.iter() {
+            root_dir
+                .write(path, true, Utc::now(), content.clone(), forest, store, rng)
+                .await
+                .unwrap();
+        }
 
-        let result = root_dir
-            .read(&["renamed.txt".into()], true, forest, store)
+        let root_cid = root_dir
+            .export_unixfs(&[], true, forest, store, out_store)
             .await
             .unwrap();
+        assert_eq!(root_cid.codec(), u64::from(IpldCodec::DagPb));
 
-        assert!(result == content);
+        for (path, content) in files.iter() {
+            assert_eq!(&read_unixfs(&root_cid, path, out_store).await, content);
+        }
 
-        let result = root_dir
-            .lookup_node("file.txt", true, forest, store)
+        let file_cid = root_dir
+            .export_unixfs(&files[0].0, true, forest, store, out_store)
             .await
             .unwrap();
-
-        assert!(result.is_none());
+        assert_eq!(read_unixfs(&file_cid, &[], out_store).await, files[0].1);
     }
 
-    #[async_std::test]
-    async fn mv_fails_moving_directories_to_files() {
+    #[test(async_std::test)]
+    async fn unixfs_export_and_import_round_trips() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::default();
+        let store = &MemoryBlockStore::default();
+        let ipfs_store = &MemoryBlockStore::default();
         let forest = &mut Rc::new(PrivateForest::new());
         let root_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
@@ -2329,9 +6048,26 @@ mod tests {
             rng,
         ));
 
+        let big = (0..2 * MAX_BLOCK_SIZE + 7)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let files = [
+            (vec!["docs".to_string(), "big.bin".to_string()], big),
+            (
+                vec!["docs".to_string(), "a".to_string(), "small.txt".to_string()],
+                b"small".to_vec(),
+            ),
+            (vec!["empty.txt".to_string()], vec![]),
+        ];
+        for (path, content) in files.iter() {
+            root_dir
+                .write(path, true, Utc::now(), content.clone(), forest, store, rng)
+                .await
+                .unwrap();
+        }
         root_dir
             .mkdir(
-                &["movies".into(), "ghibli".into()],
+                &["docs".into(), "nothing".into()],
                 true,
                 Utc::now(),
                 forest,
@@ -2341,52 +6077,24 @@ mod tests {
             .await
             .unwrap();
 
-        root_dir
-            .write(
-                &["file.txt".into()],
-                true,
-                Utc::now(),
-                b"file".to_vec(),
-                forest,
-                store,
-                rng,
-            )
+        let root_cid = root_dir
+            .export_unixfs(&[], true, forest, store, ipfs_store)
             .await
             .unwrap();
 
-        let result = root_dir
-            .basic_mv(
-                &["movies".into(), "ghibli".into()],
-                &["file.txt".into()],
-                true,
-                Utc::now(),
-                forest,
-                store,
-                rng,
-            )
-            .await;
-
-        assert!(result.is_err());
-    }
-
-    #[async_std::test]
-    async fn write_doesnt_generate_previous_link() {
-        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::new();
-        let forest = &mut Rc::new(PrivateForest::new());
-        let old_dir = &mut Rc::new(PrivateDirectory::new(
+        let imported_dir = &mut Rc::new(PrivateDirectory::new(
             Namefilter::default(),
             Utc::now(),
             rng,
         ));
-
-        let new_dir = &mut Rc::clone(old_dir);
-        new_dir
-            .write(
-                &["file.txt".into()],
-                false,
+        let base_path = vec!["imported".to_string()];
+        imported_dir
+            .import_unixfs(
+                root_cid,
+                &base_path,
+                true,
                 Utc::now(),
-                b"Hello".to_vec(),
+                ipfs_store,
                 forest,
                 store,
                 rng,
@@ -2394,37 +6102,89 @@ mod tests {
             .await
             .unwrap();
 
-        assert!(old_dir.content.previous.is_empty());
-        assert!(new_dir.content.previous.is_empty());
+        let original_paths = root_dir
+            .walk(&[], true, forest, store)
+            .map_ok(|(path, node)| (path, node.is_dir()))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let imported_paths = imported_dir
+            .walk(&base_path, true, forest, store)
+            .map_ok(|(path, node)| (path, node.is_dir()))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(original_paths, imported_paths);
+
+        for (path, content) in files.iter() {
+            let path = [&base_path[..], path].concat();
+            let read = imported_dir.read(&path, true, forest, store).await.unwrap();
+            assert_eq!(&read, content);
+        }
     }
 
-    #[async_std::test]
-    async fn store_before_write_generates_previous_link() {
-        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let store = &mut MemoryBlockStore::new();
-        let forest = &mut Rc::new(PrivateForest::new());
-        let old_dir = &mut Rc::new(PrivateDirectory::new(
-            Namefilter::default(),
-            Utc::now(),
-            rng,
-        ));
-        old_dir.store(forest, store, rng).await.unwrap();
+    #[test(async_std::test)]
+    async fn store_parallel_matches_sequential_store() {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let store_tree = |parallel: bool| async move {
+            let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+            let store = MemoryBlockStore::new();
+            let forest = &mut Rc::new(PrivateForest::new());
+            let root_dir = &mut Rc::new(PrivateDirectory::new(Namefilter::default(), time, rng));
+
+            for path in [
+                ["docs", "a.txt"],
+                ["docs", "b.txt"],
+                ["pictures", "cat.jpg"],
+            ] {
+                let path = path.map(String::from);
+                root_dir
+                    .write(
+                        &path,
+                        true,
+                        time,
+                        path.concat().into_bytes(),
+                        forest,
+                        &store,
+                        rng,
+                    )
+                    .await
+                    .unwrap();
+            }
 
-        let new_dir = &mut Rc::clone(old_dir);
-        new_dir
-            .write(
-                &["file.txt".into()],
+            let private_ref = if parallel {
+                root_dir.store_parallel(forest, &store, rng).await.unwrap()
+            } else {
+                root_dir.store(forest, &store, rng).await.unwrap()
+            };
+
+            (private_ref, Rc::clone(forest), store)
+        };
+
+        let (sequential_ref, _, sequential_store) = store_tree(false).await;
+        let (parallel_ref, forest, store) = store_tree(true).await;
+        assert_eq!(parallel_ref, sequential_ref);
+
+        let mut sequential_blocks = sequential_store.list_blocks().await.unwrap();
+        let mut parallel_blocks = store.list_blocks().await.unwrap();
+        sequential_blocks.sort();
+        parallel_blocks.sort();
+        assert_eq!(parallel_blocks, sequential_blocks);
+
+        let root_dir = PrivateNode::load(&parallel_ref, &forest, &store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+        let content = root_dir
+            .read(
+                &["pictures".into(), "cat.jpg".into()],
                 false,
-                Utc::now(),
-                b"Hello".to_vec(),
-                forest,
-                store,
-                rng,
+                &forest,
+                &store,
             )
             .await
             .unwrap();
-
-        assert!(old_dir.content.previous.is_empty());
-        assert_eq!(new_dir.content.previous.len(), 1);
+        assert_eq!(content, b"picturescat.jpg");
     }
 }
@@ -1,9 +1,10 @@
 use super::{
-    encrypted::Encrypted, PrivateFileContentSerializable, PrivateForest, PrivateNode,
-    PrivateNodeContentSerializable, PrivateNodeHeader, PrivateRef, SnapshotKey, TemporalKey,
-    AUTHENTICATION_TAG_SIZE, NONCE_SIZE,
+    encrypted::Encrypted, CipherAlgorithm, PrivateFileContentSerializable, PrivateForest,
+    PrivateNode, PrivateNodeContentSerializable, PrivateNodeHeader, PrivateRef, SnapshotKey,
+    TemporalKey, AUTHENTICATION_TAG_SIZE, KEY_BYTE_SIZE, NONCE_SIZE,
 };
 use crate::{error::FsError, traits::Id, WNFS_VERSION};
+use aes_gcm::Nonce;
 use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
 use async_stream::try_stream;
@@ -13,8 +14,12 @@ use libipld::{Cid, Ipld, IpldCodec};
 use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 use sha3::Sha3_256;
-use std::{collections::BTreeSet, iter, rc::Rc};
-use wnfs_common::{utils, BlockStore, Metadata, MAX_BLOCK_SIZE};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter,
+    rc::Rc,
+};
+use wnfs_common::{utils, BlockStore, HashOutput, Metadata, DEFAULT_FILE_MODE, MAX_BLOCK_SIZE};
 use wnfs_hamt::Hasher;
 use wnfs_namefilter::Namefilter;
 
@@ -31,6 +36,12 @@ use wnfs_namefilter::Namefilter;
 /// [priv-file]: https://github.com/wnfs-wg/spec/blob/matheus23/file-sharding/spec/private-wnfs.md#314-private-file
 pub const MAX_BLOCK_CONTENT_SIZE: usize = MAX_BLOCK_SIZE - NONCE_SIZE - AUTHENTICATION_TAG_SIZE;
 
+/// The number of bytes the rolling hash of [`ChunkingStrategy::Rabin`] covers.
+const RABIN_WINDOW_SIZE: usize = 48;
+
+/// The base of the polynomial the rolling hash of [`ChunkingStrategy::Rabin`] evaluates.
+const RABIN_BASE: u64 = 0x100_0000_01b3;
+
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
@@ -89,11 +100,127 @@ pub struct PrivateFileContent {
 pub(crate) enum FileContent {
     Inline {
         data: Vec<u8>,
+        /// The cipher the file node holding the data is encrypted with.
+        #[serde(default, skip_serializing_if = "CipherAlgorithm::is_default")]
+        cipher: CipherAlgorithm,
     },
     External {
         key: SnapshotKey,
         block_count: usize,
         block_content_size: usize,
+        /// Whether blocks are encrypted convergently. See [`PrivateFileConfig::convergent`].
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        convergent: bool,
+        /// How the content was split into blocks. See [`PrivateFileConfig::chunking`].
+        #[serde(default, skip_serializing_if = "ChunkingStrategy::is_fixed")]
+        chunking: ChunkingStrategy,
+        /// The offset each block ends at, if blocks vary in size. Empty if all blocks but the
+        /// last one hold `block_content_size` bytes.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        chunk_ends: Vec<usize>,
+        /// The exact length of the content in bytes. Missing for content stored before it
+        /// got recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_size: Option<usize>,
+        /// Blocks appended after the content was first stored. See [`ContentSegment`].
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        segments: Vec<ContentSegment>,
+        /// The cipher the blocks and the file node are encrypted with.
+        #[serde(default, skip_serializing_if = "CipherAlgorithm::is_default")]
+        cipher: CipherAlgorithm,
+    },
+}
+
+/// A run of blocks appended to external content with a key of its own.
+///
+/// Appends that fork off the same revision each get a fresh key, so their blocks never end up
+/// under the same labels. Blocks before the first segment use the content's key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ContentSegment {
+    key: SnapshotKey,
+    /// The index of the first block of the segment.
+    start: usize,
+}
+
+/// The content of a file as taken apart by [`PrivateFile::into_parts`], to be put back
+/// together with [`PrivateFile::from_parts`].
+///
+/// It's opaque, so that the stored layout can keep changing. See
+/// [`PrivateFile::content_layout`] for a description of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileContentParts(pub(crate) FileContent);
+
+/// Controls how a file's content gets stored.
+///
+/// The default matches what [`PrivateFile::with_content`] does: content is never inlined
+/// and gets split into chunks of [`MAX_BLOCK_CONTENT_SIZE`] bytes.
+///
+/// Both values need to be at most [`MAX_BLOCK_CONTENT_SIZE`], so that an encrypted chunk or
+/// a file node with inline content still fits into a block of [`MAX_BLOCK_SIZE`] bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrivateFileConfig {
+    /// Content smaller than this many bytes is stored within the file node itself.
+    pub inline_threshold: usize,
+    /// The number of content bytes that get encrypted into each block.
+    pub chunk_size: usize,
+    /// Encrypts each chunk with a key derived from the chunk's own hash, so that identical
+    /// chunks turn into identical blocks and get stored only once, even across files.
+    ///
+    /// The file's key then only encrypts those derived keys, which are stored in small extra
+    /// blocks that link to the chunks.
+    ///
+    /// # Privacy
+    ///
+    /// Anyone who can guess a chunk's content can tell whether the store holds it, and files
+    /// sharing a chunk can be linked together through the block they have in common.
+    /// Only enable this if the storage savings are worth that.
+    pub convergent: bool,
+    /// How content is split into chunks. With [`ChunkingStrategy::Rabin`], `chunk_size` is
+    /// ignored.
+    pub chunking: ChunkingStrategy,
+    /// The cipher the content and the file node get encrypted with. Content written to the
+    /// file later on keeps using it.
+    ///
+    /// Ciphers other than AES-256-GCM tag their ciphertexts, which leaves a few bytes less
+    /// room for content in each block. They can't be combined with `convergent`.
+    pub cipher: CipherAlgorithm,
+}
+
+/// How a file's content gets split into chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkingStrategy {
+    /// Every chunk but the last one holds [`PrivateFileConfig::chunk_size`] bytes.
+    ///
+    /// Inserting or removing bytes shifts all chunks after the edit.
+    #[default]
+    Fixed,
+    /// Chunks end where a rolling Rabin fingerprint of the last few bytes matches, so chunk
+    /// boundaries move along with the content around them. An edit then only changes the chunks
+    /// near it, and with [`PrivateFileConfig::convergent`] all other chunks keep their blocks.
+    ///
+    /// Chunks hold between `min` and `max` bytes, about `avg` bytes past `min` on average.
+    /// `max` must be at most [`MAX_BLOCK_CONTENT_SIZE`].
+    Rabin { min: usize, avg: usize, max: usize },
+}
+
+/// A description of how a file's content is stored.
+///
+/// See [`PrivateFile::content_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentLayout {
+    /// The content is stored within the file node itself.
+    Inline { len: usize },
+    /// The content is split into encrypted chunks stored in the private forest.
+    External {
+        chunk_count: usize,
+        /// How the content was split into chunks. With [`ChunkingStrategy::Fixed`], every
+        /// chunk but the last one holds `max_chunk_size` bytes. With [`ChunkingStrategy::Rabin`],
+        /// chunk sizes vary up to it.
+        chunking: ChunkingStrategy,
+        max_chunk_size: usize,
+        /// The exact length of the content. `None` for content stored before lengths got
+        /// recorded, [`PrivateFile::get_size`] still measures those.
+        total_len: Option<u64>,
     },
 }
 
@@ -131,7 +258,52 @@ impl PrivateFile {
                 persisted_as: OnceCell::new(),
                 metadata: Metadata::new(time),
                 previous: BTreeSet::new(),
-                content: FileContent::Inline { data: vec![] },
+                content: FileContent::Inline {
+                    data: vec![],
+                    cipher: CipherAlgorithm::default(),
+                },
+            },
+        }
+    }
+
+    /// Creates an empty file with the ratchet seed and inumber provided.
+    ///
+    /// Anyone knowing both secrets can derive the file's keys and label ahead of time,
+    /// so never reuse them for more than one file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{private::PrivateFile, namefilter::Namefilter};
+    /// use chrono::Utc;
+    /// use rand::{thread_rng, Rng};
+    ///
+    /// let rng = &mut thread_rng();
+    /// let file = PrivateFile::with_seed(
+    ///     Namefilter::default(),
+    ///     Utc::now(),
+    ///     rng.gen::<[u8; 32]>(),
+    ///     rng.gen::<[u8; 32]>(),
+    /// );
+    ///
+    /// println!("file = {:?}", file);
+    /// ```
+    pub fn with_seed(
+        parent_bare_name: Namefilter,
+        time: DateTime<Utc>,
+        ratchet_seed: HashOutput,
+        inumber: HashOutput,
+    ) -> Self {
+        Self {
+            header: PrivateNodeHeader::with_seed(parent_bare_name, ratchet_seed, inumber),
+            content: PrivateFileContent {
+                persisted_as: OnceCell::new(),
+                metadata: Metadata::new(time),
+                previous: BTreeSet::new(),
+                content: FileContent::Inline {
+                    data: vec![],
+                    cipher: CipherAlgorithm::default(),
+                },
             },
         }
     }
@@ -146,7 +318,10 @@ impl PrivateFile {
         // Header stays the same
         let header = PrivateNodeHeader::new(parent_bare_name, rng);
         // Symlinks have no file content
-        let content = FileContent::Inline { data: vec![] };
+        let content = FileContent::Inline {
+            data: vec![],
+            cipher: CipherAlgorithm::default(),
+        };
         // Create a new Metadata object
         let mut metadata: Metadata = Metadata::new(time);
         // Write the original path into the Metadata HashMap
@@ -165,6 +340,133 @@ impl PrivateFile {
         })
     }
 
+    /// Creates a file from its header, metadata and content without writing any new blocks.
+    ///
+    /// The shards of external content are labelled using the header's bare name,
+    /// so external content must have been written under the same header for the
+    /// resulting file to be readable. The file starts with no previous revisions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateFile},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///
+    ///     let file = PrivateFile::with_content(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"Hello".to_vec(),
+    ///         forest,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     let (header, metadata, content) = file.into_parts();
+    ///     let file = PrivateFile::from_parts(header, metadata, content).unwrap();
+    ///
+    ///     assert_eq!(file.get_content(forest, store).await.unwrap(), b"Hello");
+    /// }
+    /// ```
+    pub fn from_parts(
+        header: PrivateNodeHeader,
+        metadata: Metadata,
+        FileContentParts(content): FileContentParts,
+    ) -> Result<Self> {
+        if let FileContent::External {
+            block_count,
+            block_content_size,
+            chunk_ends,
+            content_size,
+            segments,
+            cipher,
+            ..
+        } = &content
+        {
+            let (block_count, block_content_size) = (*block_count, *block_content_size);
+            let max_size = cipher.max_block_content_size();
+            if block_count > 0 && (block_content_size == 0 || block_content_size > max_size) {
+                bail!(FsError::InvalidFileContent(format!(
+                    "Block content size must be between 1 and {max_size}, got {block_content_size}"
+                )));
+            }
+
+            let mut start = 0;
+            let chunks_fit = chunk_ends.iter().all(|&end| {
+                let size = end.saturating_sub(start);
+                start = end;
+                (1..=block_content_size).contains(&size)
+            });
+            if !chunk_ends.is_empty() && (chunk_ends.len() != block_count || !chunks_fit) {
+                bail!(FsError::InvalidFileContent(format!(
+                    "Expected {block_count} increasing chunk ends, at most {block_content_size} apart"
+                )));
+            }
+
+            let size_fits = match (*content_size, chunk_ends.last()) {
+                (None, _) => true,
+                (Some(size), Some(&end)) => size == end,
+                (Some(size), None) => match block_count {
+                    0 => size == 0,
+                    _ => ((block_count - 1) * block_content_size + 1
+                        ..=block_count * block_content_size)
+                        .contains(&size),
+                },
+            };
+            if !size_fits {
+                bail!(FsError::InvalidFileContent(format!(
+                    "Content size {content_size:?} doesn't fit into {block_count} blocks"
+                )));
+            }
+
+            let mut previous_start = 0;
+            let segments_fit = segments.iter().all(|segment| {
+                let fits = (previous_start + 1..block_count).contains(&segment.start);
+                previous_start = segment.start;
+                fits
+            });
+            if !segments_fit {
+                bail!(FsError::InvalidFileContent(format!(
+                    "Expected segments to start at increasing blocks below {block_count}"
+                )));
+            }
+        }
+
+        Ok(Self {
+            header,
+            content: PrivateFileContent {
+                persisted_as: OnceCell::new(),
+                metadata,
+                previous: BTreeSet::new(),
+                content,
+            },
+        })
+    }
+
+    /// Decomposes a file into its header, metadata and content.
+    ///
+    /// Links to previous revisions are dropped.
+    pub fn into_parts(self) -> (PrivateNodeHeader, Metadata, FileContentParts) {
+        (
+            self.header,
+            self.content.metadata,
+            FileContentParts(self.content.content),
+        )
+    }
+
     /// If the Metadata contains Symlink data, return it
     pub fn symlink_origin(&self) -> Option<String> {
         let meta = self.get_metadata();
@@ -217,9 +519,70 @@ impl PrivateFile {
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
+    ) -> Result<Self> {
+        Self::with_content_config(
+            parent_bare_name,
+            time,
+            content,
+            &PrivateFileConfig::default(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+    }
+
+    /// Creates a file with provided content, stored the way `config` describes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{ContentLayout, PrivateFile, PrivateFileConfig, PrivateForest},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let config = PrivateFileConfig {
+    ///         inline_threshold: 1024,
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let file = PrivateFile::with_content_config(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"Hello".to_vec(),
+    ///         &config,
+    ///         forest,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     assert_eq!(file.content_layout(), ContentLayout::Inline { len: 5 });
+    /// }
+    /// ```
+    pub async fn with_content_config(
+        parent_bare_name: Namefilter,
+        time: DateTime<Utc>,
+        content: Vec<u8>,
+        config: &PrivateFileConfig,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
     ) -> Result<Self> {
         let header = PrivateNodeHeader::new(parent_bare_name, rng);
-        let content = Self::prepare_content(&header.bare_name, content, forest, store, rng).await?;
+        let content =
+            Self::prepare_content(&header.bare_name, content, config, forest, store, rng).await?;
 
         Ok(Self {
             header,
@@ -283,8 +646,15 @@ impl PrivateFile {
         rng: &mut impl RngCore,
     ) -> Result<Self> {
         let header = PrivateNodeHeader::new(parent_bare_name, rng);
-        let content =
-            Self::prepare_content_streaming(&header.bare_name, content, forest, store, rng).await?;
+        let content = Self::prepare_content_streaming(
+            &header.bare_name,
+            content,
+            CipherAlgorithm::default(),
+            forest,
+            store,
+            rng,
+        )
+        .await?;
 
         Ok(Self {
             header,
@@ -349,7 +719,7 @@ impl PrivateFile {
     ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
         Box::pin(try_stream! {
             match &self.content.content {
-                FileContent::Inline { data } => {
+                FileContent::Inline { data, .. } => {
                     if index != 0 {
                         Err(FsError::FileShardNotFound)?
                     }
@@ -359,11 +729,21 @@ impl PrivateFile {
                 FileContent::External {
                     key,
                     block_count,
+                    convergent,
+                    segments,
                     ..
                 } => {
                     let bare_name = &self.header.bare_name;
-                    for label in Self::generate_shard_labels(key, index,  *block_count, bare_name) {
-                        let bytes = Self::decrypt_block(key, &label, forest, store).await?;
+                    let depth = store.prefetch_depth();
+                    let mut prefetched_until = index;
+                    for (i, (block_key, label)) in Self::generate_shard_labels(key, segments, index, *block_count, bare_name).enumerate() {
+                        let block_index = index + i;
+                        if depth > 0 && block_index >= prefetched_until {
+                            prefetched_until = (block_index + depth + 1).min(*block_count);
+                            Self::prefetch_blocks(key, segments, block_index, prefetched_until, bare_name, forest, store).await;
+                        }
+
+                        let bytes = Self::decrypt_block(block_key, &label, *convergent, forest, store).await?;
                         yield bytes
                     }
                 }
@@ -372,6 +752,9 @@ impl PrivateFile {
     }
 
     /// Reads a number of bytes starting from a given offset.
+    ///
+    /// Only the blocks covering the requested range are fetched and decrypted.
+    /// Ranges reaching past the end of the file return fewer bytes, down to none at all.
     pub async fn read_at<'a>(
         &'a self,
         offset: usize,
@@ -379,25 +762,45 @@ impl PrivateFile {
         forest: &'a PrivateForest,
         store: &'a impl BlockStore,
     ) -> Result<Vec<u8>> {
-        let block_content_size = MAX_BLOCK_CONTENT_SIZE;
-        let chunk_size_upper_bound = (self.get_content_size_upper_bound() - offset).min(size);
+        let (block_content_size, chunk_ends) = match &self.content.content {
+            FileContent::External {
+                block_content_size,
+                chunk_ends,
+                ..
+            } => (*block_content_size, &chunk_ends[..]),
+            FileContent::Inline { .. } => (MAX_BLOCK_CONTENT_SIZE, &[][..]),
+        };
+        let block_start = |index: usize| match (chunk_ends, index) {
+            ([], _) => index * block_content_size,
+            (_, 0) => 0,
+            (ends, _) => ends[index - 1],
+        };
+        let block_index = |offset: usize| match chunk_ends {
+            [] => offset / block_content_size,
+            ends => ends.partition_point(|&end| end <= offset),
+        };
+        let chunk_size_upper_bound = self
+            .get_content_size_upper_bound()
+            .saturating_sub(offset)
+            .min(size);
         if chunk_size_upper_bound == 0 {
             return Ok(vec![]);
         }
-        let first_block = offset / block_content_size;
-        let last_block = (offset + size) / block_content_size;
+        let end = offset + chunk_size_upper_bound;
+        let first_block = block_index(offset);
+        let last_block = block_index(end - 1);
         let mut bytes = Vec::with_capacity(chunk_size_upper_bound);
         let mut content_stream = self.stream_content(first_block, forest, store).enumerate();
         while let Some((i, chunk)) = content_stream.next().await {
             let chunk = chunk?;
             let index = first_block + i;
             let from = if index == first_block {
-                (offset - index * block_content_size).min(chunk.len())
+                (offset - block_start(index)).min(chunk.len())
             } else {
                 0
             };
             let to = if index == last_block {
-                (offset + size - index * block_content_size).min(chunk.len())
+                (end - block_start(index)).min(chunk.len())
             } else {
                 chunk.len()
             };
@@ -409,11 +812,28 @@ impl PrivateFile {
         Ok(bytes)
     }
 
+    /// Gets the cipher the file's content is encrypted with.
+    pub fn get_cipher(&self) -> CipherAlgorithm {
+        self.content.content.get_cipher()
+    }
+
     /// Gets the metadata of the file
     pub fn get_metadata(&self) -> &Metadata {
         &self.content.metadata
     }
 
+    /// Gets the POSIX permission bits of the file, or [`DEFAULT_FILE_MODE`] if none were set.
+    pub fn get_mode(&self) -> u32 {
+        self.get_metadata().get_mode().unwrap_or(DEFAULT_FILE_MODE)
+    }
+
+    /// Gets the metadata of the file for changing it, e.g. to set attributes.
+    ///
+    /// If the file was stored before, this starts a new revision of it.
+    pub fn get_metadata_mut(self: &mut Rc<Self>) -> Result<&mut Metadata> {
+        Ok(&mut self.prepare_next_revision()?.content.metadata)
+    }
+
     /// Gets the entire content of a file.
     ///
     /// # Examples
@@ -476,43 +896,179 @@ impl PrivateFile {
         rng: &mut impl RngCore,
     ) -> Result<()> {
         self.content.metadata = Metadata::new(time);
-        self.content.content =
-            Self::prepare_content_streaming(&self.header.bare_name, content, forest, store, rng)
+        self.content.content = Self::prepare_content_streaming(
+            &self.header.bare_name,
+            content,
+            self.content.content.get_cipher(),
+            forest,
+            store,
+            rng,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Appends bytes to the end of the file's content and bumps its modification time.
+    ///
+    /// If the file was stored before, this starts a new revision of it.
+    ///
+    /// If the content is stored in blocks, only the appended bytes are encrypted and stored in
+    /// new blocks, since existing blocks can't be changed without also changing them for past
+    /// revisions. The new blocks get a key of their own, so appends forked off the same revision
+    /// don't collide. If the last block isn't full, the offsets all blocks end at get recorded,
+    /// so the new blocks can follow it. Inline content gets stored anew.
+    pub async fn append(
+        self: &mut Rc<Self>,
+        time: DateTime<Utc>,
+        extra: &[u8],
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        let size = match &self.content.content {
+            FileContent::External { block_count, .. } if *block_count > 0 => {
+                Some(self.get_size(forest, store).await? as usize)
+            }
+            _ => None,
+        };
+
+        let file = self.prepare_next_revision()?;
+        let config = file.get_content_config();
+        match (&mut file.content.content, size) {
+            (
+                FileContent::External {
+                    block_count,
+                    block_content_size,
+                    chunking,
+                    chunk_ends,
+                    content_size,
+                    segments,
+                    ..
+                },
+                Some(size),
+            ) => {
+                let new_ends = chunking.chunk_ends(extra, *block_content_size);
+                if !new_ends.is_empty() {
+                    if chunk_ends.is_empty() && size < *block_count * *block_content_size {
+                        *chunk_ends = (1..*block_count)
+                            .map(|index| index * *block_content_size)
+                            .chain([size])
+                            .collect();
+                    }
+
+                    let segment = ContentSegment {
+                        key: SnapshotKey::from(utils::get_random_bytes(rng)),
+                        start: *block_count,
+                    };
+
+                    let mut start = 0;
+                    for (index, end) in new_ends.into_iter().enumerate() {
+                        let label =
+                            Self::create_block_label(&segment.key, index, &file.header.bare_name);
+                        let chunk = &extra[start..end];
+                        Self::store_block(&segment.key, label, chunk, &config, forest, store, rng)
+                            .await?;
+                        start = end;
+
+                        *block_count += 1;
+                        if !chunk_ends.is_empty() {
+                            chunk_ends.push(size + end);
+                        }
+                    }
+
+                    segments.push(segment);
+                    *content_size = Some(size + extra.len());
+                }
+            }
+            _ => {
+                let mut content = file.get_content(forest, store).await?;
+                content.extend_from_slice(extra);
+                file.content.content = Self::prepare_content(
+                    &file.header.bare_name,
+                    content,
+                    &config,
+                    forest,
+                    store,
+                    rng,
+                )
                 .await?;
+            }
+        }
+
+        file.content.metadata.upsert_mtime(time);
         Ok(())
     }
 
+    /// Returns the config the file's content was stored with, so content derived from it can be
+    /// stored the same way.
+    fn get_content_config(&self) -> PrivateFileConfig {
+        match &self.content.content {
+            FileContent::External {
+                block_content_size,
+                convergent,
+                chunking,
+                cipher,
+                ..
+            } => PrivateFileConfig {
+                chunk_size: *block_content_size,
+                convergent: *convergent,
+                chunking: *chunking,
+                cipher: *cipher,
+                ..Default::default()
+            },
+            FileContent::Inline { cipher, .. } => PrivateFileConfig {
+                cipher: *cipher,
+                ..Default::default()
+            },
+        }
+    }
+
     /// Determines where to put the content of a file. This can either be inline or stored up in chunks in a private forest.
     pub(super) async fn prepare_content(
         bare_name: &Namefilter,
         content: Vec<u8>,
+        config: &PrivateFileConfig,
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<FileContent> {
-        // TODO(appcypher): Use a better heuristic to determine when to use external storage.
+        config.validate()?;
+        if content.len() < config.inline_threshold {
+            return Ok(FileContent::Inline {
+                data: content,
+                cipher: config.cipher,
+            });
+        }
+
         let key = SnapshotKey::from(utils::get_random_bytes(rng));
-        let block_count = (content.len() as f64 / MAX_BLOCK_CONTENT_SIZE as f64).ceil() as usize;
+        let chunk_ends = config.chunking.chunk_ends(&content, config.chunk_size);
+        let block_count = chunk_ends.len();
 
-        for (index, label) in
-            Self::generate_shard_labels(&key, 0, block_count, bare_name).enumerate()
+        let mut start = 0;
+        for ((_, label), &end) in
+            Self::generate_shard_labels(&key, &[], 0, block_count, bare_name).zip(&chunk_ends)
         {
-            let start = index * MAX_BLOCK_CONTENT_SIZE;
-            let end = content.len().min((index + 1) * MAX_BLOCK_CONTENT_SIZE);
             let slice = &content[start..end];
+            start = end;
 
-            let enc_bytes = key.encrypt(slice, rng)?;
-            let content_cid = store.put_block(enc_bytes, IpldCodec::Raw).await?;
-
-            forest
-                .put_encrypted(label, Some(content_cid), store)
-                .await?;
+            Self::store_block(&key, label, slice, config, forest, store, rng).await?;
         }
 
+        let (block_content_size, chunk_ends) = match config.chunking {
+            ChunkingStrategy::Fixed => (config.chunk_size, vec![]),
+            ChunkingStrategy::Rabin { max, .. } => (max, chunk_ends),
+        };
+
         Ok(FileContent::External {
             key,
             block_count,
-            block_content_size: MAX_BLOCK_CONTENT_SIZE,
+            block_content_size,
+            convergent: config.convergent,
+            chunking: config.chunking,
+            chunk_ends,
+            content_size: Some(content.len()),
+            segments: vec![],
+            cipher: config.cipher,
         })
     }
 
@@ -523,38 +1079,32 @@ impl PrivateFile {
     pub(super) async fn prepare_content_streaming(
         bare_name: &Namefilter,
         mut content: impl AsyncRead + Unpin,
+        cipher: CipherAlgorithm,
         forest: &mut Rc<PrivateForest>,
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<FileContent> {
         let key = SnapshotKey::from(utils::get_random_bytes(rng));
+        let config = PrivateFileConfig {
+            chunk_size: cipher.max_block_content_size(),
+            cipher,
+            ..Default::default()
+        };
 
         let mut block_index = 0;
+        let mut content_size = 0;
 
         loop {
-            let mut current_block = vec![0u8; MAX_BLOCK_SIZE];
-            let nonce = SnapshotKey::generate_nonce(rng);
-            current_block[..NONCE_SIZE].copy_from_slice(&nonce);
-
-            // read up to MAX_BLOCK_CONTENT_SIZE content
-
-            let content_end = NONCE_SIZE + MAX_BLOCK_CONTENT_SIZE;
-            let (bytes_written, done) =
-                utils::read_fully(&mut content, &mut current_block[NONCE_SIZE..content_end])
-                    .await?;
+            // read up to a block's worth of content
+            let mut chunk = vec![0u8; config.chunk_size];
+            let (bytes_written, done) = utils::read_fully(&mut content, &mut chunk).await?;
 
             // truncate the vector to its actual length.
-            current_block.truncate(bytes_written + NONCE_SIZE);
-
-            let tag = key.encrypt_in_place(&nonce, &mut current_block[NONCE_SIZE..])?;
-            current_block.extend_from_slice(&tag);
-
-            let content_cid = store.put_block(current_block, IpldCodec::Raw).await?;
+            chunk.truncate(bytes_written);
+            content_size += bytes_written;
 
             let label = Self::create_block_label(&key, block_index, bare_name);
-            forest
-                .put_encrypted(label, Some(content_cid), store)
-                .await?;
+            Self::store_block(&key, label, &chunk, &config, forest, store, rng).await?;
 
             block_index += 1;
 
@@ -566,34 +1116,152 @@ impl PrivateFile {
         Ok(FileContent::External {
             key,
             block_count: block_index,
-            block_content_size: MAX_BLOCK_CONTENT_SIZE,
+            block_content_size: config.chunk_size,
+            convergent: false,
+            chunking: ChunkingStrategy::Fixed,
+            chunk_ends: vec![],
+            content_size: Some(content_size),
+            segments: vec![],
+            cipher,
         })
     }
 
-    /// Gets the upper bound of a file content size.
-    pub fn get_content_size_upper_bound(&self) -> usize {
+    /// Describes whether the file's content is stored inline or in external chunks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     private::{ContentLayout, PrivateFile},
+    ///     namefilter::Namefilter,
+    /// };
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// let file = PrivateFile::new(Namefilter::default(), Utc::now(), &mut thread_rng());
+    ///
+    /// assert_eq!(file.content_layout(), ContentLayout::Inline { len: 0 });
+    /// ```
+    pub fn content_layout(&self) -> ContentLayout {
         match &self.content.content {
-            FileContent::Inline { data } => data.len(),
+            FileContent::Inline { data, .. } => ContentLayout::Inline { len: data.len() },
             FileContent::External {
                 block_count,
                 block_content_size,
+                chunking,
                 ..
-            } => block_count * block_content_size,
+            } => ContentLayout::External {
+                chunk_count: *block_count,
+                chunking: *chunking,
+                max_chunk_size: *block_content_size,
+                total_len: self.get_recorded_size().map(|size| size as u64),
+            },
         }
     }
 
-    /// Decrypts a block of a file's content.
-    async fn decrypt_block(
-        key: &SnapshotKey,
-        label: &Namefilter,
-        forest: &PrivateForest,
-        store: &impl BlockStore,
-    ) -> Result<Vec<u8>> {
-        let label_hash = &Sha3_256::hash(&label.as_bytes());
+    /// Returns the exact size of the file's content in bytes.
+    ///
+    /// The size is recorded along with the content, so nothing gets fetched.
+    /// Only content stored before sizes got recorded has its last block fetched to measure it.
+    /// That block isn't decrypted, and no other content blocks are read.
+    pub async fn get_size(&self, forest: &PrivateForest, store: &impl BlockStore) -> Result<u64> {
+        if let Some(size) = self.get_recorded_size() {
+            return Ok(size as u64);
+        }
 
-        let cids = forest
-            .get_encrypted(label_hash, store)
-            .await?
+        match &self.content.content {
+            FileContent::Inline { data, .. } => Ok(data.len() as u64),
+            FileContent::External { block_count: 0, .. } => Ok(0),
+            FileContent::External { chunk_ends, .. } if !chunk_ends.is_empty() => {
+                Ok(chunk_ends[chunk_ends.len() - 1] as u64)
+            }
+            FileContent::External {
+                key,
+                block_count,
+                block_content_size,
+                convergent,
+                segments,
+                ..
+            } => {
+                let (key, label) =
+                    Self::block_label(key, segments, block_count - 1, &self.header.bare_name);
+                let (_, cid) = Self::resolve_block(key, &label, *convergent, forest, store).await?;
+
+                let last_block_size = store
+                    .get_block(&cid)
+                    .await?
+                    .len()
+                    .saturating_sub(NONCE_SIZE + AUTHENTICATION_TAG_SIZE);
+
+                Ok(((block_count - 1) * block_content_size + last_block_size) as u64)
+            }
+        }
+    }
+
+    /// Returns the size of the file's content if it's known without fetching anything.
+    fn get_recorded_size(&self) -> Option<usize> {
+        match &self.content.content {
+            FileContent::Inline { data, .. } => Some(data.len()),
+            FileContent::External {
+                content_size: Some(size),
+                ..
+            } => Some(*size),
+            FileContent::External { block_count: 0, .. } => Some(0),
+            FileContent::External { chunk_ends, .. } => chunk_ends.last().copied(),
+        }
+    }
+
+    /// Gets the upper bound of a file content size.
+    pub fn get_content_size_upper_bound(&self) -> usize {
+        if let Some(size) = self.get_recorded_size() {
+            return size;
+        }
+
+        match &self.content.content {
+            FileContent::Inline { data, .. } => data.len(),
+            FileContent::External { chunk_ends, .. } if !chunk_ends.is_empty() => {
+                chunk_ends[chunk_ends.len() - 1]
+            }
+            FileContent::External {
+                block_count,
+                block_content_size,
+                ..
+            } => block_count * block_content_size,
+        }
+    }
+
+    /// Decrypts a block of a file's content.
+    async fn decrypt_block(
+        key: &SnapshotKey,
+        label: &Namefilter,
+        convergent: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Vec<u8>> {
+        let (block_key, cid) = Self::resolve_block(key, label, convergent, forest, store).await?;
+
+        let enc_bytes = store.get_block(&cid).await?;
+        let bytes = block_key.decrypt(&enc_bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Looks up the block of a file's content stored under a label, along with the key it's
+    /// encrypted with.
+    ///
+    /// Convergently encrypted blocks are found through the block holding their key.
+    async fn resolve_block(
+        key: &SnapshotKey,
+        label: &Namefilter,
+        convergent: bool,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<(SnapshotKey, Cid)> {
+        let label_hash = &Sha3_256::hash(&label.as_bytes());
+
+        let cids = forest
+            .get_encrypted(label_hash, store)
+            .await?
             .ok_or(FsError::FileShardNotFound)?;
 
         let cid = cids
@@ -601,10 +1269,103 @@ impl PrivateFile {
             .next()
             .expect("Expected set with at least a Cid");
 
-        let enc_bytes = store.get_block(cid).await?;
-        let bytes = key.decrypt(&enc_bytes)?;
+        if !convergent {
+            return Ok((key.clone(), *cid));
+        }
+
+        let Ipld::Map(map) = serde_ipld_dagcbor::from_slice::<Ipld>(&store.get_block(cid).await?)?
+        else {
+            bail!(FsError::InvalidFileContent(
+                "Expected a map holding a block key".into()
+            ));
+        };
+
+        match (map.get("key"), map.get("content")) {
+            (Some(Ipld::Bytes(enc_key)), Some(Ipld::Link(content_cid))) => {
+                let block_key: [u8; KEY_BYTE_SIZE] =
+                    key.decrypt(enc_key)?.try_into().map_err(|_| {
+                        FsError::InvalidFileContent("Block key has the wrong length".into())
+                    })?;
 
-        Ok(bytes)
+                Ok((SnapshotKey::from(block_key), *content_cid))
+            }
+            _ => bail!(FsError::InvalidFileContent(
+                "Expected a map holding a block key".into()
+            )),
+        }
+    }
+
+    /// Encrypts a chunk of a file's content and stores it under a label.
+    ///
+    /// Otherwise, the chunk is encrypted with `key` and the config's cipher.
+    ///
+    /// With `convergent` set, the chunk is encrypted with a key derived from its hash and a fixed
+    /// nonce, which is safe since that key never encrypts anything else. `key` then only encrypts
+    /// the derived key, in a block linking to the chunk.
+    async fn store_block(
+        key: &SnapshotKey,
+        label: Namefilter,
+        chunk: &[u8],
+        config: &PrivateFileConfig,
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) -> Result<()> {
+        let cid = if config.convergent {
+            let block_key = SnapshotKey::from(Sha3_256::hash(&chunk));
+            let nonce = Nonce::default();
+            let mut buffer = chunk.to_vec();
+            let tag = block_key.encrypt_in_place(&nonce, &mut buffer)?;
+            let content_cid = store
+                .put_block([&nonce[..], &buffer, &tag[..]].concat(), IpldCodec::Raw)
+                .await?;
+
+            let map = BTreeMap::from([
+                (
+                    "key".to_string(),
+                    Ipld::Bytes(key.encrypt(block_key.0.as_bytes(), rng)?),
+                ),
+                ("content".to_string(), Ipld::Link(content_cid)),
+            ]);
+
+            store
+                .put_block(
+                    serde_ipld_dagcbor::to_vec(&Ipld::Map(map))?,
+                    IpldCodec::DagCbor,
+                )
+                .await?
+        } else {
+            store
+                .put_block(
+                    key.encrypt_using(config.cipher, chunk, rng)?,
+                    IpldCodec::Raw,
+                )
+                .await?
+        };
+
+        forest.put_encrypted(label, Some(cid), store).await?;
+        Ok(())
+    }
+
+    /// Advises the store to fetch the blocks in the range `from..to` of a file's content.
+    async fn prefetch_blocks(
+        key: &SnapshotKey,
+        segments: &[ContentSegment],
+        from: usize,
+        to: usize,
+        bare_name: &Namefilter,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) {
+        let mut cids = Vec::with_capacity(to - from);
+        for (_, label) in Self::generate_shard_labels(key, segments, from, to, bare_name) {
+            let label_hash = &Sha3_256::hash(&label.as_bytes());
+            if let Ok(Some(block_cids)) = forest.get_encrypted(label_hash, store).await {
+                cids.extend(block_cids.iter().next());
+            }
+        }
+
+        store.prefetch(&cids).await;
     }
 
     pub async fn get_cids<'a>(
@@ -614,18 +1375,30 @@ impl PrivateFile {
     ) -> Result<BTreeSet<Cid>> {
         match &self.content.content {
             FileContent::External {
-                key, block_count, ..
+                key,
+                block_count,
+                convergent,
+                segments,
+                ..
             } => {
                 let mut cids = <BTreeSet<Cid>>::new();
                 let bare_name = &self.header.bare_name;
 
-                for label in Self::generate_shard_labels(key, 0, *block_count, bare_name) {
+                for (key, label) in
+                    Self::generate_shard_labels(key, segments, 0, *block_count, bare_name)
+                {
                     let label_hash = &Sha3_256::hash(&label.as_bytes());
                     let block_cids = forest
                         .get_encrypted(label_hash, store)
                         .await?
                         .ok_or(FsError::FileShardNotFound)?;
                     cids.extend(block_cids);
+
+                    if *convergent {
+                        let (_, content_cid) =
+                            Self::resolve_block(key, &label, true, forest, store).await?;
+                        cids.insert(content_cid);
+                    }
                 }
                 Ok(cids)
             }
@@ -633,24 +1406,58 @@ impl PrivateFile {
         }
     }
 
-    /// Generates the labels for the shards of a file.
+    /// Returns the forest label hashes of the blocks of externally stored content.
+    pub(crate) fn get_shard_label_hashes(&self) -> Vec<HashOutput> {
+        match &self.content.content {
+            FileContent::External {
+                key,
+                block_count,
+                segments,
+                ..
+            } => {
+                Self::generate_shard_labels(key, segments, 0, *block_count, &self.header.bare_name)
+                    .map(|(_, label)| Sha3_256::hash(&label.as_bytes()))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Generates the labels for the shards of a file, along with the keys they're encrypted with.
     fn generate_shard_labels<'a>(
         key: &'a SnapshotKey,
+        segments: &'a [ContentSegment],
         mut index: usize,
         block_count: usize,
         bare_name: &'a Namefilter,
-    ) -> impl Iterator<Item = Namefilter> + 'a {
+    ) -> impl Iterator<Item = (&'a SnapshotKey, Namefilter)> + 'a {
         iter::from_fn(move || {
             if index >= block_count {
                 return None;
             }
-            let label = Self::create_block_label(key, index, bare_name);
+            let label = Self::block_label(key, segments, index, bare_name);
             index += 1;
 
             Some(label)
         })
     }
 
+    /// Looks up the key of the block at given index and creates its label.
+    fn block_label<'a>(
+        key: &'a SnapshotKey,
+        segments: &'a [ContentSegment],
+        index: usize,
+        bare_name: &Namefilter,
+    ) -> (&'a SnapshotKey, Namefilter) {
+        match segments.iter().rev().find(|segment| segment.start <= index) {
+            Some(segment) => (
+                &segment.key,
+                Self::create_block_label(&segment.key, index - segment.start, bare_name),
+            ),
+            None => (key, Self::create_block_label(key, index, bare_name)),
+        }
+    }
+
     /// Creates the label for a block of a file.
     fn create_block_label(key: &SnapshotKey, index: usize, bare_name: &Namefilter) -> Namefilter {
         let key_bytes = key.0.as_bytes();
@@ -670,6 +1477,8 @@ impl PrivateFile {
     /// Otherwise, it clones itself, stores its current CID in the previous links and
     /// advances its ratchet.
     pub(crate) fn prepare_next_revision<'a>(self: &'a mut Rc<Self>) -> Result<&'a mut Self> {
+        self.header.ensure_writable()?;
+
         let previous_cid = match self.content.persisted_as.get() {
             Some(cid) => *cid,
             None => {
@@ -716,16 +1525,23 @@ impl PrivateFile {
         store: &impl BlockStore,
         rng: &mut impl RngCore,
     ) -> Result<()> {
-        let content = self.get_content(forest, store).await?;
+        // Inline content is encrypted along with the node, so only external content moves
+        let external_content = match &self.content.content {
+            FileContent::External { .. } => Some(self.get_content(forest, store).await?),
+            FileContent::Inline { .. } => None,
+        };
 
         self.header.inumber = utils::get_random_bytes(rng);
         self.header.update_bare_name(parent_bare_name);
         self.header.reset_ratchet(rng);
         self.content.persisted_as = OnceCell::new();
 
-        let content =
-            Self::prepare_content(&self.header.bare_name, content, forest, store, rng).await?;
-        self.content.content = content;
+        if let Some(content) = external_content {
+            let config = self.get_content_config();
+            self.content.content =
+                Self::prepare_content(&self.header.bare_name, content, &config, forest, store, rng)
+                    .await?;
+        }
 
         Ok(())
     }
@@ -781,7 +1597,7 @@ impl PrivateFile {
             .await?;
 
         forest
-            .put_encrypted(label, [header_cid, content_cid], store)
+            .put_node(label, header_cid, content_cid, store)
             .await?;
 
         Ok(self
@@ -872,7 +1688,7 @@ impl PrivateFileContent {
                 let bytes = self.to_dag_cbor(header_cid)?;
 
                 // Encrypt bytes with snapshot key.
-                let block = snapshot_key.encrypt(&bytes, rng)?;
+                let block = snapshot_key.encrypt_using(self.content.get_cipher(), &bytes, rng)?;
 
                 // Store content section in blockstore and get Cid.
                 store.put_block(block, libipld::IpldCodec::Raw).await
@@ -881,6 +1697,14 @@ impl PrivateFileContent {
     }
 }
 
+impl FileContent {
+    pub(crate) fn get_cipher(&self) -> CipherAlgorithm {
+        match self {
+            Self::Inline { cipher, .. } | Self::External { cipher, .. } => *cipher,
+        }
+    }
+}
+
 impl PartialEq for PrivateFileContent {
     fn eq(&self, other: &Self) -> bool {
         self.previous == other.previous
@@ -906,6 +1730,103 @@ impl Id for PrivateFile {
     }
 }
 
+impl PrivateFileConfig {
+    /// Checks that both values stay within what fits into a block.
+    fn validate(&self) -> Result<()> {
+        let max_size = self.cipher.max_block_content_size();
+        if self.chunk_size == 0 || self.chunk_size > max_size {
+            bail!(FsError::InvalidFileConfig(format!(
+                "Chunk size must be between 1 and {max_size}, got {}",
+                self.chunk_size
+            )));
+        }
+
+        if self.inline_threshold > max_size {
+            bail!(FsError::InvalidFileConfig(format!(
+                "Inline threshold must be at most {max_size}, got {}",
+                self.inline_threshold
+            )));
+        }
+
+        if let ChunkingStrategy::Rabin { min, avg, max } = self.chunking {
+            if min == 0 || min > avg || avg > max || max > max_size {
+                bail!(FsError::InvalidFileConfig(format!(
+                    "Rabin chunk sizes must satisfy 0 < min <= avg <= max <= {max_size}, got {min}, {avg} and {max}"
+                )));
+            }
+        }
+
+        if self.convergent && !self.cipher.is_default() {
+            bail!(FsError::InvalidFileConfig(format!(
+                "Convergent encryption only works with {:?}, got {:?}",
+                CipherAlgorithm::Aes256Gcm,
+                self.cipher
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl ChunkingStrategy {
+    fn is_fixed(&self) -> bool {
+        matches!(self, Self::Fixed)
+    }
+
+    /// Returns the offset each chunk of the content ends at.
+    fn chunk_ends(&self, content: &[u8], chunk_size: usize) -> Vec<usize> {
+        match *self {
+            Self::Fixed => (1..=content.len().div_ceil(chunk_size))
+                .map(|index| content.len().min(index * chunk_size))
+                .collect(),
+            Self::Rabin { min, avg, max } => Self::rabin_chunk_ends(content, min, avg, max),
+        }
+    }
+
+    /// Ends chunks where the top bits of a rolling hash over the last [`RABIN_WINDOW_SIZE`]
+    /// bytes are all zero, which happens every `avg` bytes on average.
+    fn rabin_chunk_ends(content: &[u8], min: usize, avg: usize, max: usize) -> Vec<usize> {
+        let bits = avg.next_power_of_two().trailing_zeros();
+        let outgoing_factor = RABIN_BASE.wrapping_pow(RABIN_WINDOW_SIZE as u32);
+
+        let mut ends = Vec::new();
+        let mut start = 0;
+        let mut hash = 0u64;
+        for (index, &byte) in content.iter().enumerate() {
+            hash = hash.wrapping_mul(RABIN_BASE).wrapping_add(byte as u64 + 1);
+            if index >= RABIN_WINDOW_SIZE {
+                let outgoing = content[index - RABIN_WINDOW_SIZE] as u64 + 1;
+                hash = hash.wrapping_sub(outgoing.wrapping_mul(outgoing_factor));
+            }
+
+            let len = index + 1 - start;
+            let at_boundary = hash.checked_shr(64 - bits).unwrap_or(0) == 0;
+            if (len >= min && at_boundary) || len == max {
+                ends.push(index + 1);
+                start = index + 1;
+            }
+        }
+
+        if start < content.len() {
+            ends.push(content.len());
+        }
+
+        ends
+    }
+}
+
+impl Default for PrivateFileConfig {
+    fn default() -> Self {
+        Self {
+            inline_threshold: 0,
+            chunk_size: MAX_BLOCK_CONTENT_SIZE,
+            convergent: false,
+            chunking: ChunkingStrategy::Fixed,
+            cipher: CipherAlgorithm::Aes256Gcm,
+        }
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -913,10 +1834,12 @@ impl Id for PrivateFile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::private::{Cipher, PrivateNode, XChaCha20Poly1305Cipher};
     use async_std::fs::File;
+    use libipld::Ipld;
     use proptest::test_runner::{RngAlgorithm, TestRng};
     use rand::Rng;
-    use wnfs_common::MemoryBlockStore;
+    use wnfs_common::{MemoryBlockStore, PrefetchingBlockStore};
 
     #[async_std::test]
     async fn can_create_empty_file() {
@@ -971,19 +1894,49 @@ mod tests {
     }
 
     #[async_std::test]
-    async fn can_construct_file_from_stream() {
-        let disk_file = File::open("./test/fixtures/Clara Schumann, Scherzo no. 2, Op. 14.mp3")
-            .await
-            .unwrap();
+    async fn streams_content_one_block_at_a_time() {
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 3 + 10];
+        rand::thread_rng().fill(&mut content[..]);
 
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
         let forest = &mut Rc::new(PrivateForest::new());
-        let store = &mut MemoryBlockStore::new();
+
+        let file = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        store.reset_stats();
+        let mut stream = file.stream_content(0, forest, store);
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first, content[..MAX_BLOCK_CONTENT_SIZE]);
+        assert_eq!(store.stats().unwrap().blocks_read, 1);
+
+        let rest = stream.try_concat().await.unwrap();
+        assert_eq!([first, rest].concat(), content);
+        assert_eq!(store.stats().unwrap().blocks_read, 4);
+    }
+
+    #[async_std::test]
+    async fn can_stream_content_with_prefetching() {
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 6 + 10];
+        rand::thread_rng().fill(&mut content[..]);
+
+        let store = &PrefetchingBlockStore::new(MemoryBlockStore::default(), 4);
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
 
-        let file = PrivateFile::with_content_streaming(
+        let file = PrivateFile::with_content(
             Namefilter::default(),
             Utc::now(),
-            disk_file,
+            content.clone(),
             forest,
             store,
             rng,
@@ -991,8 +1944,816 @@ mod tests {
         .await
         .unwrap();
 
-        assert!(
-            matches!(file.content.content, FileContent::External { block_count, .. } if block_count > 0)
+        assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+        assert_eq!(
+            file.read_at(MAX_BLOCK_CONTENT_SIZE * 3 + 5, 100, forest, store)
+                .await
+                .unwrap(),
+            content[MAX_BLOCK_CONTENT_SIZE * 3 + 5..MAX_BLOCK_CONTENT_SIZE * 3 + 105]
+        );
+    }
+
+    #[async_std::test]
+    async fn can_read_ranges_across_blocks_and_past_the_end() {
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 2 + 10];
+        rand::thread_rng().fill(&mut content[..]);
+
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let file = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        let boundary = MAX_BLOCK_CONTENT_SIZE;
+        let spanning = file
+            .read_at(boundary - 5, MAX_BLOCK_CONTENT_SIZE + 10, forest, store)
+            .await
+            .unwrap();
+        assert_eq!(spanning, content[boundary - 5..2 * boundary + 5]);
+
+        let tail = file
+            .read_at(content.len() - 4, 100, forest, store)
+            .await
+            .unwrap();
+        assert_eq!(tail, content[content.len() - 4..]);
+
+        // Sizes reaching past the end of the address space are clamped at the end of the file
+        let all = file.read_at(0, usize::MAX, forest, store).await.unwrap();
+        assert_eq!(all, content);
+        let rest = file
+            .read_at(boundary + 1, usize::MAX, forest, store)
+            .await
+            .unwrap();
+        assert_eq!(rest, content[boundary + 1..]);
+
+        // A range ending on a block boundary doesn't fetch the block after it
+        store.reset_stats();
+        let first_bytes = file.read_at(0, 10, forest, store).await.unwrap();
+        let partial_reads = store.stats().unwrap().blocks_read;
+        store.reset_stats();
+        let first_block = file.read_at(0, boundary, forest, store).await.unwrap();
+        assert_eq!(store.stats().unwrap().blocks_read, partial_reads);
+        assert_eq!(first_bytes, content[..10]);
+        assert_eq!(first_block, content[..boundary]);
+
+        for offset in [
+            content.len(),
+            content.len() + 1,
+            10 * MAX_BLOCK_CONTENT_SIZE,
+        ] {
+            let past_end = file.read_at(offset, 100, forest, store).await.unwrap();
+            assert!(past_end.is_empty());
+        }
+
+        let inline = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        let past_end = inline.read_at(5, 10, forest, store).await.unwrap();
+        assert!(past_end.is_empty());
+    }
+
+    #[async_std::test]
+    async fn can_append_to_file() {
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 2];
+        rand::thread_rng().fill(&mut content[..]);
+        let mut extra = vec![0u8; MAX_BLOCK_CONTENT_SIZE + 10];
+        rand::thread_rng().fill(&mut extra[..]);
+
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let file = &mut Rc::new(
+            PrivateFile::with_content(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap(),
+        );
+        let existing_cids = file.get_cids(forest, store).await.unwrap();
+
+        // Full blocks are kept as they are
+        let time = Utc::now() + chrono::Duration::days(1);
+        file.append(time, &extra, forest, store, rng).await.unwrap();
+        content.extend_from_slice(&extra);
+
+        let cids = file.get_cids(forest, store).await.unwrap();
+        assert!(cids.is_superset(&existing_cids));
+        assert_eq!(cids.len(), 4);
+        assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+        assert_eq!(
+            file.get_metadata().get_modified().unwrap().timestamp(),
+            time.timestamp()
+        );
+
+        // A partially filled last block is kept too, new blocks follow it
+        let existing_cids = cids;
+        file.append(Utc::now(), b"tail", forest, store, rng)
+            .await
+            .unwrap();
+        content.extend_from_slice(b"tail");
+        file.append(Utc::now(), &extra, forest, store, rng)
+            .await
+            .unwrap();
+        content.extend_from_slice(&extra);
+
+        let cids = file.get_cids(forest, store).await.unwrap();
+        assert!(cids.is_superset(&existing_cids));
+        assert_eq!(cids.len(), 7);
+        assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+        assert_eq!(
+            file.get_size(forest, store).await.unwrap(),
+            content.len() as u64
+        );
+        let boundary = MAX_BLOCK_CONTENT_SIZE * 3 + 10;
+        assert_eq!(
+            file.read_at(boundary - 5, 20, forest, store).await.unwrap(),
+            content[boundary - 5..boundary + 15]
+        );
+
+        let empty = &mut Rc::new(PrivateFile::new(Namefilter::default(), Utc::now(), rng));
+        empty
+            .append(Utc::now(), b"first", forest, store, rng)
+            .await
+            .unwrap();
+        assert_eq!(empty.get_content(forest, store).await.unwrap(), b"first");
+    }
+
+    #[async_std::test]
+    async fn appends_forked_off_one_revision_read_back_separately() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let content = vec![1u8; MAX_BLOCK_CONTENT_SIZE + 10];
+
+        let file = &mut Rc::new(
+            PrivateFile::with_content(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap(),
+        );
+        let stored_ref = file.store(forest, store, rng).await.unwrap();
+
+        let mut refs = Vec::new();
+        for extra in [b"first fork".as_slice(), b"second fork"] {
+            let fork = &mut Rc::clone(file);
+            fork.append(Utc::now(), extra, forest, store, rng)
+                .await
+                .unwrap();
+            assert_eq!(fork.content.previous.len(), 1);
+            refs.push((fork.store(forest, store, rng).await.unwrap(), extra));
+        }
+
+        for (private_ref, extra) in refs {
+            assert_ne!(private_ref.content_cid, stored_ref.content_cid);
+            assert_ne!(
+                private_ref.saturated_name_hash,
+                stored_ref.saturated_name_hash
+            );
+
+            let loaded = PrivateNode::load(&private_ref, forest, store)
+                .await
+                .unwrap()
+                .as_file()
+                .unwrap();
+            assert_eq!(
+                loaded.get_content(forest, store).await.unwrap(),
+                [&content[..], extra].concat()
+            );
+        }
+    }
+
+    #[async_std::test]
+    async fn key_rotation_keeps_the_content_config() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let content = (0..10_000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let configs = [
+            PrivateFileConfig {
+                chunk_size: 1024,
+                convergent: true,
+                ..Default::default()
+            },
+            PrivateFileConfig {
+                chunking: ChunkingStrategy::Rabin {
+                    min: 512,
+                    avg: 1024,
+                    max: 4096,
+                },
+                ..Default::default()
+            },
+            PrivateFileConfig {
+                inline_threshold: 20_000,
+                ..Default::default()
+            },
+        ];
+
+        for config in configs {
+            let mut file = PrivateFile::with_content_config(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                &config,
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+            let layout = file.content_layout();
+            let content_config = file.get_content_config();
+
+            file.prepare_key_rotation(Namefilter::default(), forest, store, rng)
+                .await
+                .unwrap();
+
+            assert_eq!(file.content_layout(), layout);
+            assert_eq!(file.get_content_config(), content_config);
+            assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+        }
+    }
+
+    #[async_std::test]
+    async fn smaller_chunk_size_produces_more_blocks() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let content = (0..MAX_BLOCK_CONTENT_SIZE * 2)
+            .map(|i| i as u8)
+            .collect::<Vec<_>>();
+
+        let default_file = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        let config = PrivateFileConfig {
+            chunk_size: 1024,
+            ..Default::default()
+        };
+        let small_chunk_file = PrivateFile::with_content_config(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            &config,
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            small_chunk_file
+                .get_cids(forest, store)
+                .await
+                .unwrap()
+                .len()
+                > default_file.get_cids(forest, store).await.unwrap().len()
+        );
+        assert_eq!(
+            small_chunk_file.get_content(forest, store).await.unwrap(),
+            content
+        );
+        assert_eq!(
+            small_chunk_file
+                .read_at(1000, 100, forest, store)
+                .await
+                .unwrap(),
+            content[1000..1100]
+        );
+
+        let too_large = PrivateFileConfig {
+            chunk_size: MAX_BLOCK_SIZE,
+            ..Default::default()
+        };
+        assert!(PrivateFile::with_content_config(
+            Namefilter::default(),
+            Utc::now(),
+            content,
+            &too_large,
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .is_err());
+    }
+
+    #[async_std::test]
+    async fn rabin_chunking_confines_insertions_to_few_chunks() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut content = vec![0u8; 256 * 1024];
+        rng.fill_bytes(&mut content);
+        let mut edited = content.clone();
+        edited.insert(10, 42);
+
+        let fixed = PrivateFileConfig {
+            chunk_size: 4096,
+            convergent: true,
+            ..Default::default()
+        };
+        let rabin = PrivateFileConfig {
+            convergent: true,
+            chunking: ChunkingStrategy::Rabin {
+                min: 1024,
+                avg: 4096,
+                max: 16384,
+            },
+            ..Default::default()
+        };
+
+        let mut new_chunks = Vec::new();
+        for config in [fixed, rabin] {
+            let store = &MemoryBlockStore::default();
+            let forest = &mut Rc::new(PrivateForest::new());
+            let raw_blocks = || async {
+                let blocks = store.list_blocks().await.unwrap();
+                blocks
+                    .into_iter()
+                    .filter(|cid| cid.codec() == u64::from(IpldCodec::Raw))
+                    .count()
+            };
+
+            for content in [&content, &edited] {
+                let before = raw_blocks().await;
+                let file = PrivateFile::with_content_config(
+                    Namefilter::default(),
+                    Utc::now(),
+                    content.clone(),
+                    &config,
+                    forest,
+                    store,
+                    rng,
+                )
+                .await
+                .unwrap();
+
+                assert_eq!(&file.get_content(forest, store).await.unwrap(), content);
+                assert_eq!(
+                    file.get_size(forest, store).await.unwrap(),
+                    content.len() as u64
+                );
+                assert_eq!(
+                    file.read_at(5000, 20_000, forest, store).await.unwrap(),
+                    content[5000..25_000]
+                );
+
+                new_chunks.push((raw_blocks().await - before, file.content_layout()));
+            }
+        }
+
+        let [_, (fixed_rewritten, _), (rabin_chunks, rabin_layout), (rabin_rewritten, _)] =
+            new_chunks[..]
+        else {
+            unreachable!()
+        };
+        assert_eq!(fixed_rewritten, edited.len().div_ceil(4096));
+        assert!(rabin_chunks > 16);
+        assert!(matches!(
+            rabin_layout,
+            ContentLayout::External {
+                chunk_count,
+                chunking: ChunkingStrategy::Rabin { .. },
+                max_chunk_size: 16384,
+                total_len,
+            } if chunk_count == rabin_chunks && total_len == Some(content.len() as u64)
+        ));
+        assert!(
+            rabin_rewritten <= 2,
+            "{rabin_rewritten} chunks were rewritten"
+        );
+    }
+
+    #[async_std::test]
+    async fn rabin_chunking_appends_and_rejects_invalid_sizes() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let mut content = vec![0u8; 64 * 1024];
+        rng.fill_bytes(&mut content);
+
+        let config = PrivateFileConfig {
+            chunking: ChunkingStrategy::Rabin {
+                min: 512,
+                avg: 2048,
+                max: 8192,
+            },
+            ..Default::default()
+        };
+        let file = &mut Rc::new(
+            PrivateFile::with_content_config(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                &config,
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap(),
+        );
+
+        file.append(Utc::now(), b"appended", forest, store, rng)
+            .await
+            .unwrap();
+        content.extend_from_slice(b"appended");
+        assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+        assert!(matches!(
+            file.content.content,
+            FileContent::External {
+                chunking: ChunkingStrategy::Rabin { max: 8192, .. },
+                ..
+            }
+        ));
+
+        for (min, avg, max) in [(0, 1, 2), (4, 2, 8), (1, 2, MAX_BLOCK_CONTENT_SIZE + 1)] {
+            let config = PrivateFileConfig {
+                chunking: ChunkingStrategy::Rabin { min, avg, max },
+                ..Default::default()
+            };
+            let result = PrivateFile::with_content_config(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                &config,
+                forest,
+                store,
+                rng,
+            )
+            .await;
+            assert!(result.is_err());
+        }
+    }
+
+    #[async_std::test]
+    async fn selected_cipher_encrypts_content_and_sticks_with_the_file() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let config = PrivateFileConfig {
+            chunk_size: 1024,
+            cipher: CipherAlgorithm::XChaCha20Poly1305,
+            ..Default::default()
+        };
+
+        let mut content = vec![0u8; 3000];
+        rng.fill(&mut content[..]);
+        let file = &mut Rc::new(
+            PrivateFile::with_content_config(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                &config,
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap(),
+        );
+        file.append(Utc::now(), b"appended", forest, store, rng)
+            .await
+            .unwrap();
+        content.extend_from_slice(b"appended");
+
+        let private_ref = file.store(forest, store, rng).await.unwrap();
+        let loaded = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_file()
+            .unwrap();
+        assert_eq!(loaded.get_cipher(), CipherAlgorithm::XChaCha20Poly1305);
+        assert_eq!(loaded.get_content(forest, store).await.unwrap(), content);
+
+        // The content blocks and the file node itself are tagged with the cipher's id
+        let xchacha_id = XChaCha20Poly1305Cipher.id();
+        let node_block = store.get_block(&private_ref.content_cid).await.unwrap();
+        assert_eq!(node_block[0], xchacha_id);
+        for cid in loaded.get_cids(forest, store).await.unwrap() {
+            assert_eq!(store.get_block(&cid).await.unwrap()[0], xchacha_id);
+        }
+
+        // Content that replaces the old one keeps using the file's cipher
+        let file = &mut Rc::clone(&loaded);
+        file.prepare_next_revision()
+            .unwrap()
+            .set_content(Utc::now(), &b"replaced"[..], forest, store, rng)
+            .await
+            .unwrap();
+        assert_eq!(file.get_cipher(), CipherAlgorithm::XChaCha20Poly1305);
+        for cid in file.get_cids(forest, store).await.unwrap() {
+            assert_eq!(store.get_block(&cid).await.unwrap()[0], xchacha_id);
+        }
+
+        for config in [
+            PrivateFileConfig {
+                convergent: true,
+                ..config
+            },
+            PrivateFileConfig {
+                chunk_size: MAX_BLOCK_CONTENT_SIZE,
+                ..config
+            },
+        ] {
+            let result = PrivateFile::with_content_config(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                &config,
+                forest,
+                store,
+                rng,
+            )
+            .await;
+            assert!(result.is_err());
+        }
+    }
+
+    #[async_std::test]
+    async fn convergent_encryption_stores_shared_chunks_once() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let config = PrivateFileConfig {
+            chunk_size: 1024,
+            convergent: true,
+            ..Default::default()
+        };
+
+        let shared = vec![7u8; 1024];
+        let mut cids = Vec::new();
+        for suffix in [b"first", b"other"] {
+            let content = [&shared[..], suffix].concat();
+            let file = PrivateFile::with_content_config(
+                Namefilter::default(),
+                Utc::now(),
+                content.clone(),
+                &config,
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+            assert_eq!(
+                file.get_size(forest, store).await.unwrap(),
+                content.len() as u64
+            );
+            cids.push(file.get_cids(forest, store).await.unwrap());
+        }
+
+        let shared_cids = cids[0].intersection(&cids[1]).collect::<Vec<_>>();
+        assert_eq!(shared_cids.len(), 1);
+
+        let shared_block = store.get_block(shared_cids[0]).await.unwrap();
+        let key = SnapshotKey::from(Sha3_256::hash(&shared));
+        assert_eq!(key.decrypt(&shared_block).unwrap(), shared);
+    }
+
+    #[async_std::test]
+    async fn size_matches_content_length() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let inline = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        assert_eq!(inline.get_size(forest, store).await.unwrap(), 0);
+
+        for len in [
+            0,
+            10,
+            MAX_BLOCK_CONTENT_SIZE,
+            MAX_BLOCK_CONTENT_SIZE * 3 + 7,
+        ] {
+            let file = PrivateFile::with_content(
+                Namefilter::default(),
+                Utc::now(),
+                vec![1; len],
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+            store.reset_stats();
+            let size = file.get_size(forest, store).await.unwrap();
+            assert_eq!(store.stats().unwrap().blocks_read, 0);
+            assert_eq!(
+                size,
+                file.get_content(forest, store).await.unwrap().len() as u64
+            );
+
+            // Content stored before sizes got recorded is still measured correctly
+            let mut legacy = file.clone();
+            if let FileContent::External { content_size, .. } = &mut legacy.content.content {
+                *content_size = None;
+            }
+            assert_eq!(legacy.get_size(forest, store).await.unwrap(), size);
+        }
+    }
+
+    #[async_std::test]
+    async fn can_construct_file_from_stream() {
+        let disk_file = File::open("./test/fixtures/Clara Schumann, Scherzo no. 2, Op. 14.mp3")
+            .await
+            .unwrap();
+
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &mut MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let file = PrivateFile::with_content_streaming(
+            Namefilter::default(),
+            Utc::now(),
+            disk_file,
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            matches!(file.content.content, FileContent::External { block_count, .. } if block_count > 0)
+        );
+    }
+
+    #[async_std::test]
+    async fn can_set_large_content_from_stream() {
+        let mut content = vec![0u8; 5 * 1024 * 1024];
+        rand::thread_rng().fill(&mut content[..]);
+
+        let store = &MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let mut file = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        file.set_content(
+            Utc::now(),
+            futures::io::Cursor::new(content.clone()),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            file.content_layout(),
+            ContentLayout::External {
+                chunk_count: content.len().div_ceil(MAX_BLOCK_CONTENT_SIZE),
+                chunking: ChunkingStrategy::Fixed,
+                max_chunk_size: MAX_BLOCK_CONTENT_SIZE,
+                total_len: Some(content.len() as u64),
+            }
+        );
+        assert_eq!(file.get_content(forest, store).await.unwrap(), content);
+    }
+
+    #[async_std::test]
+    async fn can_recompose_file_from_parts() {
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 2 + 10];
+        rand::thread_rng().fill(&mut content[..]);
+
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let file = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        let (header, metadata, file_content) = file.clone().into_parts();
+        let recomposed = Rc::new(PrivateFile::from_parts(header, metadata, file_content).unwrap());
+
+        assert_eq!(recomposed.header, file.header);
+        assert_eq!(
+            recomposed.get_content(forest, store).await.unwrap(),
+            content
+        );
+
+        let private_ref = recomposed.store(forest, store, rng).await.unwrap();
+        let loaded = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_file()
+            .unwrap();
+
+        assert_eq!(loaded.get_content(forest, store).await.unwrap(), content);
+    }
+
+    #[async_std::test]
+    async fn content_layout_describes_storage() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let file = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        assert_eq!(file.content_layout(), ContentLayout::Inline { len: 0 });
+
+        let file = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            vec![0u8; MAX_BLOCK_CONTENT_SIZE * 2 + 10],
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            file.content_layout(),
+            ContentLayout::External {
+                chunk_count: 3,
+                chunking: ChunkingStrategy::Fixed,
+                max_chunk_size: MAX_BLOCK_CONTENT_SIZE,
+                total_len: Some((MAX_BLOCK_CONTENT_SIZE * 2 + 10) as u64),
+            }
+        );
+    }
+
+    #[test]
+    fn from_parts_rejects_oversized_blocks() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let header = PrivateNodeHeader::new(Namefilter::default(), rng);
+        let content = FileContentParts(FileContent::External {
+            key: SnapshotKey::from(utils::get_random_bytes(rng)),
+            block_count: 1,
+            block_content_size: MAX_BLOCK_CONTENT_SIZE + 1,
+            convergent: false,
+            chunking: ChunkingStrategy::Fixed,
+            chunk_ends: vec![],
+            content_size: None,
+            segments: vec![],
+            cipher: CipherAlgorithm::default(),
+        });
+
+        let result = PrivateFile::from_parts(header, Metadata::new(Utc::now()), content);
+
+        assert!(result.is_err());
+    }
+
+    #[async_std::test]
+    async fn attributes_survive_store_and_load() {
+        let store = &MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let file = &mut Rc::new(PrivateFile::new(Namefilter::default(), Utc::now(), rng));
+        file.get_metadata_mut()
+            .unwrap()
+            .set_attribute("content-type", Ipld::String("text/markdown".into()))
+            .unwrap();
+
+        let private_ref = file.store(forest, store, rng).await.unwrap();
+        let loaded = PrivateNode::load(&private_ref, forest, store)
+            .await
+            .unwrap()
+            .as_file()
+            .unwrap();
+
+        assert_eq!(
+            loaded.get_metadata().get_attribute("content-type"),
+            Some(&Ipld::String("text/markdown".into()))
         );
     }
 }
@@ -1101,7 +2862,7 @@ mod proptests {
 
             let error = error.downcast_ref::<BlockStoreError>().unwrap();
 
-            assert!(matches!(error, BlockStoreError::CIDNotFound(_)));
+            assert!(matches!(error, BlockStoreError::NotFound(_)));
         })
     }
 
@@ -1,15 +1,15 @@
-use super::{PrivateNode, RevisionRef};
-use crate::error::AesError;
-use anyhow::Result;
+use super::{gc, HamtProof, PrivateNode, PrivateRef, RevisionRef};
+use crate::error::{AesError, ForestError, FsError};
+use anyhow::{bail, Result};
 use async_stream::stream;
 use async_trait::async_trait;
-use futures::Stream;
-use libipld::Cid;
+use futures::{lock::Mutex, Stream, TryStreamExt};
+use libipld::{Cid, Ipld};
 use serde::{Deserialize, Deserializer, Serializer};
 use sha3::Sha3_256;
-use std::{collections::BTreeSet, rc::Rc};
-use wnfs_common::{AsyncSerialize, BlockStore, HashOutput, Link};
-use wnfs_hamt::{merge, Hamt, Hasher, KeyValueChange};
+use std::{cell::Cell, collections::BTreeSet, rc::Rc};
+use wnfs_common::{dagcbor, AsyncSerialize, BlockStore, HashOutput, Link};
+use wnfs_hamt::{merge, Hamt, Hasher, KeyValueChange, Node, Pair};
 use wnfs_namefilter::Namefilter;
 
 //--------------------------------------------------------------------------------------------------
@@ -34,7 +34,27 @@ use wnfs_namefilter::Namefilter;
 /// println!("{:?}", forest);
 /// ```
 #[derive(Debug, Clone)]
-pub struct PrivateForest<H: Hasher = Sha3_256>(Hamt<Namefilter, BTreeSet<Cid>, H>);
+pub struct PrivateForest<H: Hasher = Sha3_256>(
+    Hamt<Namefilter, BTreeSet<Cid>, H>,
+    /// The CID this forest was last stored or loaded as, reset on every mutation.
+    Cell<Option<Cid>>,
+);
+
+/// A label whose set of values differs between two forests.
+///
+/// The change type is `Add` if the label is only in the forest `diff` was called on, `Remove` if
+/// it's only in the other one, and `Modify` if it's in both. `value1` holds the values at the label
+/// in whichever forest has it, preferring the former, and `value2` the other forest's values of
+/// a modified label.
+pub type ForestChange = KeyValueChange<Namefilter, BTreeSet<Cid>>;
+
+/// A private forest that can be written to through shared references.
+///
+/// Mutations wait for each other and run one at a time, so tasks writing different subtrees can
+/// share one forest. Values put at the same label add up, like with
+/// [`PrivateForest::put_encrypted`].
+#[derive(Debug, Default)]
+pub struct SharedForest(Mutex<Rc<PrivateForest>>);
 
 //--------------------------------------------------------------------------------------------------
 // Implementations
@@ -43,7 +63,7 @@ pub struct PrivateForest<H: Hasher = Sha3_256>(Hamt<Namefilter, BTreeSet<Cid>, H
 impl PrivateForest {
     /// Creates a new empty PrivateForest.
     pub fn new() -> Self {
-        Self(Hamt::new())
+        Self(Hamt::new(), Cell::default())
     }
 
     /// Checks that a value with the given saturated name hash key exists.
@@ -92,6 +112,45 @@ impl PrivateForest {
             .is_some())
     }
 
+    /// Checks whether the exact revision a private ref points to is stored in the forest,
+    /// without loading or decrypting the node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     assert!(forest.contains_ref(&private_ref, store).await.unwrap());
+    ///     assert!(!PrivateForest::new().contains_ref(&private_ref, store).await.unwrap());
+    /// }
+    /// ```
+    pub async fn contains_ref(
+        &self,
+        private_ref: &PrivateRef,
+        store: &impl BlockStore,
+    ) -> Result<bool> {
+        Ok(self
+            .get_encrypted(&private_ref.saturated_name_hash, store)
+            .await?
+            .map(|cids| cids.contains(&private_ref.content_cid))
+            .unwrap_or(false))
+    }
+
     /// Adds new encrypted values at the given key.
     pub async fn put_encrypted(
         self: &mut Rc<Self>,
@@ -112,11 +171,47 @@ impl PrivateForest {
 
         cids.extend(values);
 
-        Rc::make_mut(self).0.root.set(name, cids, store).await?;
+        let forest = Rc::make_mut(self);
+        forest.0.root.set(name, cids, store).await?;
+        forest.1.set(None);
         Ok(())
     }
 
-    /// Gets the encrypted values at the given key.
+    /// Adds a node's header and content at its label, like [`put_encrypted`](Self::put_encrypted).
+    ///
+    /// Revisions written concurrently end up at the same label, so the label may hold headers
+    /// already. Those have to belong to the same node, which shows in their inumber. If one doesn't,
+    /// two unrelated nodes collided on the label, which fails with
+    /// [`ForestError::LabelCollision`].
+    pub(crate) async fn put_node(
+        self: &mut Rc<Self>,
+        name: Namefilter,
+        header_cid: Cid,
+        content_cid: Cid,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        if let Some(existing) = self.0.root.get(&name, store).await? {
+            let inumber = Self::header_inumber(&header_cid, store).await?;
+            for cid in existing.iter().filter(|&&cid| cid != header_cid) {
+                if let Some(existing_inumber) = Self::header_inumber(cid, store).await? {
+                    if Some(existing_inumber) != inumber {
+                        bail!(ForestError::LabelCollision {
+                            existing: *cid,
+                            new: header_cid,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.put_encrypted(name, [header_cid, content_cid], store)
+            .await
+    }
+
+    /// Gets all the encrypted values at the given key.
+    ///
+    /// There is more than one value whenever concurrent writes of a revision were merged in, and
+    /// the header blocks of nodes are stored next to their content too.
     #[inline]
     pub async fn get_encrypted<'b>(
         &'b self,
@@ -132,11 +227,12 @@ impl PrivateForest {
         name_hash: &HashOutput,
         store: &impl BlockStore,
     ) -> Result<Option<BTreeSet<Cid>>> {
-        let pair = Rc::make_mut(self)
-            .0
-            .root
-            .remove_by_hash(name_hash, store)
-            .await?;
+        let forest = Rc::make_mut(self);
+        let pair = forest.0.root.remove_by_hash(name_hash, store).await?;
+        if pair.is_some() {
+            forest.1.set(None);
+        }
+
         Ok(pair.map(|p| p.value))
     }
 
@@ -174,25 +270,328 @@ impl PrivateForest {
         })
     }
 
-    /// Gets the difference in changes between two forests.
+    /// Gets the labels that were added, removed, or had their set of values changed in this forest
+    /// compared to `other`.
+    ///
+    /// Subtrees of the HAMT both forests share are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     hamt::ChangeType,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let old_forest = Rc::new(PrivateForest::new());
+    ///     let forest = &mut Rc::clone(&old_forest);
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let changes = forest.diff(&old_forest, store).await.unwrap();
+    ///
+    ///     assert_eq!(changes.len(), 1);
+    ///     assert_eq!(changes[0].r#type, ChangeType::Add);
+    ///     assert!(changes[0].value1.as_ref().unwrap().contains(&private_ref.content_cid));
+    /// }
+    /// ```
     #[inline]
-    pub async fn diff(
+    pub async fn diff(&self, other: &Self, store: &impl BlockStore) -> Result<Vec<ForestChange>> {
+        self.0.diff(&other.0, store).await
+    }
+
+    /// Rebuilds the HAMT from scratch by re-inserting all live entries, dropping
+    /// labels that map to no CIDs.
+    ///
+    /// The returned forest is as dense and shallow as its entries allow.
+    /// Blocks only referenced by the old forest can be garbage collected afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory, PrivateNode},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let compacted = forest.compact(store).await.unwrap();
+    ///     let node = PrivateNode::load(&private_ref, &compacted, store).await.unwrap();
+    ///
+    ///     assert_eq!(node, PrivateNode::Dir(dir));
+    /// }
+    /// ```
+    pub async fn compact(&self, store: &impl BlockStore) -> Result<Rc<Self>> {
+        self.with_entries(self.entries(store).await?, store).await
+    }
+
+    /// Streams every label in the forest together with the CIDs stored at it.
+    ///
+    /// The HAMT is walked lazily, so only the nodes the stream has reached are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let entries = forest.iter(store).try_collect::<Vec<_>>().await.unwrap();
+    ///
+    ///     assert_eq!(entries.len(), 1);
+    ///     assert!(entries[0].1.contains(&private_ref.content_cid));
+    /// }
+    /// ```
+    pub fn iter<'a>(
+        &'a self,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<(Namefilter, BTreeSet<Cid>)>> + 'a {
+        self.0
+            .root
+            .pairs(store)
+            .map_ok(|Pair { key, value }| (key, value))
+    }
+
+    /// Counts the distinct blocks reachable from the given roots and sums up their sizes in bytes.
+    ///
+    /// This covers the headers and content of every node below the roots, as well as the blocks
+    /// of file content. Blocks shared between roots are only counted once. The blocks of the
+    /// forest itself aren't counted.
+    ///
+    /// Previous links can't be decrypted with the keys of newer revisions, so they aren't
+    /// followed. To include past revisions, pass them as roots too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let (count, bytes) = forest.reachable_size(&[private_ref], store).await.unwrap();
+    ///
+    ///     // The content block and the header, which links to its three parts
+    ///     assert_eq!(count, 5);
+    ///     assert!(bytes > 0);
+    /// }
+    /// ```
+    pub async fn reachable_size(
         &self,
-        other: &Self,
+        roots: &[PrivateRef],
         store: &impl BlockStore,
-    ) -> Result<Vec<KeyValueChange<Namefilter, BTreeSet<Cid>>>> {
-        self.0.diff(&other.0, store).await
+    ) -> Result<(usize, u64)> {
+        let mut cids = BTreeSet::new();
+        for label in gc::reachable_labels(roots, self, store).await? {
+            let Some(values) = self.get_encrypted(&label, store).await? else {
+                continue;
+            };
+
+            for cid in values {
+                if cids.insert(*cid) {
+                    cids.extend(gc::header_links(cid, store).await?);
+                }
+            }
+        }
+
+        let mut bytes = 0;
+        for cid in cids.iter() {
+            bytes += store.get_block(cid).await?.len() as u64;
+        }
+
+        Ok((cids.len(), bytes))
+    }
+
+    /// Proves that the label is in the forest, so it can be checked against the forest's root
+    /// CID without access to the rest of the forest.
+    ///
+    /// Any HAMT nodes that haven't been stored yet get flushed first, see [`flush`](Self::flush).
+    /// Fails with `FsError::NotFound` if the label isn't in the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let label_hash = &private_ref.saturated_name_hash;
+    ///     let proof = forest.prove_inclusion(label_hash, store).await.unwrap();
+    ///
+    ///     let root_cid = forest.flush(store).await.unwrap();
+    ///     let value = forest.get_encrypted(label_hash, store).await.unwrap().unwrap();
+    ///
+    ///     assert!(proof.verify(&root_cid, label_hash, value).await);
+    /// }
+    /// ```
+    pub async fn prove_inclusion(
+        &self,
+        label_hash: &HashOutput,
+        store: &impl BlockStore,
+    ) -> Result<HamtProof> {
+        let root_cid = self.flush(store).await?;
+        match HamtProof::generate(&root_cid, label_hash, store).await? {
+            Some(proof) => Ok(proof),
+            None => bail!(FsError::NotFound),
+        }
+    }
+
+    /// Returns all labels and the CIDs stored at them.
+    pub(crate) async fn entries(
+        &self,
+        store: &impl BlockStore,
+    ) -> Result<Vec<(Namefilter, BTreeSet<Cid>)>> {
+        self.0
+            .root
+            .flat_map(&|pair| Ok((pair.key.clone(), pair.value.clone())), store)
+            .await
+    }
+
+    /// Builds a new forest of the same version from scratch, containing only given non-empty entries.
+    pub(crate) async fn with_entries(
+        &self,
+        entries: impl IntoIterator<Item = (Namefilter, BTreeSet<Cid>)>,
+        store: &impl BlockStore,
+    ) -> Result<Rc<Self>> {
+        let mut root = Rc::new(Node::default());
+        for (name, cids) in entries {
+            if !cids.is_empty() {
+                root.set(name, cids, store).await?;
+            }
+        }
+
+        Ok(Rc::new(Self(
+            Hamt {
+                version: self.0.version.clone(),
+                root,
+            },
+            Cell::default(),
+        )))
     }
 
     /// Serializes the forest and stores it in the given block store.
     pub async fn store(&self, store: &impl BlockStore) -> Result<Cid> {
-        store.put_async_serializable(&self.0).await
+        let cid = store.put_async_serializable(&self.0).await?;
+        self.1.set(Some(cid));
+        Ok(cid)
+    }
+
+    /// Stores any HAMT nodes that haven't been written yet and returns the forest's root CID.
+    ///
+    /// Nothing is written if the forest hasn't changed since it was last stored, flushed or loaded,
+    /// in which case the same CID is returned again. Use [`store`](Self::store) to write the
+    /// forest to a different block store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let cid = forest.flush(store).await.unwrap();
+    ///
+    ///     assert_eq!(forest.flush(store).await.unwrap(), cid);
+    /// }
+    /// ```
+    pub async fn flush(&self, store: &impl BlockStore) -> Result<Cid> {
+        match self.1.get() {
+            Some(cid) => Ok(cid),
+            None => self.store(store).await,
+        }
     }
 
     /// Deserializes a forest from the given block store.
     pub async fn load(cid: &Cid, store: &impl BlockStore) -> Result<Self> {
         let hamt = store.get_deserializable(cid).await?;
-        Ok(Self(hamt))
+        Ok(Self(hamt, Cell::new(Some(*cid))))
+    }
+
+    /// Returns the link to a node header's inumber, or `None` if the block isn't a node header.
+    ///
+    /// The inumber is encrypted deterministically, so it's the same in every header of a node,
+    /// whichever version of the header format it was written in.
+    async fn header_inumber(cid: &Cid, store: &impl BlockStore) -> Result<Option<Cid>> {
+        let bytes = store.get_block(cid).await?;
+        Ok(match dagcbor::decode::<Ipld>(&bytes) {
+            Ok(Ipld::Map(map)) => match map.get("inumber") {
+                Some(Ipld::Link(inumber_cid)) => Some(*inumber_cid),
+                _ => None,
+            },
+            _ => None,
+        })
     }
 }
 
@@ -272,16 +671,19 @@ where
         )
         .await?;
 
-        Ok(Self(Hamt {
-            version: self.0.version.clone(),
-            root: merge_node,
-        }))
+        Ok(Self(
+            Hamt {
+                version: self.0.version.clone(),
+                root: merge_node,
+            },
+            Cell::default(),
+        ))
     }
 }
 
 impl Default for PrivateForest {
     fn default() -> Self {
-        Self(Hamt::new())
+        Self(Hamt::new(), Cell::default())
     }
 }
 
@@ -301,7 +703,77 @@ impl<'de> Deserialize<'de> for PrivateForest {
     where
         D: Deserializer<'de>,
     {
-        Hamt::deserialize(deserializer).map(Self)
+        Hamt::deserialize(deserializer).map(|hamt| Self(hamt, Cell::default()))
+    }
+}
+
+impl SharedForest {
+    /// Wraps a forest for sharing.
+    pub fn new(forest: Rc<PrivateForest>) -> Self {
+        Self(Mutex::new(forest))
+    }
+
+    /// Adds new encrypted values at the given key, once no other mutation is running.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use libipld::Cid;
+    /// use sha3::Sha3_256;
+    /// use wnfs::{
+    ///     private::{PrivateForest, SharedForest},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    ///     hamt::Hasher,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let forest = SharedForest::new(Rc::new(PrivateForest::new()));
+    ///     let name = Namefilter::default();
+    ///     let name_hash = Sha3_256::hash(&name.as_bytes());
+    ///
+    ///     let (first, second) = futures::join!(
+    ///         forest.put_encrypted(name.clone(), [Cid::default()], store),
+    ///         forest.get_encrypted(&name_hash, store),
+    ///     );
+    ///     first.unwrap();
+    ///     second.unwrap();
+    ///
+    ///     let values = forest.get_encrypted(&name_hash, store).await.unwrap();
+    ///     assert_eq!(values, Some([Cid::default()].into()));
+    /// }
+    /// ```
+    pub async fn put_encrypted(
+        &self,
+        name: Namefilter,
+        values: impl IntoIterator<Item = Cid>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let mut forest = self.0.lock().await;
+        forest.put_encrypted(name, values, store).await
+    }
+
+    /// Gets the encrypted values at the given key.
+    pub async fn get_encrypted(
+        &self,
+        name_hash: &HashOutput,
+        store: &impl BlockStore,
+    ) -> Result<Option<BTreeSet<Cid>>> {
+        let forest = self.0.lock().await;
+        Ok(forest.get_encrypted(name_hash, store).await?.cloned())
+    }
+
+    /// Returns the forest as it is after all mutations that finished so far.
+    pub async fn snapshot(&self) -> Rc<PrivateForest> {
+        Rc::clone(&*self.0.lock().await)
+    }
+
+    /// Returns the wrapped forest.
+    pub fn into_inner(self) -> Rc<PrivateForest> {
+        self.0.into_inner()
     }
 }
 
@@ -312,13 +784,18 @@ impl<'de> Deserialize<'de> for PrivateForest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::private::PrivateDirectory;
+    use crate::private::{PrivateDirectory, PrivateFile};
     use chrono::Utc;
     use helper::*;
+    use libipld::Ipld;
     use proptest::test_runner::{RngAlgorithm, TestRng};
-    use std::rc::Rc;
+    use std::{
+        borrow::Cow,
+        collections::{BTreeMap, HashSet},
+        rc::Rc,
+    };
     use wnfs_common::MemoryBlockStore;
-    use wnfs_hamt::{HashNibbles, Node};
+    use wnfs_hamt::{ChangeType, HashNibbles};
 
     mod helper {
         use libipld::{Cid, Multihash};
@@ -522,13 +999,15 @@ mod tests {
                 .unwrap();
         }
 
-        let main_forest = PrivateForest(Hamt::<Namefilter, BTreeSet<Cid>, _>::with_root(
-            Rc::clone(main_node),
-        ));
+        let main_forest = PrivateForest(
+            Hamt::<Namefilter, BTreeSet<Cid>, _>::with_root(Rc::clone(main_node)),
+            Cell::default(),
+        );
 
-        let other_forest = PrivateForest(Hamt::<Namefilter, BTreeSet<Cid>, _>::with_root(
-            Rc::clone(other_node),
-        ));
+        let other_forest = PrivateForest(
+            Hamt::<Namefilter, BTreeSet<Cid>, _>::with_root(Rc::clone(other_node)),
+            Cell::default(),
+        );
 
         let merge_forest = main_forest.merge(&other_forest, store).await.unwrap();
 
@@ -548,4 +1027,460 @@ mod tests {
             }
         }
     }
+
+    #[async_std::test]
+    async fn merged_forest_keeps_concurrent_revisions_at_a_shared_label() {
+        let store = &MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let base_forest = &mut Rc::new(PrivateForest::new());
+
+        let base = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        base.store(base_forest, store, rng).await.unwrap();
+
+        // Two peers each write the next revision of the same directory into their own forest.
+        let mut peers = vec![];
+        for name in ["ours", "theirs"] {
+            let forest = &mut Rc::clone(base_forest);
+            let dir = &mut Rc::clone(base);
+            dir.mkdir(&[name.into()], true, Utc::now(), forest, store, rng)
+                .await
+                .unwrap();
+            let private_ref = dir.store(forest, store, rng).await.unwrap();
+            peers.push((
+                Rc::clone(forest),
+                private_ref,
+                PrivateNode::Dir(Rc::clone(dir)),
+            ));
+        }
+
+        let (ours_forest, ours_ref, ours_node) = &peers[0];
+        let (theirs_forest, theirs_ref, theirs_node) = &peers[1];
+        assert_eq!(ours_ref.saturated_name_hash, theirs_ref.saturated_name_hash);
+        assert!(PrivateNode::load(theirs_ref, ours_forest, store)
+            .await
+            .is_err());
+
+        let merged = ours_forest.merge(theirs_forest, store).await.unwrap();
+
+        let ours = PrivateNode::load(ours_ref, &merged, store).await.unwrap();
+        let theirs = PrivateNode::load(theirs_ref, &merged, store).await.unwrap();
+
+        assert_eq!(&ours, ours_node);
+        assert_eq!(&theirs, theirs_node);
+    }
+
+    #[async_std::test]
+    async fn diff_reports_added_removed_and_modified_labels() {
+        let store = &MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let labels = (0..4)
+            .map(|_| generate_saturated_name_hash(rng))
+            .collect::<Vec<_>>();
+        let cids = (0..5).map(|_| generate_cid(rng)).collect::<Vec<_>>();
+
+        let main_forest = &mut Rc::new(PrivateForest::new());
+        for (label, cid) in labels.iter().zip(&cids).take(3) {
+            main_forest
+                .put_encrypted(label.clone(), [*cid], store)
+                .await
+                .unwrap();
+        }
+
+        // Shares the first label as is, adds a value to the second one,
+        // lacks the third one and has a fourth of its own.
+        let other_forest = &mut Rc::new(PrivateForest::new());
+        for (label, values) in [
+            (&labels[0], vec![cids[0]]),
+            (&labels[1], vec![cids[1], cids[3]]),
+            (&labels[3], vec![cids[4]]),
+        ] {
+            other_forest
+                .put_encrypted(label.clone(), values, store)
+                .await
+                .unwrap();
+        }
+
+        let mut changes = main_forest.diff(other_forest, store).await.unwrap();
+        changes.sort_by_key(|change| {
+            labels
+                .iter()
+                .position(|label| label == &change.key)
+                .unwrap()
+        });
+
+        assert_eq!(
+            changes,
+            vec![
+                ForestChange {
+                    r#type: ChangeType::Modify,
+                    key: labels[1].clone(),
+                    value1: Some(BTreeSet::from([cids[1]])),
+                    value2: Some(BTreeSet::from([cids[1], cids[3]])),
+                },
+                ForestChange {
+                    r#type: ChangeType::Add,
+                    key: labels[2].clone(),
+                    value1: Some(BTreeSet::from([cids[2]])),
+                    value2: None,
+                },
+                ForestChange {
+                    r#type: ChangeType::Remove,
+                    key: labels[3].clone(),
+                    value1: Some(BTreeSet::from([cids[4]])),
+                    value2: None,
+                },
+            ]
+        );
+    }
+
+    #[async_std::test]
+    async fn iter_yields_each_label_once_with_its_values() {
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let mut private_refs = vec![];
+        for name in ["a", "b", "c", "d", "e"] {
+            dir.mkdir(&[name.into()], true, Utc::now(), forest, store, rng)
+                .await
+                .unwrap();
+            private_refs.push(dir.store(forest, store, rng).await.unwrap());
+        }
+
+        let entries = forest.iter(store).try_collect::<Vec<_>>().await.unwrap();
+
+        let labels = entries
+            .iter()
+            .map(|(label, _)| label.clone())
+            .collect::<HashSet<_>>();
+        assert_eq!(labels.len(), entries.len());
+        assert_eq!(entries, forest.entries(store).await.unwrap());
+
+        for (label, cids) in &entries {
+            let stored = forest
+                .get_encrypted(&Sha3_256::hash(label), store)
+                .await
+                .unwrap();
+            assert_eq!(stored, Some(cids));
+        }
+
+        for private_ref in private_refs {
+            assert!(entries.iter().any(|(label, cids)| {
+                Sha3_256::hash(label) == private_ref.saturated_name_hash
+                    && cids.contains(&private_ref.content_cid)
+            }));
+        }
+    }
+
+    #[async_std::test]
+    async fn reachable_size_counts_shared_blocks_once() {
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        dir.write(
+            &["file.txt".into()],
+            true,
+            Utc::now(),
+            b"Hello".to_vec(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+        let old_ref = dir.store(forest, store, rng).await.unwrap();
+
+        // Each node has a content block and a header linking to three parts,
+        // and the file has one more block for its content.
+        let (count, bytes) = forest
+            .reachable_size(std::slice::from_ref(&old_ref), store)
+            .await
+            .unwrap();
+        let mut expected_bytes = 0;
+        for cid in store.list_blocks().await.unwrap() {
+            expected_bytes += store.get_block(&cid).await.unwrap().len() as u64;
+        }
+
+        assert_eq!(count, 11);
+        assert_eq!(bytes, expected_bytes);
+
+        dir.mkdir(&["docs".into()], true, Utc::now(), forest, store, rng)
+            .await
+            .unwrap();
+        let new_ref = dir.store(forest, store, rng).await.unwrap();
+
+        // The new revision shares the file with the old one
+        let (count, _) = forest
+            .reachable_size(&[old_ref.clone(), new_ref.clone(), new_ref], store)
+            .await
+            .unwrap();
+
+        assert_eq!(count, 21);
+    }
+
+    #[async_std::test]
+    async fn contains_ref_only_finds_stored_revisions() {
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let stored_ref = dir.store(forest, store, rng).await.unwrap();
+
+        assert!(forest.contains_ref(&stored_ref, store).await.unwrap());
+
+        // Same label, but a content CID that was never stored under it.
+        let mut unknown_ref = stored_ref.clone();
+        unknown_ref.content_cid = generate_cid(rng);
+        assert!(!forest.contains_ref(&unknown_ref, store).await.unwrap());
+
+        // A label that isn't in the forest at all.
+        let never_stored = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+        let never_stored_ref = never_stored
+            .header
+            .derive_revision_ref()
+            .as_private_ref(stored_ref.content_cid);
+        assert!(!forest.contains_ref(&never_stored_ref, store).await.unwrap());
+    }
+
+    #[async_std::test]
+    async fn putting_a_different_node_header_at_a_label_is_a_collision() {
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let file = PrivateNode::File(Rc::new(PrivateFile::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        )));
+        let private_ref = file.store(forest, store, rng).await.unwrap();
+        let label = file.get_header().get_saturated_name();
+        let header_cid = file.get_header().store(store).await.unwrap();
+
+        // A revision of the same node with a header in the unversioned format has a different
+        // header CID, but the same inumber, so stacking it is fine.
+        let Ipld::Map(mut legacy_header) =
+            dagcbor::decode(&store.get_block(&header_cid).await.unwrap()).unwrap()
+        else {
+            panic!("Expected header map");
+        };
+        legacy_header.remove("version");
+        let legacy_header_cid = store
+            .put_block(
+                dagcbor::encode(&Ipld::Map(legacy_header)).unwrap(),
+                libipld::IpldCodec::Raw,
+            )
+            .await
+            .unwrap();
+        assert_ne!(legacy_header_cid, header_cid);
+        forest
+            .put_node(
+                label.clone(),
+                legacy_header_cid,
+                private_ref.content_cid,
+                store,
+            )
+            .await
+            .unwrap();
+
+        let other = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        let other_header_cid = other.header.store(store).await.unwrap();
+        let error = forest
+            .put_node(
+                label.clone(),
+                other_header_cid,
+                private_ref.content_cid,
+                store,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ForestError>(),
+            Some(ForestError::LabelCollision { existing, new })
+                if [header_cid, legacy_header_cid].contains(existing) && *new == other_header_cid
+        ));
+
+        let values = forest
+            .get_encrypted(&file.get_header().get_saturated_name_hash(), store)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(values.contains(&legacy_header_cid));
+        assert!(!values.contains(&other_header_cid));
+    }
+
+    #[async_std::test]
+    async fn compaction_keeps_live_entries_and_shrinks_the_hamt() {
+        let store = &MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        let mut live = Vec::new();
+        for _ in 0..20 {
+            let name = generate_saturated_name_hash(rng);
+            let cid = generate_cid(rng);
+            forest
+                .put_encrypted(name.clone(), [cid], store)
+                .await
+                .unwrap();
+            live.push((name, cid));
+        }
+
+        for _ in 0..200 {
+            let name = generate_saturated_name_hash(rng);
+            forest.put_encrypted(name, [], store).await.unwrap();
+        }
+
+        let compacted = forest.compact(store).await.unwrap();
+
+        for (name, cid) in live.iter() {
+            let name_hash = &Sha3_256::hash(&name.as_bytes());
+            let cids = compacted.get_encrypted(name_hash, store).await.unwrap();
+            assert_eq!(cids, Some(&BTreeSet::from([*cid])));
+        }
+
+        assert!(count_blocks(&compacted).await < count_blocks(forest).await);
+    }
+
+    /// Counts the HAMT nodes of a forest that hasn't been stored yet.
+    async fn count_blocks(forest: &PrivateForest) -> usize {
+        let store = &MemoryBlockStore::new();
+        forest.store(store).await.unwrap();
+        dagcbor::decode::<BTreeMap<String, Ipld>>(&dagcbor::encode(store).unwrap())
+            .unwrap()
+            .len()
+    }
+
+    #[async_std::test]
+    async fn flush_is_idempotent_and_reloadable() {
+        let store = &MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let mut refs = vec![];
+        for _ in 0..10 {
+            let dir = Rc::new(PrivateDirectory::new(
+                Namefilter::default(),
+                Utc::now(),
+                rng,
+            ));
+            refs.push(dir.store(forest, store, rng).await.unwrap());
+        }
+
+        let cid = forest.flush(store).await.unwrap();
+        let blocks_written = store.stats().unwrap().blocks_written;
+
+        assert_eq!(forest.flush(store).await.unwrap(), cid);
+        assert_eq!(store.stats().unwrap().blocks_written, blocks_written);
+
+        let loaded = PrivateForest::load(&cid, store).await.unwrap();
+        for private_ref in refs.iter() {
+            assert!(loaded.contains_ref(private_ref, store).await.unwrap());
+        }
+
+        assert_eq!(loaded.flush(store).await.unwrap(), cid);
+        assert_eq!(store.stats().unwrap().blocks_written, blocks_written);
+
+        let dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        dir.store(forest, store, rng).await.unwrap();
+        assert_ne!(forest.flush(store).await.unwrap(), cid);
+    }
+
+    /// Yields to other tasks before every read, so concurrent forest operations interleave.
+    #[derive(Default)]
+    struct YieldingBlockStore(MemoryBlockStore);
+
+    #[async_trait(?Send)]
+    impl BlockStore for YieldingBlockStore {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+            async_std::task::yield_now().await;
+            self.0.get_block(cid).await
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, codec: libipld::IpldCodec) -> Result<Cid> {
+            self.0.put_block(bytes, codec).await
+        }
+    }
+
+    #[async_std::test]
+    async fn shared_forest_serializes_concurrent_writers() {
+        let store = &YieldingBlockStore::default();
+        let label = |writer: &str, index: usize| {
+            let mut name = Namefilter::default();
+            name.add(&format!("{writer}/{index}"));
+            name
+        };
+
+        // Load the forest from the store, so that writes have to fetch HAMT nodes
+        let forest = &mut Rc::new(PrivateForest::new());
+        for index in 0..50 {
+            forest
+                .put_encrypted(label("setup", index), [Cid::default()], store)
+                .await
+                .unwrap();
+        }
+        let cid = forest.store(store).await.unwrap();
+        let forest = SharedForest::new(Rc::new(PrivateForest::load(&cid, store).await.unwrap()));
+
+        let common = label("common", 0);
+        let write = |writer: &'static str| {
+            let forest = &forest;
+            let common = common.clone();
+            async move {
+                let value = store
+                    .put_block(writer.as_bytes().to_vec(), libipld::IpldCodec::Raw)
+                    .await?;
+                for index in 0..20 {
+                    forest
+                        .put_encrypted(label(writer, index), [value], store)
+                        .await?;
+                }
+                forest.put_encrypted(common, [value], store).await?;
+                Ok::<_, anyhow::Error>(value)
+            }
+        };
+
+        let (first, second) = futures::join!(write("first"), write("second"));
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        for (writer, value) in [("first", first), ("second", second)] {
+            for index in 0..20 {
+                let name_hash = Sha3_256::hash(&label(writer, index).as_bytes());
+                let values = forest.get_encrypted(&name_hash, store).await.unwrap();
+                assert_eq!(values, Some(BTreeSet::from([value])));
+            }
+        }
+
+        let common_hash = Sha3_256::hash(&common.as_bytes());
+        let forest = forest.into_inner();
+        assert_eq!(
+            forest.get_encrypted(&common_hash, store).await.unwrap(),
+            Some(&BTreeSet::from([first, second]))
+        );
+    }
 }
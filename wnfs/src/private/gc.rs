@@ -0,0 +1,415 @@
+use super::{PrivateForest, PrivateNode, PrivateRef};
+use anyhow::Result;
+use async_trait::async_trait;
+use libipld::{Cid, Ipld, IpldCodec};
+use sha3::Sha3_256;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeSet, HashSet},
+    rc::Rc,
+};
+use wnfs_common::{dagcbor::DecodeLimits, BlockStore, HashOutput};
+use wnfs_hamt::Hasher;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The outcome of a garbage collection run.
+#[derive(Debug)]
+pub struct GcReport {
+    /// The forest with all unreachable entries removed. It has already been stored.
+    pub forest: Rc<PrivateForest>,
+    /// The CIDs of all blocks deleted from the store.
+    pub deleted: Vec<Cid>,
+    /// The total size in bytes of all deleted blocks.
+    pub bytes_freed: usize,
+}
+
+/// Records the CIDs of all blocks put through it.
+struct RecordingBlockStore<'a, B: BlockStore> {
+    inner: &'a B,
+    recorded: RefCell<Vec<Cid>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Deletes all blocks from the store that aren't reachable from the given roots.
+///
+/// This is a mark-and-sweep collection. The mark phase walks every node reachable from `roots` and
+/// builds a new forest containing only their entries. Everything the new forest and those nodes
+/// refer to is kept, and all other blocks the store lists are deleted.
+///
+/// Ratchets can't be walked backwards, so past revisions can't be told apart from unrelated nodes.
+/// With `preserve_history` set, every entry in the forest is kept instead, which preserves all
+/// revisions the forest holds and only collects blocks the forest doesn't refer to.
+///
+/// The store needs to support `list_blocks` and `delete_block`.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::private::{gc, PrivateDirectory, PrivateForest};
+/// use wnfs::common::{BlockStore, MemoryBlockStore};
+/// use wnfs::namefilter::Namefilter;
+/// use libipld::IpldCodec;
+/// use chrono::Utc;
+/// use rand::thread_rng;
+/// use std::rc::Rc;
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::new();
+///     let rng = &mut thread_rng();
+///     let forest = &mut Rc::new(PrivateForest::new());
+///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+///
+///     let orphan = store.put_block(b"orphan".to_vec(), IpldCodec::Raw).await.unwrap();
+///
+///     let report = gc(&[private_ref], forest, false, store).await.unwrap();
+///
+///     assert_eq!(report.deleted, vec![orphan]);
+/// }
+/// ```
+pub async fn gc(
+    roots: &[PrivateRef],
+    forest: &PrivateForest,
+    preserve_history: bool,
+    store: &impl BlockStore,
+) -> Result<GcReport> {
+    let mut entries = forest.entries(store).await?;
+    if !preserve_history {
+        let reachable = reachable_labels(roots, forest, store).await?;
+        entries.retain(|(name, _)| reachable.contains(&Sha3_256::hash(name)));
+    }
+
+    let values = entries
+        .iter()
+        .flat_map(|(_, cids)| cids.iter().cloned())
+        .collect::<Vec<_>>();
+
+    let recording_store = RecordingBlockStore::new(store);
+    let forest = forest.with_entries(entries, store).await?;
+    forest.store(&recording_store).await?;
+
+    let mut reachable = recording_store.into_recorded();
+    for cid in values {
+        reachable.insert(cid);
+        reachable.extend(header_links(&cid, store).await?);
+    }
+
+    let mut deleted = Vec::new();
+    let mut bytes_freed = 0;
+    for cid in store.list_blocks().await? {
+        if reachable.contains(&cid) {
+            continue;
+        }
+
+        bytes_freed += store.get_block(&cid).await?.len();
+        store.delete_block(&cid).await?;
+        deleted.push(cid);
+    }
+
+    Ok(GcReport {
+        forest,
+        deleted,
+        bytes_freed,
+    })
+}
+
+/// Collects the forest labels of all nodes reachable from the given roots.
+pub(crate) async fn reachable_labels(
+    roots: &[PrivateRef],
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+) -> Result<HashSet<HashOutput>> {
+    let mut labels = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+    for root in roots {
+        stack.push(PrivateNode::load(root, forest, store).await?);
+    }
+
+    while let Some(node) = stack.pop() {
+        if let Some(private_ref) = node.get_private_ref() {
+            if !visited.insert(private_ref.content_cid) {
+                continue;
+            }
+        }
+
+        labels.insert(node.get_header().get_saturated_name_hash());
+        match &node {
+            PrivateNode::File(file) => labels.extend(file.get_shard_label_hashes()),
+            PrivateNode::Dir(dir) => {
+                for link in dir.content.entries.values() {
+                    stack.push(link.resolve_node(forest, store).await?.clone());
+                }
+            }
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Returns the blocks a forest value links to, if `cid` refers to a DagCbor map.
+///
+/// These are node headers and the blocks holding the keys of convergently encrypted file content.
+/// Any other block has no links. Failing to read the block is an error, since the blocks it links
+/// to would go unnoticed otherwise.
+pub(crate) async fn header_links(cid: &Cid, store: &impl BlockStore) -> Result<Vec<Cid>> {
+    let bytes = store.get_block(cid).await?;
+    let Ok(Ipld::Map(map)) = serde_ipld_dagcbor::from_slice::<Ipld>(&bytes) else {
+        return Ok(vec![]);
+    };
+
+    Ok(map
+        .values()
+        .filter_map(|value| match value {
+            Ipld::Link(cid) => Some(*cid),
+            _ => None,
+        })
+        .collect())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a, B: BlockStore> RecordingBlockStore<'a, B> {
+    fn new(inner: &'a B) -> Self {
+        Self {
+            inner,
+            recorded: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn into_recorded(self) -> BTreeSet<Cid> {
+        self.recorded.into_inner().into_iter().collect()
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for RecordingBlockStore<'_, B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        self.inner.get_block(cid).await
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let cid = self.inner.put_block(bytes, codec).await?;
+        self.recorded.borrow_mut().push(cid);
+        Ok(cid)
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private::PrivateDirectory;
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+    use wnfs_common::MemoryBlockStore;
+    use wnfs_namefilter::Namefilter;
+
+    struct TestSetup {
+        store: MemoryBlockStore,
+        forest: Rc<PrivateForest>,
+        old_ref: PrivateRef,
+        new_ref: PrivateRef,
+        orphan: Cid,
+    }
+
+    impl TestSetup {
+        async fn new() -> Self {
+            let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+            let store = MemoryBlockStore::new();
+            let forest = &mut Rc::new(PrivateForest::new());
+            let root_dir = &mut Rc::new(PrivateDirectory::new(
+                Namefilter::default(),
+                Utc::now(),
+                rng,
+            ));
+
+            let a = &["a.txt".into()];
+            let b = &["b.txt".into()];
+            root_dir
+                .write(a, true, Utc::now(), b"a".to_vec(), forest, &store, rng)
+                .await
+                .unwrap();
+            let old_ref = root_dir.store(forest, &store, rng).await.unwrap();
+
+            root_dir.rm(a, true, forest, &store).await.unwrap();
+            root_dir
+                .write(b, true, Utc::now(), b"b".to_vec(), forest, &store, rng)
+                .await
+                .unwrap();
+            let new_ref = root_dir.store(forest, &store, rng).await.unwrap();
+
+            let orphan = store
+                .put_block(b"orphan".to_vec(), IpldCodec::Raw)
+                .await
+                .unwrap();
+
+            Self {
+                store,
+                forest: Rc::clone(forest),
+                old_ref,
+                new_ref,
+                orphan,
+            }
+        }
+    }
+
+    /// Fails reading one particular block, as a flaky store would.
+    struct FlakyBlockStore<'a> {
+        inner: &'a MemoryBlockStore,
+        failing: Cid,
+    }
+
+    #[async_trait(?Send)]
+    impl BlockStore for FlakyBlockStore<'_> {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+            if *cid == self.failing {
+                anyhow::bail!("Connection reset");
+            }
+
+            self.inner.get_block(cid).await
+        }
+
+        async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+            self.inner.put_block(bytes, codec).await
+        }
+
+        async fn delete_block(&self, cid: &Cid) -> Result<()> {
+            self.inner.delete_block(cid).await
+        }
+
+        async fn list_blocks(&self) -> Result<Vec<Cid>> {
+            self.inner.list_blocks().await
+        }
+    }
+
+    async fn read(
+        private_ref: &PrivateRef,
+        path: &str,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Vec<u8>> {
+        PrivateNode::load(private_ref, forest, store)
+            .await?
+            .as_dir()?
+            .read(&[path.into()], false, forest, store)
+            .await
+    }
+
+    #[test(async_std::test)]
+    async fn gc_deletes_everything_unreachable_from_roots() {
+        let TestSetup {
+            store,
+            forest,
+            old_ref,
+            new_ref,
+            orphan,
+        } = TestSetup::new().await;
+        let store = &store;
+
+        let report = gc(std::slice::from_ref(&new_ref), &forest, false, store)
+            .await
+            .unwrap();
+
+        assert!(report.deleted.contains(&orphan));
+        assert!(report.deleted.contains(&old_ref.content_cid));
+        assert!(report.bytes_freed > 0);
+        assert_eq!(
+            read(&new_ref, "b.txt", &report.forest, store)
+                .await
+                .unwrap(),
+            b"b"
+        );
+        assert!(store.get_block(&old_ref.content_cid).await.is_err());
+
+        // Collecting again finds nothing left to delete
+        let report = gc(&[new_ref], &report.forest, false, store).await.unwrap();
+        assert!(report.deleted.is_empty());
+        assert_eq!(report.bytes_freed, 0);
+    }
+
+    #[test(async_std::test)]
+    async fn gc_preserving_history_keeps_past_revisions() {
+        let TestSetup {
+            store,
+            forest,
+            old_ref,
+            new_ref,
+            orphan,
+        } = TestSetup::new().await;
+        let store = &store;
+
+        let report = gc(std::slice::from_ref(&new_ref), &forest, true, store)
+            .await
+            .unwrap();
+
+        assert_eq!(report.deleted, vec![orphan]);
+        assert_eq!(report.bytes_freed, b"orphan".len());
+        assert_eq!(
+            read(&old_ref, "a.txt", &report.forest, store)
+                .await
+                .unwrap(),
+            b"a"
+        );
+        assert_eq!(
+            read(&new_ref, "b.txt", &report.forest, store)
+                .await
+                .unwrap(),
+            b"b"
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn gc_fails_without_deleting_when_a_header_cannot_be_read() {
+        let TestSetup {
+            store,
+            forest,
+            new_ref,
+            orphan,
+            ..
+        } = TestSetup::new().await;
+        let header_cid = *forest
+            .get_encrypted(&new_ref.saturated_name_hash, &store)
+            .await
+            .unwrap()
+            .unwrap()
+            .iter()
+            .find(|&&cid| cid != new_ref.content_cid)
+            .unwrap();
+        let blocks_before = store.list_blocks().await.unwrap();
+
+        let flaky_store = &FlakyBlockStore {
+            inner: &store,
+            failing: header_cid,
+        };
+        let result = gc(std::slice::from_ref(&new_ref), &forest, true, flaky_store).await;
+
+        assert!(result.is_err());
+        assert!(store.get_block(&orphan).await.is_ok());
+        for cid in blocks_before {
+            assert!(store.has_block(&cid).await.unwrap());
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use super::{AesKey, AUTHENTICATION_TAG_SIZE, NONCE_SIZE};
+use crate::error::AesError;
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use anyhow::Result;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use wnfs_common::MAX_BLOCK_SIZE;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The size of the nonce XChaCha20-Poly1305 uses.
+const XNONCE_SIZE: usize = 24;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// An authenticated symmetric cipher that content can be encrypted with.
+///
+/// Ciphertexts from [`SnapshotKey::encrypt_with`] start with the cipher's id, which
+/// [`SnapshotKey::decrypt`] uses to pick the cipher to decrypt with.
+///
+/// The trait is sealed: only the built-in ciphers implement it, so that every stored
+/// ciphertext can be decrypted without being told its cipher up front.
+/// Use [`CipherAlgorithm`] to pick one for a file.
+///
+/// [`SnapshotKey::encrypt_with`]: crate::private::SnapshotKey::encrypt_with
+/// [`SnapshotKey::decrypt`]: crate::private::SnapshotKey::decrypt
+pub trait Cipher: sealed::Sealed {
+    /// The byte identifying this cipher. It has to be unique among all ciphers.
+    fn id(&self) -> u8;
+
+    /// Encrypts the data with a fresh nonce, which is prepended to the ciphertext.
+    fn encrypt(&self, key: &AesKey, data: &[u8], rng: &mut dyn RngCore) -> Result<Vec<u8>>;
+
+    /// Decrypts a ciphertext produced by `encrypt`.
+    fn decrypt(&self, key: &AesKey, ciphertext: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// AES-256-GCM with a 12-byte nonce. This is the default cipher.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aes256GcmCipher;
+
+/// XChaCha20-Poly1305 with a 24-byte nonce.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XChaCha20Poly1305Cipher;
+
+/// Selects the built-in cipher a file's content gets encrypted with.
+///
+/// See [`PrivateFileConfig::cipher`](crate::private::PrivateFileConfig::cipher).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CipherAlgorithm {
+    /// [`Aes256GcmCipher`]. Its ciphertexts stay untagged, like they always were.
+    #[default]
+    Aes256Gcm,
+    /// [`XChaCha20Poly1305Cipher`].
+    XChaCha20Poly1305,
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Aes256GcmCipher {}
+    impl Sealed for super::XChaCha20Poly1305Cipher {}
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Looks up a built-in cipher by its id.
+pub(crate) fn cipher_by_id(id: u8) -> Option<&'static dyn Cipher> {
+    [&Aes256GcmCipher as &dyn Cipher, &XChaCha20Poly1305Cipher]
+        .into_iter()
+        .find(|cipher| cipher.id() == id)
+}
+
+/// Splits a nonce of given size off the start of a ciphertext.
+fn split_nonce(ciphertext: &[u8], nonce_size: usize) -> Result<(&[u8], &[u8])> {
+    if ciphertext.len() < nonce_size {
+        Err(AesError::UnableToDecrypt(format!(
+            "Ciphertext of {} bytes is shorter than its {nonce_size} byte nonce",
+            ciphertext.len()
+        )))?;
+    }
+
+    Ok(ciphertext.split_at(nonce_size))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl CipherAlgorithm {
+    /// Returns the cipher this selects.
+    pub fn cipher(self) -> &'static dyn Cipher {
+        match self {
+            Self::Aes256Gcm => &Aes256GcmCipher,
+            Self::XChaCha20Poly1305 => &XChaCha20Poly1305Cipher,
+        }
+    }
+
+    /// Returns the largest plaintext whose ciphertext still fits into a block.
+    pub(crate) fn max_block_content_size(self) -> usize {
+        let overhead = match self {
+            Self::Aes256Gcm => NONCE_SIZE,
+            // The ciphertext is tagged with the cipher's id.
+            Self::XChaCha20Poly1305 => 1 + XNONCE_SIZE,
+        };
+
+        MAX_BLOCK_SIZE - overhead - AUTHENTICATION_TAG_SIZE
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Cipher for Aes256GcmCipher {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn encrypt(&self, key: &AesKey, data: &[u8], rng: &mut dyn RngCore) -> Result<Vec<u8>> {
+        let mut nonce = Nonce::default();
+        rng.fill_bytes(&mut nonce);
+
+        let ciphertext = Aes256Gcm::new(key.as_bytes().into())
+            .encrypt(&nonce, data)
+            .map_err(|e| AesError::UnableToEncrypt(format!("{e}")))?;
+
+        Ok([nonce.to_vec(), ciphertext].concat())
+    }
+
+    fn decrypt(&self, key: &AesKey, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let (nonce, data) = split_nonce(ciphertext, NONCE_SIZE)?;
+
+        Ok(Aes256Gcm::new(key.as_bytes().into())
+            .decrypt(Nonce::from_slice(nonce), data)
+            .map_err(|e| AesError::UnableToDecrypt(format!("{e}")))?)
+    }
+}
+
+impl Cipher for XChaCha20Poly1305Cipher {
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn encrypt(&self, key: &AesKey, data: &[u8], rng: &mut dyn RngCore) -> Result<Vec<u8>> {
+        let mut nonce = XNonce::default();
+        rng.fill_bytes(&mut nonce);
+
+        let ciphertext = XChaCha20Poly1305::new(key.as_bytes().into())
+            .encrypt(&nonce, data)
+            .map_err(|e| AesError::UnableToEncrypt(format!("{e}")))?;
+
+        Ok([nonce.to_vec(), ciphertext].concat())
+    }
+
+    fn decrypt(&self, key: &AesKey, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let (nonce, data) = split_nonce(ciphertext, XNONCE_SIZE)?;
+
+        Ok(XChaCha20Poly1305::new(key.as_bytes().into())
+            .decrypt(XNonce::from_slice(nonce), data)
+            .map_err(|e| AesError::UnableToDecrypt(format!("{e}")))?)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private::SnapshotKey;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+
+    #[test]
+    fn xchacha_ciphertexts_can_be_decrypted() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let key = SnapshotKey::from([7; 32]);
+
+        let ciphertext = key
+            .encrypt_with(&XChaCha20Poly1305Cipher, b"Hello World!", rng)
+            .unwrap();
+
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), b"Hello World!");
+        assert!(SnapshotKey::from([8; 32]).decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn cipher_id_is_persisted_and_honored() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let key = SnapshotKey::from([7; 32]);
+
+        let ciphertext = key
+            .encrypt_with(&XChaCha20Poly1305Cipher, b"Hello World!", rng)
+            .unwrap();
+        let (id, body) = ciphertext.split_first().unwrap();
+
+        assert_eq!(*id, XChaCha20Poly1305Cipher.id());
+        assert_eq!(body.len(), XNONCE_SIZE + b"Hello World!".len() + 16);
+        assert!(Aes256GcmCipher.decrypt(&key.0, body).is_err());
+        assert_eq!(
+            XChaCha20Poly1305Cipher.decrypt(&key.0, body).unwrap(),
+            b"Hello World!"
+        );
+
+        // Claiming a different cipher makes decryption fail
+        let mut relabeled = ciphertext.clone();
+        relabeled[0] = Aes256GcmCipher.id();
+        assert!(key.decrypt(&relabeled).is_err());
+    }
+
+    #[test]
+    fn default_encryption_stays_untagged_aes() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let key = SnapshotKey::from([7; 32]);
+
+        let ciphertext = key.encrypt(b"Hello World!", rng).unwrap();
+        let tagged = key
+            .encrypt_with(&Aes256GcmCipher, b"Hello World!", rng)
+            .unwrap();
+
+        assert_eq!(
+            Aes256GcmCipher.decrypt(&key.0, &ciphertext).unwrap(),
+            b"Hello World!"
+        );
+        assert_eq!(key.decrypt(&tagged).unwrap(), b"Hello World!");
+        assert!(key.decrypt(&[1, 2, 3]).is_err());
+    }
+}
@@ -5,7 +5,15 @@ use anyhow::anyhow;
 use anyhow::Result;
 use async_trait::async_trait;
 #[cfg(test)]
-use rsa::{traits::PublicKeyParts, BigUint, Oaep};
+use rand_core::CryptoRngCore;
+#[cfg(test)]
+use rsa::{
+    pkcs8::EncodePublicKey,
+    traits::{PrivateKeyParts, PublicKeyParts},
+    BigUint, Oaep,
+};
+#[cfg(test)]
+use serde::{Deserialize, Serialize};
 #[cfg(test)]
 use sha2::Sha256;
 
@@ -32,7 +40,7 @@ pub const PUBLIC_KEY_EXPONENT: u64 = 65537;
 pub trait ExchangeKey {
     /// Creates an RSA public key from the public key modulus.
     ///
-    /// The exponent is expected to be of the value [`PUBLIC_KEY_EXPONENT`](constant.PUBLIC_KEY_EXPONENT.html) constant.
+    /// The modulus is expected in little-endian byte order. The exponent is expected to be of the value [`PUBLIC_KEY_EXPONENT`](constant.PUBLIC_KEY_EXPONENT.html) constant.
     async fn from_modulus(modulus: &[u8]) -> Result<Self>
     where
         Self: Sized;
@@ -58,32 +66,169 @@ pub struct RsaPublicKey(rsa::RsaPublicKey);
 #[derive(Debug, Clone)]
 pub struct RsaPrivateKey(rsa::RsaPrivateKey);
 
+/// An RSA key in JSON Web Key format.
+///
+/// All numbers are big-endian and base64url encoded without padding. The private exponent `d` and
+/// the primes `p` and `q` are only set for private keys.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RsaJwk {
+    pub kty: String,
+    pub n: String,
+    pub e: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+fn encode_jwk_number(number: &BigUint) -> String {
+    base64::encode_config(number.to_bytes_be(), base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(test)]
+fn decode_jwk_number(encoded: &str) -> Result<BigUint> {
+    let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
 
 #[cfg(test)]
 impl RsaPublicKey {
-    /// Gets the public key modulus.
+    /// Gets the public key modulus in little-endian byte order, as `from_modulus` expects it.
+    ///
+    /// Most other tooling, like JWKs or `SubjectPublicKeyInfo`, uses big-endian moduli. Use
+    /// `get_public_key_modulus_be` for those.
     pub fn get_public_key_modulus(&self) -> Result<Vec<u8>> {
         Ok(self.0.n().to_bytes_le())
     }
+
+    /// Gets the public key modulus in big-endian byte order, as `from_modulus_be` expects it.
+    pub fn get_public_key_modulus_be(&self) -> Result<Vec<u8>> {
+        Ok(self.0.n().to_bytes_be())
+    }
+
+    /// Creates an RSA public key from a big-endian public key modulus.
+    ///
+    /// The exponent is expected to be of the value [`PUBLIC_KEY_EXPONENT`](constant.PUBLIC_KEY_EXPONENT.html) constant.
+    pub fn from_modulus_be(modulus: &[u8]) -> Result<Self> {
+        let n = BigUint::from_bytes_be(modulus);
+        let e = BigUint::from(PUBLIC_KEY_EXPONENT);
+
+        Ok(Self(
+            rsa::RsaPublicKey::new(n, e).map_err(|e| RsaError::InvalidPublicKey(anyhow!(e)))?,
+        ))
+    }
+
+    /// Encodes the public key as a DER `SubjectPublicKeyInfo` in standard base64.
+    pub fn to_spki_base64(&self) -> Result<String> {
+        let der = self
+            .0
+            .to_public_key_der()
+            .map_err(|e| RsaError::InvalidPublicKey(anyhow!(e)))?;
+
+        Ok(base64::encode(der.as_bytes()))
+    }
+
+    /// Creates an RSA public key from its JWK.
+    pub fn from_jwk(jwk: &RsaJwk) -> Result<Self> {
+        if jwk.kty != "RSA" {
+            Err(RsaError::InvalidPublicKey(anyhow!(
+                "Expected JWK key type RSA, got {}",
+                jwk.kty
+            )))?;
+        }
+
+        let n = decode_jwk_number(&jwk.n).map_err(RsaError::InvalidPublicKey)?;
+        let e = decode_jwk_number(&jwk.e).map_err(RsaError::InvalidPublicKey)?;
+
+        Ok(Self(
+            rsa::RsaPublicKey::new(n, e).map_err(|e| RsaError::InvalidPublicKey(anyhow!(e)))?,
+        ))
+    }
+
+    /// Exports the public key as a JWK.
+    pub fn to_jwk(&self) -> RsaJwk {
+        RsaJwk {
+            kty: "RSA".into(),
+            n: encode_jwk_number(self.0.n()),
+            e: encode_jwk_number(self.0.e()),
+            d: None,
+            p: None,
+            q: None,
+        }
+    }
 }
 
 #[cfg(test)]
 impl RsaPrivateKey {
     /// Constructs a new 2048-bit RSA private key.
     pub fn new() -> Result<Self> {
-        Ok(Self(rsa::RsaPrivateKey::new(
-            &mut rand::thread_rng(),
-            RSA_KEY_SIZE,
-        )?))
+        Self::from_rng(&mut rand::thread_rng())
+    }
+
+    /// Constructs a new 2048-bit RSA private key from the given random number generator.
+    ///
+    /// Two identically seeded generators yield the same key.
+    pub fn from_rng(rng: &mut impl CryptoRngCore) -> Result<Self> {
+        Ok(Self(rsa::RsaPrivateKey::new(rng, RSA_KEY_SIZE)?))
     }
 
     /// Gets the public key.
     pub fn get_public_key(&self) -> RsaPublicKey {
         RsaPublicKey(self.0.to_public_key())
     }
+
+    /// Creates an RSA private key from its JWK, which has to include `d`, `p` and `q`.
+    pub fn from_jwk(jwk: &RsaJwk) -> Result<Self> {
+        let public_key = RsaPublicKey::from_jwk(jwk)?;
+
+        let decode_private = |name: &str, value: &Option<String>| {
+            let value = value
+                .as_ref()
+                .ok_or_else(|| anyhow!("JWK is missing the private field {name}"))?;
+            decode_jwk_number(value)
+        };
+
+        let d = decode_private("d", &jwk.d).map_err(RsaError::InvalidPrivateKey)?;
+        let p = decode_private("p", &jwk.p).map_err(RsaError::InvalidPrivateKey)?;
+        let q = decode_private("q", &jwk.q).map_err(RsaError::InvalidPrivateKey)?;
+
+        let key = rsa::RsaPrivateKey::from_components(
+            public_key.0.n().clone(),
+            public_key.0.e().clone(),
+            d,
+            vec![p, q],
+        )
+        .map_err(|e| RsaError::InvalidPrivateKey(anyhow!(e)))?;
+
+        key.validate()
+            .map_err(|e| RsaError::InvalidPrivateKey(anyhow!(e)))?;
+
+        Ok(Self(key))
+    }
+
+    /// Exports the private key as a JWK.
+    pub fn to_jwk(&self) -> RsaJwk {
+        let primes = self.0.primes();
+
+        RsaJwk {
+            d: Some(encode_jwk_number(self.0.d())),
+            p: Some(encode_jwk_number(&primes[0])),
+            q: Some(encode_jwk_number(&primes[1])),
+            ..self.get_public_key().to_jwk()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -124,6 +269,16 @@ impl PrivateKey for RsaPrivateKey {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const KNOWN_PRIVATE_JWK: &str = r#"{
+        "kty": "RSA",
+        "n": "1XXorB72Am-RWtHPgLUeMVqxd-T9c6jy1j7cvEnlpM-UwC6C9vQO4kA8HM2dv81pMi_k403LNqVGm2cvvMO3lA1eszu3TaiD8OF3RdxDtb37TWY-2m95n3uie7WPwvTVZq5E48ZzfNreoyjXcrufRHcXX57MHh1Nx2oja7HPF4iXCyLECGJUDuWVTy0ojEYh5j9CyvOQz-wMI5n7Q-Ok6PzpLfofZi8j_f9SZKXtw_pTEJmUFLGL6cNNr7Kd8cH_SLficMs6PbjysCECSLIEmmp5HZHIRG1UY5yV1h30ZieXtrd58ZyetJNqFwhTrMqjR1JLyRIHr_8rak7qLO8igw",
+        "e": "AQAB",
+        "d": "qg9J0vbHn6Eq-GP3IN8qsr9Ca_ZvRdnvvWhPi2KEJg2KwKFNjVlbEj2Cpp_ZcZlAmc82VzD_GQYLfntyuLxkYPtV2w1UN_ZtR1cZIvscpoo4z60JnhT5nGlvRYechQFxkmB8Wcsc7vegL_S10J5RgMYwROnKTKOj959DoOUDzAZ9UDsDb995SwWJfkAZYU6V4AhTY_2dqs-GqKbbsu7C92MuQ4Gh1hfKfANS4r1RP9a5rL9g5tB2jNGUvCXRCSrIKtMQAwXjx5ml16OHN8lMWIn8ugpj85fV9e9Ovdnl7C9xZ6zcQDai_PmYlygOT-4T_XxeCfNCQ7wRyBgO0YiHIQ",
+        "p": "8F6TLSX0e_nnS-4cE5brTICzr7KCuSLOm4En3cvr-EbS0UiF2NxITJNMIPVvmEXByDqEeS3I1cArFF-4c_-ztSkxPRehn1Rymm6aGbNIaL107V15wwAnROCCbeLmbs50yfi-vGXOXkj66ERxEcKwgX7opHq7-LcBM53Z9zl7420",
+        "q": "41diAw1Tndw9q9WOaBaZEUyvgdrLU2OLENjSyFyiqkT5wqrfZECzUSbQEWCyty0uyZ4zoihZR575UScNpxjPiwg68qtoSsSStUpnl9zRjbu0bTvBB7BbvoYnzY0Sl8hZmVKVOkuFl4BDqAscR-VzuLi9nchbm5QYPbdEN7lDd68"
+    }"#;
 
     #[async_std::test]
     async fn test_rsa_key_pair() {
@@ -153,4 +308,90 @@ mod test {
 
         assert_eq!(plaintext, &decrypted[..]);
     }
+
+    #[test]
+    fn identically_seeded_rngs_generate_the_same_key() {
+        let key = RsaPrivateKey::from_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+        let same_key = RsaPrivateKey::from_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+        let other_key = RsaPrivateKey::from_rng(&mut StdRng::seed_from_u64(43)).unwrap();
+
+        let modulus = key.get_public_key().get_public_key_modulus().unwrap();
+
+        assert_eq!(
+            modulus,
+            same_key.get_public_key().get_public_key_modulus().unwrap()
+        );
+        assert_ne!(
+            modulus,
+            other_key.get_public_key().get_public_key_modulus().unwrap()
+        );
+    }
+
+    #[async_std::test]
+    async fn big_endian_modulus_round_trips() {
+        let priv_key = RsaPrivateKey::from_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+        let pub_key = priv_key.get_public_key();
+
+        let modulus_be = pub_key.get_public_key_modulus_be().unwrap();
+        let modulus_le = pub_key.get_public_key_modulus().unwrap();
+        assert_eq!(
+            modulus_be,
+            modulus_le.iter().rev().copied().collect::<Vec<_>>()
+        );
+
+        let imported = RsaPublicKey::from_modulus_be(&modulus_be).unwrap();
+        assert_eq!(imported.get_public_key_modulus_be().unwrap(), modulus_be);
+        assert_eq!(
+            imported.to_spki_base64().unwrap(),
+            pub_key.to_spki_base64().unwrap()
+        );
+
+        let plaintext = b"Hello, world!";
+        let ciphertext = imported.encrypt(plaintext).await.unwrap();
+        let decrypted = priv_key.decrypt(&ciphertext).await.unwrap();
+
+        assert_eq!(plaintext, &decrypted[..]);
+    }
+
+    #[test]
+    fn jwk_round_trips_to_the_same_key() {
+        let priv_key = RsaPrivateKey::from_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+        let pub_key = priv_key.get_public_key();
+
+        let priv_jwk = priv_key.to_jwk();
+        let pub_jwk = pub_key.to_jwk();
+        assert_eq!(pub_jwk.d, None);
+        assert_eq!(
+            RsaPrivateKey::from_jwk(&priv_jwk).unwrap().to_jwk(),
+            priv_jwk
+        );
+        assert_eq!(RsaPublicKey::from_jwk(&pub_jwk).unwrap().to_jwk(), pub_jwk);
+
+        // A public JWK lacks what's needed for a private key
+        assert!(RsaPrivateKey::from_jwk(&pub_jwk).is_err());
+    }
+
+    #[async_std::test]
+    async fn known_jwk_encrypts_and_decrypts() {
+        let priv_jwk: RsaJwk = serde_json::from_str(KNOWN_PRIVATE_JWK).unwrap();
+        let pub_jwk = RsaJwk {
+            d: None,
+            p: None,
+            q: None,
+            ..priv_jwk.clone()
+        };
+
+        let priv_key = RsaPrivateKey::from_jwk(&priv_jwk).unwrap();
+        let pub_key = RsaPublicKey::from_jwk(&pub_jwk).unwrap();
+
+        let plaintext = b"Hello, world!";
+        let ciphertext = pub_key.encrypt(plaintext).await.unwrap();
+        let decrypted = priv_key.decrypt(&ciphertext).await.unwrap();
+
+        assert_eq!(plaintext, &decrypted[..]);
+        assert_eq!(
+            serde_json::to_value(priv_key.to_jwk()).unwrap(),
+            serde_json::from_str::<serde_json::Value>(KNOWN_PRIVATE_JWK).unwrap()
+        );
+    }
 }
@@ -1,5 +1,7 @@
 mod aes;
+mod cipher;
 mod exchange;
 
 pub use self::exchange::*;
 pub use aes::*;
+pub use cipher::*;
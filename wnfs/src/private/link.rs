@@ -1,7 +1,9 @@
-use super::{PrivateDirectory, PrivateFile, PrivateForest, PrivateNode, PrivateRef};
-use anyhow::Result;
+use super::{PrivateDirectory, PrivateFile, PrivateForest, PrivateNode, PrivateRef, SnapshotKey};
+use crate::error::FsError;
+use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
 use async_recursion::async_recursion;
+use libipld::Cid;
 use rand_core::RngCore;
 use std::rc::Rc;
 use wnfs_common::BlockStore;
@@ -17,6 +19,13 @@ pub(crate) enum PrivateLink {
         // which if full, combined with the `PrivateNode` derives the `PrivateRef`.
         node: PrivateNode,
     },
+    /// A link read from a directory that was itself loaded with only a snapshot key.
+    /// It can only be decrypted with the child's snapshot key, which is fetched on access.
+    Snapshot {
+        snapshot_key: SnapshotKey,
+        content_cid: Cid,
+        cache: OnceCell<PrivateNode>,
+    },
 }
 
 impl PrivateLink {
@@ -27,6 +36,14 @@ impl PrivateLink {
         }
     }
 
+    pub(crate) fn from_snapshot(snapshot_key: SnapshotKey, content_cid: Cid) -> Self {
+        Self::Snapshot {
+            snapshot_key,
+            content_cid,
+            cache: OnceCell::new(),
+        }
+    }
+
     #[async_recursion(?Send)]
     pub(crate) async fn resolve_ref(
         &self,
@@ -37,6 +54,9 @@ impl PrivateLink {
         match self {
             Self::Encrypted { private_ref, .. } => Ok(private_ref.clone()),
             Self::Decrypted { node } => Ok(node.store(forest, store, rng).await?),
+            // Without the ratchet there's no private ref to derive, and storing the
+            // node again would write it under the wrong label.
+            Self::Snapshot { .. } => bail!(FsError::ReadOnlySnapshot),
         }
     }
 
@@ -52,6 +72,19 @@ impl PrivateLink {
                     .await
             }
             Self::Decrypted { node, .. } => Ok(node),
+            Self::Snapshot {
+                snapshot_key,
+                content_cid,
+                cache,
+            } => {
+                cache
+                    .get_or_try_init(PrivateNode::from_cid_snapshot(
+                        *content_cid,
+                        snapshot_key,
+                        store,
+                    ))
+                    .await
+            }
         }
     }
 
@@ -81,6 +114,25 @@ impl PrivateLink {
                 })
             }
             Self::Decrypted { node, .. } => Ok(node),
+            Self::Snapshot {
+                snapshot_key,
+                content_cid,
+                cache,
+            } => {
+                let private_node = match cache.take() {
+                    Some(node) => node,
+                    None => {
+                        PrivateNode::from_cid_snapshot(*content_cid, snapshot_key, store).await?
+                    }
+                };
+
+                *self = Self::Decrypted { node: private_node };
+
+                Ok(match self {
+                    Self::Decrypted { node, .. } => node,
+                    _ => unreachable!(),
+                })
+            }
         }
     }
 
@@ -102,6 +154,14 @@ impl PrivateLink {
                 }
             },
             Self::Decrypted { node, .. } => Ok(node),
+            Self::Snapshot {
+                snapshot_key,
+                content_cid,
+                cache,
+            } => match cache.into_inner() {
+                Some(cached) => Ok(cached),
+                None => PrivateNode::from_cid_snapshot(content_cid, &snapshot_key, store).await,
+            },
         }
     }
 
@@ -122,6 +182,23 @@ impl PrivateLink {
         match self {
             Self::Encrypted { private_ref, .. } => Some(private_ref.clone()),
             Self::Decrypted { node } => node.get_private_ref(),
+            Self::Snapshot { cache, .. } => cache.get().and_then(PrivateNode::get_private_ref),
+        }
+    }
+
+    /// Returns the content CID of the stored revision this link points to, if any.
+    fn get_content_cid(&self) -> Option<Cid> {
+        match self {
+            Self::Snapshot { content_cid, .. } => Some(*content_cid),
+            _ => self.get_ref().map(|private_ref| private_ref.content_cid),
+        }
+    }
+
+    /// Returns whether both links point to the same stored revision of a node.
+    pub(crate) fn is_same_revision(&self, other: &Self) -> bool {
+        match (self.get_content_cid(), other.get_content_cid()) {
+            (Some(content_cid), Some(other_cid)) => content_cid == other_cid,
+            _ => false,
         }
     }
 }
@@ -148,6 +225,7 @@ impl PartialEq for PrivateLink {
             (Self::Decrypted { node }, Self::Encrypted { private_ref, cache }) => {
                 Some(private_ref) == node.get_private_ref().as_ref() || Some(node) == cache.get()
             }
+            (Self::Snapshot { .. }, _) | (_, Self::Snapshot { .. }) => self.is_same_revision(other),
         }
     }
 }
@@ -160,6 +238,15 @@ impl Clone for PrivateLink {
                 cache: OnceCell::new_with(cache.get().cloned()),
             },
             Self::Decrypted { node } => Self::Decrypted { node: node.clone() },
+            Self::Snapshot {
+                snapshot_key,
+                content_cid,
+                cache,
+            } => Self::Snapshot {
+                snapshot_key: snapshot_key.clone(),
+                content_cid: *content_cid,
+                cache: OnceCell::new_with(cache.get().cloned()),
+            },
         }
     }
 }
@@ -0,0 +1,402 @@
+use super::{
+    encrypted::Encrypted, link::PrivateLink, PrivateDirectory, PrivateDirectoryContent,
+    PrivateForest, PrivateNode,
+};
+use crate::error::FsError;
+use anyhow::{bail, Result};
+use async_once_cell::OnceCell;
+use async_recursion::async_recursion;
+use rand_core::RngCore;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
+use wnfs_common::BlockStore;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// How many large ratchet epochs apart two revisions may be for them to still be recognized as
+/// revisions of the same node.
+const MAX_RATCHET_STEPS: usize = 1_000;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The outcome of a three-way merge.
+#[derive(Debug)]
+pub struct MergeOutcome {
+    /// The merged directory. It has already been stored.
+    pub dir: Rc<PrivateDirectory>,
+    /// The paths changed on both sides in ways that couldn't be merged.
+    /// The merged directory keeps our side of these.
+    pub conflicts: Vec<Vec<String>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Merges two revisions of a directory that diverged from a common `base` revision.
+///
+/// Entries added, removed or changed on only one side are taken from that side. Directories
+/// changed on both sides are merged recursively. Anything else changed on both sides is a
+/// conflict: we keep our side of it, and its path gets listed in the outcome.
+///
+/// Entries are compared by their stored content CIDs, so all three revisions should have been
+/// stored. Each merged directory is a new revision whose previous links point to both of its
+/// parents.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use chrono::Utc;
+/// use rand::thread_rng;
+/// use wnfs::{
+///     private::{merge, PrivateDirectory, PrivateForest},
+///     common::MemoryBlockStore,
+///     namefilter::Namefilter,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///     let forest = &mut Rc::new(PrivateForest::new());
+///     let base = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+///     base.store(forest, store, rng).await.unwrap();
+///
+///     let ours = &mut Rc::clone(&base);
+///     ours.write(&["ours.txt".into()], true, Utc::now(), b"ours".to_vec(), forest, store, rng)
+///         .await
+///         .unwrap();
+///     ours.store(forest, store, rng).await.unwrap();
+///
+///     let theirs = &mut Rc::clone(&base);
+///     theirs.write(&["theirs.txt".into()], true, Utc::now(), b"theirs".to_vec(), forest, store, rng)
+///         .await
+///         .unwrap();
+///     theirs.store(forest, store, rng).await.unwrap();
+///
+///     let outcome = merge(&base, ours, theirs, forest, store, rng).await.unwrap();
+///
+///     assert!(outcome.conflicts.is_empty());
+///     assert_eq!(outcome.dir.get_entries().collect::<Vec<_>>(), ["ours.txt", "theirs.txt"]);
+/// }
+/// ```
+pub async fn merge(
+    base: &Rc<PrivateDirectory>,
+    ours: &Rc<PrivateDirectory>,
+    theirs: &Rc<PrivateDirectory>,
+    forest: &mut Rc<PrivateForest>,
+    store: &impl BlockStore,
+    rng: &mut impl RngCore,
+) -> Result<MergeOutcome> {
+    let mut conflicts = vec![];
+    let Some(dir) = merge_helper(
+        &mut vec![],
+        Some(base),
+        ours,
+        theirs,
+        forest,
+        store,
+        &mut conflicts,
+    )
+    .await?
+    else {
+        bail!(FsError::UnrelatedRevisions)
+    };
+
+    let dir = Rc::new(dir);
+    dir.store(forest, store, rng).await?;
+
+    Ok(MergeOutcome { dir, conflicts })
+}
+
+/// Merges the entries of two directory revisions, or returns `None` if they aren't revisions of
+/// the same directory.
+#[async_recursion(?Send)]
+async fn merge_helper(
+    path: &mut Vec<String>,
+    base: Option<&PrivateDirectory>,
+    ours: &PrivateDirectory,
+    theirs: &PrivateDirectory,
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+    conflicts: &mut Vec<Vec<String>>,
+) -> Result<Option<PrivateDirectory>> {
+    let Some(mut merged) = merged_revision(ours, theirs)? else {
+        return Ok(None);
+    };
+
+    let empty = BTreeMap::new();
+    let base_entries = base.map_or(&empty, |base| &base.content.entries);
+    let names = base_entries
+        .keys()
+        .chain(ours.content.entries.keys())
+        .chain(theirs.content.entries.keys())
+        .collect::<BTreeSet<_>>();
+
+    for name in names {
+        let base_link = base_entries.get(name);
+        let our_link = ours.content.entries.get(name);
+        let their_link = theirs.content.entries.get(name);
+
+        let link = if is_same(our_link, their_link) || is_same(their_link, base_link) {
+            our_link.cloned()
+        } else if is_same(our_link, base_link) {
+            their_link.cloned()
+        } else {
+            path.push(name.clone());
+            let merged_dir = match (our_link, their_link) {
+                (Some(our_link), Some(their_link)) => {
+                    let our_node = our_link.resolve_node(forest, store).await?;
+                    let their_node = their_link.resolve_node(forest, store).await?;
+                    match (our_node, their_node) {
+                        (PrivateNode::Dir(our_dir), PrivateNode::Dir(their_dir)) => {
+                            let base_dir = match base_link {
+                                Some(link) => link.resolve_node(forest, store).await?.as_dir().ok(),
+                                None => None,
+                            };
+
+                            merge_helper(
+                                path,
+                                base_dir.as_deref(),
+                                our_dir,
+                                their_dir,
+                                forest,
+                                store,
+                                conflicts,
+                            )
+                            .await?
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+
+            if merged_dir.is_none() {
+                conflicts.push(path.clone());
+            }
+
+            path.pop();
+            match merged_dir {
+                Some(dir) => Some(PrivateLink::with_dir(dir)),
+                None => our_link.cloned(),
+            }
+        };
+
+        if let Some(link) = link {
+            merged.content.entries.insert(name.clone(), link);
+        }
+    }
+
+    Ok(Some(merged))
+}
+
+/// Creates an empty revision succeeding both given revisions of a directory, or returns `None`
+/// if they aren't revisions of the same directory.
+#[allow(clippy::mutable_key_type)]
+fn merged_revision(
+    ours: &PrivateDirectory,
+    theirs: &PrivateDirectory,
+) -> Result<Option<PrivateDirectory>> {
+    if ours.header.inumber != theirs.header.inumber {
+        return Ok(None);
+    }
+
+    let Ok(steps) = ours
+        .header
+        .ratchet
+        .compare(&theirs.header.ratchet, MAX_RATCHET_STEPS)
+    else {
+        return Ok(None);
+    };
+
+    let (ahead, behind) = if steps >= 0 {
+        (ours, theirs)
+    } else {
+        (theirs, ours)
+    };
+
+    let mut header = ahead.header.clone();
+    header.advance_ratchet();
+
+    let mut previous = BTreeSet::new();
+    for (parent, revisions_back) in [(ahead, 1), (behind, steps.unsigned_abs() + 1)] {
+        if let Some(cid) = parent.content.persisted_as.get() {
+            let temporal_key = parent.header.derive_temporal_key();
+            previous.insert((revisions_back, Encrypted::from_value(*cid, &temporal_key)?));
+        }
+    }
+
+    Ok(Some(PrivateDirectory {
+        header,
+        content: PrivateDirectoryContent {
+            persisted_as: OnceCell::new(),
+            previous,
+            metadata: ahead.content.metadata.clone(),
+            entries: BTreeMap::new(),
+        },
+    }))
+}
+
+/// Returns whether both entries are missing, or point to the same stored revision.
+fn is_same(link: Option<&PrivateLink>, other: Option<&PrivateLink>) -> bool {
+    match (link, other) {
+        (None, None) => true,
+        (Some(link), Some(other)) => link.is_same_revision(other),
+        _ => false,
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::path;
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+    use wnfs_common::MemoryBlockStore;
+    use wnfs_namefilter::Namefilter;
+
+    async fn write(
+        dir: &mut Rc<PrivateDirectory>,
+        file: &str,
+        content: &[u8],
+        forest: &mut Rc<PrivateForest>,
+        store: &impl BlockStore,
+        rng: &mut impl RngCore,
+    ) {
+        dir.write(
+            &path(file),
+            true,
+            Utc::now(),
+            content.to_vec(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn read(
+        dir: &Rc<PrivateDirectory>,
+        file: &str,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Vec<u8> {
+        dir.read(&path(file), false, forest, store).await.unwrap()
+    }
+
+    #[test(async_std::test)]
+    async fn merge_applies_non_conflicting_changes_from_both_sides() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let base = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        write(base, "docs/a.txt", b"a", forest, store, rng).await;
+        write(base, "docs/b.txt", b"b", forest, store, rng).await;
+        write(base, "removed.txt", b"removed", forest, store, rng).await;
+        base.store(forest, store, rng).await.unwrap();
+
+        let ours = &mut Rc::clone(base);
+        write(ours, "docs/a.txt", b"ours", forest, store, rng).await;
+        write(ours, "ours.txt", b"ours", forest, store, rng).await;
+        ours.store(forest, store, rng).await.unwrap();
+
+        let theirs = &mut Rc::clone(base);
+        write(theirs, "docs/b.txt", b"theirs", forest, store, rng).await;
+        theirs
+            .rm(&path("removed.txt"), true, forest, store)
+            .await
+            .unwrap();
+        theirs.store(forest, store, rng).await.unwrap();
+
+        let MergeOutcome { dir, conflicts } =
+            merge(base, ours, theirs, forest, store, rng).await.unwrap();
+
+        assert!(conflicts.is_empty());
+        assert_eq!(dir.get_entries().collect::<Vec<_>>(), ["docs", "ours.txt"]);
+        assert_eq!(read(&dir, "docs/a.txt", forest, store).await, b"ours");
+        assert_eq!(read(&dir, "docs/b.txt", forest, store).await, b"theirs");
+        assert_eq!(read(&dir, "ours.txt", forest, store).await, b"ours");
+
+        assert_eq!(dir.content.previous.len(), 2);
+        for parent in [ours, theirs] {
+            let cid = parent.content.persisted_as.get().unwrap();
+            let temporal_key = parent.header.derive_temporal_key();
+            assert!(dir
+                .content
+                .previous
+                .iter()
+                .any(|(_, encrypted)| encrypted.resolve_value(&temporal_key).ok() == Some(cid)));
+        }
+    }
+
+    #[test(async_std::test)]
+    async fn merge_reports_files_modified_on_both_sides() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let base = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        write(base, "docs/a.txt", b"a", forest, store, rng).await;
+        base.store(forest, store, rng).await.unwrap();
+
+        let ours = &mut Rc::clone(base);
+        write(ours, "docs/a.txt", b"ours", forest, store, rng).await;
+        ours.store(forest, store, rng).await.unwrap();
+
+        let theirs = &mut Rc::clone(base);
+        write(theirs, "docs/a.txt", b"theirs", forest, store, rng).await;
+        write(theirs, "theirs.txt", b"theirs", forest, store, rng).await;
+        theirs.store(forest, store, rng).await.unwrap();
+
+        let MergeOutcome { dir, conflicts } =
+            merge(base, ours, theirs, forest, store, rng).await.unwrap();
+
+        assert_eq!(conflicts, [path("docs/a.txt")]);
+        assert_eq!(read(&dir, "docs/a.txt", forest, store).await, b"ours");
+        assert_eq!(read(&dir, "theirs.txt", forest, store).await, b"theirs");
+    }
+
+    #[async_std::test]
+    async fn merge_rejects_unrelated_directories() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let base = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let other = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let result = merge(&base, &base, &other, forest, store, rng).await;
+
+        assert!(result.is_err());
+    }
+}
@@ -1,18 +1,34 @@
+mod buffered;
+mod cache;
+mod car;
+mod diff;
 mod directory;
 mod encrypted;
 mod file;
 mod forest;
+mod gc;
 mod keys;
 mod link;
+mod merge;
 mod node;
 mod previous;
 mod privateref;
+mod proof;
 pub mod share;
+mod transaction;
+mod unixfs;
 
+pub use cache::*;
+pub use car::*;
+pub use diff::*;
 pub use directory::*;
 pub use file::*;
 pub use forest::*;
+pub use gc::*;
 pub use keys::*;
+pub use merge::*;
 pub use node::*;
 pub use previous::*;
 pub use privateref::*;
+pub use proof::*;
+pub use transaction::*;
@@ -1,16 +1,24 @@
 use super::{SnapshotKey, TemporalKey};
-use crate::private::RevisionRef;
-use anyhow::Result;
+use crate::{error::FsError, private::RevisionRef};
+use anyhow::{bail, Result};
 use libipld::{Cid, Ipld, IpldCodec};
 use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 use sha3::Sha3_256;
 use skip_ratchet::Ratchet;
 use std::{collections::BTreeMap, fmt::Debug};
-use wnfs_common::{utils, BlockStore, HashOutput, HASH_BYTE_SIZE};
+use wnfs_common::{dagcbor, utils, BlockStore, HashOutput, HASH_BYTE_SIZE};
 use wnfs_hamt::Hasher;
 use wnfs_namefilter::Namefilter;
 
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The version of stored headers. Version 1 stores the bare name in its compact encoding,
+/// while headers without a version store it as DAG-CBOR.
+const HEADER_VERSION: i128 = 1;
+
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
@@ -48,6 +56,10 @@ pub struct PrivateNodeHeader {
     pub(crate) ratchet: Ratchet,
     /// Used for ancestry checks and as a key for the private forest.
     pub(crate) bare_name: Namefilter,
+    /// Set for headers loaded with only a snapshot key. Those don't know their ratchet,
+    /// so they can't derive the labels and keys needed to store new revisions.
+    #[serde(skip)]
+    pub(crate) read_only: bool,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -68,6 +80,7 @@ impl PrivateNodeHeader {
             },
             ratchet: Ratchet::zero(ratchet_seed),
             inumber,
+            read_only: false,
         }
     }
 
@@ -85,7 +98,17 @@ impl PrivateNodeHeader {
             },
             ratchet: Ratchet::zero(ratchet_seed),
             inumber,
+            read_only: false,
+        }
+    }
+
+    /// Fails with `FsError::ReadOnlySnapshot` if this header was loaded from a snapshot key.
+    pub(crate) fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            bail!(FsError::ReadOnlySnapshot);
         }
+
+        Ok(())
     }
 
     /// Advances the ratchet.
@@ -215,6 +238,8 @@ impl PrivateNodeHeader {
     /// Encrypts this private node header in an block, then stores that in the given
     /// BlockStore and returns its CID.
     pub async fn store(&self, store: &impl BlockStore) -> Result<Cid> {
+        self.ensure_writable()?;
+
         let temporal_key = self.derive_temporal_key();
         let snapshot_key = TemporalKey(temporal_key.derive_snapshot_key().0);
 
@@ -222,8 +247,7 @@ impl PrivateNodeHeader {
             snapshot_key.key_wrap_encrypt(&serde_ipld_dagcbor::to_vec(&self.inumber)?)?;
         let ratchet_bytes =
             temporal_key.key_wrap_encrypt(&serde_ipld_dagcbor::to_vec(&self.ratchet)?)?;
-        let bare_name_bytes =
-            snapshot_key.key_wrap_encrypt(&serde_ipld_dagcbor::to_vec(&self.bare_name)?)?;
+        let bare_name_bytes = snapshot_key.key_wrap_encrypt(&self.bare_name.to_compact_bytes())?;
 
         let inumber_cid = store.put_block(inumber_bytes, IpldCodec::Raw).await?;
         let ratchet_cid = store.put_block(ratchet_bytes, IpldCodec::Raw).await?;
@@ -233,6 +257,7 @@ impl PrivateNodeHeader {
         map.insert("inumber".to_string(), Ipld::Link(inumber_cid));
         map.insert("ratchet".to_string(), Ipld::Link(ratchet_cid));
         map.insert("bare_name".to_string(), Ipld::Link(bare_name_cid));
+        map.insert("version".to_string(), Ipld::Integer(HEADER_VERSION));
 
         let ipld_bytes = serde_ipld_dagcbor::to_vec(&Ipld::Map(map))?;
         store.put_block(ipld_bytes, IpldCodec::Raw).await
@@ -251,7 +276,9 @@ impl PrivateNodeHeader {
     ) -> Result<PrivateNodeHeader> {
         let snapshot_key = temporal_key.derive_snapshot_key();
 
+        let limits = store.decode_limits();
         let ipld_bytes = store.get_block(cid).await?;
+        dagcbor::check_limits(&ipld_bytes, &limits)?;
         let Ipld::Map(map) = serde_ipld_dagcbor::from_slice(&ipld_bytes)? else {
             return Err(anyhow::anyhow!("Unable to deserialize ipld map"));
         };
@@ -272,14 +299,19 @@ impl PrivateNodeHeader {
         let bare_name_bytes = TemporalKey(snapshot_key.0.to_owned())
             .key_wrap_decrypt(&store.get_block(bare_name_cid).await?)?;
 
+        for bytes in [&inumber_bytes, &ratchet_bytes] {
+            dagcbor::check_limits(bytes, &limits)?;
+        }
+
         let inumber: [u8; HASH_BYTE_SIZE] = serde_ipld_dagcbor::from_slice(&inumber_bytes)?;
         let ratchet: Ratchet = serde_ipld_dagcbor::from_slice(&ratchet_bytes)?;
-        let bare_name: Namefilter = serde_ipld_dagcbor::from_slice(&bare_name_bytes)?;
+        let bare_name = Self::decode_bare_name(&map, &bare_name_bytes, &limits)?;
 
         Ok(Self {
             inumber,
             ratchet,
             bare_name,
+            read_only: false,
         })
     }
 
@@ -288,7 +320,9 @@ impl PrivateNodeHeader {
         snapshot_key: &SnapshotKey,
         store: &impl BlockStore,
     ) -> Result<PrivateNodeHeader> {
+        let limits = store.decode_limits();
         let ipld_bytes = store.get_block(cid).await?;
+        dagcbor::check_limits(&ipld_bytes, &limits)?;
         let Ipld::Map(map) = serde_ipld_dagcbor::from_slice(&ipld_bytes)? else {
             return Err(anyhow::anyhow!("Unable to deserialize ipld map"));
         };
@@ -305,15 +339,34 @@ impl PrivateNodeHeader {
         let bare_name_bytes = TemporalKey(snapshot_key.0.to_owned())
             .key_wrap_decrypt(&store.get_block(bare_name_cid).await?)?;
 
+        dagcbor::check_limits(&inumber_bytes, &limits)?;
+
         let inumber: [u8; HASH_BYTE_SIZE] = serde_ipld_dagcbor::from_slice(&inumber_bytes)?;
-        let bare_name: Namefilter = serde_ipld_dagcbor::from_slice(&bare_name_bytes)?;
+        let bare_name = Self::decode_bare_name(&map, &bare_name_bytes, &limits)?;
 
         Ok(Self {
             inumber,
             ratchet: Ratchet::zero([0; 32]),
             bare_name,
+            read_only: true,
         })
     }
+
+    /// Decodes a bare name in the encoding the version of the header map says.
+    fn decode_bare_name(
+        map: &BTreeMap<String, Ipld>,
+        bytes: &[u8],
+        limits: &dagcbor::DecodeLimits,
+    ) -> Result<Namefilter> {
+        match map.get("version") {
+            None => {
+                dagcbor::check_limits(bytes, limits)?;
+                Ok(serde_ipld_dagcbor::from_slice(bytes)?)
+            }
+            Some(Ipld::Integer(HEADER_VERSION)) => Namefilter::from_compact_bytes(bytes),
+            Some(other) => bail!(FsError::UnsupportedHeaderVersion(other.clone())),
+        }
+    }
 }
 
 impl Debug for PrivateNodeHeader {
@@ -330,3 +383,142 @@ impl Debug for PrivateNodeHeader {
             .finish()
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use wnfs_common::{DecodeError, MemoryBlockStore};
+
+    #[async_std::test]
+    async fn header_round_trips_through_store() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let header = PrivateNodeHeader::new(Namefilter::default(), rng);
+
+        let cid = header.store(store).await.unwrap();
+        let loaded = PrivateNodeHeader::load_temporal(&cid, &header.derive_temporal_key(), store)
+            .await
+            .unwrap();
+
+        assert_eq!(loaded, header);
+    }
+
+    #[async_std::test]
+    async fn headers_without_version_still_load() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let mut parent_bare_name = Namefilter::default();
+        parent_bare_name.add(&[0xF5u8; 32]);
+        let header = PrivateNodeHeader::new(parent_bare_name, rng);
+        let temporal_key = header.derive_temporal_key();
+        let snapshot_key = TemporalKey(temporal_key.derive_snapshot_key().0);
+
+        // The layout before headers were versioned
+        let mut map = <BTreeMap<String, Ipld>>::new();
+        for (key, key_used, bytes) in [
+            (
+                "inumber",
+                &snapshot_key,
+                serde_ipld_dagcbor::to_vec(&header.inumber).unwrap(),
+            ),
+            (
+                "ratchet",
+                &temporal_key,
+                serde_ipld_dagcbor::to_vec(&header.ratchet).unwrap(),
+            ),
+            (
+                "bare_name",
+                &snapshot_key,
+                serde_ipld_dagcbor::to_vec(&header.bare_name).unwrap(),
+            ),
+        ] {
+            let ciphertext = key_used.key_wrap_encrypt(&bytes).unwrap();
+            let cid = store.put_block(ciphertext, IpldCodec::Raw).await.unwrap();
+            map.insert(key.to_string(), Ipld::Link(cid));
+        }
+        let ipld_bytes = serde_ipld_dagcbor::to_vec(&Ipld::Map(map)).unwrap();
+        let cid = store.put_block(ipld_bytes, IpldCodec::Raw).await.unwrap();
+
+        let loaded = PrivateNodeHeader::load_temporal(&cid, &temporal_key, store)
+            .await
+            .unwrap();
+
+        assert_eq!(loaded, header);
+    }
+
+    #[async_std::test]
+    async fn stored_bare_names_are_compact() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let header = PrivateNodeHeader::new(Namefilter::default(), rng);
+
+        let cid = header.store(store).await.unwrap();
+        let Ipld::Map(map) =
+            serde_ipld_dagcbor::from_slice(&store.get_block(&cid).await.unwrap()).unwrap()
+        else {
+            panic!("Expected header map");
+        };
+        let Some(Ipld::Link(bare_name_cid)) = map.get("bare_name") else {
+            panic!("Expected bare name link");
+        };
+        let stored_size = store.get_block(bare_name_cid).await.unwrap().len();
+        let legacy_size = TemporalKey(header.derive_temporal_key().derive_snapshot_key().0)
+            .key_wrap_encrypt(&serde_ipld_dagcbor::to_vec(&header.bare_name).unwrap())
+            .unwrap()
+            .len();
+
+        assert_eq!(map.get("version"), Some(&Ipld::Integer(HEADER_VERSION)));
+        assert!(stored_size < legacy_size);
+    }
+
+    #[async_std::test]
+    async fn load_temporal_rejects_deeply_nested_blocks() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let header = PrivateNodeHeader::new(Namefilter::default(), rng);
+
+        let mut nested = vec![0x81; 10_000];
+        nested.push(0x00);
+        let cid = store.put_block(nested, IpldCodec::DagCbor).await.unwrap();
+
+        let error = PrivateNodeHeader::load_temporal(&cid, &header.derive_temporal_key(), store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<DecodeError>(),
+            Some(DecodeError::DepthLimitExceeded(_))
+        ));
+    }
+
+    #[async_std::test]
+    async fn unknown_header_versions_are_rejected() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &MemoryBlockStore::new();
+        let header = PrivateNodeHeader::new(Namefilter::default(), rng);
+
+        let cid = header.store(store).await.unwrap();
+        let Ipld::Map(mut map) =
+            serde_ipld_dagcbor::from_slice(&store.get_block(&cid).await.unwrap()).unwrap()
+        else {
+            panic!("Expected header map");
+        };
+        map.insert("version".to_string(), Ipld::Integer(HEADER_VERSION + 1));
+        let ipld_bytes = serde_ipld_dagcbor::to_vec(&Ipld::Map(map)).unwrap();
+        let cid = store.put_block(ipld_bytes, IpldCodec::Raw).await.unwrap();
+
+        let error = PrivateNodeHeader::load_temporal(&cid, &header.derive_temporal_key(), store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::UnsupportedHeaderVersion(Ipld::Integer(2)))
+        ));
+    }
+}
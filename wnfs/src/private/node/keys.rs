@@ -1,6 +1,6 @@
 use crate::{
     error::AesError,
-    private::{AesKey, KEY_BYTE_SIZE, NONCE_SIZE},
+    private::{cipher_by_id, Aes256GcmCipher, AesKey, Cipher, CipherAlgorithm, KEY_BYTE_SIZE},
 };
 use aes_gcm::{
     aead::{consts::U12, Aead},
@@ -93,6 +93,54 @@ impl SnapshotKey {
         Ok([nonce.to_vec(), cipher_text].concat())
     }
 
+    /// Encrypts the given plaintext using the key and the given cipher.
+    ///
+    /// The ciphertext starts with the cipher's id, so `decrypt` can tell which cipher to use.
+    /// Ciphertexts from `encrypt` don't carry an id and are always AES-256-GCM, which keeps
+    /// them readable by older versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::private::{SnapshotKey, XChaCha20Poly1305Cipher};
+    /// use wnfs::common::utils;
+    /// use rand::thread_rng;
+    ///
+    /// let rng = &mut thread_rng();
+    /// let key = SnapshotKey::from(utils::get_random_bytes(rng));
+    ///
+    /// let plaintext = b"Hello World!";
+    /// let ciphertext = key.encrypt_with(&XChaCha20Poly1305Cipher, plaintext, rng).unwrap();
+    /// let decrypted = key.decrypt(&ciphertext).unwrap();
+    ///
+    /// assert_eq!(plaintext, &decrypted[..]);
+    /// ```
+    pub fn encrypt_with(
+        &self,
+        cipher: &dyn Cipher,
+        data: &[u8],
+        rng: &mut impl RngCore,
+    ) -> Result<Vec<u8>> {
+        let ciphertext = cipher.encrypt(&self.0, data, rng)?;
+        Ok([vec![cipher.id()], ciphertext].concat())
+    }
+
+    /// Encrypts the given plaintext with the selected cipher.
+    ///
+    /// AES-256-GCM ciphertexts are produced by `encrypt` and stay untagged, all others come from
+    /// `encrypt_with`.
+    pub(crate) fn encrypt_using(
+        &self,
+        algorithm: CipherAlgorithm,
+        data: &[u8],
+        rng: &mut impl RngCore,
+    ) -> Result<Vec<u8>> {
+        match algorithm {
+            CipherAlgorithm::Aes256Gcm => self.encrypt(data, rng),
+            _ => self.encrypt_with(algorithm.cipher(), data, rng),
+        }
+    }
+
     /// Generates a random 12-byte nonce for encryption.
     pub(crate) fn generate_nonce(rng: &mut impl RngCore) -> Nonce<U12> {
         let mut nonce = Nonce::default();
@@ -114,6 +162,9 @@ impl SnapshotKey {
 
     /// Decrypts the given ciphertext using the key.
     ///
+    /// Ciphertexts from `encrypt_with` get decrypted with the cipher their id names. All others
+    /// are taken to be AES-256-GCM ciphertexts from `encrypt`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -131,11 +182,17 @@ impl SnapshotKey {
     /// assert_eq!(plaintext, &decrypted[..]);
     /// ```
     pub fn decrypt(&self, cipher_text: &[u8]) -> Result<Vec<u8>> {
-        let (nonce_bytes, data) = cipher_text.split_at(NONCE_SIZE);
+        // An untagged ciphertext starts with a random nonce, so its first byte may look like a
+        // cipher id. Authentication fails in that case, and we fall back to AES.
+        if let Some((id, tagged)) = cipher_text.split_first() {
+            if let Some(cipher) = cipher_by_id(*id) {
+                if let Ok(data) = cipher.decrypt(&self.0, tagged) {
+                    return Ok(data);
+                }
+            }
+        }
 
-        Ok(Aes256Gcm::new(&self.0.clone().bytes().into())
-            .decrypt(Nonce::from_slice(nonce_bytes), data)
-            .map_err(|e| AesError::UnableToDecrypt(format!("{e}")))?)
+        Aes256GcmCipher.decrypt(&self.0, cipher_text)
     }
 
     /// Decrypts the ciphertext in the given buffer in-place, with given key.
@@ -199,7 +256,7 @@ impl From<SnapshotKey> for AesKey {
 #[cfg(test)]
 mod proptests {
     use super::*;
-    use crate::private::KEY_BYTE_SIZE;
+    use crate::private::{KEY_BYTE_SIZE, NONCE_SIZE};
     use proptest::{
         prelude::any,
         prop_assert_eq, prop_assert_ne,
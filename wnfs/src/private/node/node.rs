@@ -1,22 +1,29 @@
-use super::{PrivateNodeHeader, SnapshotKey, TemporalKey};
+use super::{INumber, PrivateNodeHeader, SnapshotKey, TemporalKey};
 use crate::{
-    error::FsError,
+    error::{AesError, FsError},
     private::{
         encrypted::Encrypted, link::PrivateLink, share::SnapshotSharePointer, PrivateDirectory,
-        PrivateFile, PrivateForest, PrivateNodeContentSerializable, PrivateRef,
+        PrivateFile, PrivateForest, PrivateNodeContentSerializable, PrivateRef, RevisionRef,
     },
     traits::Id,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
 use async_recursion::async_recursion;
+use async_stream::try_stream;
 use chrono::{DateTime, Utc};
-use futures::StreamExt;
+use futures::{Stream, StreamExt, TryStreamExt};
 use libipld::Cid;
 use rand_core::RngCore;
-use skip_ratchet::{seek::JumpSize, RatchetSeeker};
-use std::{cmp::Ordering, collections::BTreeSet, fmt::Debug, rc::Rc};
-use wnfs_common::BlockStore;
+use skip_ratchet::{ratchet::PreviousIterator, seek::JumpSize, Ratchet, RatchetSeeker};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Debug,
+    rc::Rc,
+};
+use wnfs_common::{dagcbor, BlockStore, HashOutput, NodeType};
 use wnfs_namefilter::Namefilter;
 
 //--------------------------------------------------------------------------------------------------
@@ -53,6 +60,19 @@ pub enum PrivateNode {
     Dir(Rc<PrivateDirectory>),
 }
 
+/// A session cache of the latest revisions found by [`PrivateNode::search_latest_cached`].
+///
+/// Entries are keyed by a node's inumber and the ratchet a search started from.
+/// Repeated searches resume from the newest revision found so far instead of
+/// seeking all the way from the original revision again.
+///
+/// Since revisions are only ever added to a private forest, entries stay valid
+/// as long as the cache is used with the same forest (or newer versions of it).
+/// Call [`clear`](Self::clear) when switching to an unrelated forest, or one that
+/// may have had revisions removed.
+#[derive(Debug, Default)]
+pub struct SearchLatestCache(RefCell<HashMap<(INumber, HashOutput), Ratchet>>);
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -194,8 +214,126 @@ impl PrivateNode {
         }
     }
 
+    /// Walks the revision history of this node back to the revision `past_node`, newest first.
+    ///
+    /// The first item is this node itself. The following items are the revisions its previous
+    /// links lead to, decrypted with temporal keys derived from the ratchets in between. Merge
+    /// nodes have several previous links, and each of their parents gets yielded. A revision
+    /// reachable through several parents is only yielded once.
+    ///
+    /// Previous links can only be decrypted by ratcheting forward from an older revision, so
+    /// history before `past_node` isn't visited. The discrepancy budget bounds how many
+    /// revisions apart `past_node` may be, like in [`PrivateNodeHistory::of`].
+    ///
+    /// [`PrivateNodeHistory::of`]: crate::private::PrivateNodeHistory::of
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateNode, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let root_dir = &mut Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///     root_dir.store(forest, store, rng).await.unwrap();
+    ///     let first_revision = PrivateNode::Dir(Rc::clone(root_dir));
+    ///
+    ///     root_dir
+    ///         .mkdir(&["pictures".into()], true, Utc::now(), forest, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     root_dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let history = PrivateNode::Dir(Rc::clone(root_dir))
+    ///         .history(&first_revision, 1_000, forest, store)
+    ///         .try_collect::<Vec<_>>()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(history.len(), 2);
+    ///     assert_eq!(history[1], first_revision);
+    /// }
+    /// ```
+    pub fn history<'a>(
+        &'a self,
+        past_node: &PrivateNode,
+        discrepancy_budget: usize,
+        forest: &'a PrivateForest,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<PrivateNode>> + 'a {
+        let past_ratchet = past_node.get_header().ratchet.clone();
+        Box::pin(try_stream! {
+            let header = self.get_header();
+            let mut ratchets =
+                PreviousIterator::new(&past_ratchet, &header.ratchet, discrepancy_budget)
+                    .map_err(FsError::NoIntermediateRatchet)?;
+
+            // `previous_ratchets[n - 1]` is the ratchet `n` revisions before this node's.
+            // It gets filled in as far back as the previous links reach.
+            let mut previous_ratchets = Vec::new();
+            // Revisions yet to yield, ordered by how many revisions back they are.
+            let mut pending = BTreeSet::new();
+            let mut visited = HashSet::new();
+
+            let mut node = self.clone();
+            let mut revisions_back = 0;
+            loop {
+                yield node.clone();
+
+                for (steps, encrypted_cid) in node.get_previous() {
+                    let previous_back = revisions_back + steps;
+                    while previous_ratchets.len() < previous_back {
+                        match ratchets.next() {
+                            Some(ratchet) => previous_ratchets.push(ratchet),
+                            None => break,
+                        }
+                    }
+
+                    // The link leads to before `past_node`.
+                    let Some(ratchet) = previous_ratchets.get(previous_back - 1) else {
+                        continue;
+                    };
+
+                    let cid = *encrypted_cid.resolve_value(&TemporalKey::from(ratchet))?;
+                    if visited.insert(cid) {
+                        pending.insert((previous_back, cid));
+                    }
+                }
+
+                let Some((previous_back, cid)) = pending.pop_first() else {
+                    break;
+                };
+
+                let mut previous_header = header.clone();
+                previous_header.ratchet = previous_ratchets[previous_back - 1].clone();
+                let private_ref = previous_header.derive_revision_ref().as_private_ref(cid);
+
+                node = PrivateNode::load(&private_ref, forest, store).await?;
+                revisions_back = previous_back;
+            }
+        })
+    }
+
     /// Casts a node to a directory.
     ///
+    /// Fails with `FsError::UnexpectedNodeType` otherwise. A node doesn't know its
+    /// own path, so callers that do should report `FsError::NotADirectory` instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -220,7 +358,7 @@ impl PrivateNode {
     pub fn as_dir(&self) -> Result<Rc<PrivateDirectory>> {
         Ok(match self {
             Self::Dir(dir) => Rc::clone(dir),
-            _ => bail!(FsError::NotADirectory),
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PrivateDirectory)),
         })
     }
 
@@ -228,12 +366,15 @@ impl PrivateNode {
     pub(crate) fn as_dir_mut(&mut self) -> Result<&mut Rc<PrivateDirectory>> {
         Ok(match self {
             Self::Dir(dir) => dir,
-            _ => bail!(FsError::NotADirectory),
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PrivateDirectory)),
         })
     }
 
     /// Casts a node to a file.
     ///
+    /// Fails with `FsError::UnexpectedNodeType` otherwise. A node doesn't know its
+    /// own path, so callers that do should report `FsError::NotAFile` instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -258,7 +399,15 @@ impl PrivateNode {
     pub fn as_file(&self) -> Result<Rc<PrivateFile>> {
         Ok(match self {
             Self::File(file) => Rc::clone(file),
-            _ => bail!(FsError::NotAFile),
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PrivateFile)),
+        })
+    }
+
+    /// Casts a node to a mutable file.
+    pub(crate) fn as_file_mut(&mut self) -> Result<&mut Rc<PrivateFile>> {
+        Ok(match self {
+            Self::File(file) => file,
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PrivateFile)),
         })
     }
 
@@ -390,10 +539,70 @@ impl PrivateNode {
         store: &impl BlockStore,
     ) -> Result<Vec<PrivateNode>> {
         let header = self.get_header();
+        match Self::seek_latest_ratchet(header, forest, store).await? {
+            Some(ratchet) => Self::load_revision(header, ratchet, forest, store).await,
+            None => Ok(vec![self.clone()]),
+        }
+    }
+
+    /// Like [`search_latest`](Self::search_latest), but resumes seeking from the
+    /// newest revision remembered in given cache and records what it finds there.
+    pub async fn search_latest_cached(
+        &self,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+        cache: &SearchLatestCache,
+    ) -> Result<PrivateNode> {
+        self.search_latest_nodes_cached(forest, store, cache)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(FsError::NotFound.into())
+    }
+
+    /// Like [`search_latest_nodes`](Self::search_latest_nodes), but resumes seeking from the
+    /// newest revision remembered in given cache and records what it finds there.
+    pub async fn search_latest_nodes_cached(
+        &self,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+        cache: &SearchLatestCache,
+    ) -> Result<Vec<PrivateNode>> {
+        let header = self.get_header();
+        let key = (header.inumber, header.ratchet.derive_key());
 
+        let mut latest = None;
+        if let Some(cached) = cache.get(&key) {
+            let mut cached_header = header.clone();
+            cached_header.ratchet = cached;
+            latest = Self::seek_latest_ratchet(&cached_header, forest, store).await?;
+        }
+
+        if latest.is_none() {
+            latest = Self::seek_latest_ratchet(header, forest, store).await?;
+        }
+
+        let Some(ratchet) = latest else {
+            return Ok(vec![self.clone()]);
+        };
+
+        cache.insert(key, ratchet.clone());
+        cache.insert((header.inumber, ratchet.derive_key()), ratchet.clone());
+
+        Self::load_revision(header, ratchet, forest, store).await
+    }
+
+    /// Seeks the ratchet of the latest revision of given header that's in the forest.
+    ///
+    /// Returns `None` if the given revision itself isn't in the forest.
+    async fn seek_latest_ratchet(
+        header: &PrivateNodeHeader,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Option<Ratchet>> {
         let current_name = &header.get_saturated_name_hash();
         if !forest.has(current_name, store).await? {
-            return Ok(vec![self.clone()]);
+            return Ok(None);
         }
 
         // Start an exponential search, starting with a small jump.
@@ -422,7 +631,18 @@ impl PrivateNode {
             }
         }
 
-        current_header.ratchet = search.current().clone();
+        Ok(Some(search.current().clone()))
+    }
+
+    /// Loads all nodes stored at the revision of given header with the given ratchet.
+    async fn load_revision(
+        header: &PrivateNodeHeader,
+        ratchet: Ratchet,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Vec<PrivateNode>> {
+        let mut current_header = header.clone();
+        current_header.ratchet = ratchet;
 
         Ok(forest
             .get_multivalue(&current_header.derive_revision_ref(), store)
@@ -473,16 +693,72 @@ impl PrivateNode {
         forest: &PrivateForest,
         store: &impl BlockStore,
     ) -> Result<PrivateNode> {
-        let cid = match forest
+        let cids = match forest
             .get_encrypted(&private_ref.saturated_name_hash, store)
             .await?
         {
-            Some(cids) if cids.contains(&private_ref.content_cid) => private_ref.content_cid,
+            Some(cids) if cids.contains(&private_ref.content_cid) => cids,
             _ => return Err(FsError::NotFound.into()),
         };
 
-        // let snapshot_key = private_ref.temporal_key.derive_snapshot_key();
-        Self::from_cid(cid, &private_ref.temporal_key, store).await
+        match Self::from_cid(private_ref.content_cid, &private_ref.temporal_key, store).await {
+            Err(e) if e.downcast_ref::<AesError>().is_some() && cids.len() > 1 => {
+                // If nothing at this label decrypts with our key, the label must be shared
+                // with some unrelated node, and we can't tell which value is ours.
+                let snapshot_key = private_ref.temporal_key.derive_snapshot_key();
+                for cid in cids {
+                    let bytes = store.get_block(cid).await?;
+                    if snapshot_key.decrypt(&bytes).is_ok() {
+                        return Err(e);
+                    }
+                }
+
+                Err(FsError::LabelCollision.into())
+            }
+            result => result,
+        }
+    }
+
+    /// Loads every node stored at the revision, in order of their CIDs.
+    ///
+    /// Concurrent writes of the same revision end up next to each other in the forest. Loading
+    /// all of them lets callers detect such divergent writes and resolve them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateNode, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let revision_ref = dir.header.derive_revision_ref();
+    ///     let nodes = PrivateNode::load_all(&revision_ref, forest, store).await.unwrap();
+    ///
+    ///     assert_eq!(nodes, vec![PrivateNode::Dir(dir)]);
+    /// }
+    /// ```
+    pub async fn load_all(
+        revision_ref: &RevisionRef,
+        forest: &PrivateForest,
+        store: &impl BlockStore,
+    ) -> Result<Vec<PrivateNode>> {
+        forest
+            .get_multivalue(revision_ref, store)
+            .try_collect()
+            .await
     }
 
     /// A version of the load function designed to work when only a SnapshotKey is available
@@ -506,6 +782,7 @@ impl PrivateNode {
     ) -> Result<PrivateNode> {
         let encrypted_bytes = store.get_block(&cid).await?;
         let bytes = snapshot_key.decrypt(&encrypted_bytes)?;
+        dagcbor::check_limits(&bytes, &store.decode_limits())?;
         let node: PrivateNodeContentSerializable = serde_ipld_dagcbor::from_slice(&bytes)?;
         let node = match node {
             PrivateNodeContentSerializable::File(file) => {
@@ -513,8 +790,11 @@ impl PrivateNode {
                     PrivateFile::from_serializable_snapshot(file, snapshot_key, cid, store).await?;
                 PrivateNode::File(Rc::new(file))
             }
-            PrivateNodeContentSerializable::Dir(_) => {
-                return Err(anyhow!("Not yet able to deserialize Dir from snapshot"));
+            PrivateNodeContentSerializable::Dir(dir) => {
+                let dir =
+                    PrivateDirectory::from_serializable_snapshot(dir, snapshot_key, cid, store)
+                        .await?;
+                PrivateNode::Dir(Rc::new(dir))
             }
         };
 
@@ -529,6 +809,7 @@ impl PrivateNode {
         let encrypted_bytes = store.get_block(&cid).await?;
         let snapshot_key = temporal_key.derive_snapshot_key();
         let bytes = snapshot_key.decrypt(&encrypted_bytes)?;
+        dagcbor::check_limits(&bytes, &store.decode_limits())?;
         let node: PrivateNodeContentSerializable = serde_ipld_dagcbor::from_slice(&bytes)?;
         let node = match node {
             PrivateNodeContentSerializable::File(file) => {
@@ -574,6 +855,26 @@ impl PrivateNode {
     }
 }
 
+impl SearchLatestCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets all remembered revisions.
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    fn get(&self, key: &(INumber, HashOutput)) -> Option<Ratchet> {
+        self.0.borrow().get(key).cloned()
+    }
+
+    fn insert(&self, key: (INumber, HashOutput), ratchet: Ratchet) {
+        self.0.borrow_mut().insert(key, ratchet);
+    }
+}
+
 impl Id for PrivateNode {
     fn get_id(&self) -> String {
         match self {
@@ -602,6 +903,9 @@ impl From<PrivateDirectory> for PrivateNode {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::private::merge;
+    use futures::TryStreamExt;
+    use libipld::IpldCodec;
     use proptest::test_runner::{RngAlgorithm, TestRng};
     use wnfs_common::MemoryBlockStore;
 
@@ -653,4 +957,487 @@ mod tests {
         assert_eq!(file_node, deserialized_file_node);
         assert_eq!(dir_node, deserialized_dir_node);
     }
+
+    #[async_std::test]
+    async fn history_yields_file_revisions_newest_first() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = &["file.txt".into()];
+
+        let mut revisions = vec![];
+        for content in ["one", "two", "three"] {
+            root_dir
+                .write(
+                    path,
+                    true,
+                    Utc::now(),
+                    content.as_bytes().to_vec(),
+                    forest,
+                    store,
+                    rng,
+                )
+                .await
+                .unwrap();
+            root_dir.store(forest, store, rng).await.unwrap();
+
+            let file = root_dir
+                .lookup_node("file.txt", false, forest, store)
+                .await
+                .unwrap()
+                .unwrap();
+            revisions.push(file);
+        }
+
+        let history = revisions[2]
+            .history(&revisions[0], 1_000, forest, store)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut contents = vec![];
+        for node in history {
+            let content = node
+                .as_file()
+                .unwrap()
+                .get_content(forest, store)
+                .await
+                .unwrap();
+            contents.push(String::from_utf8(content).unwrap());
+        }
+
+        assert_eq!(contents, ["three", "two", "one"]);
+    }
+
+    #[async_std::test]
+    async fn history_follows_every_parent_of_merges() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+        let base = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        base.store(forest, store, rng).await.unwrap();
+
+        let mut sides = vec![];
+        for name in ["ours", "theirs"] {
+            let dir = &mut Rc::clone(base);
+            dir.mkdir(&[name.into()], true, Utc::now(), forest, store, rng)
+                .await
+                .unwrap();
+            dir.store(forest, store, rng).await.unwrap();
+            sides.push(Rc::clone(dir));
+        }
+
+        let outcome = merge(base, &sides[0], &sides[1], forest, store, rng)
+            .await
+            .unwrap();
+
+        let history = PrivateNode::Dir(outcome.dir)
+            .history(&PrivateNode::Dir(Rc::clone(base)), 1_000, forest, store)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let mut entries = vec![];
+        for node in &history[1..] {
+            let names = node
+                .as_dir()
+                .unwrap()
+                .get_entries()
+                .cloned()
+                .collect::<Vec<_>>();
+            entries.push(names);
+        }
+        entries[..2].sort();
+
+        assert_eq!(entries, [vec!["ours"], vec!["theirs"], vec![]]);
+    }
+
+    #[async_std::test]
+    async fn load_all_returns_every_concurrent_write() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+
+        let dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let mut nodes = vec![];
+        for name in ["ours", "theirs"] {
+            let dir = &mut Rc::clone(&dir);
+            dir.mkdir(&[name.into()], true, Utc::now(), forest, store, rng)
+                .await
+                .unwrap();
+            let node = PrivateNode::Dir(Rc::clone(dir));
+            node.store(forest, store, rng).await.unwrap();
+            nodes.push(node);
+        }
+
+        let revision_ref = dir.header.derive_revision_ref();
+        let cids = forest
+            .get_encrypted(&revision_ref.saturated_name_hash, store)
+            .await
+            .unwrap()
+            .unwrap();
+        let loaded = PrivateNode::load_all(&revision_ref, forest, store)
+            .await
+            .unwrap();
+
+        // Both writes share the header block
+        assert_eq!(cids.len(), 3);
+        assert_eq!(loaded.len(), 2);
+        for node in nodes {
+            assert!(loaded.contains(&node));
+        }
+    }
+
+    #[async_std::test]
+    async fn loading_with_mismatched_key_reports_label_collision() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+
+        let file = PrivateNode::File(Rc::new(PrivateFile::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        )));
+        let private_ref = file.store(forest, store, rng).await.unwrap();
+
+        // Force a second node's value, encrypted under an unrelated key, onto the same label.
+        let foreign_key = TemporalKey::from(&Ratchet::zero([7; 32]));
+        let foreign_bytes = foreign_key
+            .derive_snapshot_key()
+            .encrypt(b"unrelated node", rng)
+            .unwrap();
+        let foreign_cid = store
+            .put_block(foreign_bytes, IpldCodec::Raw)
+            .await
+            .unwrap();
+        forest
+            .put_encrypted(file.get_header().get_saturated_name(), [foreign_cid], store)
+            .await
+            .unwrap();
+
+        // Nothing at the label decrypts with this key.
+        let colliding_ref = PrivateRef {
+            temporal_key: TemporalKey::from(&Ratchet::zero([8; 32])),
+            ..private_ref.clone()
+        };
+        let error = PrivateNode::load(&colliding_ref, forest, store)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::LabelCollision)
+        ));
+
+        // The foreign value decrypts with this key, so the label isn't ambiguous for it.
+        let foreign_ref = PrivateRef {
+            temporal_key: foreign_key,
+            ..private_ref.clone()
+        };
+        let error = PrivateNode::load(&foreign_ref, forest, store)
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<AesError>().is_some());
+
+        assert_eq!(
+            PrivateNode::load(&private_ref, forest, store)
+                .await
+                .unwrap(),
+            file
+        );
+    }
+
+    #[async_std::test]
+    async fn cached_search_latest_resumes_from_newest_known_revision() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+        let cache = &SearchLatestCache::new();
+
+        let dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        dir.store(forest, store, rng).await.unwrap();
+        let original = PrivateNode::Dir(Rc::clone(dir));
+
+        for _ in 0..20 {
+            dir.prepare_next_revision().unwrap();
+            dir.store(forest, store, rng).await.unwrap();
+        }
+
+        let uncached = original.search_latest(forest, store).await.unwrap();
+        for _ in 0..2 {
+            let cached = original
+                .search_latest_cached(forest, store, cache)
+                .await
+                .unwrap();
+            assert_eq!(cached, uncached);
+        }
+
+        for _ in 0..5 {
+            dir.prepare_next_revision().unwrap();
+            dir.store(forest, store, rng).await.unwrap();
+        }
+
+        let cached = original
+            .search_latest_cached(forest, store, cache)
+            .await
+            .unwrap();
+        assert_eq!(cached, PrivateNode::Dir(Rc::clone(dir)));
+
+        cache.clear();
+        let unknown = PrivateNode::Dir(Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        )));
+        let found = unknown
+            .search_latest_cached(forest, store, cache)
+            .await
+            .unwrap();
+        assert_eq!(found, unknown);
+    }
+
+    #[async_std::test]
+    async fn nested_nodes_load_from_snapshot_key() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let path = ["docs".to_string(), "nested".into(), "notes.txt".into()];
+        root_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                b"Hello".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+
+        let snapshot = SnapshotSharePointer {
+            label: private_ref.saturated_name_hash,
+            content_cid: private_ref.content_cid,
+            snapshot_key: private_ref.temporal_key.derive_snapshot_key(),
+        };
+        let shared_dir = PrivateNode::load_from_snapshot(snapshot, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+
+        let content = shared_dir.read(&path, false, forest, store).await.unwrap();
+        assert_eq!(content, b"Hello");
+
+        // Without the ratchets, none of the shared nodes can derive the keys of later revisions
+        let nested_dir = shared_dir
+            .get_node(&path[..2], false, forest, store)
+            .await
+            .unwrap()
+            .unwrap();
+        let original_nested_dir = root_dir
+            .get_node(&path[..2], false, forest, store)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(
+            shared_dir.header.derive_temporal_key(),
+            private_ref.temporal_key
+        );
+        assert_ne!(
+            nested_dir.get_header().derive_temporal_key(),
+            original_nested_dir.get_header().derive_temporal_key()
+        );
+    }
+
+    #[async_std::test]
+    async fn writes_through_snapshot_share_are_rejected() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let path = ["docs".to_string(), "notes.txt".into()];
+        root_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                b"Hello".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+
+        let snapshot = SnapshotSharePointer {
+            label: private_ref.saturated_name_hash,
+            content_cid: private_ref.content_cid,
+            snapshot_key: private_ref.temporal_key.derive_snapshot_key(),
+        };
+        let shared_dir = &mut PrivateNode::load_from_snapshot(snapshot, forest, store)
+            .await
+            .unwrap()
+            .as_dir()
+            .unwrap();
+
+        let is_read_only = |error: anyhow::Error| {
+            matches!(
+                error.downcast_ref::<FsError>(),
+                Some(FsError::ReadOnlySnapshot)
+            )
+        };
+
+        let error = shared_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                b"Overwritten".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap_err();
+        assert!(is_read_only(error));
+
+        let error = shared_dir.store(forest, store, rng).await.unwrap_err();
+        assert!(is_read_only(error));
+
+        let shared_file = &mut shared_dir
+            .get_node(&path, false, forest, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .as_file()
+            .unwrap();
+        let error = shared_file.prepare_next_revision().unwrap_err();
+        assert!(is_read_only(error));
+
+        let content = root_dir.read(&path, false, forest, store).await.unwrap();
+        assert_eq!(content, b"Hello");
+    }
+
+    #[async_std::test]
+    async fn snapshot_children_are_loaded_on_access() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let forest = &mut Rc::new(PrivateForest::new());
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let path = ["docs".to_string(), "nested".into(), "notes.txt".into()];
+        root_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                b"Hello".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let private_ref = root_dir.store(forest, store, rng).await.unwrap();
+        let empty_ref = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ))
+        .store(forest, store, rng)
+        .await
+        .unwrap();
+
+        let blocks_read_loading = |private_ref: PrivateRef| async move {
+            store.reset_stats();
+            let snapshot_key = private_ref.temporal_key.derive_snapshot_key();
+            let node =
+                PrivateNode::from_cid_snapshot(private_ref.content_cid, &snapshot_key, store)
+                    .await
+                    .unwrap();
+            (node, store.stats().unwrap().blocks_read)
+        };
+
+        // Loading the root fetches no more blocks than loading an empty directory
+        let (_, empty_reads) = blocks_read_loading(empty_ref).await;
+        let (shared_node, root_reads) = blocks_read_loading(private_ref).await;
+        assert_eq!(root_reads, empty_reads);
+
+        let shared_dir = shared_node.as_dir().unwrap();
+        let content = shared_dir.read(&path, false, forest, store).await.unwrap();
+        assert_eq!(content, b"Hello");
+        assert!(store.stats().unwrap().blocks_read > root_reads);
+    }
+
+    #[test]
+    fn casts_only_succeed_for_the_matching_variant() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let file = Rc::new(PrivateFile::new(Namefilter::default(), Utc::now(), rng));
+        let dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let mut file_node = PrivateNode::File(Rc::clone(&file));
+        let mut dir_node = PrivateNode::Dir(Rc::clone(&dir));
+
+        assert_eq!(file_node.as_file().unwrap(), file);
+        assert_eq!(file_node.as_file_mut().unwrap(), &file);
+        assert_eq!(dir_node.as_dir().unwrap(), dir);
+        assert_eq!(dir_node.as_dir_mut().unwrap(), &dir);
+
+        for error in [
+            dir_node.as_file().unwrap_err(),
+            dir_node.as_file_mut().unwrap_err(),
+        ] {
+            assert!(matches!(
+                error.downcast_ref::<FsError>(),
+                Some(FsError::UnexpectedNodeType(NodeType::PrivateFile))
+            ));
+        }
+        for error in [
+            file_node.as_dir().unwrap_err(),
+            file_node.as_dir_mut().unwrap_err(),
+        ] {
+            assert!(matches!(
+                error.downcast_ref::<FsError>(),
+                Some(FsError::UnexpectedNodeType(NodeType::PrivateDirectory))
+            ));
+        }
+    }
 }
@@ -7,7 +7,7 @@ use anyhow::{bail, Result};
 use libipld::Cid;
 use skip_ratchet::{ratchet::PreviousIterator, Ratchet};
 use std::{collections::BTreeSet, rc::Rc};
-use wnfs_common::{BlockStore, PathNodes, PathNodesResult};
+use wnfs_common::{BlockStore, NodeType, PathNodes, PathNodesResult};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -151,7 +151,7 @@ impl PrivateNodeHistory {
     /// Like `previous_node`, but attempts to resolve a directory.
     ///
     /// Returns `None` if there is no previous node with that revision in the `PrivateForest`,
-    /// throws `FsError::NotADirectory` if the previous node happens to not be a directory.
+    /// throws `FsError::UnexpectedNodeType` if the previous node happens to not be a directory.
     /// That should only happen for all nodes or for none.
     pub async fn get_previous_dir(
         &mut self,
@@ -159,7 +159,7 @@ impl PrivateNodeHistory {
     ) -> Result<Option<Rc<PrivateDirectory>>> {
         match self.get_previous_node(store).await? {
             Some(PrivateNode::Dir(dir)) => Ok(Some(dir)),
-            Some(_) => Err(FsError::NotADirectory.into()),
+            Some(_) => Err(FsError::UnexpectedNodeType(NodeType::PrivateDirectory).into()),
             None => Ok(None),
         }
     }
@@ -167,7 +167,7 @@ impl PrivateNodeHistory {
     /// Like `previous_node`, but attempts to resolve a file.
     ///
     /// Returns `None` if there is no previous node with that revision in the `PrivateForest`,
-    /// throws `FsError::NotAFile` if the previous node happens to not be a file.
+    /// throws `FsError::UnexpectedNodeType` if the previous node happens to not be a file.
     /// That should only happen for all nodes or for none.
     pub async fn get_previous_file(
         &mut self,
@@ -175,7 +175,7 @@ impl PrivateNodeHistory {
     ) -> Result<Option<Rc<PrivateFile>>> {
         match self.get_previous_node(store).await? {
             Some(PrivateNode::File(file)) => Ok(Some(file)),
-            Some(_) => Err(FsError::NotAFile.into()),
+            Some(_) => Err(FsError::UnexpectedNodeType(NodeType::PrivateFile).into()),
             None => Ok(None),
         }
     }
@@ -285,7 +285,9 @@ impl PrivateNodeOnPathHistory {
         {
             PathNodesResult::Complete(path_nodes) => path_nodes,
             PathNodesResult::MissingLink(_, _) => bail!(FsError::NotFound),
-            PathNodesResult::NotADirectory(_, _) => bail!(FsError::NotADirectory),
+            PathNodesResult::NotADirectory(path_nodes, _) => bail!(FsError::NotADirectory(
+                path_segments[..=path_nodes.path.len()].to_vec()
+            )),
         };
 
         let Some(target) = (*path_nodes.tail)
@@ -417,7 +419,12 @@ impl PrivateNodeOnPathHistory {
             // Pop elements off the end of the path
             if let Some(mut segment) = self.path.pop() {
                 // Try to find a path segment for which we have previous history entries
-                if let Some(prev) = segment.history.get_previous_dir(store).await? {
+                let prev = segment
+                    .history
+                    .get_previous_dir(store)
+                    .await
+                    .map_err(|e| self.not_a_directory_at_stack(e))?;
+                if let Some(prev) = prev {
                     segment.dir = prev;
                     self.path.push(segment);
                     // Once found, we can continue.
@@ -481,7 +488,11 @@ impl PrivateNodeOnPathHistory {
             };
 
             // We need to find the in-between history entry! See the test case `previous_with_multiple_child_changes`.
-            let Some(directory_prev) = directory_history.get_previous_dir(store).await? else {
+            let directory_prev = directory_history
+                .get_previous_dir(store)
+                .await
+                .map_err(|e| self.not_a_directory_at_stack(e))?;
+            let Some(directory_prev) = directory_prev else {
                 return Ok(false);
             };
 
@@ -494,6 +505,21 @@ impl PrivateNodeOnPathHistory {
 
         Ok(true)
     }
+
+    /// Reports a type mismatch in the history of the directory below the
+    /// path segment history stack, using that directory's path.
+    fn not_a_directory_at_stack(&self, error: anyhow::Error) -> anyhow::Error {
+        match error.downcast_ref::<FsError>() {
+            Some(FsError::UnexpectedNodeType(_)) => FsError::NotADirectory(
+                self.path
+                    .iter()
+                    .map(|segment| segment.path_segment.clone())
+                    .collect(),
+            )
+            .into(),
+            _ => error,
+        }
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -0,0 +1,220 @@
+use super::PrivateForest;
+use anyhow::Result;
+use async_trait::async_trait;
+use libipld::{Cid, IpldCodec};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, cell::RefCell, collections::BTreeSet};
+use wnfs_common::{dagcbor::DecodeLimits, BlockStore, HashOutput, MemoryBlockStore};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A proof that a label is stored in a forest with a given root CID.
+///
+/// It holds the forest's root block and the HAMT node blocks on the path to the label, and
+/// nothing else. The blocks refer to each other by CID, so tampering with any of them breaks
+/// the proof.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HamtProof {
+    blocks: Vec<Vec<u8>>,
+}
+
+/// Records the bytes of all blocks fetched through it.
+struct RecordingBlockStore<'a, B: BlockStore> {
+    inner: &'a B,
+    fetched: RefCell<Vec<Vec<u8>>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl HamtProof {
+    /// Collects the blocks on the path from the forest's root to the label.
+    ///
+    /// Returns `None` if the label isn't in the forest.
+    pub(crate) async fn generate(
+        root_cid: &Cid,
+        label_hash: &HashOutput,
+        store: &impl BlockStore,
+    ) -> Result<Option<Self>> {
+        let recording_store = RecordingBlockStore::new(store);
+
+        // Loading the forest afresh makes sure every node on the path gets fetched.
+        let forest = PrivateForest::load(root_cid, &recording_store).await?;
+        if forest
+            .get_encrypted(label_hash, &recording_store)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            blocks: recording_store.into_fetched(),
+        }))
+    }
+
+    /// Checks that the forest with the given root CID stores exactly `value` at the label.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateDirectory},
+    ///     common::MemoryBlockStore,
+    ///     namefilter::Namefilter,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = &mut Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = dir.store(forest, store, rng).await.unwrap();
+    ///
+    ///     let label_hash = &private_ref.saturated_name_hash;
+    ///     let root_cid = forest.store(store).await.unwrap();
+    ///     let proof = forest.prove_inclusion(label_hash, store).await.unwrap();
+    ///     let value = forest.get_encrypted(label_hash, store).await.unwrap().unwrap();
+    ///
+    ///     assert!(proof.verify(&root_cid, label_hash, value).await);
+    /// }
+    /// ```
+    pub async fn verify(
+        &self,
+        root_cid: &Cid,
+        label_hash: &HashOutput,
+        value: &BTreeSet<Cid>,
+    ) -> bool {
+        let store = MemoryBlockStore::new();
+        for block in self.blocks.iter() {
+            if store
+                .put_block(block.clone(), IpldCodec::DagCbor)
+                .await
+                .is_err()
+            {
+                return false;
+            }
+        }
+
+        let Ok(forest) = PrivateForest::load(root_cid, &store).await else {
+            return false;
+        };
+
+        matches!(
+            forest.get_encrypted(label_hash, &store).await,
+            Ok(Some(stored)) if stored == value
+        )
+    }
+}
+
+impl<'a, B: BlockStore> RecordingBlockStore<'a, B> {
+    fn new(inner: &'a B) -> Self {
+        Self {
+            inner,
+            fetched: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn into_fetched(self) -> Vec<Vec<u8>> {
+        self.fetched.into_inner()
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: BlockStore> BlockStore for RecordingBlockStore<'_, B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<Vec<u8>>> {
+        let bytes = self.inner.get_block(cid).await?;
+        self.fetched.borrow_mut().push(bytes.to_vec());
+        Ok(bytes)
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        self.inner.put_block(bytes, codec).await
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.inner.decode_limits()
+    }
+
+    fn allowed_codecs(&self) -> &[IpldCodec] {
+        self.inner.allowed_codecs()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private::{PrivateDirectory, PrivateRef};
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use std::rc::Rc;
+    use test_log::test;
+    use wnfs_namefilter::Namefilter;
+
+    async fn setup() -> (MemoryBlockStore, Rc<PrivateForest>, Cid, PrivateRef) {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = MemoryBlockStore::new();
+        let forest = &mut Rc::new(PrivateForest::new());
+
+        // Enough nodes for the HAMT to grow a few levels
+        let mut private_ref = None;
+        for _ in 0..100 {
+            let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+            private_ref = Some(dir.store(forest, &store, rng).await.unwrap());
+        }
+
+        let root_cid = forest.store(&store).await.unwrap();
+        (store, Rc::clone(forest), root_cid, private_ref.unwrap())
+    }
+
+    #[test(async_std::test)]
+    async fn proof_verifies_against_root_cid() {
+        let (store, forest, root_cid, private_ref) = setup().await;
+        let label_hash = &private_ref.saturated_name_hash;
+
+        let proof = forest.prove_inclusion(label_hash, &store).await.unwrap();
+        let value = forest.get_encrypted(label_hash, &store).await.unwrap();
+
+        // The proof only holds the path, not the whole HAMT
+        assert!(proof.blocks.len() > 1);
+        assert!(proof.blocks.len() < store.list_blocks().await.unwrap().len());
+        assert!(proof.verify(&root_cid, label_hash, value.unwrap()).await);
+    }
+
+    #[test(async_std::test)]
+    async fn tampered_proofs_and_values_fail_verification() {
+        let (store, forest, root_cid, private_ref) = setup().await;
+        let label_hash = &private_ref.saturated_name_hash;
+
+        let proof = forest.prove_inclusion(label_hash, &store).await.unwrap();
+        let value = forest
+            .get_encrypted(label_hash, &store)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut tampered_value = value.clone();
+        tampered_value.remove(&private_ref.content_cid);
+        assert!(!proof.verify(&root_cid, label_hash, &tampered_value).await);
+
+        let mut tampered_proof = proof.clone();
+        let last_block = tampered_proof.blocks.last_mut().unwrap();
+        let last_byte = last_block.len() - 1;
+        last_block[last_byte] ^= 1;
+        assert!(!tampered_proof.verify(&root_cid, label_hash, value).await);
+
+        let other_label = &[0; 32];
+        assert!(!proof.verify(&root_cid, other_label, value).await);
+    }
+}
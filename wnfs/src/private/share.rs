@@ -221,7 +221,7 @@ pub mod sharer {
     use async_stream::try_stream;
     use futures::{Stream, StreamExt};
     use libipld::IpldCodec;
-    use std::rc::Rc;
+    use std::{collections::BTreeSet, rc::Rc};
     use wnfs_common::BlockStore;
     use wnfs_namefilter::Namefilter;
 
@@ -243,20 +243,81 @@ pub mod sharer {
 
         while let Some(result) = exchange_keys.next().await {
             let public_key_modulus = result?;
-            let exchange_key = K::from_modulus(&public_key_modulus).await?;
-            let encrypted_payload = exchange_key.encrypt(encoded_payload).await?;
-            let share_label = create_share_label(share_count, sharer_root_did, &public_key_modulus);
+            share_encoded::<K>(
+                encoded_payload,
+                share_count,
+                sharer_root_did,
+                &public_key_modulus,
+                sharer_forest,
+                sharer_store,
+            )
+            .await?;
+        }
 
-            let payload_cid = sharer_store
-                .put_block(encrypted_payload, IpldCodec::Raw)
-                .await?;
+        Ok(())
+    }
 
-            sharer_forest
-                .put_encrypted(share_label, Some(payload_cid), sharer_store)
-                .await?;
+    /// Encrypts and shares a payload with each of the given recipient exchange keys and stores the
+    /// shares in the sharer's private forest.
+    ///
+    /// The payload is only shared once per distinct exchange key. Returns the share labels in
+    /// the order of the exchange keys they were created for.
+    pub async fn share_with_many<K: ExchangeKey>(
+        share_payload: &SharePayload,
+        share_count: u64,
+        sharer_root_did: &str,
+        recipient_exchange_keys: &[PublicKeyModulus],
+        sharer_forest: &mut Rc<PrivateForest>,
+        sharer_store: &impl BlockStore,
+    ) -> Result<Vec<Namefilter>> {
+        let encoded_payload = &serde_ipld_dagcbor::to_vec(share_payload)?;
+        let mut seen = BTreeSet::new();
+        let mut share_labels = Vec::new();
+
+        for public_key_modulus in recipient_exchange_keys {
+            if !seen.insert(public_key_modulus) {
+                continue;
+            }
+
+            let share_label = share_encoded::<K>(
+                encoded_payload,
+                share_count,
+                sharer_root_did,
+                public_key_modulus,
+                sharer_forest,
+                sharer_store,
+            )
+            .await?;
+
+            share_labels.push(share_label);
         }
 
-        Ok(())
+        Ok(share_labels)
+    }
+
+    /// Encrypts an encoded payload with an exchange key and stores it under the share label
+    /// for that key.
+    async fn share_encoded<K: ExchangeKey>(
+        encoded_payload: &[u8],
+        share_count: u64,
+        sharer_root_did: &str,
+        public_key_modulus: &[u8],
+        sharer_forest: &mut Rc<PrivateForest>,
+        sharer_store: &impl BlockStore,
+    ) -> Result<Namefilter> {
+        let exchange_key = K::from_modulus(public_key_modulus).await?;
+        let encrypted_payload = exchange_key.encrypt(encoded_payload).await?;
+        let share_label = create_share_label(share_count, sharer_root_did, public_key_modulus);
+
+        let payload_cid = sharer_store
+            .put_block(encrypted_payload, IpldCodec::Raw)
+            .await?;
+
+        sharer_forest
+            .put_encrypted(share_label.clone(), Some(payload_cid), sharer_store)
+            .await?;
+
+        Ok(share_label)
     }
 
     /// Fetches the exchange keys of recipients using their exchange root, resolve the root_dir,
@@ -398,7 +459,7 @@ mod tests {
         sharer, Recipient, Share, SharePayload, Sharer, EXCHANGE_KEY_NAME,
     };
     use crate::{
-        private::{PrivateDirectory, PrivateForest, RsaPublicKey},
+        private::{PrivateDirectory, PrivateForest, RsaPrivateKey, RsaPublicKey},
         public::{PublicLink, PublicNode},
     };
     use chrono::Utc;
@@ -728,4 +789,48 @@ mod tests {
         // We expect the count to be the latest share
         assert_eq!(max_share_count, Some(expected_max_share_count));
     }
+
+    #[async_std::test]
+    async fn can_share_with_many_recipients_at_once() {
+        let store = &MemoryBlockStore::default();
+        let forest = &mut Rc::new(PrivateForest::new());
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+
+        let sharer_root_did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let sharer_dir = helper::create_sharer_dir(forest, store, rng).await.unwrap();
+        let payload = SharePayload::from_node(&sharer_dir.as_node(), true, forest, store, rng)
+            .await
+            .unwrap();
+
+        let recipient_keys = (0..3)
+            .map(|_| RsaPrivateKey::new().unwrap())
+            .collect::<Vec<_>>();
+        let mut exchange_keys = recipient_keys
+            .iter()
+            .map(|key| key.get_public_key().get_public_key_modulus().unwrap())
+            .collect::<Vec<_>>();
+        exchange_keys.push(exchange_keys[0].clone());
+
+        let share_labels = sharer::share_with_many::<RsaPublicKey>(
+            &payload,
+            0,
+            sharer_root_did,
+            &exchange_keys,
+            forest,
+            store,
+        )
+        .await
+        .unwrap();
+
+        // The duplicate key doesn't get a share of its own
+        assert_eq!(share_labels.len(), 3);
+
+        for (share_label, recipient_key) in share_labels.into_iter().zip(recipient_keys.iter()) {
+            let node = recipient::receive_share(share_label, recipient_key, forest, store)
+                .await
+                .unwrap();
+
+            assert_eq!(node.as_dir().unwrap(), sharer_dir);
+        }
+    }
 }
@@ -0,0 +1,21 @@
+use super::{PrivateDirectory, PrivateForest};
+use std::rc::Rc;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The working state of a [`PrivateDirectory::transaction`].
+///
+/// Mutations go against `dir` and `forest`, which start out as copies of the directory and forest
+/// the transaction was opened on. They only replace those if the transaction commits.
+pub struct Transaction<'a, B, R> {
+    /// The working copy of the directory.
+    pub dir: Rc<PrivateDirectory>,
+    /// The working copy of the forest.
+    pub forest: Rc<PrivateForest>,
+    /// The block store the transaction writes to.
+    pub store: &'a B,
+    /// The random number generator the transaction uses.
+    pub rng: &'a mut R,
+}
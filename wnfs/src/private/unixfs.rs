@@ -0,0 +1,417 @@
+//! Converts between private nodes and UnixFS DAGs, the format IPFS gateways serve files in.
+
+use super::{PrivateForest, PrivateNode};
+use crate::error::FsError;
+use anyhow::Result;
+use async_recursion::async_recursion;
+use libipld::{codec::Codec, Cid, Ipld, IpldCodec};
+use std::collections::BTreeMap;
+use wnfs_common::{BlockStore, MAX_BLOCK_SIZE};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The UnixFS data type of raw data nodes.
+const UNIXFS_RAW: u64 = 0;
+
+/// The UnixFS data type of directories.
+const UNIXFS_DIRECTORY: u64 = 1;
+
+/// The UnixFS data type of files.
+const UNIXFS_FILE: u64 = 2;
+
+/// The most links a file node has, the same as in the balanced layout of go-unixfs.
+const MAX_LINKS_PER_NODE: usize = 174;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block written to the output store, with its CID and the size of the DAG below it.
+#[derive(Debug, Clone, Copy)]
+struct UnixFsBlock {
+    cid: Cid,
+    /// The size of the block and all blocks it links to, in bytes.
+    tsize: u64,
+    /// The number of file bytes below the block.
+    filesize: u64,
+}
+
+/// A UnixFS node read from a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum UnixFsNode {
+    /// A file with its reassembled content.
+    File(Vec<u8>),
+    /// A directory with the names and CIDs of its entries.
+    Directory(Vec<(String, Cid)>),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Decrypts a node and its descendants and writes them into `out_store` as a UnixFS DAG.
+///
+/// Returns the CID of the root of the DAG.
+pub(crate) async fn export_node(
+    node: &PrivateNode,
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+    out_store: &impl BlockStore,
+) -> Result<Cid> {
+    Ok(export_node_helper(node, forest, store, out_store)
+        .await?
+        .cid)
+}
+
+#[async_recursion(?Send)]
+async fn export_node_helper(
+    node: &PrivateNode,
+    forest: &PrivateForest,
+    store: &impl BlockStore,
+    out_store: &impl BlockStore,
+) -> Result<UnixFsBlock> {
+    match node {
+        PrivateNode::File(file) => {
+            let content = file.get_content(forest, store).await?;
+            put_file(&content, out_store).await
+        }
+        PrivateNode::Dir(dir) => {
+            let mut links = Vec::with_capacity(dir.content.entries.len());
+            for (name, private_link) in dir.content.entries.iter() {
+                let child = private_link.resolve_node(forest, store).await?;
+                let block = export_node_helper(child, forest, store, out_store).await?;
+                links.push((Some(name.clone()), block));
+            }
+
+            put_pb_node(
+                links,
+                encode_data(UNIXFS_DIRECTORY, None, &[]),
+                0,
+                out_store,
+            )
+            .await
+        }
+    }
+}
+
+/// Writes file content as raw leaves, linked by a balanced tree of UnixFS file nodes if it
+/// doesn't fit into a single block.
+async fn put_file(content: &[u8], out_store: &impl BlockStore) -> Result<UnixFsBlock> {
+    let mut layer = Vec::with_capacity(content.len() / MAX_BLOCK_SIZE + 1);
+    for chunk in content.chunks(MAX_BLOCK_SIZE) {
+        layer.push(UnixFsBlock {
+            cid: out_store.put_block(chunk.to_vec(), IpldCodec::Raw).await?,
+            tsize: chunk.len() as u64,
+            filesize: chunk.len() as u64,
+        });
+    }
+
+    if layer.is_empty() {
+        layer.push(UnixFsBlock {
+            cid: out_store.put_block(vec![], IpldCodec::Raw).await?,
+            tsize: 0,
+            filesize: 0,
+        });
+    }
+
+    while layer.len() > 1 {
+        let mut parents = Vec::with_capacity(layer.len() / MAX_LINKS_PER_NODE + 1);
+        for children in layer.chunks(MAX_LINKS_PER_NODE) {
+            let filesize = children.iter().map(|child| child.filesize).sum();
+            let blocksizes = children
+                .iter()
+                .map(|child| child.filesize)
+                .collect::<Vec<_>>();
+            let data = encode_data(UNIXFS_FILE, Some(filesize), &blocksizes);
+            let links = children.iter().map(|child| (None, *child)).collect();
+
+            parents.push(put_pb_node(links, data, filesize, out_store).await?);
+        }
+        layer = parents;
+    }
+
+    Ok(layer[0])
+}
+
+/// Writes a dag-pb node with the given links and UnixFS data.
+async fn put_pb_node(
+    links: Vec<(Option<String>, UnixFsBlock)>,
+    data: Vec<u8>,
+    filesize: u64,
+    out_store: &impl BlockStore,
+) -> Result<UnixFsBlock> {
+    let children_tsize = links.iter().map(|(_, block)| block.tsize).sum::<u64>();
+    let links = links
+        .into_iter()
+        .map(|(name, block)| {
+            let mut link = BTreeMap::from([
+                ("Hash".to_string(), Ipld::Link(block.cid)),
+                ("Tsize".to_string(), Ipld::Integer(block.tsize as i128)),
+            ]);
+            if let Some(name) = name {
+                link.insert("Name".to_string(), Ipld::String(name));
+            }
+            Ipld::Map(link)
+        })
+        .collect();
+
+    let node = Ipld::Map(BTreeMap::from([
+        ("Links".to_string(), Ipld::List(links)),
+        ("Data".to_string(), Ipld::Bytes(data)),
+    ]));
+
+    let bytes = IpldCodec::DagPb.encode(&node)?;
+    let tsize = bytes.len() as u64 + children_tsize;
+
+    Ok(UnixFsBlock {
+        cid: out_store.put_block(bytes, IpldCodec::DagPb).await?,
+        tsize,
+        filesize,
+    })
+}
+
+/// Reads the UnixFS node with the given CID.
+///
+/// Files made of raw leaves as well as of dag-pb leaves are supported. Sharded directories and
+/// symlinks aren't.
+pub(crate) async fn load_node(cid: &Cid, store: &impl BlockStore) -> Result<UnixFsNode> {
+    if cid.codec() == u64::from(IpldCodec::Raw) {
+        return Ok(UnixFsNode::File(store.get_block(cid).await?.to_vec()));
+    }
+
+    let (data_type, _, links) = load_pb_node(cid, store).await?;
+    if data_type == UNIXFS_DIRECTORY {
+        let entries = links
+            .into_iter()
+            .map(|(name, cid)| {
+                Ok((
+                    name.ok_or_else(|| unixfs_error("Unnamed directory entry"))?,
+                    cid,
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        return Ok(UnixFsNode::Directory(entries));
+    }
+
+    let mut content = vec![];
+    read_file(cid, store, &mut content).await?;
+    Ok(UnixFsNode::File(content))
+}
+
+/// Appends the content of the file node with the given CID and all of its leaves.
+#[async_recursion(?Send)]
+async fn read_file(cid: &Cid, store: &impl BlockStore, content: &mut Vec<u8>) -> Result<()> {
+    if cid.codec() == u64::from(IpldCodec::Raw) {
+        content.extend_from_slice(&store.get_block(cid).await?);
+        return Ok(());
+    }
+
+    let (data_type, data, links) = load_pb_node(cid, store).await?;
+    if data_type != UNIXFS_FILE && data_type != UNIXFS_RAW {
+        Err(unixfs_error(format!(
+            "Expected a file node, found type {data_type}"
+        )))?;
+    }
+
+    content.extend_from_slice(&data);
+    for (_, link) in links {
+        read_file(&link, store, content).await?;
+    }
+
+    Ok(())
+}
+
+/// Loads a dag-pb node and returns its UnixFS data type, inline data and links.
+async fn load_pb_node(
+    cid: &Cid,
+    store: &impl BlockStore,
+) -> Result<(u64, Vec<u8>, Vec<(Option<String>, Cid)>)> {
+    if cid.codec() != u64::from(IpldCodec::DagPb) {
+        Err(unixfs_error(format!(
+            "Unsupported codec {:#x}",
+            cid.codec()
+        )))?;
+    }
+
+    let node: Ipld = IpldCodec::DagPb.decode(&store.get_block(cid).await?)?;
+    let (data_type, data) = match node.get("Data") {
+        Ok(Ipld::Bytes(data)) => decode_data(data)?,
+        _ => Err(unixfs_error("Node without UnixFS data"))?,
+    };
+
+    let links = match node.get("Links") {
+        Ok(Ipld::List(links)) => links
+            .iter()
+            .map(|link| {
+                let name = match link.get("Name") {
+                    Ok(Ipld::String(name)) if !name.is_empty() => Some(name.clone()),
+                    _ => None,
+                };
+                match link.get("Hash") {
+                    Ok(Ipld::Link(cid)) => Ok((name, *cid)),
+                    _ => Err(unixfs_error("Link without a CID").into()),
+                }
+            })
+            .collect::<Result<_>>()?,
+        _ => vec![],
+    };
+
+    Ok((data_type, data, links))
+}
+
+/// Decodes the type and inline data of a UnixFS `Data` protobuf message.
+fn decode_data(mut bytes: &[u8]) -> Result<(u64, Vec<u8>)> {
+    let mut data_type = None;
+    let mut data = vec![];
+
+    while !bytes.is_empty() {
+        let key = decode_varint(&mut bytes)?;
+        match (key >> 3, key & 0b111) {
+            (1, 0) => data_type = Some(decode_varint(&mut bytes)?),
+            (2, 2) => data = decode_length_delimited(&mut bytes)?.to_vec(),
+            (_, 0) => {
+                decode_varint(&mut bytes)?;
+            }
+            (_, 2) => {
+                decode_length_delimited(&mut bytes)?;
+            }
+            (_, wire_type) => Err(unixfs_error(format!("Unexpected wire type {wire_type}")))?,
+        }
+    }
+
+    let data_type = data_type.ok_or_else(|| unixfs_error("Missing UnixFS data type"))?;
+    Ok((data_type, data))
+}
+
+/// Reads a protobuf varint off the front of `bytes`.
+fn decode_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| unixfs_error("Truncated varint"))?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(unixfs_error("Varint too long").into())
+}
+
+/// Reads a length-delimited protobuf field off the front of `bytes`.
+fn decode_length_delimited<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = decode_varint(bytes)? as usize;
+    if len > bytes.len() {
+        Err(unixfs_error("Truncated field"))?;
+    }
+
+    let (field, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(field)
+}
+
+fn unixfs_error(message: impl Into<String>) -> FsError {
+    FsError::InvalidDeserialization(format!("Invalid UnixFS node: {}", message.into()))
+}
+
+/// Encodes the UnixFS `Data` protobuf message.
+fn encode_data(data_type: u64, filesize: Option<u64>, blocksizes: &[u64]) -> Vec<u8> {
+    let mut bytes = vec![];
+
+    encode_varint(1 << 3, &mut bytes);
+    encode_varint(data_type, &mut bytes);
+
+    if let Some(filesize) = filesize {
+        encode_varint(3 << 3, &mut bytes);
+        encode_varint(filesize, &mut bytes);
+    }
+
+    for blocksize in blocksizes {
+        encode_varint(4 << 3, &mut bytes);
+        encode_varint(*blocksize, &mut bytes);
+    }
+
+    bytes
+}
+
+/// Appends a protobuf varint.
+fn encode_varint(mut value: u64, bytes: &mut Vec<u8>) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wnfs_common::MemoryBlockStore;
+
+    #[test]
+    fn encodes_varints() {
+        let mut bytes = vec![];
+        encode_varint(1, &mut bytes);
+        encode_varint(300, &mut bytes);
+        encode_varint(262144, &mut bytes);
+
+        assert_eq!(bytes, [0x01, 0xac, 0x02, 0x80, 0x80, 0x10]);
+    }
+
+    #[test]
+    fn encodes_file_data() {
+        let data = encode_data(UNIXFS_FILE, Some(300), &[256, 44]);
+
+        assert_eq!(
+            data,
+            [0x08, 0x02, 0x18, 0xac, 0x02, 0x20, 0x80, 0x02, 0x20, 0x2c]
+        );
+    }
+
+    #[test]
+    fn decodes_inline_file_data() {
+        // Type File, Data "hi", filesize 2, blocksizes [2]
+        let bytes = [0x08, 0x02, 0x12, 0x02, b'h', b'i', 0x18, 0x02, 0x20, 0x02];
+
+        assert_eq!(decode_data(&bytes).unwrap(), (UNIXFS_FILE, b"hi".to_vec()));
+        assert!(decode_data(&bytes[..4]).is_err());
+    }
+
+    #[async_std::test]
+    async fn reads_files_with_dag_pb_leaves() {
+        let store = &MemoryBlockStore::default();
+
+        let mut leaves = vec![];
+        for chunk in [&b"Hello, "[..], &b"World!"[..]] {
+            let mut data = encode_data(UNIXFS_RAW, None, &[]);
+            data.extend([0x12, chunk.len() as u8]);
+            data.extend_from_slice(chunk);
+            let leaf = put_pb_node(vec![], data, chunk.len() as u64, store)
+                .await
+                .unwrap();
+            leaves.push((None, leaf));
+        }
+        let root = put_pb_node(
+            leaves,
+            encode_data(UNIXFS_FILE, Some(13), &[7, 6]),
+            13,
+            store,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            load_node(&root.cid, store).await.unwrap(),
+            UnixFsNode::File(b"Hello, World!".to_vec())
+        );
+    }
+}
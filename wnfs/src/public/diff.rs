@@ -0,0 +1,226 @@
+use super::{PublicDirectory, PublicNode};
+use anyhow::Result;
+use async_recursion::async_recursion;
+use std::rc::Rc;
+use wnfs_common::BlockStore;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A change to a node between two revisions of a directory, with the path to the node.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeChange {
+    /// The node only exists in the new revision.
+    Added(Vec<String>),
+    /// The node only exists in the old revision. Its descendants aren't listed separately.
+    Removed(Vec<String>),
+    /// The file's content or metadata changed, or the node turned into a file from a directory
+    /// or the other way around.
+    Modified(Vec<String>),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Compares two revisions of a directory and returns the changes made to get from `old` to `new`.
+///
+/// Entries are compared recursively. Subtrees with the same CID are skipped without being
+/// loaded. Entries that haven't been stored yet are stored to get their CIDs.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use chrono::Utc;
+/// use libipld::Cid;
+/// use wnfs::{
+///     public::{diff, NodeChange, PublicDirectory},
+///     common::MemoryBlockStore,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &MemoryBlockStore::default();
+///     let old_dir = Rc::new(PublicDirectory::new(Utc::now()));
+///
+///     let new_dir = &mut Rc::clone(&old_dir);
+///     new_dir
+///         .write(&["hello.txt".into()], Cid::default(), Utc::now(), store)
+///         .await
+///         .unwrap();
+///
+///     let changes = diff(&old_dir, new_dir, store).await.unwrap();
+///
+///     assert_eq!(changes, vec![NodeChange::Added(vec!["hello.txt".into()])]);
+/// }
+/// ```
+pub async fn diff(
+    old: &Rc<PublicDirectory>,
+    new: &Rc<PublicDirectory>,
+    store: &impl BlockStore,
+) -> Result<Vec<NodeChange>> {
+    let mut changes = vec![];
+    diff_helper(&mut vec![], old, new, store, &mut changes).await?;
+    Ok(changes)
+}
+
+#[async_recursion(?Send)]
+async fn diff_helper(
+    path: &mut Vec<String>,
+    old: &PublicDirectory,
+    new: &PublicDirectory,
+    store: &impl BlockStore,
+    changes: &mut Vec<NodeChange>,
+) -> Result<()> {
+    let old_entries = &old.userland;
+    let new_entries = &new.userland;
+
+    for (name, old_link) in old_entries {
+        path.push(name.clone());
+        match new_entries.get(name) {
+            None => changes.push(NodeChange::Removed(path.clone())),
+            Some(new_link)
+                if old_link.resolve_cid(store).await? != new_link.resolve_cid(store).await? =>
+            {
+                let old_node = old_link.resolve_value(store).await?;
+                let new_node = new_link.resolve_value(store).await?;
+                match (old_node, new_node) {
+                    (PublicNode::Dir(old_dir), PublicNode::Dir(new_dir)) => {
+                        diff_helper(path, old_dir, new_dir, store, changes).await?;
+                    }
+                    (PublicNode::File(old_file), PublicNode::File(new_file)) => {
+                        if old_file.userland != new_file.userland
+                            || old_file.metadata != new_file.metadata
+                        {
+                            changes.push(NodeChange::Modified(path.clone()));
+                        }
+                    }
+                    _ => changes.push(NodeChange::Modified(path.clone())),
+                }
+            }
+            Some(_) => {}
+        }
+        path.pop();
+    }
+
+    for name in new_entries.keys() {
+        if !old_entries.contains_key(name) {
+            path.push(name.clone());
+            changes.push(NodeChange::Added(path.clone()));
+            path.pop();
+        }
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::path;
+    use chrono::Utc;
+    use libipld::{Cid, IpldCodec};
+    use wnfs_common::MemoryBlockStore;
+
+    async fn content(data: &[u8], store: &impl BlockStore) -> Cid {
+        store
+            .put_block(data.to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn diff_detects_added_removed_and_modified_nodes() {
+        let store = &MemoryBlockStore::default();
+        let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+
+        for file in [
+            "docs/a.txt",
+            "docs/b.txt",
+            "pics/cats/c.jpg",
+            "static/d.txt",
+        ] {
+            let content_cid = content(b"old", store).await;
+            root_dir
+                .write(&path(file), content_cid, Utc::now(), store)
+                .await
+                .unwrap();
+        }
+
+        root_dir.store(store).await.unwrap();
+        let old_dir = Rc::clone(root_dir);
+
+        let new_cid = content(b"new", store).await;
+        root_dir
+            .write(&path("docs/new.txt"), new_cid, Utc::now(), store)
+            .await
+            .unwrap();
+        let changed_cid = content(b"changed", store).await;
+        root_dir
+            .write(&path("docs/a.txt"), changed_cid, Utc::now(), store)
+            .await
+            .unwrap();
+        root_dir.rm(&path("pics"), store).await.unwrap();
+        root_dir.store(store).await.unwrap();
+
+        let changes = diff(&old_dir, root_dir, store).await.unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                NodeChange::Modified(path("docs/a.txt")),
+                NodeChange::Added(path("docs/new.txt")),
+                NodeChange::Removed(path("pics")),
+            ]
+        );
+
+        let changes = diff(root_dir, &old_dir, store).await.unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                NodeChange::Modified(path("docs/a.txt")),
+                NodeChange::Removed(path("docs/new.txt")),
+                NodeChange::Added(path("pics")),
+            ]
+        );
+    }
+
+    #[async_std::test]
+    async fn diff_skips_identical_subtrees_without_loading_them() {
+        let store = &MemoryBlockStore::default();
+        let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+
+        let content_cid = content(b"a", store).await;
+        root_dir
+            .write(&path("docs/a.txt"), content_cid, Utc::now(), store)
+            .await
+            .unwrap();
+        let cid = root_dir.store(store).await.unwrap();
+
+        let old_dir = Rc::new(
+            store
+                .get_deserializable::<PublicDirectory>(&cid)
+                .await
+                .unwrap(),
+        );
+        let new_dir = Rc::new(
+            store
+                .get_deserializable::<PublicDirectory>(&cid)
+                .await
+                .unwrap(),
+        );
+
+        // Loading the "docs" subtree from this store would fail
+        let empty_store = &MemoryBlockStore::default();
+        let changes = diff(&old_dir, &new_dir, empty_store).await.unwrap();
+
+        assert!(changes.is_empty());
+    }
+}
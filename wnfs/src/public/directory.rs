@@ -9,7 +9,7 @@ use async_once_cell::OnceCell;
 use async_recursion::async_recursion;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use libipld::Cid;
+use libipld::{codec::Codec, json::DagJsonCodec, Cid, Ipld};
 use serde::{
     de::Error as DeError, ser::Error as SerError, Deserialize, Deserializer, Serialize, Serializer,
 };
@@ -19,6 +19,7 @@ use std::{
 };
 use wnfs_common::{
     utils::error, AsyncSerialize, BlockStore, Metadata, PathNodes, PathNodesResult, RemembersCid,
+    DEFAULT_DIR_MODE,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -111,6 +112,11 @@ impl PublicDirectory {
         &self.metadata
     }
 
+    /// Gets the POSIX permission bits of the directory, or [`DEFAULT_DIR_MODE`] if none were set.
+    pub fn get_mode(self: &Rc<Self>) -> u32 {
+        self.get_metadata().get_mode().unwrap_or(DEFAULT_DIR_MODE)
+    }
+
     /// Takes care of creating previous links, in case the current
     /// directory was previously `.store()`ed.
     /// In any case it'll try to give you ownership of the directory if possible,
@@ -198,7 +204,9 @@ impl PublicDirectory {
 
                 Ok(dir)
             }
-            SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
+            SearchResult::NotADir(_, depth) => {
+                bail!(FsError::NotADirectory(path_segments[..=depth].to_vec()))
+            }
         }
     }
 
@@ -237,6 +245,7 @@ impl PublicDirectory {
         path_segments: &[String],
         store: &impl BlockStore,
     ) -> Result<Option<&'a PublicNode>> {
+        utils::ensure_valid_path_segments(path_segments)?;
         let Some((tail, path)) = path_segments.split_last() else {
             return Ok(None);
         };
@@ -248,8 +257,79 @@ impl PublicDirectory {
         dir.lookup_node(tail, store).await
     }
 
+    /// Follows a path within a past revision of this directory and fetches the node at the end of the path.
+    ///
+    /// The revision is identified by its CID, which must be reachable through the `previous` links
+    /// of this directory, or be the CID this directory itself was stored as.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+    ///     let store = &MemoryBlockStore::default();
+    ///
+    ///     dir.mkdir(&["pictures".into()], Utc::now(), store).await.unwrap();
+    ///     let old_cid = dir.store(store).await.unwrap();
+    ///
+    ///     dir.rm(&["pictures".into()], store).await.unwrap();
+    ///
+    ///     let result = dir
+    ///         .get_node_at(&["pictures".into()], &old_cid, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(result.is_some());
+    /// }
+    /// ```
+    pub async fn get_node_at(
+        &self,
+        path_segments: &[String],
+        as_of: &Cid,
+        store: &impl BlockStore,
+    ) -> Result<Option<PublicNode>> {
+        utils::ensure_valid_path_segments(path_segments)?;
+        let revision = self.load_past_revision(as_of, store).await?;
+        Ok(revision.get_node(path_segments, store).await?.cloned())
+    }
+
+    /// Searches the history of this directory for the revision stored as given CID.
+    async fn load_past_revision(&self, cid: &Cid, store: &impl BlockStore) -> Result<Self> {
+        if self.persisted_as.get() == Some(cid) {
+            return Ok(self.clone());
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut queue = self.previous.iter().cloned().collect::<Vec<_>>();
+        while let Some(previous_cid) = queue.pop() {
+            if !visited.insert(previous_cid) {
+                continue;
+            }
+
+            let previous = store.get_deserializable::<Self>(&previous_cid).await?;
+            if previous_cid == *cid {
+                return Ok(previous);
+            }
+
+            queue.extend(previous.previous.iter().cloned());
+        }
+
+        bail!(FsError::NotInHistory(*cid))
+    }
+
     /// Looks up a node by its path name in the current directory.
     ///
+    /// Returns `None` if the node isn't in the directory. If it is, but its block is missing from the
+    /// store, this fails with [`BlockStoreError::NotFound`](wnfs_common::BlockStoreError::NotFound).
+    ///
     /// # Examples
     ///
     /// ```
@@ -281,6 +361,7 @@ impl PublicDirectory {
         path_segment: &str,
         store: &impl BlockStore,
     ) -> Result<Option<&'a PublicNode>> {
+        utils::ensure_valid_path_segment(path_segment)?;
         Ok(match self.userland.get(path_segment) {
             Some(link) => Some(link.resolve_value(store).await?),
             None => None,
@@ -341,9 +422,12 @@ impl PublicDirectory {
         match self.get_leaf_dir(path, store).await? {
             SearchResult::Found(dir) => match dir.lookup_node(filename, store).await? {
                 Some(PublicNode::File(file)) => Ok(file.userland),
-                Some(_) => error(FsError::NotAFile),
+                Some(_) => error(FsError::NotAFile(path_segments.to_vec())),
                 None => error(FsError::NotFound),
             },
+            SearchResult::NotADir(_, depth) => {
+                error(FsError::NotADirectory(path_segments[..=depth].to_vec()))
+            }
             _ => error(FsError::NotFound),
         }
     }
@@ -384,6 +468,7 @@ impl PublicDirectory {
         time: DateTime<Utc>,
         store: &impl BlockStore,
     ) -> Result<()> {
+        utils::ensure_valid_path_segments(path_segments)?;
         let (path, filename) = utils::split_last(path_segments)?;
         let dir = self.get_or_create_leaf_dir_mut(path, time, store).await?;
 
@@ -445,6 +530,7 @@ impl PublicDirectory {
         time: DateTime<Utc>,
         store: &impl BlockStore,
     ) -> Result<()> {
+        utils::ensure_valid_path_segments(path_segments)?;
         let _ = self
             .get_or_create_leaf_dir_mut(path_segments, time, store)
             .await?;
@@ -494,6 +580,7 @@ impl PublicDirectory {
         path_segments: &[String],
         store: &impl BlockStore,
     ) -> Result<Vec<(String, Metadata)>> {
+        utils::ensure_valid_path_segments(path_segments)?;
         match self.get_leaf_dir(path_segments, store).await? {
             SearchResult::Found(dir) => {
                 let mut result = vec![];
@@ -509,7 +596,9 @@ impl PublicDirectory {
                 }
                 Ok(result)
             }
-            SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
+            SearchResult::NotADir(_, depth) => {
+                bail!(FsError::NotADirectory(path_segments[..=depth].to_vec()))
+            }
             _ => bail!(FsError::NotFound),
         }
     }
@@ -567,6 +656,7 @@ impl PublicDirectory {
         path_segments: &[String],
         store: &impl BlockStore,
     ) -> Result<PublicNode> {
+        utils::ensure_valid_path_segments(path_segments)?;
         let (path, node_name) = utils::split_last(path_segments)?;
 
         let SearchResult::Found(dir) = self.get_leaf_dir_mut(path, store).await? else {
@@ -636,6 +726,8 @@ impl PublicDirectory {
         time: DateTime<Utc>,
         store: &impl BlockStore,
     ) -> Result<()> {
+        utils::ensure_valid_path_segments(path_segments_from)?;
+        utils::ensure_valid_path_segments(path_segments_to)?;
         let (path, filename) = utils::split_last(path_segments_to)?;
         let mut removed_node = self.rm(path_segments_from, store).await?;
 
@@ -691,6 +783,68 @@ impl PublicDirectory {
             .await?)
     }
 
+    /// Describes the directory tree as nested JSON objects.
+    ///
+    /// Directories have their entries by name, and files have the CID of their content instead of
+    /// the content itself. Metadata is included in full. CIDs and bytes are written the way
+    /// DAG-JSON writes them, so the tree's structure can be rebuilt from the JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use libipld::Cid;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+    ///
+    ///     root_dir
+    ///         .write(&["pictures".into(), "cat.jpg".into()], Cid::default(), Utc::now(), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let json = root_dir.to_json(store).await.unwrap();
+    ///
+    ///     assert_eq!(json["entries"]["pictures"]["type"], "directory");
+    ///     assert_eq!(json["entries"]["pictures"]["entries"]["cat.jpg"]["type"], "file");
+    /// }
+    /// ```
+    pub async fn to_json(&self, store: &impl BlockStore) -> Result<serde_json::Value> {
+        let bytes = DagJsonCodec.encode(&self.to_json_ipld(store).await?)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Builds the IPLD the JSON description of this directory is encoded from.
+    #[async_recursion(?Send)]
+    async fn to_json_ipld(&self, store: &impl BlockStore) -> Result<Ipld> {
+        let mut entries = BTreeMap::new();
+        for (name, link) in self.userland.iter() {
+            let entry = match link.resolve_value(store).await? {
+                PublicNode::Dir(dir) => dir.to_json_ipld(store).await?,
+                PublicNode::File(file) => Ipld::Map(BTreeMap::from([
+                    ("type".into(), Ipld::String("file".into())),
+                    ("metadata".into(), Ipld::Map(file.metadata.0.clone())),
+                    ("content".into(), Ipld::Link(file.userland)),
+                ])),
+            };
+
+            entries.insert(name.clone(), entry);
+        }
+
+        Ok(Ipld::Map(BTreeMap::from([
+            ("type".into(), Ipld::String("directory".into())),
+            ("metadata".into(), Ipld::Map(self.metadata.0.clone())),
+            ("entries".into(), Ipld::Map(entries)),
+        ])))
+    }
+
     /// Creates a new directory from provided serializable.
     pub(crate) fn from_serializable(serializable: PublicDirectorySerializable) -> Result<Self> {
         if serializable.version.major != 0 || serializable.version.minor != 2 {
@@ -795,9 +949,10 @@ impl<'de> Deserialize<'de> for PublicDirectory {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
-    use libipld::Ipld;
-    use wnfs_common::MemoryBlockStore;
+    use chrono::{TimeZone, Utc};
+    use libipld::{Ipld, IpldCodec};
+    use serde_json::json;
+    use wnfs_common::{is_not_found_error, MemoryBlockStore};
 
     #[async_std::test]
     async fn look_up_can_fetch_file_added_to_directory() {
@@ -1195,4 +1350,285 @@ mod tests {
             vec![previous_cid]
         );
     }
+
+    #[async_std::test]
+    async fn type_mismatches_report_offending_path() {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let root_dir = &mut Rc::new(PublicDirectory::new(time));
+
+        root_dir
+            .mkdir(&["docs".into(), "drafts".into()], time, store)
+            .await
+            .unwrap();
+
+        root_dir
+            .write(
+                &["docs".into(), "notes.txt".into()],
+                Cid::default(),
+                time,
+                store,
+            )
+            .await
+            .unwrap();
+
+        let error = root_dir
+            .read(&["docs".into(), "drafts".into()], store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotAFile(path)) if path == &["docs", "drafts"]
+        ));
+
+        let error = root_dir
+            .ls(&["docs".into(), "notes.txt".into()], store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotADirectory(path)) if path == &["docs", "notes.txt"]
+        ));
+        assert_eq!(error.to_string(), "Expected a directory: docs/notes.txt");
+
+        let error = root_dir
+            .read(&["docs".into(), "notes.txt".into(), "inner".into()], store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotADirectory(path)) if path == &["docs", "notes.txt"]
+        ));
+    }
+
+    #[async_std::test]
+    async fn get_node_at_navigates_past_revisions() {
+        let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+        let store = &MemoryBlockStore::default();
+
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.png".into()],
+                Cid::default(),
+                Utc::now(),
+                store,
+            )
+            .await
+            .unwrap();
+        let first_cid = root_dir.store(store).await.unwrap();
+
+        root_dir
+            .rm(&["pictures".into(), "cats".into()], store)
+            .await
+            .unwrap();
+        root_dir
+            .mkdir(&["pictures".into(), "dogs".into()], Utc::now(), store)
+            .await
+            .unwrap();
+        let second_cid = root_dir.store(store).await.unwrap();
+
+        root_dir
+            .mkdir(&["music".into()], Utc::now(), store)
+            .await
+            .unwrap();
+
+        let cats_path = ["pictures".into(), "cats".into()];
+        assert!(root_dir
+            .get_node(&cats_path, store)
+            .await
+            .unwrap()
+            .is_none());
+
+        let cats = root_dir
+            .get_node_at(&cats_path, &first_cid, store)
+            .await
+            .unwrap()
+            .unwrap();
+        let cats = cats.as_dir().unwrap();
+        assert_eq!(cats.ls(&[], store).await.unwrap()[0].0, "tabby.png");
+
+        let dogs_path = ["pictures".into(), "dogs".into()];
+        assert!(root_dir
+            .get_node_at(&dogs_path, &first_cid, store)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(root_dir
+            .get_node_at(&dogs_path, &second_cid, store)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[async_std::test]
+    async fn get_node_at_rejects_unrelated_revisions() {
+        let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+        let store = &MemoryBlockStore::default();
+        root_dir.store(store).await.unwrap();
+
+        let unrelated_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+        unrelated_dir
+            .mkdir(&["pictures".into()], Utc::now(), store)
+            .await
+            .unwrap();
+        let unrelated_cid = unrelated_dir.store(store).await.unwrap();
+        let error = root_dir
+            .get_node_at(&["pictures".into()], &unrelated_cid, store)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::NotInHistory(cid)) if *cid == unrelated_cid
+        ));
+    }
+
+    #[async_std::test]
+    async fn invalid_path_segments_are_rejected_before_mutation() {
+        let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+        let store = &MemoryBlockStore::default();
+
+        root_dir
+            .mkdir(&["pictures".into()], Utc::now(), store)
+            .await
+            .unwrap();
+        let cid = root_dir.store(store).await.unwrap();
+
+        for segment in ["", "cats/dogs", "cats\0"] {
+            let path = ["pictures".to_string(), segment.to_string()];
+            let results = [
+                root_dir
+                    .write(&path, Cid::default(), Utc::now(), store)
+                    .await
+                    .map(|_| ()),
+                root_dir.mkdir(&path, Utc::now(), store).await,
+                root_dir
+                    .basic_mv(&["pictures".into()], &path, Utc::now(), store)
+                    .await,
+                root_dir.lookup_node(segment, store).await.map(|_| ()),
+            ];
+
+            for result in results {
+                assert!(matches!(
+                    result.unwrap_err().downcast_ref::<FsError>(),
+                    Some(FsError::InvalidPathSegment(s)) if s == segment
+                ));
+            }
+        }
+
+        assert_eq!(root_dir.store(store).await.unwrap(), cid);
+    }
+
+    #[async_std::test]
+    async fn lookup_node_reports_missing_blocks_as_not_found() {
+        let store = &MemoryBlockStore::default();
+        let root_dir = &mut Rc::new(PublicDirectory::new(Utc::now()));
+        root_dir
+            .mkdir(&["pictures".into()], Utc::now(), store)
+            .await
+            .unwrap();
+        let cid = root_dir.store(store).await.unwrap();
+
+        // A store with only the root block, missing the child directory's block
+        let partial_store = &MemoryBlockStore::default();
+        partial_store
+            .put_block_keyed(cid, store.get_block(&cid).await.unwrap().into_owned())
+            .await
+            .unwrap();
+        let loaded_dir = partial_store
+            .get_deserializable::<PublicDirectory>(&cid)
+            .await
+            .unwrap();
+
+        let error = loaded_dir
+            .lookup_node("pictures", partial_store)
+            .await
+            .unwrap_err();
+        assert!(is_not_found_error(&error));
+
+        // Writing below the entry fails rather than treating it as a missing directory
+        let dir = &mut Rc::new(loaded_dir.clone());
+        let error = dir
+            .mkdir(
+                &["pictures".into(), "cats".into()],
+                Utc::now(),
+                partial_store,
+            )
+            .await
+            .unwrap_err();
+        assert!(is_not_found_error(&error));
+        assert!(loaded_dir
+            .lookup_node("pictures", store)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[async_std::test]
+    async fn to_json_describes_nested_tree() {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let store = &MemoryBlockStore::default();
+        let root_dir = &mut Rc::new(PublicDirectory::new(time));
+
+        let content_cid = store
+            .put_block(b"meow".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                content_cid,
+                time,
+                store,
+            )
+            .await
+            .unwrap();
+        root_dir
+            .mkdir(&["music".into()], time, store)
+            .await
+            .unwrap();
+
+        let metadata = json!({ "created": 1_000_000, "modified": 1_000_000 });
+        let expected = json!({
+            "type": "directory",
+            "metadata": metadata,
+            "entries": {
+                "music": {
+                    "type": "directory",
+                    "metadata": metadata,
+                    "entries": {}
+                },
+                "pictures": {
+                    "type": "directory",
+                    "metadata": metadata,
+                    "entries": {
+                        "cats": {
+                            "type": "directory",
+                            "metadata": metadata,
+                            "entries": {
+                                "tabby.jpg": {
+                                    "type": "file",
+                                    "metadata": metadata,
+                                    "content": { "/": content_cid.to_string() }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        assert_eq!(root_dir.to_json(store).await.unwrap(), expected);
+
+        // Stored directories load their children from the store
+        let cid = root_dir.store(store).await.unwrap();
+        let loaded_dir = store
+            .get_deserializable::<PublicDirectory>(&cid)
+            .await
+            .unwrap();
+        assert_eq!(loaded_dir.to_json(store).await.unwrap(), expected);
+    }
 }
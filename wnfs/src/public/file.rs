@@ -4,11 +4,19 @@ use super::{PublicFileSerializable, PublicNodeSerializable};
 use crate::{error::FsError, traits::Id, WNFS_VERSION};
 use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
+use async_stream::try_stream;
 use chrono::{DateTime, Utc};
-use libipld::Cid;
+use futures::{future, AsyncRead, Stream, TryStreamExt};
+use libipld::{Cid, IpldCodec};
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 use std::{collections::BTreeSet, rc::Rc};
-use wnfs_common::{BlockStore, Metadata, RemembersCid};
+use wnfs_common::{
+    dagcbor, utils, BlockStore, Metadata, RemembersCid, DEFAULT_FILE_MODE, MAX_BLOCK_SIZE,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
 
 /// Represents a file in the WNFS public filesystem.
 ///
@@ -31,6 +39,24 @@ pub struct PublicFile {
     pub previous: BTreeSet<Cid>,
 }
 
+/// Tag marking a dag-cbor block as a chunked content index, so it can be told apart from
+/// other content that happens to be dag-cbor encoded.
+const CHUNKED_CONTENT_TYPE: &str = "wnfs/chunked-content";
+
+/// The maximum number of links in a single chunked content index block. A CIDv1 with a 32 byte
+/// digest takes about 41 bytes in dag-cbor, so this leaves room for the rest of the block.
+const MAX_CHUNKED_CONTENT_LINKS: usize = MAX_BLOCK_SIZE / 48;
+
+/// An index block of content that didn't fit into a single block. Indices of height zero link
+/// the raw content chunks in order, higher ones link indices of the height below.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChunkedContent {
+    #[serde(rename = "type")]
+    kind: String,
+    height: u8,
+    links: Vec<Cid>,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -95,11 +121,171 @@ impl PublicFile {
         &self.metadata
     }
 
+    /// Gets the POSIX permission bits of the file, or [`DEFAULT_FILE_MODE`] if none were set.
+    pub fn get_mode(&self) -> u32 {
+        self.get_metadata().get_mode().unwrap_or(DEFAULT_FILE_MODE)
+    }
+
     /// Gets the content cid of a file
     pub fn get_content_cid(&self) -> &Cid {
         &self.userland
     }
 
+    /// Sets the content of the file from a stream, chunking it into raw blocks.
+    ///
+    /// Content that fits into a single block is linked directly. Larger content is linked via a
+    /// tree of index blocks listing its chunks, which `get_content` and `stream_content`
+    /// reassemble.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::PublicFile, common::MemoryBlockStore};
+    /// use chrono::Utc;
+    /// use libipld::Cid;
+    /// use std::rc::Rc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &MemoryBlockStore::default();
+    ///     let content = vec![42u8; 1024 * 1024];
+    ///
+    ///     let file = &mut Rc::new(PublicFile::new(Utc::now(), Cid::default()));
+    ///     file.set_content_streaming(Utc::now(), &content[..], store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(file.get_content(store).await.unwrap(), content);
+    /// }
+    /// ```
+    pub async fn set_content_streaming(
+        self: &mut Rc<Self>,
+        time: DateTime<Utc>,
+        mut content: impl AsyncRead + Unpin,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let mut chunks = Vec::new();
+
+        loop {
+            let mut chunk = vec![0u8; MAX_BLOCK_SIZE];
+            let (bytes_read, done) = utils::read_fully(&mut content, &mut chunk).await?;
+            chunk.truncate(bytes_read);
+
+            // The stream may only report its end on a read after the last full chunk.
+            if bytes_read > 0 || chunks.is_empty() {
+                chunks.push(store.put_block(chunk, IpldCodec::Raw).await?);
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        let content_cid = Self::store_chunk_index(chunks, MAX_CHUNKED_CONTENT_LINKS, store).await?;
+
+        let file = self.prepare_next_revision();
+        file.metadata.upsert_mtime(time);
+        file.userland = content_cid;
+
+        Ok(())
+    }
+
+    /// Links the chunks via index blocks of at most `max_links` links each, adding levels until
+    /// a single root remains. A single chunk is returned as is.
+    async fn store_chunk_index(
+        mut links: Vec<Cid>,
+        max_links: usize,
+        store: &impl BlockStore,
+    ) -> Result<Cid> {
+        let mut height = 0;
+        while links.len() > 1 {
+            let mut parents = Vec::with_capacity(links.len().div_ceil(max_links));
+            for group in links.chunks(max_links) {
+                let index = ChunkedContent {
+                    kind: CHUNKED_CONTENT_TYPE.to_string(),
+                    height,
+                    links: group.to_vec(),
+                };
+                parents.push(store.put_serializable(&index).await?);
+            }
+
+            links = parents;
+            height += 1;
+        }
+
+        Ok(links[0])
+    }
+
+    /// Loads the chunked content index at given CID, or returns `None` if the block isn't one.
+    async fn load_chunk_index(
+        cid: &Cid,
+        store: &impl BlockStore,
+    ) -> Result<Option<ChunkedContent>> {
+        if cid.codec() != u64::from(IpldCodec::DagCbor) {
+            return Ok(None);
+        }
+
+        let bytes = store.get_block(cid).await?;
+        Ok(
+            dagcbor::decode_with_limits::<ChunkedContent>(bytes.as_ref(), &store.decode_limits())
+                .ok()
+                .filter(|index| index.kind == CHUNKED_CONTENT_TYPE),
+        )
+    }
+
+    /// Streams the content of the file chunk by chunk.
+    ///
+    /// Content set with `set_content_streaming` is reassembled from its chunks. Any other
+    /// content CID is streamed as the single block it points to, whatever its codec.
+    pub fn stream_content<'a>(
+        &'a self,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+        Box::pin(try_stream! {
+            match Self::load_chunk_index(&self.userland, store).await? {
+                None => yield store.get_block(&self.userland).await?.to_vec(),
+                Some(root) => {
+                    // Depth-first walk over the index tree, in content order.
+                    let mut stack = vec![(root.height, root.links.into_iter())];
+                    while let Some((height, links)) = stack.last_mut() {
+                        let height = *height;
+                        let Some(link) = links.next() else {
+                            stack.pop();
+                            continue;
+                        };
+
+                        if height == 0 {
+                            yield store.get_block(&link).await?.to_vec();
+                            continue;
+                        }
+
+                        match Self::load_chunk_index(&link, store).await? {
+                            Some(child) if child.height + 1 == height => {
+                                stack.push((child.height, child.links.into_iter()))
+                            }
+                            _ => Err(FsError::InvalidFileContent(format!(
+                                "Expected a chunked content index of height {} at {link}",
+                                height - 1
+                            )))?,
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Gets the entire content of the file.
+    pub async fn get_content(&self, store: &impl BlockStore) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.stream_content(store)
+            .try_for_each(|chunk| {
+                content.extend_from_slice(&chunk);
+                future::ready(Ok(()))
+            })
+            .await?;
+        Ok(content)
+    }
+
     /// Stores file in provided block store.
     ///
     /// # Examples
@@ -256,4 +442,83 @@ mod tests {
             vec![previous_cid]
         );
     }
+
+    #[async_std::test]
+    async fn streamed_content_spanning_many_chunks_reads_back() {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let content = (0..3 * MAX_BLOCK_SIZE + 100)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let file = &mut Rc::new(PublicFile::new(time, Cid::default()));
+        let previous_cid = file.store(store).await.unwrap();
+        file.set_content_streaming(time, &content[..], store)
+            .await
+            .unwrap();
+
+        let root = store
+            .get_deserializable::<ChunkedContent>(file.get_content_cid())
+            .await
+            .unwrap();
+        assert_eq!(root.kind, CHUNKED_CONTENT_TYPE);
+        assert_eq!(root.height, 0);
+        assert_eq!(root.links.len(), 4);
+        assert_eq!(file.get_content(store).await.unwrap(), content);
+        assert_eq!(file.previous, [previous_cid].into_iter().collect());
+
+        // Content that fits a single block is linked directly
+        file.set_content_streaming(time, &b"Hello World"[..], store)
+            .await
+            .unwrap();
+        assert_eq!(file.get_content_cid().codec(), u64::from(IpldCodec::Raw));
+        assert_eq!(file.get_content(store).await.unwrap(), b"Hello World");
+    }
+
+    #[async_std::test]
+    async fn chunk_indices_nest_when_exceeding_max_links() {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let mut chunks = Vec::new();
+        for i in 0..10u8 {
+            chunks.push(store.put_block(vec![i; 3], IpldCodec::Raw).await.unwrap());
+        }
+
+        let root_cid = PublicFile::store_chunk_index(chunks, 3, store)
+            .await
+            .unwrap();
+        let root = store
+            .get_deserializable::<ChunkedContent>(&root_cid)
+            .await
+            .unwrap();
+        assert_eq!(root.height, 2);
+        assert_eq!(root.links.len(), 2);
+
+        let file = PublicFile::new(time, root_cid);
+        let expected = (0..10u8).flat_map(|i| [i; 3]).collect::<Vec<_>>();
+        assert_eq!(file.get_content(store).await.unwrap(), expected);
+    }
+
+    #[async_std::test]
+    async fn content_of_other_codecs_is_read_as_a_single_block() {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+
+        let dag_pb = vec![0x0a, 0x02, 0x08, 0x01];
+        let cid = store
+            .put_block(dag_pb.clone(), IpldCodec::DagPb)
+            .await
+            .unwrap();
+        let file = PublicFile::new(time, cid);
+        assert_eq!(file.get_content(store).await.unwrap(), dag_pb);
+
+        // dag-cbor content that isn't a chunk index is returned as is too
+        let cid = store
+            .put_serializable(&vec!["not", "an", "index"])
+            .await
+            .unwrap();
+        let bytes = store.get_block(&cid).await.unwrap().to_vec();
+        let file = PublicFile::new(time, cid);
+        assert_eq!(file.get_content(store).await.unwrap(), bytes);
+    }
 }
@@ -1,8 +1,10 @@
+mod diff;
 mod directory;
 mod file;
 mod link;
 mod node;
 
+pub use diff::*;
 pub use directory::*;
 pub use file::*;
 pub use link::*;
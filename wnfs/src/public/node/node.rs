@@ -13,7 +13,7 @@ use chrono::{DateTime, Utc};
 use libipld::Cid;
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 use std::{collections::BTreeSet, rc::Rc};
-use wnfs_common::{AsyncSerialize, BlockStore, RemembersCid};
+use wnfs_common::{AsyncSerialize, BlockStore, NodeType, RemembersCid};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -145,6 +145,9 @@ impl PublicNode {
 
     /// Casts a node to a directory.
     ///
+    /// Fails with `FsError::UnexpectedNodeType` otherwise. A node doesn't know its
+    /// own path, so callers that do should report `FsError::NotADirectory` instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -160,7 +163,7 @@ impl PublicNode {
     pub fn as_dir(&self) -> Result<Rc<PublicDirectory>> {
         Ok(match self {
             Self::Dir(dir) => Rc::clone(dir),
-            _ => bail!(FsError::NotADirectory),
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PublicDirectory)),
         })
     }
 
@@ -168,12 +171,15 @@ impl PublicNode {
     pub(crate) fn as_dir_mut(&mut self) -> Result<&mut Rc<PublicDirectory>> {
         Ok(match self {
             Self::Dir(dir) => dir,
-            _ => bail!(FsError::NotADirectory),
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PublicDirectory)),
         })
     }
 
     /// Casts a node to a file.
     ///
+    /// Fails with `FsError::UnexpectedNodeType` otherwise. A node doesn't know its
+    /// own path, so callers that do should report `FsError::NotAFile` instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -190,7 +196,7 @@ impl PublicNode {
     pub fn as_file(&self) -> Result<Rc<PublicFile>> {
         Ok(match self {
             Self::File(file) => Rc::clone(file),
-            _ => bail!(FsError::NotAFile),
+            _ => bail!(FsError::UnexpectedNodeType(NodeType::PublicFile)),
         })
     }
 
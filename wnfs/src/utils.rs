@@ -13,6 +13,67 @@ pub(crate) fn split_last(path_segments: &[String]) -> Result<(&[String], &String
     }
 }
 
+/// Checks that a path segment can be used as a directory entry name.
+///
+/// Empty segments and segments containing `/` or NUL can't be represented
+/// on a regular file system, so they're rejected.
+pub(crate) fn ensure_valid_path_segment(path_segment: &str) -> Result<()> {
+    if path_segment.is_empty() || path_segment.contains(['/', '\0']) {
+        return error(FsError::InvalidPathSegment(path_segment.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Checks that all segments of a path can be used as directory entry names.
+pub(crate) fn ensure_valid_path_segments(path_segments: &[String]) -> Result<()> {
+    path_segments
+        .iter()
+        .try_for_each(|segment| ensure_valid_path_segment(segment))
+}
+
+/// Matches a name against a glob pattern.
+///
+/// `*` matches any run of characters and `?` matches any single character.
+/// All other characters only match themselves.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*` if the rest doesn't match.
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    // Let the `*` swallow one more character.
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Splits a `/`-separated path into its segments.
+#[cfg(test)]
+pub(crate) fn path(path: &str) -> Vec<String> {
+    path.split('/').map(String::from).collect()
+}
+
 /// Deserialize a constant-size slice as a byte array in serde's data model,
 /// instead of serde's default, which is an array of integers.
 ///
@@ -52,4 +113,47 @@ mod tests {
         assert_eq!(rest, &["a", "b"]);
         assert_eq!(last, &"c");
     }
+
+    #[test]
+    fn glob_patterns_match_names() {
+        for (pattern, name) in [
+            ("*.py", "main.py"),
+            ("*.py", ".py"),
+            ("test_*.rs", "test_glob.rs"),
+            ("?.txt", "a.txt"),
+            ("*a*b*", "xxaxxbxx"),
+            ("*", ""),
+            ("exact", "exact"),
+        ] {
+            assert!(glob_match(pattern, name), "{pattern} should match {name}");
+        }
+
+        for (pattern, name) in [
+            ("*.py", "main.rs"),
+            ("*.py", "main.pyc"),
+            ("?.txt", "ab.txt"),
+            ("*a*b", "xxbxxa"),
+            ("exact", "exactly"),
+        ] {
+            assert!(
+                !glob_match(pattern, name),
+                "{pattern} shouldn't match {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_path_segments_are_rejected() {
+        for segment in ["", "a/b", "/", "a\0b"] {
+            let path_segments = ["valid".into(), segment.into()];
+            let error = ensure_valid_path_segments(&path_segments).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<FsError>(),
+                Some(FsError::InvalidPathSegment(s)) if s == segment
+            ));
+        }
+
+        assert!(ensure_valid_path_segments(&["a".into(), "b.txt".into()]).is_ok());
+        assert!(ensure_valid_path_segments(&[]).is_ok());
+    }
 }